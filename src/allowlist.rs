@@ -0,0 +1,170 @@
+// Copyright 2022 Martin Pool
+
+//! A checked-in list of mutants that are acknowledged to be missed by the tests,
+//! in `mutants-allowlist.toml` at the root of the tree.
+//!
+//! These might be equivalent mutants, whose behavior can't be distinguished from
+//! the original, or code that isn't practical to test. Allowed mutants are still
+//! tested and reported, but if they're missed they don't count against the score,
+//! don't fail a minimum score, and don't fail the run.
+//!
+//! The file looks like
+//!
+//! ```toml
+//! allow = [
+//!     # The loop runs at most once, so this is equivalent.
+//!     "src/lib.rs: replace < with <= in count",
+//! ]
+//! ```
+//!
+//! Only this subset of TOML is understood: see [crate::toml_subset].
+
+use std::collections::HashSet;
+use std::fs;
+
+use anyhow::{anyhow, Context};
+use camino::Utf8Path;
+
+use crate::outcome::{RunScore, SummaryOutcome};
+use crate::toml_subset::{expect, parse_string_array, skip_blank};
+use crate::*;
+
+/// The name of the allowlist file, in the root of the source tree.
+pub const ALLOWLIST_TOML: &str = "mutants-allowlist.toml";
+
+/// The ids of mutants that are allowed to be missed: see [Mutant::id].
+#[derive(Debug, Default, Clone)]
+pub struct Allowlist {
+    ids: HashSet<String>,
+}
+
+impl Allowlist {
+    /// Read the allowlist from the root of a source tree, or return an empty
+    /// allowlist if there is no allowlist file.
+    pub fn read(tree_path: &Utf8Path) -> Result<Allowlist> {
+        let path = tree_path.join(ALLOWLIST_TOML);
+        if !path.is_file() {
+            return Ok(Allowlist::default());
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("read {}", path))?;
+        let ids =
+            parse_allowlist(&content).with_context(|| format!("parse {}", path.to_slash_path()))?;
+        Ok(Allowlist {
+            ids: ids.into_iter().collect(),
+        })
+    }
+
+    /// True if the mutant with this id is allowed to be missed.
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Count up the summaries of some mutants, given with their ids, counting allowed
+    /// missed mutants separately from the score.
+    pub fn score<I, S>(&self, mutants: I) -> RunScore
+    where
+        I: IntoIterator<Item = (S, SummaryOutcome)>,
+        S: AsRef<str>,
+    {
+        let mut allowed = 0;
+        let mut run_score =
+            RunScore::from_summaries(mutants.into_iter().filter_map(|(id, summary)| {
+                if summary == SummaryOutcome::MissedMutant && self.contains(id.as_ref()) {
+                    allowed += 1;
+                    None
+                } else {
+                    Some(summary)
+                }
+            }));
+        run_score.allowed = allowed;
+        run_score
+    }
+}
+
+/// Parse the contents of an allowlist file into a list of mutant ids.
+fn parse_allowlist(content: &str) -> Result<Vec<String>> {
+    let mut chars = content.chars().peekable();
+    skip_blank(&mut chars);
+    if chars.peek().is_none() {
+        return Ok(Vec::new());
+    }
+    let key: String =
+        std::iter::from_fn(|| chars.next_if(|c| c.is_alphanumeric() || *c == '_')).collect();
+    if key != "allow" {
+        return Err(anyhow!("expected `allow = [...]`"));
+    }
+    expect(&mut chars, '=')?;
+    skip_blank(&mut chars);
+    let ids = parse_string_array(&mut chars)?;
+    skip_blank(&mut chars);
+    if chars.peek().is_some() {
+        return Err(anyhow!("unexpected text after `allow = [...]`"));
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_allowlist_file() {
+        assert_eq!(parse_allowlist("").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            parse_allowlist("# nothing yet\nallow = []\n").unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            parse_allowlist(
+                r#"
+# Mutants that are known to be missed.
+allow = [
+    # Equivalent.
+    "src/lib.rs: replace < with <= in count",
+    'src/lib.rs: replace name -> String with "xyzzy".into()', # Only logged.
+    "src/a\\b.rs: replace \"q\" with é",
+]
+"#
+            )
+            .unwrap(),
+            [
+                "src/lib.rs: replace < with <= in count",
+                r#"src/lib.rs: replace name -> String with "xyzzy".into()"#,
+                r#"src/a\b.rs: replace "q" with é"#,
+            ]
+        );
+        assert_eq!(
+            parse_allowlist("allow = [\"a\" \"b\"]")
+                .unwrap_err()
+                .to_string(),
+            "expected `,` or `]` after string"
+        );
+        assert_eq!(
+            parse_allowlist("[allow]\n").unwrap_err().to_string(),
+            "expected `allow = [...]`"
+        );
+        assert_eq!(
+            parse_allowlist("allow = [\"a]").unwrap_err().to_string(),
+            "unterminated string"
+        );
+    }
+
+    #[test]
+    fn allowed_missed_mutants_dont_count_against_the_score() {
+        let allowlist = Allowlist {
+            ids: vec!["a".to_owned(), "b".to_owned()].into_iter().collect(),
+        };
+        let run_score = allowlist.score([
+            ("a", SummaryOutcome::MissedMutant),
+            ("b", SummaryOutcome::CaughtMutant),
+            ("c", SummaryOutcome::MissedMutant),
+            ("d", SummaryOutcome::CaughtMutant),
+        ]);
+        assert_eq!(
+            run_score.to_string(),
+            "2 caught, 1 missed, 0 unviable, 0 timed out, 1 allowed, score 66%"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2022 Martin Pool
+
+//! `cargo mutants annotate`: write a copy of each source file that had mutants,
+//! marking each line with the mutants generated there and their outcomes, in the
+//! style of coverage annotation tools.
+//!
+//! The mutants are read from the `mutants.json` and `outcomes.json` (or
+//! `outcomes.ndjson`) of an earlier run. Mutants listed in `mutants.json` with no
+//! outcome, for example because the run was interrupted, are marked "untested".
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::compare::{mutant_summaries, read_outcomes, MutantJson};
+use crate::outcome::SummaryOutcome;
+use crate::*;
+
+/// The outcome of a mutant, as shown in the annotations.
+///
+/// These are in increasing order of interest, and a line with several mutants is
+/// labelled with the most interesting.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+enum Mark {
+    Caught,
+    Unviable,
+    Untested,
+    Timeout,
+    Missed,
+}
+
+impl Mark {
+    fn from_summary(summary: &SummaryOutcome) -> Mark {
+        match summary {
+            SummaryOutcome::CaughtMutant => Mark::Caught,
+            SummaryOutcome::MissedMutant => Mark::Missed,
+            SummaryOutcome::Unviable => Mark::Unviable,
+            SummaryOutcome::Timeout => Mark::Timeout,
+            // Mutants don't otherwise succeed or fail.
+            SummaryOutcome::Success | SummaryOutcome::Failure => Mark::Untested,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Mark::Caught => "caught",
+            Mark::Unviable => "unviable",
+            Mark::Untested => "untested",
+            Mark::Timeout => "timeout",
+            Mark::Missed => "missed",
+        }
+    }
+}
+
+/// Annotate the sources in `tree_dir` with the mutants from a `mutants.out` directory,
+/// or an outcomes file within it, writing the annotated files into `output_dir`, or
+/// by default into `annotated` within the `mutants.out` directory.
+///
+/// Returns the paths of the files written.
+pub fn annotate_tree(
+    tree_dir: &Utf8Path,
+    outcomes_path: &Utf8Path,
+    output_dir: Option<&Utf8Path>,
+) -> Result<Vec<Utf8PathBuf>> {
+    let mutants_out = if outcomes_path.is_dir() {
+        outcomes_path
+    } else {
+        outcomes_path.parent().unwrap_or(outcomes_path)
+    };
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir.to_owned(),
+        None => mutants_out.join("annotated"),
+    };
+    let lab_outcome = read_outcomes(outcomes_path)?;
+    let mut by_file: BTreeMap<String, Vec<(MutantJson, Mark)>> = BTreeMap::new();
+    let mut tested_ids = HashSet::new();
+    for (mutant, summary) in mutant_summaries(&lab_outcome) {
+        tested_ids.insert(mutant.id());
+        by_file
+            .entry(mutant.file.clone())
+            .or_default()
+            .push((mutant.clone(), Mark::from_summary(summary)));
+    }
+    let mutants_json_path = mutants_out.join("mutants.json");
+    if mutants_json_path.is_file() {
+        let json = fs::read_to_string(&mutants_json_path)
+            .with_context(|| format!("read {}", mutants_json_path))?;
+        let mutants: Vec<MutantJson> =
+            serde_json::from_str(&json).with_context(|| format!("parse {}", mutants_json_path))?;
+        for mutant in mutants {
+            if !tested_ids.contains(&mutant.id()) {
+                by_file
+                    .entry(mutant.file.clone())
+                    .or_default()
+                    .push((mutant, Mark::Untested));
+            }
+        }
+    }
+    let mut written = Vec::new();
+    for (file, mutants) in by_file {
+        let source_path = tree_dir.join(&file);
+        let code =
+            fs::read_to_string(&source_path).with_context(|| format!("read {}", source_path))?;
+        let output_path = output_dir.join(&file);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent))?;
+        }
+        fs::write(&output_path, annotate_file(&code, &mutants))
+            .with_context(|| format!("write {}", output_path))?;
+        written.push(output_path);
+    }
+    Ok(written)
+}
+
+/// Return the text of a source file with a margin showing the line numbers and the
+/// most interesting outcome of the mutants on each line, and each mutant described
+/// under the line where it starts.
+fn annotate_file(code: &str, mutants: &[(MutantJson, Mark)]) -> String {
+    let mut by_line: BTreeMap<usize, Vec<&(MutantJson, Mark)>> = BTreeMap::new();
+    for entry in mutants {
+        by_line.entry(entry.0.line).or_default().push(entry);
+    }
+    let mut s = String::new();
+    for (i, line) in code.lines().enumerate() {
+        let line_number = i + 1;
+        let line_mutants = by_line.get(&line_number);
+        let label = line_mutants
+            .and_then(|entries| entries.iter().map(|(_, mark)| *mark).max())
+            .map_or("", |mark| mark.label());
+        if line.is_empty() {
+            writeln!(s, "{:8} {:5} |", label, line_number).unwrap();
+        } else {
+            writeln!(s, "{:8} {:5} | {}", label, line_number, line).unwrap();
+        }
+        for (mutant, mark) in line_mutants.into_iter().flatten() {
+            writeln!(
+                s,
+                "{:8} {:5} |   ^ {}: {}",
+                "",
+                "",
+                mark.label(),
+                mutant.describe_change()
+            )
+            .unwrap();
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn mutant(line: usize, function: &str, replacement: &str) -> MutantJson {
+        MutantJson {
+            file: "src/lib.rs".to_owned(),
+            line,
+            function: function.to_owned(),
+            return_type: "-> u32".to_owned(),
+            replacement: replacement.to_owned(),
+            original: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn annotate_lines_with_mutants() {
+        let code = "fn a() -> u32 {\n    1\n}\n\nfn b() -> u32 {\n    2\n}\n";
+        let mutants = [
+            (mutant(1, "a", "0"), Mark::Caught),
+            (mutant(1, "a", "1"), Mark::Missed),
+            (mutant(5, "b", "0"), Mark::Unviable),
+        ];
+        assert_eq!(
+            annotate_file(code, &mutants),
+            "\
+missed       1 | fn a() -> u32 {
+               |   ^ caught: replace a -> u32 with 0
+               |   ^ missed: replace a -> u32 with 1
+             2 |     1
+             3 | }
+             4 |
+unviable     5 | fn b() -> u32 {
+               |   ^ unviable: replace b -> u32 with 0
+             6 |     2
+             7 | }
+"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2022 Martin Pool
+
+//! Generate a badge showing the mutation score, as an SVG image and as a
+//! [shields.io endpoint](https://shields.io/endpoint) JSON file.
+
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::outcome::RunScore;
+use crate::*;
+
+const BADGE_SVG: &str = "badge.svg";
+const BADGE_JSON: &str = "badge.json";
+const LABEL: &str = "mutants";
+
+/// Approximate width of one character in the badge font, in pixels.
+const CHAR_WIDTH: usize = 7;
+/// Horizontal padding on each side of the text, in pixels.
+const PADDING: usize = 6;
+
+/// The contents of a shields.io endpoint file.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShieldsEndpoint {
+    schema_version: u32,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+/// Write `badge.svg` and `badge.json` into the `mutants.out` directory.
+pub fn write_badges(output_dir: &Utf8Path, run_score: &RunScore) -> Result<()> {
+    let message = badge_message(run_score);
+    let color = badge_color(run_score);
+    let svg_path = output_dir.join(BADGE_SVG);
+    fs::write(&svg_path, badge_svg(&message, color))
+        .with_context(|| format!("write {}", svg_path))?;
+    let json_path = output_dir.join(BADGE_JSON);
+    let endpoint = ShieldsEndpoint {
+        schema_version: 1,
+        label: LABEL,
+        message,
+        color,
+    };
+    fs::write(&json_path, serde_json::to_string_pretty(&endpoint)?)
+        .with_context(|| format!("write {}", json_path))
+}
+
+fn badge_message(run_score: &RunScore) -> String {
+    match run_score.score {
+        Some(score) => format!("{:.0}%", score.floor()),
+        None => "n/a".to_owned(),
+    }
+}
+
+/// Return a color name understood by shields.io, mapped to a hex color in [color_hex].
+fn badge_color(run_score: &RunScore) -> &'static str {
+    match run_score.score {
+        None => "lightgrey",
+        Some(score) if score >= 90.0 => "brightgreen",
+        Some(score) if score >= 75.0 => "green",
+        Some(score) if score >= 60.0 => "yellow",
+        Some(score) if score >= 40.0 => "orange",
+        Some(_) => "red",
+    }
+}
+
+fn color_hex(color: &str) -> &'static str {
+    match color {
+        "brightgreen" => "#4c1",
+        "green" => "#97ca00",
+        "yellow" => "#dfb317",
+        "orange" => "#fe7d37",
+        "red" => "#e05d44",
+        _ => "#9f9f9f",
+    }
+}
+
+/// Render a flat badge in the style of shields.io.
+fn badge_svg(message: &str, color: &str) -> String {
+    let label_width = LABEL.len() * CHAR_WIDTH + 2 * PADDING;
+    let message_width = message.len() * CHAR_WIDTH + 2 * PADDING;
+    let width = label_width + message_width;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <rect width="{label_width}" height="20" fill="#555"/>
+  <rect x="{label_width}" width="{message_width}" height="20" fill="{fill}"/>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        width = width,
+        label = LABEL,
+        message = message,
+        label_width = label_width,
+        message_width = message_width,
+        fill = color_hex(color),
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::outcome::SummaryOutcome;
+
+    #[test]
+    fn message_and_color() {
+        let run_score = RunScore::from_summaries([
+            SummaryOutcome::CaughtMutant,
+            SummaryOutcome::CaughtMutant,
+            SummaryOutcome::MissedMutant,
+            SummaryOutcome::Unviable,
+        ]);
+        assert_eq!(badge_message(&run_score), "66%");
+        assert_eq!(badge_color(&run_score), "yellow");
+
+        let no_mutants = RunScore::default();
+        assert_eq!(badge_message(&no_mutants), "n/a");
+        assert_eq!(badge_color(&no_mutants), "lightgrey");
+    }
+
+    #[test]
+    fn write_badge_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(tmp.path()).unwrap();
+        let run_score = RunScore::from_summaries([SummaryOutcome::CaughtMutant]);
+        write_badges(path, &run_score).unwrap();
+        assert_eq!(
+            fs::read_to_string(path.join(BADGE_JSON)).unwrap(),
+            r#"{
+  "schemaVersion": 1,
+  "label": "mutants",
+  "message": "100%",
+  "color": "brightgreen"
+}"#
+        );
+        let svg = fs::read_to_string(path.join(BADGE_SVG)).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"aria-label="mutants: 100%""#));
+        assert!(svg.contains(r##"fill="#4c1""##));
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2022 Martin Pool
+
+//! A short report of why the unmutated tree failed: the compiler's errors, or the tests
+//! that failed and what they printed. It's shown on the console and written to
+//! `mutants.out/baseline-failure.txt`, so that the tree can be fixed without digging
+//! through the logs.
+
+use crate::log_file::output_text;
+use crate::*;
+
+/// The most compiler errors included in the report; the rest are only in the log.
+const MAX_ERRORS: usize = 10;
+
+/// The most lines of output included for each failed test, or from the end of the
+/// log when nothing more specific is found.
+const MAX_LINES: usize = 20;
+
+/// Describe why the unmutated tree failed, from its outcome and log.
+pub fn describe_failure(outcome: &Outcome) -> Result<String> {
+    let mut report = String::new();
+    let errors = outcome.compiler_errors();
+    let failed_tests = outcome.failed_tests()?;
+    let phase_log = outcome.last_phase_log()?;
+    if !errors.is_empty() {
+        report.push_str("Compiler errors:\n");
+        for error in errors.iter().take(MAX_ERRORS) {
+            report.push('\n');
+            report.push_str(error.trim_end());
+            report.push('\n');
+        }
+        if errors.len() > MAX_ERRORS {
+            report.push_str(&format!(
+                "\n... and {} more errors\n",
+                errors.len() - MAX_ERRORS
+            ));
+        }
+    } else if !failed_tests.is_empty() {
+        report.push_str("Failed tests:\n");
+        for name in &failed_tests {
+            report.push_str(&format!("\n  {}\n", name));
+            for line in test_output(&phase_log, name) {
+                report.push_str(&format!("    {}\n", line));
+            }
+        }
+    } else {
+        report.push_str("End of cargo's output:\n\n");
+        let lines: Vec<&str> = phase_log
+            .lines()
+            .filter(|line| !line.starts_with("*** "))
+            .map(output_text)
+            .collect();
+        for line in &lines[lines.len().saturating_sub(MAX_LINES)..] {
+            report.push_str(&format!("  {}\n", line));
+        }
+    }
+    Ok(report)
+}
+
+/// Return what libtest showed for a failed test, such as its panic message, up to
+/// the stack backtrace if there is one.
+fn test_output<'a>(log: &'a str, name: &str) -> Vec<&'a str> {
+    let header = format!("---- {} stdout ----", name);
+    let mut lines: Vec<&str> = log
+        .lines()
+        .map(output_text)
+        .skip_while(|line| *line != header)
+        .skip(1)
+        .skip_while(|line| line.trim().is_empty())
+        .take_while(|line| {
+            !(line.starts_with("---- ")
+                || *line == "failures:"
+                || line.starts_with("stack backtrace:")
+                || line.starts_with("note: run with `RUST_BACKTRACE"))
+        })
+        .take(MAX_LINES)
+        .collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn output_of_failed_test() {
+        let log = "\
+*** run cargo test
+[   0.014 stdout] running 2 tests
+[   0.015 stdout] test tests::add ... ok
+[   0.015 stdout] test tests::sub ... FAILED
+[   0.015 stdout] \n\
+[   0.015 stdout] failures:
+[   0.015 stdout] \n\
+[   0.015 stdout] ---- tests::sub stdout ----
+[   0.015 stdout] \n\
+[   0.015 stdout] thread 'tests::sub' panicked at src/lib.rs:12:9:
+[   0.015 stdout] assertion `left == right` failed
+[   0.015 stdout]   left: 1
+[   0.015 stdout]  right: 2
+[   0.015 stdout] stack backtrace:
+[   0.015 stdout]    0: rust_begin_unwind
+[   0.015 stdout] \n\
+[   0.015 stdout] \n\
+[   0.015 stdout] failures:
+[   0.015 stdout]     tests::sub
+";
+        assert_eq!(
+            test_output(log, "tests::sub"),
+            [
+                "thread 'tests::sub' panicked at src/lib.rs:12:9:",
+                "assertion `left == right` failed",
+                "  left: 1",
+                " right: 2",
+            ]
+        );
+        assert_eq!(test_output(log, "tests::add"), Vec::<&str>::new());
+    }
+}
@@ -1,32 +1,67 @@
 //! A temporary directory containing mutated source to run cargo builds and tests.
 
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::path::Path;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use camino::{Utf8Path, Utf8PathBuf};
 use tempfile::TempDir;
 
+use crate::cargo::CargoEnv;
 use crate::console::CopyActivity;
+use crate::manifest::workspace_excludes;
+use crate::scratch_registry::ScratchRegistration;
+use crate::user_cache::UserCache;
 use crate::*;
 
 /// Filenames excluded from being copied with the source.
-const SOURCE_EXCLUDE: &[&str] = &[
-    ".git",
-    ".hg",
-    ".bzr",
-    ".svn",
-    "_darcs",
-    ".pijul",
-    "mutants.out",
-    "mutants.out.old",
-];
+const SOURCE_EXCLUDE: &[&str] = &["mutants.out", "mutants.out.old"];
+
+/// The directories of version control systems, which are only copied with `--copy-vcs`.
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".bzr", ".svn", "_darcs", ".pijul"];
+
+/// How symlinks in the source tree are copied to the scratch directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Copy the files and directories they point to, skipping broken symlinks.
+    Follow,
+    /// Copy them as symlinks, pointing to the same place, even if it doesn't exist.
+    #[default]
+    Preserve,
+    /// Don't copy them.
+    Skip,
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "follow" => Ok(SymlinkPolicy::Follow),
+            "preserve" => Ok(SymlinkPolicy::Preserve),
+            "skip" => Ok(SymlinkPolicy::Skip),
+            _ => Err(anyhow!(
+                "unknown symlink policy {:?}: expected follow, preserve, or skip",
+                s
+            )),
+        }
+    }
+}
 
 /// A temporary directory initialized with a copy of the source, where mutations can be tested.
 #[derive(Debug)]
 pub struct BuildDir {
     path: Utf8PathBuf,
+    /// The root of the copy of the workspace, or of the package if it's not in one.
+    workspace_root: Utf8PathBuf,
+    cargo_env: CargoEnv,
     _temp_dir: TempDir,
+    /// Shows that the directory is in use, until after it's been removed.
+    _registration: Option<ScratchRegistration>,
 }
 
 impl BuildDir {
@@ -40,48 +75,491 @@ impl BuildDir {
             .suffix(".tmp")
             .tempdir()
             .context("create temp dir")?;
-        let temp_dir_path = temp_dir.path().to_owned().try_into().unwrap();
+        let temp_dir_path: Utf8PathBuf = temp_dir.path().to_owned().try_into().unwrap();
+        // The registry only helps to clean up after a crash, so the run carries on
+        // if the directory can't be registered.
+        let registration = UserCache::find().and_then(|cache| {
+            cache
+                .scratch_registry()
+                .register(&temp_dir_path, source.path())
+                .ok()
+        });
+        let (workspace_root, package_dir) = source.workspace_root()?;
+        let filter = CopyFilter {
+            options,
+            workspace_excludes: workspace_excludes(&workspace_root)?,
+        };
         let copy_target = options.copy_target;
         let name = if copy_target {
             "Copy source and build products to scratch directory"
         } else {
             "Copy source to scratch directory"
         };
-        let mut activity = CopyActivity::new(name, options.clone());
-        let target_path = Path::new("target");
+        let total_files = if options.quiet_copy {
+            None
+        } else {
+            // Only used for progress, so don't fail the copy if the tree can't be scanned.
+            count_files(workspace_root.as_std_path(), Path::new(""), &filter).ok()
+        };
+        let mut activity = CopyActivity::new(name, total_files, options.clone());
+        // Symlinks that are followed are copied after the rest of the tree.
+        let mut symlinks = Vec::new();
         match cp_r::CopyOptions::new()
             .after_entry_copied(|path, _ft, stats| {
-                activity.bytes_copied(stats.file_bytes);
+                activity.copied(stats.file_bytes, stats.files, path.parent().unwrap_or(path));
                 check_interrupted()
                     .map_err(|_| cp_r::Error::new(cp_r::ErrorKind::Interrupted, path))
             })
             .filter(|path, dir_entry| {
-                Ok(!SOURCE_EXCLUDE.iter().any(|ex| path.ends_with(ex))
-                    && (copy_target
-                        || !(dir_entry.file_type().unwrap().is_dir() && path == target_path)))
+                let file_type = dir_entry.file_type().unwrap();
+                if !filter.should_copy(path, file_type.is_dir()) {
+                    return Ok(false);
+                }
+                if !file_type.is_symlink() {
+                    return Ok(true);
+                }
+                match options.symlinks {
+                    SymlinkPolicy::Preserve => Ok(true),
+                    SymlinkPolicy::Follow => {
+                        symlinks.push(path.to_owned());
+                        Ok(false)
+                    }
+                    SymlinkPolicy::Skip => Ok(false),
+                }
             })
-            .copy_tree(source.path(), &temp_dir.path())
+            .copy_tree(&workspace_root, temp_dir.path())
             .context("copy source tree to lab directory")
-        {
-            Ok(stats) => activity.succeed(stats.file_bytes),
+            .and_then(|stats| {
+                copy_symlink_targets(
+                    workspace_root.as_std_path(),
+                    temp_dir.path(),
+                    symlinks,
+                    &filter,
+                )
+                .map(|warnings| (stats, warnings))
+            }) {
+            Ok((stats, warnings)) => {
+                activity.succeed(stats.file_bytes);
+                for warning in warnings {
+                    console::print_warning(&warning);
+                }
+            }
             Err(err) => {
                 activity.fail();
                 eprintln!(
                     "error copying source tree {} to {}: {:?}",
-                    &source.path().to_slash_path(),
+                    &workspace_root.to_slash_path(),
                     &temp_dir.path().to_slash_lossy(),
                     err
                 );
                 return Err(err);
             }
         }
+        let path = temp_dir_path.join(package_dir);
+        let target_dir = if options.isolate_nested_cargo {
+            temp_dir_path.join("target")
+        } else {
+            configured_target_dir(&path).unwrap_or_else(|| temp_dir_path.join("target"))
+        };
+        let cargo_env = CargoEnv::new(target_dir, options);
         Ok(BuildDir {
             _temp_dir: temp_dir,
-            path: temp_dir_path,
+            _registration: registration,
+            path,
+            workspace_root: temp_dir_path,
+            cargo_env,
         })
     }
 
+    /// Return the path of the package within the build directory, which is inside a copy
+    /// of the whole workspace if the package is a member of one.
     pub fn path(&self) -> &Utf8Path {
         self.path.as_path()
     }
+
+    /// True if the copy of the workspace has a `Cargo.lock`.
+    pub fn has_lockfile(&self) -> bool {
+        self.workspace_root.join("Cargo.lock").is_file()
+    }
+
+    /// Return the settings for running cargo in this directory, including the target
+    /// directory that it builds into.
+    pub fn cargo_env(&self) -> &CargoEnv {
+        &self.cargo_env
+    }
+
+    /// Copy files that changed in the source package into this directory, or remove
+    /// them if they were removed from the source, given their paths relative to the
+    /// package with forward slashes.
+    pub fn refresh<'a, I>(&self, source: &SourceTree, changed_paths: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        for rel_path in changed_paths {
+            let (from, to) = (source.path().join(rel_path), self.path.join(rel_path));
+            if from.is_file() {
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent).with_context(|| format!("create {}", parent))?;
+                }
+                fs::copy(&from, &to).with_context(|| format!("copy {} to {}", from, to))?;
+            } else if to.is_file() {
+                fs::remove_file(&to).with_context(|| format!("remove {}", to))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The target directory set in the environment, which cargo resolves relative to
+/// the directory it runs in, `package_dir`.
+fn configured_target_dir(package_dir: &Utf8Path) -> Option<Utf8PathBuf> {
+    ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .map(|value| package_dir.join(value))
+}
+
+/// Decides which files in the workspace are copied into the build directory.
+struct CopyFilter<'a> {
+    options: &'a Options,
+    /// Paths within the workspace that are excluded from it.
+    workspace_excludes: Vec<Utf8PathBuf>,
+}
+
+impl CopyFilter<'_> {
+    /// True if this path, relative to the workspace root, should be copied into the
+    /// build directory.
+    fn should_copy(&self, path: &Path, is_dir: bool) -> bool {
+        if SOURCE_EXCLUDE.iter().any(|ex| path.ends_with(ex)) {
+            return false;
+        }
+        if VCS_DIRS.iter().any(|vcs| path.ends_with(vcs)) {
+            return self.options.copy_vcs;
+        }
+        if is_dir && path == Path::new("target") {
+            return self.options.copy_target;
+        }
+        if self
+            .workspace_excludes
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+        {
+            return false;
+        }
+        !self
+            .options
+            .copy_exclude
+            .as_ref()
+            .is_some_and(|globset| globset.is_match(path))
+    }
+}
+
+/// Copy what each of the symlinks in `links`, relative to `source`, points to into the
+/// same place in `dest`, following symlinks within the directories it copies too.
+///
+/// The contents of linked directories are filtered as if they were in the tree at
+/// the path of the link.
+///
+/// Broken symlinks and symlinks to a directory containing them, which could otherwise
+/// be copied forever, are skipped. A symlink to a directory that was already copied
+/// through another link is made a symlink to that copy. Returns warnings describing
+/// them.
+fn copy_symlink_targets(
+    source: &Path,
+    dest: &Path,
+    mut links: Vec<PathBuf>,
+    filter: &CopyFilter,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    // The canonical paths of the directories copied through links so far, and the
+    // links they were copied to.
+    let mut copied_dirs: HashMap<PathBuf, PathBuf> = HashMap::new();
+    while let Some(link) = links.pop() {
+        let link_path = source.join(&link);
+        let dest_path = dest.join(&link);
+        let metadata = match fs::metadata(&link_path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warnings.push(format!(
+                    "skipped broken symlink {}: {}",
+                    link.to_slash_lossy(),
+                    err
+                ));
+                continue;
+            }
+        };
+        if !metadata.is_dir() {
+            fs::copy(&link_path, &dest_path)
+                .with_context(|| format!("copy target of symlink {}", link.display()))?;
+            continue;
+        }
+        let target = link_path.canonicalize()?;
+        let parent = link_path.parent().unwrap_or(source).canonicalize()?;
+        if parent.starts_with(&target) {
+            warnings.push(format!(
+                "skipped symlink {} to a directory containing it",
+                link.to_slash_lossy()
+            ));
+            continue;
+        }
+        if let Some(copied_to) = copied_dirs.get(&target) {
+            if let Some(parent) = dest_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("create {}", parent.display()))?;
+            }
+            symlink_dir(&relative_link_target(&link, copied_to), &dest_path)
+                .with_context(|| format!("create symlink {}", dest_path.display()))?;
+            warnings.push(format!(
+                "symlink {} is to a directory that was already copied, so it links to the copy at {}",
+                link.to_slash_lossy(),
+                copied_to.to_slash_lossy()
+            ));
+            continue;
+        }
+        copied_dirs.insert(target, link.clone());
+        fs::create_dir_all(&dest_path)
+            .with_context(|| format!("create {}", dest_path.display()))?;
+        cp_r::CopyOptions::new()
+            .filter(|path, dir_entry| {
+                let file_type = dir_entry.file_type().unwrap();
+                let tree_path = link.join(path);
+                if !filter.should_copy(&tree_path, file_type.is_dir()) {
+                    Ok(false)
+                } else if file_type.is_symlink() {
+                    links.push(tree_path);
+                    Ok(false)
+                } else {
+                    Ok(true)
+                }
+            })
+            .copy_tree(&link_path, &dest_path)
+            .with_context(|| format!("copy target of symlink {}", link.display()))?;
+    }
+    Ok(warnings)
+}
+
+/// Return the target for a symlink at `link` to `to`, both relative to the top of the
+/// tree, that's relative to the directory containing the link.
+fn relative_link_target(link: &Path, to: &Path) -> PathBuf {
+    let depth = link
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+    let mut target: PathBuf = std::iter::repeat_n("..", depth).collect();
+    target.push(to);
+    target
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+/// Count the files under `relative_dir` of the source tree that will be copied, so that
+/// the copy can show its progress.
+fn count_files(source: &Path, relative_dir: &Path, filter: &CopyFilter) -> Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(source.join(relative_dir))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let relative_path = relative_dir.join(entry.file_name());
+        if !filter.should_copy(&relative_path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            count += count_files(source, &relative_path, filter)?;
+        } else if file_type.is_file() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use globset::{Glob, GlobSetBuilder};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn count_files_to_copy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        for dir in ["src/a", ".git", "target/debug"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/a/mod.rs",
+            ".git/HEAD",
+            "target/debug/lib",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let count = |options: &Options| {
+            let filter = CopyFilter {
+                options,
+                workspace_excludes: Vec::new(),
+            };
+            count_files(root, Path::new(""), &filter).unwrap()
+        };
+        let mut options = Options::default();
+        assert_eq!(count(&options), 3);
+        options.copy_target = true;
+        assert_eq!(count(&options), 4);
+        options.copy_target = false;
+        options.copy_vcs = true;
+        assert_eq!(count(&options), 4);
+        options.copy_vcs = false;
+        options.copy_exclude = Some(
+            GlobSetBuilder::new()
+                .add(Glob::new("src/a").unwrap())
+                .add(Glob::new("**/*.toml").unwrap())
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(count(&options), 1);
+    }
+
+    #[test]
+    fn copy_filter_honors_workspace_excludes() {
+        let options = Options::default();
+        let filter = CopyFilter {
+            options: &options,
+            workspace_excludes: vec!["old".into()],
+        };
+        assert!(filter.should_copy(Path::new("member/src/lib.rs"), false));
+        assert!(filter.should_copy(Path::new("member/fixtures"), true));
+        assert!(!filter.should_copy(Path::new("old"), true));
+        assert!(!filter.should_copy(Path::new("old/src/lib.rs"), false));
+        assert!(filter.should_copy(Path::new("older"), true));
+    }
+
+    #[test]
+    fn refresh_copies_changed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source_dir = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir_all(source_dir.join("src")).unwrap();
+        fs::write(
+            source_dir.join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"0.0.0\"\n",
+        )
+        .unwrap();
+        fs::write(source_dir.join("src/lib.rs"), "").unwrap();
+        fs::write(source_dir.join("src/old.rs"), "").unwrap();
+        let source_tree = SourceTree::new(source_dir).unwrap();
+        let mut options = Options::default();
+        options.quiet_copy = true;
+        let build_dir = BuildDir::new(&source_tree, &options).unwrap();
+        assert!(build_dir.path().join("src/old.rs").is_file());
+
+        fs::write(source_dir.join("src/lib.rs"), "mod new;").unwrap();
+        fs::create_dir_all(source_dir.join("src/new")).unwrap();
+        fs::write(source_dir.join("src/new/mod.rs"), "fn f() {}").unwrap();
+        fs::remove_file(source_dir.join("src/old.rs")).unwrap();
+        let changed = ["src/lib.rs", "src/new/mod.rs", "src/old.rs"].map(String::from);
+        build_dir.refresh(&source_tree, &changed).unwrap();
+        assert_eq!(
+            fs::read_to_string(build_dir.path().join("src/lib.rs")).unwrap(),
+            "mod new;"
+        );
+        assert_eq!(
+            fs::read_to_string(build_dir.path().join("src/new/mod.rs")).unwrap(),
+            "fn f() {}"
+        );
+        assert!(!build_dir.path().join("src/old.rs").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_targets_of_followed_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source");
+        let dest = tmp.path().join("dest");
+        fs::create_dir_all(source.join("data/nested")).unwrap();
+        fs::create_dir_all(source.join("data/.git")).unwrap();
+        fs::create_dir_all(source.join("x")).unwrap();
+        fs::create_dir_all(source.join("y")).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(source.join("data/nested/a.txt"), "a").unwrap();
+        fs::write(source.join("data/.git/HEAD"), "").unwrap();
+        symlink("data/nested/a.txt", source.join("a_link.txt")).unwrap();
+        symlink("data", source.join("data_link")).unwrap();
+        symlink("../missing", source.join("data/broken")).unwrap();
+        symlink("..", source.join("data/up")).unwrap();
+        symlink("../y", source.join("x/to_y")).unwrap();
+        symlink("../x", source.join("y/to_x")).unwrap();
+        let options = Options::default();
+        let filter = CopyFilter {
+            options: &options,
+            workspace_excludes: Vec::new(),
+        };
+        let mut warnings = copy_symlink_targets(
+            &source,
+            &dest,
+            vec![
+                "a_link.txt".into(),
+                "data_link".into(),
+                "x/to_y".into(),
+                "y/to_x".into(),
+            ],
+            &filter,
+        )
+        .unwrap();
+        warnings.sort();
+        assert_eq!(fs::read_to_string(dest.join("a_link.txt")).unwrap(), "a");
+        assert!(!dest.join("a_link.txt").is_symlink());
+        assert_eq!(
+            fs::read_to_string(dest.join("data_link/nested/a.txt")).unwrap(),
+            "a"
+        );
+        assert!(!dest.join("data_link/broken").exists());
+        // The copy is filtered like the rest of the tree.
+        assert!(!dest.join("data_link/.git").exists());
+        // Each directory is copied once through the links between them, and then the
+        // other links to it point to that copy.
+        assert!(dest.join("y/to_x/to_y").is_dir());
+        assert!(!dest.join("y/to_x/to_y").is_symlink());
+        assert_eq!(
+            fs::read_link(dest.join("y/to_x/to_y/to_x")).unwrap(),
+            Path::new("../../../y/to_x")
+        );
+        assert_eq!(
+            fs::read_link(dest.join("x/to_y")).unwrap(),
+            Path::new("../y/to_x/to_y")
+        );
+        assert!(dest.join("x/to_y/to_x/to_y").is_dir());
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].starts_with("skipped broken symlink data_link/broken: "));
+        assert_eq!(
+            warnings[1],
+            "skipped symlink data_link/up to a directory containing it"
+        );
+        assert_eq!(
+            warnings[2],
+            "symlink x/to_y is to a directory that was already copied, so it links to the copy at y/to_x/to_y"
+        );
+        assert_eq!(
+            warnings[3],
+            "symlink y/to_x/to_y/to_x is to a directory that was already copied, so it links to the copy at y/to_x"
+        );
+    }
+
+    #[test]
+    fn parse_symlink_policy() {
+        assert_eq!(
+            "follow".parse::<SymlinkPolicy>().unwrap(),
+            SymlinkPolicy::Follow
+        );
+        assert_eq!(SymlinkPolicy::default(), SymlinkPolicy::Preserve);
+        assert!("sideways".parse::<SymlinkPolicy>().is_err());
+    }
 }
@@ -3,21 +3,285 @@
 //! Run Cargo as a subprocess, including timeouts and propagating signals.
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Cursor};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::diagnostic::DiagnosticLevel;
+use cargo_metadata::Message;
 use serde::Serialize;
-use subprocess::{Popen, PopenConfig, Redirection};
+use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
 
 use crate::console::CargoActivity;
-use crate::log_file::LogFile;
+use crate::log_file::{LogFile, OutputStream};
 use crate::*;
 
 /// How frequently to check if cargo finished.
 const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
+/// How frequently to measure the memory and CPU time used by cargo and its subprocesses.
+const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long to wait for more output after cargo exits: processes started by the tests
+/// might keep its output pipes open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Messages from cargo or rustc suggesting that a build failed because the cached
+/// state in the target directory is corrupt, rather than because of the source.
+const CORRUPT_CACHE_MESSAGES: &[&str] = &[
+    "internal compiler error",
+    "error: linking with `",
+    "found possibly newer version of crate",
+    "error: incremental compilation",
+    "could not write output to",
+];
+
+/// Messages in cargo's output showing that a command failed because of a problem with
+/// the machine it ran on, rather than with the code, and a description of each problem.
+const INFRASTRUCTURE_ERRORS: &[(&str, &str)] = &[
+    ("No space left on device", "the disk is full"),
+    (
+        "couldn't create a temp dir",
+        "the temporary directory is missing",
+    ),
+    (
+        "failed to acquire package cache lock",
+        "another cargo process held the package cache lock",
+    ),
+    (
+        "signal: 9, SIGKILL",
+        "a process was killed, perhaps because memory ran out",
+    ),
+];
+
+/// Messages from cargo or rustc showing that the tree uses features that are only
+/// available with a nightly toolchain, for example from `-Z` flags in
+/// `.cargo/config.toml`, `cargo-features` in `Cargo.toml`, or `#![feature]`.
+const NIGHTLY_ONLY_MESSAGES: &[&str] = &[
+    "is only accepted on the nightly compiler",
+    "is only accepted on the nightly channel of Cargo",
+    "requires a nightly version of Cargo",
+    "may not be used on the stable release channel",
+    "may not be used on the beta release channel",
+];
+
+/// The argument that makes cargo write its messages, and the compiler's, as JSON on
+/// stdout, so that they can be read by [parse_build_messages].
+pub const MESSAGE_FORMAT_JSON: &str = "--message-format=json";
+
+/// The archive of test binaries built by the build phase with `--test-tool nextest`,
+/// relative to the build directory.
+const NEXTEST_ARCHIVE: &str = "target/mutants-nextest.tar.zst";
+
+/// The directory within the target directory used by cargo commands that are run by
+/// the tests, with `--isolate-nested-cargo`.
+const NESTED_TARGET_DIR: &str = "mutants-nested";
+
+/// Environment variables that would otherwise send nested cargo commands to the
+/// target directory of the cargo command running the tests.
+const NESTED_CARGO_REMOVED_ENV: &[&str] = &["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"];
+
+/// Variables from the developer's environment that change how the tree is built,
+/// removed with `--deterministic-env`.
+const DETERMINISTIC_REMOVED_ENV: &[&str] = &[
+    "RUSTFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_BUILD_RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "CARGO_ENCODED_RUSTDOCFLAGS",
+    "CARGO_BUILD_RUSTDOCFLAGS",
+];
+
+/// Variables set to fixed values with `--deterministic-env`.
+const DETERMINISTIC_ENV: &[(&str, &str)] =
+    &[("CARGO_INCREMENTAL", "1"), ("SOURCE_DATE_EPOCH", "0")];
+
+/// The rustc flag to stop lints denied by the source failing the build.
+const CAP_LINTS: &str = "--cap-lints=allow";
+
+/// Settings for cargo commands run in a build directory, as opposed to the source tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoEnv {
+    /// With `--isolate-nested-cargo`, the target directory for cargo-mutants's own
+    /// cargo commands, which is given on the command line. Cargo commands run by the
+    /// tests, for example in the tests of a cargo plugin, get a directory within it.
+    ///
+    /// `cargo test` holds a lock on its target directory while the tests run, so nested
+    /// commands using the same directory would wait forever, and even if they didn't
+    /// they would spoil its incremental state.
+    isolated_target_dir: Option<Utf8PathBuf>,
+    /// The target directory that cargo builds into, if it's in the build directory.
+    target_dir: Option<Utf8PathBuf>,
+    /// Cap lints at `allow`, so that mutants that only cause warnings, such as unused
+    /// variables, aren't unviable in trees that deny warnings.
+    cap_lints: bool,
+    /// Build the same way whatever the developer's environment, by removing their
+    /// flags for rustc and rustdoc and fixing variables that affect the build.
+    deterministic: bool,
+    /// Variables set for this command only, like `PROPTEST_CASES` for the tests of
+    /// mutants. These override the inherited environment.
+    vars: Vec<(String, String)>,
+}
+
+impl CargoEnv {
+    /// Make the settings for a build directory whose target directory is `target_dir`.
+    pub fn new(target_dir: Utf8PathBuf, options: &Options) -> CargoEnv {
+        CargoEnv {
+            isolated_target_dir: options.isolate_nested_cargo.then(|| target_dir.clone()),
+            target_dir: Some(target_dir),
+            cap_lints: options.cap_lints,
+            deterministic: options.deterministic_env,
+            vars: Vec::new(),
+        }
+    }
+
+    /// Return these settings with additional variables set.
+    pub fn with_vars(&self, vars: &[(String, String)]) -> CargoEnv {
+        let mut cargo_env = self.clone();
+        cargo_env.vars.extend(vars.iter().cloned());
+        cargo_env
+    }
+
+    /// The target directory that cargo builds into, or None when building the source tree.
+    pub fn target_dir(&self) -> Option<&Utf8Path> {
+        self.target_dir.as_deref()
+    }
+
+    /// The target directory for cargo commands run by the tests.
+    fn nested_target_dir(&self) -> Option<Utf8PathBuf> {
+        self.isolated_target_dir
+            .as_ref()
+            .map(|dir| dir.join(NESTED_TARGET_DIR))
+    }
+
+    /// Return the environment for cargo, and so for the tests, based on the
+    /// environment of this process, or None if it's unchanged.
+    fn env<I>(&self, inherited: I) -> Option<Vec<(OsString, OsString)>>
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        if self.isolated_target_dir.is_none()
+            && !self.cap_lints
+            && !self.deterministic
+            && self.vars.is_empty()
+        {
+            return None;
+        }
+        let mut env: Vec<(OsString, OsString)> = inherited.into_iter().collect();
+        if self.deterministic {
+            env.retain(|(name, _)| {
+                !DETERMINISTIC_REMOVED_ENV.iter().any(|r| name == r)
+                    && !DETERMINISTIC_ENV.iter().any(|(r, _)| name == r)
+            });
+            env.extend(
+                DETERMINISTIC_ENV
+                    .iter()
+                    .map(|&(name, value)| (name.into(), value.into())),
+            );
+        }
+        if let Some(nested_target_dir) = self.nested_target_dir() {
+            env.retain(|(name, _)| !NESTED_CARGO_REMOVED_ENV.iter().any(|r| name == r));
+            env.push((
+                "CARGO_TARGET_DIR".into(),
+                nested_target_dir.into_string().into(),
+            ));
+        }
+        if self.cap_lints {
+            // If CARGO_ENCODED_RUSTFLAGS is set, cargo ignores RUSTFLAGS.
+            if let Some((_, flags)) = env
+                .iter_mut()
+                .find(|(name, _)| name == "CARGO_ENCODED_RUSTFLAGS")
+            {
+                if !flags.is_empty() {
+                    flags.push("\x1f");
+                }
+                flags.push(CAP_LINTS);
+            } else if let Some((_, flags)) = env.iter_mut().find(|(name, _)| name == "RUSTFLAGS") {
+                flags.push(" ");
+                flags.push(CAP_LINTS);
+            } else {
+                env.push(("RUSTFLAGS".into(), CAP_LINTS.into()));
+            }
+        }
+        for (name, value) in &self.vars {
+            env.retain(|(n, _)| n != name.as_str());
+            env.push((name.into(), value.into()));
+        }
+        Some(env)
+    }
+}
+
+/// The tool that runs the tests in the test phase.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TestTool {
+    /// `cargo test`.
+    #[default]
+    Cargo,
+    /// `cargo miri test`, which also detects undefined behavior in unsafe code, but
+    /// runs the tests much more slowly.
+    Miri,
+    /// `cargo nextest run`, running the tests from an archive made by the build phase,
+    /// so that the test phase doesn't check again whether they need to be rebuilt.
+    Nextest,
+}
+
+impl TestTool {
+    /// The cargo arguments that run the tests.
+    pub fn test_args(&self) -> &'static [&'static str] {
+        match self {
+            TestTool::Cargo => &["test"],
+            TestTool::Miri => &["miri", "test"],
+            TestTool::Nextest => &["nextest", "run", "--archive-file", NEXTEST_ARCHIVE],
+        }
+    }
+
+    /// The cargo arguments that build the tests, if this tool builds them differently
+    /// from `cargo build --tests`.
+    pub fn build_args(&self) -> Option<&'static [&'static str]> {
+        match self {
+            TestTool::Nextest => Some(&["nextest", "archive", "--archive-file", NEXTEST_ARCHIVE]),
+            _ => None,
+        }
+    }
+
+    /// The shortest test timeout set automatically from the time of the baseline tests.
+    ///
+    /// Under Miri, the time of the baseline includes building its sysroot, and the
+    /// time of the tests varies more with how much code they exercise, so the
+    /// minimum is longer.
+    pub fn min_auto_timeout(&self) -> Duration {
+        match self {
+            TestTool::Cargo | TestTool::Nextest => Duration::from_secs(20),
+            TestTool::Miri => Duration::from_secs(120),
+        }
+    }
+}
+
+impl FromStr for TestTool {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cargo" => Ok(TestTool::Cargo),
+            "miri" => Ok(TestTool::Miri),
+            "nextest" => Ok(TestTool::Nextest),
+            _ => Err(anyhow!(
+                "unknown test tool {:?}: expected cargo, miri, or nextest",
+                s
+            )),
+        }
+    }
+}
+
 /// The result of running a single Cargo command.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
 pub enum CargoResult {
@@ -28,6 +292,8 @@ pub enum CargoResult {
     // probably stop the cargo-mutants job.
     /// Cargo was killed by a timeout.
     Timeout,
+    /// Cargo was killed because it wrote no output for longer than the idle timeout.
+    Hung,
     /// Cargo exited successfully.
     Success,
     /// Cargo failed for some reason.
@@ -41,44 +307,400 @@ impl CargoResult {
     }
 }
 
-/// Run one `cargo` subprocess, with a timeout, and with appropriate handling of interrupts.
+/// What cargo said about a build, in its JSON messages.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct BuildMessages {
+    /// The names of the targets that were compiled, rather than being up to date.
+    pub rebuilt: Vec<String>,
+    /// The codes of the compiler's errors, like `E0308`, without duplicates.
+    pub error_codes: Vec<String>,
+    /// The compiler's errors, as they would have been shown without JSON.
+    #[serde(skip)]
+    pub errors: Vec<String>,
+    /// Whether cargo said the build succeeded, or None if it didn't say, for example
+    /// because it was killed.
+    #[serde(skip)]
+    pub success: Option<bool>,
+}
+
+/// Parse the JSON messages written to stdout by cargo with [MESSAGE_FORMAT_JSON].
+///
+/// Returns what they say about the build, and the text to show in the log: the
+/// compiler's messages as they would have been shown without JSON, and any lines that
+/// aren't JSON messages, such as output from a `--cargo-command` wrapper.
+pub fn parse_build_messages(stdout: &str) -> (BuildMessages, String) {
+    let mut messages = BuildMessages::default();
+    let mut text = String::new();
+    for message in Message::parse_stream(Cursor::new(stdout)).flatten() {
+        match message {
+            Message::CompilerArtifact(artifact)
+                if !artifact.fresh && !messages.rebuilt.contains(&artifact.target.name) =>
+            {
+                messages.rebuilt.push(artifact.target.name);
+            }
+            Message::CompilerMessage(compiler_message) => {
+                let diagnostic = compiler_message.message;
+                if diagnostic.level == DiagnosticLevel::Error {
+                    if let Some(code) = &diagnostic.code {
+                        if !messages.error_codes.contains(&code.code) {
+                            messages.error_codes.push(code.code.clone());
+                        }
+                    }
+                    // The summary at the end says nothing about what's wrong.
+                    if !diagnostic.message.starts_with("aborting due to") {
+                        if let Some(rendered) = &diagnostic.rendered {
+                            messages.errors.push(rendered.clone());
+                        }
+                    }
+                }
+                if let Some(rendered) = &diagnostic.rendered {
+                    text.push_str(rendered);
+                }
+            }
+            Message::BuildFinished(finished) => messages.success = Some(finished.success),
+            Message::TextLine(line) => {
+                text.push_str(&line);
+                text.push('\n');
+            }
+            _ => (),
+        }
+    }
+    (messages, text)
+}
+
+/// Return the name or path of the cargo binary to run.
+pub fn cargo_bin() -> Cow<'static, str> {
+    // When run as a Cargo subcommand, which is the usual/intended case,
+    // $CARGO tells us the right way to call back into it, so that we get
+    // the matching toolchain etc.
+    env::var("CARGO")
+        .map(Cow::from)
+        .unwrap_or(Cow::Borrowed("cargo"))
+}
+
+/// Return the command line to run cargo with the given arguments in `in_dir`, either
+/// directly or in a container.
+fn cargo_argv(
+    cargo_args: &[&str],
+    in_dir: &Utf8Path,
+    cargo_env: &CargoEnv,
+    options: &Options,
+) -> Result<Vec<String>> {
+    let mut argv: Vec<String> = Vec::new();
+    if let Some(image) = &options.container_image {
+        // Mount the directory at the same path inside the container, so that paths in
+        // cargo's output match the host.
+        let dir = in_dir
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {}", in_dir))?;
+        argv.extend([
+            options.container_cli.clone(),
+            "run".to_owned(),
+            "--rm".to_owned(),
+            "--init".to_owned(),
+            "--volume".to_owned(),
+            format!("{}:{}", dir, dir),
+            "--workdir".to_owned(),
+            dir.to_string(),
+        ]);
+        // The environment of this process isn't passed into the container, so only
+        // cargo-mutants's own settings are needed.
+        for (name, value) in cargo_env.env(Vec::new()).unwrap_or_default() {
+            argv.extend([
+                "--env".to_owned(),
+                format!("{}={}", name.to_string_lossy(), value.to_string_lossy()),
+            ]);
+        }
+        argv.extend([
+            image.clone(),
+            // $CARGO is a path on the host, so use the container's own cargo.
+            options
+                .cargo_command
+                .clone()
+                .unwrap_or_else(|| "cargo".to_owned()),
+        ]);
+    } else if let Some(cargo_command) = &options.cargo_command {
+        argv.push(cargo_command.clone());
+    } else if options.rust_toolchain.is_some() {
+        // $CARGO is the cargo of the toolchain that launched us, so run rustup's proxy,
+        // which will run the toolchain named by $RUSTUP_TOOLCHAIN.
+        argv.push("cargo".to_owned());
+    } else {
+        argv.push(cargo_bin().into_owned());
+    }
+    // The subcommand comes first, possibly in several words like `miri test`, and
+    // later arguments might be passed on to the tests.
+    let n_subcommand = cargo_args
+        .iter()
+        .take_while(|arg| !arg.starts_with('-'))
+        .count()
+        .max(1);
+    let (subcommand, rest) = cargo_args.split_at(n_subcommand.min(cargo_args.len()));
+    argv.extend(subcommand.iter().map(|&arg| arg.to_owned()));
+    // Tests run from an archive are already built, and nextest won't take build
+    // options with an archive.
+    let runs_archive = cargo_args.contains(&"--archive-file") && subcommand.ends_with(&["run"]);
+    if let Some(target_dir) = cargo_env
+        .isolated_target_dir
+        .as_ref()
+        .filter(|_| !runs_archive)
+    {
+        argv.extend(["--target-dir".to_owned(), target_dir.to_string()]);
+    }
+    argv.extend(rest.iter().map(|&arg| arg.to_owned()));
+    Ok(argv)
+}
+
+/// Return the environment for a cargo subprocess, or None to inherit this process's.
+fn cargo_process_env(cargo_env: &CargoEnv, options: &Options) -> Option<Vec<(OsString, OsString)>> {
+    let env = cargo_env.env(env::vars_os());
+    match &options.rust_toolchain {
+        Some(toolchain) if options.container_image.is_none() => {
+            let mut env = env.unwrap_or_else(|| env::vars_os().collect());
+            env.retain(|(name, _)| name != "RUSTUP_TOOLCHAIN");
+            env.push(("RUSTUP_TOOLCHAIN".into(), toolchain.channel.clone().into()));
+            Some(env)
+        }
+        _ => env,
+    }
+}
+
+/// A cargo command as it was run, with everything needed to run it again by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CargoCommand {
+    /// The command and its arguments, starting with cargo or the container tool.
+    pub argv: Vec<String>,
+    /// The absolute path of the directory it ran in.
+    pub cwd: String,
+    /// Variables set to a different value than in the environment of cargo-mutants.
+    pub env: BTreeMap<String, String>,
+    /// Variables in the environment of cargo-mutants that were removed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_removed: Vec<String>,
+}
+
+impl CargoCommand {
+    /// Describe a command run in `in_dir` with environment `env`, or with the
+    /// `inherited` environment of this process if `env` is None.
+    fn new<I>(
+        argv: Vec<String>,
+        in_dir: &Utf8Path,
+        inherited: I,
+        env: Option<&[(OsString, OsString)]>,
+    ) -> CargoCommand
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        let lossy = |s: &OsString| s.to_string_lossy().into_owned();
+        let inherited: BTreeMap<String, String> = inherited
+            .into_iter()
+            .map(|(name, value)| (lossy(&name), lossy(&value)))
+            .collect();
+        let mut changed = BTreeMap::new();
+        let mut env_removed = Vec::new();
+        if let Some(env) = env {
+            let env: BTreeMap<String, String> = env
+                .iter()
+                .map(|(name, value)| (lossy(name), lossy(value)))
+                .collect();
+            for (name, value) in &env {
+                if inherited.get(name) != Some(value) {
+                    changed.insert(name.clone(), value.clone());
+                }
+            }
+            env_removed = inherited
+                .keys()
+                .filter(|name| !env.contains_key(*name))
+                .cloned()
+                .collect();
+        }
+        CargoCommand {
+            argv,
+            cwd: in_dir
+                .canonicalize_utf8()
+                .unwrap_or_else(|_| in_dir.to_owned())
+                .into_string(),
+            env: changed,
+            env_removed,
+        }
+    }
+}
+
+/// What happened when cargo ran.
+#[derive(Debug)]
+pub struct CargoRun {
+    pub result: CargoResult,
+    /// The exit code of cargo, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// The messages from cargo, if they were parsed.
+    pub build_messages: Option<BuildMessages>,
+    /// The most resident memory used at once by cargo and all its subprocesses, in
+    /// bytes, if it could be measured.
+    pub peak_rss: Option<u64>,
+    /// The command that was run.
+    pub command: CargoCommand,
+}
+
+/// Limits on how long a cargo command can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// The longest the command can run altogether.
+    pub total: Duration,
+    /// The longest the command can run without writing any output, if that's limited.
+    pub idle: Option<Duration>,
+    /// The most CPU time that cargo and all its subprocesses can use, if that's limited.
+    pub cpu: Option<Duration>,
+}
+
+/// A line of output from a cargo subprocess.
+struct OutputLine {
+    stream: OutputStream,
+    /// The time since cargo started.
+    elapsed: Duration,
+    text: String,
+}
+
+/// Read lines from one of cargo's output pipes, until it's closed, and send them
+/// to be logged.
+fn read_output_lines(pipe: File, stream: OutputStream, start: Instant, sender: Sender<OutputLine>) {
+    let mut reader = BufReader::new(pipe);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                let text = String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_owned();
+                let line = OutputLine {
+                    stream,
+                    elapsed: start.elapsed(),
+                    text,
+                };
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Write an output line to the log, or if it's a JSON message from cargo, keep it
+/// to be parsed.
+fn log_output_line(line: OutputLine, log_file: &mut LogFile, json_stdout: &mut Option<String>) {
+    match json_stdout {
+        Some(json) if line.stream == OutputStream::Stdout => {
+            json.push_str(&line.text);
+            json.push('\n');
+        }
+        _ => log_file.output_line(line.elapsed, line.stream, &line.text),
+    }
+}
+
+/// Run one `cargo` subprocess, with timeouts, and with appropriate handling of interrupts.
+///
+/// Cargo's stdout and stderr are read separately, and each line is written to the log
+/// with the time it was read and the stream it came from.
+///
+/// If the arguments include [MESSAGE_FORMAT_JSON], cargo's messages are parsed, and
+/// the compiler's messages are written to the log as text after cargo exits.
+///
+/// On Linux, the memory and CPU time used by cargo's process group are sampled while
+/// it runs, so that a limit on CPU time can be enforced.
 pub fn run_cargo(
     cargo_args: &[&str],
     in_dir: &Utf8Path,
+    cargo_env: &CargoEnv,
     activity: &mut CargoActivity,
     log_file: &mut LogFile,
-    timeout: Duration,
-) -> Result<CargoResult> {
+    timeouts: Timeouts,
+    options: &Options,
+) -> Result<CargoRun> {
     let start = Instant::now();
-    // When run as a Cargo subcommand, which is the usual/intended case,
-    // $CARGO tells us the right way to call back into it, so that we get
-    // the matching toolchain etc.
-    let cargo_bin: Cow<str> = env::var("CARGO")
-        .map(Cow::from)
-        .unwrap_or(Cow::Borrowed("cargo"));
-    log_file.message(&format!("run {} {}", cargo_bin, cargo_args.join(" "),));
+    let argv = cargo_argv(cargo_args, in_dir, cargo_env, options)?;
+    log_file.message(&format!("run {}", argv.join(" ")));
+    let process_env = cargo_process_env(cargo_env, options);
+    let command = CargoCommand::new(argv.clone(), in_dir, env::vars_os(), process_env.as_deref());
 
-    let mut argv: Vec<&str> = vec![&cargo_bin];
-    argv.extend(cargo_args.iter());
     let mut child = Popen::create(
         &argv,
         PopenConfig {
             stdin: Redirection::None,
-            stdout: Redirection::File(log_file.open_append()?),
-            stderr: Redirection::Merge,
+            stdout: Redirection::Pipe,
+            stderr: Redirection::Pipe,
             cwd: Some(in_dir.as_os_str().to_owned()),
+            env: process_env,
             ..setpgid_on_unix()
         },
     )
-    .with_context(|| format!("failed to spawn {} {}", cargo_bin, cargo_args.join(" ")))?;
+    .with_context(|| format!("failed to spawn {}", argv.join(" ")))?;
+    let (sender, receiver) = mpsc::channel();
+    for (stream, pipe) in [
+        (OutputStream::Stdout, child.stdout.take()),
+        (OutputStream::Stderr, child.stderr.take()),
+    ] {
+        let pipe = pipe.expect("cargo output is piped");
+        let sender = sender.clone();
+        thread::spawn(move || read_output_lines(pipe, stream, start, sender));
+    }
+    drop(sender);
+    // The JSON messages on stdout are kept aside to be parsed.
+    let mut json_stdout = cargo_args.contains(&MESSAGE_FORMAT_JSON).then(String::new);
+    let pgid = child.pid().expect("child has a pid");
+    let mut peak_rss: Option<u64> = None;
+    let mut cpu_time = Duration::ZERO;
+    let mut last_sample: Option<Instant> = None;
+    let mut last_output = start;
     let exit_status = loop {
-        if start.elapsed() > timeout {
-            log_file.message(&format!(
-                "timeout after {:.3}s, terminating cargo process...\n",
-                start.elapsed().as_secs_f32()
-            ));
+        if last_sample.is_none_or(|t| t.elapsed() >= USAGE_SAMPLE_INTERVAL) {
+            if let Some(usage) = process_group_usage(pgid) {
+                peak_rss = Some(peak_rss.unwrap_or_default().max(usage.rss));
+                cpu_time = cpu_time.max(usage.cpu);
+            }
+            last_sample = Some(Instant::now());
+        }
+        for line in receiver.try_iter() {
+            last_output = Instant::now();
+            log_output_line(line, log_file, &mut json_stdout);
+        }
+        let stopped = if start.elapsed() > timeouts.total {
+            Some((
+                CargoResult::Timeout,
+                format!("timeout after {:.3}s", start.elapsed().as_secs_f32()),
+            ))
+        } else if timeouts.cpu.is_some_and(|cpu| cpu_time > cpu) {
+            Some((
+                CargoResult::Timeout,
+                format!("timeout after {:.3}s of CPU time", cpu_time.as_secs_f32()),
+            ))
+        } else if timeouts
+            .idle
+            .is_some_and(|idle| last_output.elapsed() > idle)
+        {
+            Some((
+                CargoResult::Hung,
+                format!(
+                    "no output for {:.3}s, so cargo looks hung",
+                    last_output.elapsed().as_secs_f32()
+                ),
+            ))
+        } else {
+            None
+        };
+        if let Some((cargo_result, reason)) = stopped {
+            log_file.message(&format!("{}, terminating cargo process...\n", reason));
             terminate_child(child, log_file)?;
-            return Ok(CargoResult::Timeout);
+            while let Ok(line) = receiver.recv_timeout(OUTPUT_GRACE) {
+                log_output_line(line, log_file, &mut json_stdout);
+            }
+            return Ok(CargoRun {
+                result: cargo_result,
+                exit_code: None,
+                build_messages: None,
+                peak_rss,
+                command,
+            });
         } else if let Err(e) = check_interrupted() {
             activity.interrupted();
             log_file.message("interrupted\n");
@@ -89,17 +711,200 @@ pub fn run_cargo(
         }
         activity.tick();
     };
+    while let Ok(line) = receiver.recv_timeout(OUTPUT_GRACE) {
+        log_output_line(line, log_file, &mut json_stdout);
+    }
+    let exit_code = match exit_status {
+        ExitStatus::Exited(code) => Some(code as i32),
+        _ => None,
+    };
+    let messages = match json_stdout {
+        Some(stdout) => {
+            let (messages, text) = parse_build_messages(&stdout);
+            log_file.append(&text);
+            if !messages.rebuilt.is_empty() {
+                log_file.message(&format!("rebuilt {}", messages.rebuilt.join(", ")));
+            }
+            Some(messages)
+        }
+        None => None,
+    };
     log_file.message(&format!(
         "cargo result: {:?} in {:.3}s",
         exit_status,
         start.elapsed().as_secs_f64()
     ));
     check_interrupted()?;
-    if exit_status.success() {
-        Ok(CargoResult::Success)
-    } else {
-        Ok(CargoResult::Failure)
+    // Cargo says whether the build succeeded even if a wrapper command loses its
+    // exit status.
+    let build_failed = messages
+        .as_ref()
+        .is_some_and(|messages| messages.success == Some(false));
+    Ok(CargoRun {
+        result: if exit_status.success() && !build_failed {
+            CargoResult::Success
+        } else {
+            CargoResult::Failure
+        },
+        exit_code,
+        build_messages: messages,
+        peak_rss,
+        command,
+    })
+}
+
+/// Resources used by the processes in a process group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ProcessGroupUsage {
+    /// The total resident memory, in bytes.
+    rss: u64,
+    /// The total CPU time, including that of children that have exited and been
+    /// waited for.
+    cpu: Duration,
+}
+
+/// Return the resources used by the processes in a process group, or None if there
+/// are none or they can't be measured.
+#[cfg(target_os = "linux")]
+fn process_group_usage(pgid: u32) -> Option<ProcessGroupUsage> {
+    use nix::unistd::{sysconf, SysconfVar};
+
+    let page_size = sysconf(SysconfVar::PAGE_SIZE).ok().flatten()? as u64;
+    let ticks_per_sec = sysconf(SysconfVar::CLK_TCK).ok().flatten()? as f64;
+    let mut total: Option<ProcessGroupUsage> = None;
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        // Processes can exit while they're being read, so errors are skipped.
+        if let Some(stat) = fs::read_to_string(entry.path().join("stat"))
+            .ok()
+            .and_then(|stat| parse_proc_stat(&stat))
+        {
+            if stat.pgrp == pgid {
+                let total = total.get_or_insert_with(ProcessGroupUsage::default);
+                total.rss += stat.rss_pages * page_size;
+                total.cpu += Duration::from_secs_f64(stat.cpu_ticks as f64 / ticks_per_sec);
+            }
+        }
     }
+    total
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_group_usage(_pgid: u32) -> Option<ProcessGroupUsage> {
+    None
+}
+
+/// The fields of `/proc/PID/stat` that are used to measure a process group.
+#[cfg(target_os = "linux")]
+#[derive(Debug, PartialEq, Eq)]
+struct ProcStat {
+    pgrp: u32,
+    /// User and system CPU time of the process and its waited-for children, in clock ticks.
+    cpu_ticks: u64,
+    rss_pages: u64,
+}
+
+/// Parse the contents of `/proc/PID/stat`.
+#[cfg(target_os = "linux")]
+fn parse_proc_stat(stat: &str) -> Option<ProcStat> {
+    // The command name is in parentheses and might contain spaces or parentheses,
+    // so count the fields after the last parenthesis.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let field = |i: usize| fields.get(i)?.parse::<u64>().ok();
+    Some(ProcStat {
+        pgrp: fields.get(2)?.parse().ok()?,
+        // utime, stime, cutime, and cstime.
+        cpu_ticks: field(11)? + field(12)? + field(13)? + field(14)?,
+        rss_pages: field(21)?,
+    })
+}
+
+/// True if the output from a failed cargo command suggests that the target directory
+/// is corrupt: for example the compiler crashed, or linking failed.
+///
+/// These errors are very unlikely to be caused by a mutation, and if they were left
+/// alone they might cause every following mutant to fail to build.
+pub fn looks_like_corrupt_build_cache(cargo_output: &str) -> bool {
+    CORRUPT_CACHE_MESSAGES
+        .iter()
+        .any(|message| cargo_output.contains(message))
+}
+
+/// If the output of a failed cargo command suggests that it failed because of a problem
+/// with the machine, such as a full disk, return a description of the problem.
+///
+/// These failures say nothing about the mutant, and are likely to be transient.
+pub fn infrastructure_problem(cargo_output: &str) -> Option<&'static str> {
+    INFRASTRUCTURE_ERRORS
+        .iter()
+        .find(|(message, _)| cargo_output.contains(message))
+        .map(|(_, problem)| *problem)
+}
+
+/// True if the output of a failed cargo command shows that the tree needs a nightly
+/// toolchain, but cargo or rustc is from the stable or beta channel.
+pub fn needs_nightly_toolchain(cargo_output: &str) -> bool {
+    NIGHTLY_ONLY_MESSAGES
+        .iter()
+        .any(|message| cargo_output.contains(message))
+}
+
+/// Generate a `Cargo.lock` in a tree that doesn't have one, so that every scenario
+/// builds with the same versions of the dependencies.
+///
+/// If the dependencies can't be resolved from the registry, for example because
+/// there's no network, this tries again with `--offline`, using only packages that
+/// were already downloaded.
+pub fn generate_lockfile(in_dir: &Utf8Path, cargo_env: &CargoEnv, options: &Options) -> Result<()> {
+    let mut stderr = String::new();
+    for cargo_args in [
+        &["generate-lockfile"][..],
+        &["generate-lockfile", "--offline"],
+    ] {
+        let argv = cargo_argv(cargo_args, in_dir, &CargoEnv::default(), options)?;
+        let mut command = Command::new(&argv[0]);
+        command.args(&argv[1..]).current_dir(in_dir);
+        if let Some(env) = cargo_process_env(cargo_env, options) {
+            command.env_clear().envs(env);
+        }
+        let output = command
+            .output()
+            .with_context(|| format!("failed to run {}", argv.join(" ")))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    }
+    Err(anyhow!(
+        "the tree has no Cargo.lock, and `cargo generate-lockfile` failed, even with --offline:\n{}",
+        stderr.trim_end()
+    ))
+}
+
+/// Remove the incremental compilation state for every profile in `target_dir`, so that
+/// the next build starts from a clean state, while keeping built dependencies.
+pub fn clean_incremental_state(target_dir: &Utf8Path) -> Result<()> {
+    if !target_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in target_dir
+        .read_dir_utf8()
+        .with_context(|| format!("read {}", target_dir))?
+    {
+        let incremental_dir = entry?.path().join("incremental");
+        if incremental_dir.is_dir() {
+            fs::remove_dir_all(&incremental_dir)
+                .with_context(|| format!("remove {}", incremental_dir))?;
+        }
+    }
+    Ok(())
 }
 
 #[cfg(unix)]
@@ -149,3 +954,465 @@ fn setpgid_on_unix() -> PopenConfig {
 fn setpgid_on_unix() -> PopenConfig {
     Default::default()
 }
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::toolchain::RustToolchain;
+
+    #[test]
+    fn parse_build_messages_from_cargo() {
+        let stdout = r#"{"reason":"compiler-artifact","package_id":"dep 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)","manifest_path":"/dep/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"dep","src_path":"/dep/src/lib.rs","edition":"2018","doc":true,"doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":[],"executable":null,"fresh":true}
+{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","manifest_path":"/app/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"app","src_path":"/app/src/lib.rs","edition":"2018","doc":true,"doctest":true,"test":true},"message":{"rendered":"error[E0308]: mismatched types\n","children":[],"code":{"code":"E0308","explanation":null},"level":"error","message":"mismatched types","spans":[]}}
+{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","manifest_path":"/app/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"app","src_path":"/app/src/lib.rs","edition":"2018","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: unused variable: `n`\n","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `n`","spans":[]}}
+wrapper says hello
+{"reason":"compiler-artifact","package_id":"app 0.1.0 (path+file:///app)","manifest_path":"/app/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"app","src_path":"/app/src/lib.rs","edition":"2018","doc":true,"doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":[],"executable":null,"fresh":false}
+{"reason":"build-finished","success":false}
+"#;
+        let (messages, text) = parse_build_messages(stdout);
+        assert_eq!(
+            messages,
+            BuildMessages {
+                rebuilt: vec!["app".to_owned()],
+                error_codes: vec!["E0308".to_owned()],
+                errors: vec!["error[E0308]: mismatched types\n".to_owned()],
+                success: Some(false),
+            }
+        );
+        assert_eq!(
+            text,
+            "error[E0308]: mismatched types\n\
+            warning: unused variable: `n`\n\
+            wrapper says hello\n"
+        );
+    }
+
+    #[test]
+    fn corrupt_build_cache() {
+        assert!(looks_like_corrupt_build_cache(
+            "error: internal compiler error: encountered incremental compilation error"
+        ));
+        assert!(looks_like_corrupt_build_cache(
+            "error: linking with `cc` failed: exit status: 1"
+        ));
+        assert!(!looks_like_corrupt_build_cache(
+            "error[E0308]: mismatched types"
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_proc_stat_fields() {
+        let stat = "4242 (my (odd) test) S 4200 4242 4200 0 -1 4194560 300 0 0 0 2 1 0 0 \
+            20 0 3 0 123456 45678592 1536 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 3 \
+            0 0 0 0 0";
+        assert_eq!(
+            parse_proc_stat(stat),
+            Some(ProcStat {
+                pgrp: 4242,
+                cpu_ticks: 3,
+                rss_pages: 1536
+            })
+        );
+        assert_eq!(parse_proc_stat("4242 (truncated"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn measure_own_process_group() {
+        let pgid = nix::unistd::getpgrp().as_raw() as u32;
+        let usage = process_group_usage(pgid).unwrap();
+        assert!(usage.rss > 0);
+        assert!(usage.cpu > Duration::ZERO);
+    }
+
+    #[test]
+    fn infrastructure_problems() {
+        assert_eq!(
+            infrastructure_problem(
+                "[   1.021 stderr] error: failed to write target/debug/deps/x.o: \
+                No space left on device (os error 28)"
+            ),
+            Some("the disk is full")
+        );
+        assert_eq!(
+            infrastructure_problem(
+                "error: test failed, to rerun pass `--lib`\n\
+                Caused by:\n  process didn't exit successfully: `x-1234` (signal: 9, SIGKILL: kill)"
+            ),
+            Some("a process was killed, perhaps because memory ran out")
+        );
+        assert_eq!(
+            infrastructure_problem("test tests::add ... FAILED\nerror: test failed"),
+            None
+        );
+    }
+
+    #[test]
+    fn nightly_only_features() {
+        assert!(needs_nightly_toolchain(
+            "error: the option `Z` is only accepted on the nightly compiler"
+        ));
+        assert!(needs_nightly_toolchain(
+            "error[E0554]: `#![feature]` may not be used on the stable release channel"
+        ));
+        assert!(needs_nightly_toolchain(
+            "the cargo feature `test-dummy-unstable` requires a nightly version of Cargo, \
+            but this is the `stable` channel"
+        ));
+        assert!(!needs_nightly_toolchain("error[E0308]: mismatched types"));
+    }
+
+    #[test]
+    fn generate_missing_lockfile() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"0.0.0\"\nedition = \"2018\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        generate_lockfile(dir, &CargoEnv::default(), &Options::default()).unwrap();
+        assert!(dir.join("Cargo.lock").is_file());
+
+        fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        let err = generate_lockfile(dir, &CargoEnv::default(), &Options::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("the tree has no Cargo.lock, and `cargo generate-lockfile` failed"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn clean_incremental_state_keeps_dependencies() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(tmp.path()).unwrap();
+        let debug = dir.join("target/debug");
+        fs::create_dir_all(debug.join("incremental/foo-123")).unwrap();
+        fs::create_dir_all(debug.join("deps")).unwrap();
+        clean_incremental_state(&dir.join("target")).unwrap();
+        assert!(!debug.join("incremental").exists());
+        assert!(debug.join("deps").is_dir());
+        // A tree that's never been built has nothing to clean.
+        clean_incremental_state(&dir.join("unbuilt")).unwrap();
+    }
+
+    #[test]
+    fn cargo_argv_in_container() {
+        let mut options = Options::default();
+        options.container_image = Some("rust:1.62".to_owned());
+        options.container_cli = "podman".to_owned();
+        let dir = Utf8Path::new("testdata/tree/factorial")
+            .canonicalize_utf8()
+            .unwrap();
+        assert_eq!(
+            cargo_argv(
+                &["test", "--", "--nocapture"],
+                &dir,
+                &CargoEnv::default(),
+                &options
+            )
+            .unwrap(),
+            [
+                "podman",
+                "run",
+                "--rm",
+                "--init",
+                "--volume",
+                &format!("{}:{}", dir, dir),
+                "--workdir",
+                dir.as_str(),
+                "rust:1.62",
+                "cargo",
+                "test",
+                "--",
+                "--nocapture",
+            ]
+        );
+    }
+
+    #[test]
+    fn cargo_argv_with_cargo_command() {
+        let mut options = Options::default();
+        options.cargo_command = Some("cross".to_owned());
+        assert_eq!(
+            cargo_argv(
+                &["build", "--tests"],
+                Utf8Path::new("testdata/tree/factorial"),
+                &CargoEnv::default(),
+                &options
+            )
+            .unwrap(),
+            ["cross", "build", "--tests"]
+        );
+    }
+
+    #[test]
+    fn cargo_runs_through_rustup_with_the_toolchain_from_a_file() {
+        let mut options = Options::default();
+        options.rust_toolchain = Some(RustToolchain {
+            channel: "nightly-2022-06-01".to_owned(),
+            file: "rust-toolchain.toml".into(),
+        });
+        assert_eq!(
+            cargo_argv(
+                &["test"],
+                Utf8Path::new("testdata/tree/factorial"),
+                &CargoEnv::default(),
+                &options
+            )
+            .unwrap(),
+            ["cargo", "test"]
+        );
+        let env = cargo_process_env(&CargoEnv::default(), &options).unwrap();
+        assert_eq!(
+            env.iter()
+                .filter(|(name, _)| name == "RUSTUP_TOOLCHAIN")
+                .map(|(_, value)| value.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            ["nightly-2022-06-01"]
+        );
+    }
+
+    #[test]
+    fn nested_cargo_gets_its_own_target_dir() {
+        let mut options = Options::default();
+        options.isolate_nested_cargo = true;
+        let cargo_env = CargoEnv::new(Utf8PathBuf::from("/tmp/build/target"), &options);
+        assert_eq!(
+            cargo_argv(
+                &["test", "--", "--nocapture"],
+                Utf8Path::new("/tmp/build"),
+                &cargo_env,
+                &options
+            )
+            .unwrap()[1..],
+            [
+                "test",
+                "--target-dir",
+                "/tmp/build/target",
+                "--",
+                "--nocapture"
+            ]
+        );
+        assert_eq!(
+            cargo_env
+                .env(vec![
+                    ("PATH".into(), "/bin".into()),
+                    ("CARGO_TARGET_DIR".into(), "/home/me/target".into()),
+                    ("CARGO_BUILD_TARGET_DIR".into(), "/home/me/target".into()),
+                ])
+                .unwrap(),
+            [
+                (OsString::from("PATH"), OsString::from("/bin")),
+                (
+                    OsString::from("CARGO_TARGET_DIR"),
+                    OsString::from("/tmp/build/target/mutants-nested")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn nextest_runs_archive_without_target_dir() {
+        let mut options = Options::default();
+        options.isolate_nested_cargo = true;
+        let cargo_env = CargoEnv::new(Utf8PathBuf::from("/tmp/build/target"), &options);
+        let argv = |args: &[&str]| {
+            cargo_argv(args, Utf8Path::new("/tmp/build"), &cargo_env, &options).unwrap()[1..]
+                .to_vec()
+        };
+        assert_eq!(
+            argv(TestTool::Nextest.build_args().unwrap()),
+            [
+                "nextest",
+                "archive",
+                "--target-dir",
+                "/tmp/build/target",
+                "--archive-file",
+                "target/mutants-nextest.tar.zst"
+            ]
+        );
+        assert_eq!(
+            argv(TestTool::Nextest.test_args()),
+            [
+                "nextest",
+                "run",
+                "--archive-file",
+                "target/mutants-nextest.tar.zst"
+            ]
+        );
+        assert_eq!(TestTool::Cargo.build_args(), None);
+    }
+
+    #[test]
+    fn target_dir_follows_miri_subcommand() {
+        let mut options = Options::default();
+        options.isolate_nested_cargo = true;
+        let cargo_env = CargoEnv::new(Utf8PathBuf::from("/tmp/build/target"), &options);
+        let mut cargo_args = TestTool::Miri.test_args().to_vec();
+        cargo_args.extend(["--", "--nocapture"]);
+        assert_eq!(
+            cargo_argv(
+                &cargo_args,
+                Utf8Path::new("/tmp/build"),
+                &cargo_env,
+                &options
+            )
+            .unwrap()[1..],
+            [
+                "miri",
+                "test",
+                "--target-dir",
+                "/tmp/build/target",
+                "--",
+                "--nocapture"
+            ]
+        );
+        assert_eq!("miri".parse::<TestTool>().unwrap(), TestTool::Miri);
+        assert!("valgrind".parse::<TestTool>().is_err());
+    }
+
+    #[test]
+    fn cap_lints_adds_to_rustflags() {
+        let mut options = Options::default();
+        assert_eq!(
+            CargoEnv::new(Utf8PathBuf::from("target"), &options).env(vec![]),
+            None
+        );
+        options.cap_lints = true;
+        let cargo_env = CargoEnv::new(Utf8PathBuf::from("target"), &options);
+        let env = |name: &str, value: &str| {
+            cargo_env
+                .env(vec![(name.into(), value.into())])
+                .unwrap()
+                .into_iter()
+                .map(|(name, value)| (name.into_string().unwrap(), value.into_string().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            env("PATH", "/bin"),
+            [
+                ("PATH".to_owned(), "/bin".to_owned()),
+                ("RUSTFLAGS".to_owned(), "--cap-lints=allow".to_owned())
+            ]
+        );
+        assert_eq!(
+            env("RUSTFLAGS", "-Ctarget-cpu=native"),
+            [(
+                "RUSTFLAGS".to_owned(),
+                "-Ctarget-cpu=native --cap-lints=allow".to_owned()
+            )]
+        );
+        assert_eq!(
+            env("CARGO_ENCODED_RUSTFLAGS", "-Ctarget-cpu=native"),
+            [(
+                "CARGO_ENCODED_RUSTFLAGS".to_owned(),
+                "-Ctarget-cpu=native\x1f--cap-lints=allow".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn vars_override_the_inherited_environment() {
+        let cargo_env = CargoEnv::default().with_vars(&[
+            ("PROPTEST_CASES".to_owned(), "16".to_owned()),
+            ("QUICKCHECK_TESTS".to_owned(), "10".to_owned()),
+        ]);
+        let env = cargo_env
+            .env(vec![
+                ("PATH".into(), "/bin".into()),
+                ("PROPTEST_CASES".into(), "1000".into()),
+            ])
+            .unwrap()
+            .into_iter()
+            .map(|(name, value)| (name.into_string().unwrap(), value.into_string().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            env,
+            [
+                ("PATH".to_owned(), "/bin".to_owned()),
+                ("PROPTEST_CASES".to_owned(), "16".to_owned()),
+                ("QUICKCHECK_TESTS".to_owned(), "10".to_owned()),
+            ]
+        );
+        assert_eq!(CargoEnv::default().with_vars(&[]).env(vec![]), None);
+    }
+
+    #[test]
+    fn command_records_changes_to_the_environment() {
+        let inherited = || {
+            vec![
+                ("PATH".into(), "/bin".into()),
+                ("RUSTFLAGS".into(), "-Ctarget-cpu=native".into()),
+                ("CARGO_TARGET_DIR".into(), "/tmp/target".into()),
+            ]
+        };
+        let env: Vec<(OsString, OsString)> = vec![
+            ("PATH".into(), "/bin".into()),
+            (
+                "RUSTFLAGS".into(),
+                "-Ctarget-cpu=native --cap-lints=allow".into(),
+            ),
+            ("PROPTEST_CASES".into(), "10".into()),
+        ];
+        let argv = vec!["cargo".to_owned(), "test".to_owned()];
+        let command = CargoCommand::new(
+            argv.clone(),
+            Utf8Path::new("/nonexistent/build"),
+            inherited(),
+            Some(&env),
+        );
+        assert_eq!(command.argv, argv);
+        assert_eq!(command.cwd, "/nonexistent/build");
+        assert_eq!(
+            command.env.into_iter().collect::<Vec<_>>(),
+            [
+                ("PROPTEST_CASES".to_owned(), "10".to_owned()),
+                (
+                    "RUSTFLAGS".to_owned(),
+                    "-Ctarget-cpu=native --cap-lints=allow".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(command.env_removed, ["CARGO_TARGET_DIR"]);
+
+        let unchanged = CargoCommand::new(argv, Utf8Path::new("/nonexistent"), inherited(), None);
+        assert!(unchanged.env.is_empty());
+        assert!(unchanged.env_removed.is_empty());
+    }
+
+    #[test]
+    fn deterministic_env_removes_developer_flags() {
+        let mut options = Options::default();
+        options.deterministic_env = true;
+        options.cap_lints = true;
+        let env = CargoEnv::new(Utf8PathBuf::from("target"), &options)
+            .env(vec![
+                ("PATH".into(), "/bin".into()),
+                ("RUSTFLAGS".into(), "-Ctarget-cpu=native".into()),
+                ("CARGO_INCREMENTAL".into(), "0".into()),
+                ("RUSTDOCFLAGS".into(), "--cfg docsrs".into()),
+            ])
+            .unwrap()
+            .into_iter()
+            .map(|(name, value)| (name.into_string().unwrap(), value.into_string().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            env,
+            [
+                ("PATH".to_owned(), "/bin".to_owned()),
+                ("CARGO_INCREMENTAL".to_owned(), "1".to_owned()),
+                ("SOURCE_DATE_EPOCH".to_owned(), "0".to_owned()),
+                ("RUSTFLAGS".to_owned(), "--cap-lints=allow".to_owned()),
+            ]
+        );
+    }
+}
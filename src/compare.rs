@@ -0,0 +1,367 @@
+// Copyright 2022 Martin Pool
+
+//! Compare the outcomes of two runs, as recorded in their `outcomes.json` or
+//! `outcomes.ndjson`, to find regressions in test coverage.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
+
+use crate::mutate::{describe_insertion, describe_replacement, mutant_id};
+use crate::outcome::{RunScore, SummaryOutcome};
+use crate::*;
+
+/// The parts of a serialized `outcomes.json` that are needed to compare runs.
+#[derive(Debug, Deserialize)]
+pub struct LabOutcomeJson {
+    outcomes: Vec<OutcomeJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutcomeJson {
+    scenario: ScenarioJson,
+    summary: SummaryOutcome,
+}
+
+#[derive(Debug, Deserialize)]
+enum ScenarioJson {
+    SourceTree,
+    Baseline,
+    Mutant(MutantJson),
+}
+
+/// A mutant as described in `outcomes.json` or `mutants.json`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct MutantJson {
+    pub file: String,
+    pub line: usize,
+    pub function: String,
+    pub return_type: String,
+    pub replacement: String,
+    /// The original code, for mutants that replace only part of a function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    /// Where code is inserted, for mutants that insert code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl MutantJson {
+    /// Return the stable identifier for this mutant: see [Mutant::id].
+    pub fn id(&self) -> String {
+        mutant_id(
+            &self.file,
+            &self.function,
+            &self.return_type,
+            self.describe_within().as_deref(),
+            &self.replacement,
+        )
+    }
+
+    /// Describe the change, for mutants that change only part of a function:
+    /// see [MutationOp::describe_within].
+    fn describe_within(&self) -> Option<String> {
+        if let Some(location) = &self.location {
+            Some(describe_insertion(
+                &self.replacement,
+                location,
+                &self.function,
+            ))
+        } else {
+            self.original
+                .as_ref()
+                .map(|original| describe_replacement(original, &self.replacement, &self.function))
+        }
+    }
+
+    /// Describe the mutant briefly, not including the location: see
+    /// [Mutant::describe_change].
+    pub fn describe_change(&self) -> String {
+        self.describe_within().unwrap_or_else(|| {
+            format!(
+                "replace {}{}{} with {}",
+                self.function,
+                if self.return_type.is_empty() { "" } else { " " },
+                self.return_type,
+                self.replacement
+            )
+        })
+    }
+}
+
+impl fmt::Display for MutantJson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.describe_change())
+    }
+}
+
+/// The differences between two runs.
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub old: RunScore,
+    pub new: RunScore,
+    /// Change in the mutation score, in percentage points.
+    pub score_delta: Option<f64>,
+    /// Mutants that were caught in the old run but missed in the new run.
+    pub regressions: Vec<MutantJson>,
+    /// Missed mutants in the new run that did not exist in the old run.
+    pub new_missed: Vec<MutantJson>,
+    /// Mutants that were missed in the old run but caught in the new run.
+    pub fixed: Vec<MutantJson>,
+}
+
+impl Comparison {
+    /// True if the new run is worse than the old one.
+    pub fn has_regressions(&self) -> bool {
+        !self.regressions.is_empty() || !self.new_missed.is_empty()
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn format_score(score: Option<f64>) -> String {
+            score.map_or_else(|| "n/a".to_owned(), |s| format!("{:.1}%", s))
+        }
+        write!(
+            f,
+            "Mutation score: {} -> {}",
+            format_score(self.old.score),
+            format_score(self.new.score)
+        )?;
+        if let Some(delta) = self.score_delta {
+            write!(f, " ({:+.1})", delta)?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Caught: {} -> {}, missed: {} -> {}",
+            self.old.caught, self.new.caught, self.old.missed, self.new.missed
+        )?;
+        for (title, mutants) in [
+            ("Caught before, missed now", &self.regressions),
+            ("New missed mutants", &self.new_missed),
+            ("Missed before, caught now", &self.fixed),
+        ] {
+            if !mutants.is_empty() {
+                writeln!(f, "\n{}:", title)?;
+                for mutant in mutants {
+                    writeln!(f, "  {}", mutant)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare two runs, given the paths of their `outcomes.json` or the `mutants.out`
+/// directories containing them.
+pub fn compare_outcome_files(old_path: &Utf8Path, new_path: &Utf8Path) -> Result<Comparison> {
+    Ok(compare(
+        &read_outcomes(old_path)?,
+        &read_outcomes(new_path)?,
+    ))
+}
+
+/// Read outcomes from a file, or from a `mutants.out` directory.
+///
+/// If the run in that directory did not finish, there will be no `outcomes.json`, so
+/// the outcomes recorded so far are read from `outcomes.ndjson`.
+pub fn read_outcomes(path: &Utf8Path) -> Result<LabOutcomeJson> {
+    let path = if path.is_dir() {
+        let json_path = path.join("outcomes.json");
+        let ndjson_path = path.join("outcomes.ndjson");
+        if !json_path.exists() && ndjson_path.exists() {
+            ndjson_path
+        } else {
+            json_path
+        }
+    } else {
+        path.to_owned()
+    };
+    let json = fs::read_to_string(&path).with_context(|| format!("read {}", path))?;
+    if path.extension() == Some("ndjson") {
+        let outcomes = json
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<Vec<OutcomeJson>>>()
+            .with_context(|| format!("parse {}", path))?;
+        Ok(LabOutcomeJson { outcomes })
+    } else {
+        serde_json::from_str(&json).with_context(|| format!("parse {}", path))
+    }
+}
+
+/// Return the mutants and their summarized results from one run.
+pub fn mutant_summaries(lab_outcome: &LabOutcomeJson) -> Vec<(&MutantJson, &SummaryOutcome)> {
+    lab_outcome
+        .outcomes
+        .iter()
+        .filter_map(|outcome| match &outcome.scenario {
+            ScenarioJson::Mutant(mutant) => Some((mutant, &outcome.summary)),
+            ScenarioJson::SourceTree | ScenarioJson::Baseline => None,
+        })
+        .collect()
+}
+
+fn run_score(summaries: &[(&MutantJson, &SummaryOutcome)]) -> RunScore {
+    RunScore::from_summaries(summaries.iter().map(|(_, summary)| *summary))
+}
+
+fn compare(old: &LabOutcomeJson, new: &LabOutcomeJson) -> Comparison {
+    let old_summaries = mutant_summaries(old);
+    let new_summaries = mutant_summaries(new);
+
+    // The same id can occur more than once, for example for functions of the same
+    // name under different cfg guards, so match them up in order.
+    let mut old_by_key: HashMap<String, Vec<&SummaryOutcome>> = HashMap::new();
+    for (mutant, summary) in &old_summaries {
+        old_by_key.entry(mutant.id()).or_default().push(summary);
+    }
+    for matches in old_by_key.values_mut() {
+        matches.reverse();
+    }
+
+    let mut regressions = Vec::new();
+    let mut new_missed = Vec::new();
+    let mut fixed = Vec::new();
+    for (mutant, summary) in &new_summaries {
+        let old_summary = old_by_key.get_mut(&mutant.id()).and_then(Vec::pop);
+        match (old_summary, summary) {
+            (None, SummaryOutcome::MissedMutant) => new_missed.push((*mutant).clone()),
+            (Some(SummaryOutcome::CaughtMutant), SummaryOutcome::MissedMutant) => {
+                regressions.push((*mutant).clone())
+            }
+            (Some(SummaryOutcome::MissedMutant), SummaryOutcome::CaughtMutant) => {
+                fixed.push((*mutant).clone())
+            }
+            _ => {}
+        }
+    }
+
+    let old_score = run_score(&old_summaries);
+    let new_score = run_score(&new_summaries);
+    let score_delta = match (old_score.score, new_score.score) {
+        (Some(old), Some(new)) => Some(new - old),
+        _ => None,
+    };
+    Comparison {
+        old: old_score,
+        new: new_score,
+        score_delta,
+        regressions,
+        new_missed,
+        fixed,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn outcomes_json(mutants: &[(&str, usize, &str, &str)]) -> LabOutcomeJson {
+        let outcomes = mutants
+            .iter()
+            .map(|(function, line, replacement, summary)| {
+                format!(
+                    r#"{{
+                        "scenario": {{"Mutant": {{
+                            "file": "src/lib.rs",
+                            "line": {},
+                            "function": "{}",
+                            "return_type": "-> bool",
+                            "replacement": "{}"
+                        }}}},
+                        "log_path": "mutants.out/log/x.log",
+                        "summary": "{}",
+                        "phase_results": []
+                    }}"#,
+                    line, function, replacement, summary
+                )
+            })
+            .collect::<Vec<_>>();
+        serde_json::from_str(&format!(
+            r#"{{"outcomes": [
+                {{"scenario": "Baseline", "log_path": "b.log", "summary": "Success", "phase_results": []}},
+                {}
+            ]}}"#,
+            outcomes.join(",")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn regressions_are_matched_across_line_changes() {
+        let old = outcomes_json(&[
+            ("a", 10, "true", "CaughtMutant"),
+            ("a", 10, "false", "CaughtMutant"),
+            ("b", 20, "true", "MissedMutant"),
+        ]);
+        let new = outcomes_json(&[
+            ("a", 12, "true", "CaughtMutant"),
+            ("a", 12, "false", "MissedMutant"),
+            ("b", 22, "true", "CaughtMutant"),
+            ("c", 30, "true", "MissedMutant"),
+            ("c", 30, "false", "Unviable"),
+        ]);
+        let comparison = compare(&old, &new);
+        assert_eq!((comparison.old.caught, comparison.old.missed), (2, 1));
+        assert_eq!(
+            (
+                comparison.new.caught,
+                comparison.new.missed,
+                comparison.new.unviable
+            ),
+            (2, 2, 1)
+        );
+        assert_eq!(
+            comparison
+                .regressions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["src/lib.rs:12: replace a -> bool with false"]
+        );
+        assert_eq!(comparison.new_missed.len(), 1);
+        assert_eq!(comparison.new_missed[0].function, "c");
+        assert_eq!(comparison.fixed.len(), 1);
+        assert_eq!(comparison.fixed[0].function, "b");
+        assert!(comparison.has_regressions());
+        let delta = comparison.score_delta.unwrap();
+        assert!((delta - (50.0 - 200.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn read_outcomes_from_unfinished_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::write(
+            dir.join("outcomes.ndjson"),
+            r#"{"scenario": "Baseline", "log_path": "b.log", "summary": "Success", "phase_results": []}
+{"scenario": {"Mutant": {"file": "src/lib.rs", "line": 3, "function": "f", "return_type": "", "replacement": "()"}}, "log_path": "f.log", "summary": "MissedMutant", "phase_results": []}
+"#,
+        )
+        .unwrap();
+        let outcomes = read_outcomes(dir).unwrap();
+        assert_eq!(run_score(&mutant_summaries(&outcomes)).missed, 1);
+    }
+
+    #[test]
+    fn identical_runs_have_no_regressions() {
+        let old = outcomes_json(&[("a", 10, "true", "CaughtMutant")]);
+        let new = outcomes_json(&[("a", 10, "true", "CaughtMutant")]);
+        let comparison = compare(&old, &new);
+        assert!(!comparison.has_regressions());
+        assert_eq!(comparison.score_delta, Some(0.0));
+        assert_eq!(
+            comparison.to_string(),
+            "Mutation score: 100.0% -> 100.0% (+0.0)\nCaught: 1 -> 1, missed: 0 -> 0\n"
+        );
+    }
+}
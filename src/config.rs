@@ -0,0 +1,282 @@
+// Copyright 2022 Martin Pool
+
+//! Configuration read from the `[package.metadata.mutants]` table in the tree's
+//! `Cargo.toml`.
+//!
+//! Command-line options take precedence over the config.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context};
+use camino::Utf8Path;
+use serde::Deserialize;
+
+use crate::cargo::cargo_bin;
+use crate::outcome::Phase;
+use crate::*;
+
+/// Configuration for a source tree.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Names of the kinds of mutation to generate, like `--mutation-kinds`.
+    pub mutation_kinds: Option<Vec<String>>,
+
+    /// Globs matching the fully-qualified paths of functions that should not be mutated,
+    /// like `telemetry::*` or `*::fmt`.
+    pub exclude_functions: Option<Vec<String>>,
+
+    /// Attributes of items whose code should not be mutated, like `--exclude-tag`:
+    /// `deprecated` or `hidden`.
+    pub exclude_tags: Option<Vec<String>>,
+
+    /// Globs matching paths in the source tree that shouldn't be copied to the scratch
+    /// directory, like `--copy-exclude`.
+    pub copy_exclude: Option<Vec<String>>,
+
+    /// Copy version control directories to the scratch directory, like `--copy-vcs`.
+    pub copy_vcs: Option<bool>,
+
+    /// How to copy symlinks to the scratch directory, like `--symlinks`: `follow`,
+    /// `preserve`, or `skip`.
+    pub symlinks: Option<String>,
+
+    /// Cap lints at `allow` in the scratch directory, like `--cap-lints`.
+    pub cap_lints: Option<bool>,
+
+    /// A cargo-compatible command to build and test with, like `--cargo-command`.
+    pub cargo_command: Option<String>,
+
+    /// The image of a container to run cargo in, like `--container-image`.
+    pub container_image: Option<String>,
+
+    /// Build in the scratch directory the same way whatever the developer's environment,
+    /// like `--deterministic-env`.
+    pub deterministic_env: Option<bool>,
+
+    /// Seconds to run each fuzz target for each mutant, like `--fuzz-time`.
+    pub fuzz_time: Option<f64>,
+
+    /// Seconds the tests can run without writing any output, like `--idle-timeout`.
+    pub idle_timeout: Option<f64>,
+
+    /// Give cargo commands run by the tests their own target directory, like
+    /// `--isolate-nested-cargo`.
+    pub isolate_nested_cargo: Option<bool>,
+
+    /// The most mutants to test in each function, like `--max-mutants-per-fn`.
+    pub max_mutants_per_fn: Option<usize>,
+
+    /// Minimum mutation scores, as percentages, for source directories or files like
+    /// `src/parser`, or for modules like `crate::parser`.
+    pub score: Option<BTreeMap<String, f64>>,
+
+    /// Multipliers for the test timeout of mutants in source paths like `src/net/**`,
+    /// or in functions matching patterns containing `::` like `net::*`.
+    pub timeout_multipliers: Option<BTreeMap<String, f64>>,
+
+    /// The directory to create `mutants.out` in, relative to the tree, like `--output`.
+    pub output: Option<String>,
+
+    /// Settings for each phase, from tables like `[package.metadata.mutants.phase.test]`.
+    pub phase: Option<PhaseConfigs>,
+}
+
+/// Settings for each phase of testing a mutant.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseConfigs {
+    pub check: Option<PhaseConfig>,
+    pub build: Option<PhaseConfig>,
+    pub test: Option<PhaseConfig>,
+    pub fuzz: Option<PhaseConfig>,
+}
+
+impl PhaseConfigs {
+    /// Return the config for one phase, if there is any.
+    pub fn get(&self, phase: Phase) -> Option<&PhaseConfig> {
+        match phase {
+            Phase::Check => self.check.as_ref(),
+            Phase::Build => self.build.as_ref(),
+            Phase::Test => self.test.as_ref(),
+            Phase::Fuzz => self.fuzz.as_ref(),
+        }
+    }
+}
+
+/// Settings for one phase.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseConfig {
+    /// Additional arguments to cargo, after the arguments cargo-mutants uses for this
+    /// phase, like `["--workspace"]` or `["--", "--skip", "slow_"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables set when testing mutants, but not the baseline, such as
+    /// `PROPTEST_CASES` lowered to make each mutant quicker to test.
+    #[serde(default)]
+    pub mutant_env: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Read the config from the root package of the tree, or return the default
+    /// config if there is no root package or it has no `mutants` metadata.
+    pub fn read_tree_config(source_tree: &SourceTree) -> Result<Config> {
+        let manifest = source_tree.path().join("Cargo.toml");
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&manifest)
+            .exec()
+            .with_context(|| format!("run {} metadata", cargo_bin()))?;
+        match metadata.root_package() {
+            Some(package) => Config::from_package_metadata(&package.metadata, &manifest),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Extract the config from the `metadata` of a package, as returned by `cargo metadata`.
+    fn from_package_metadata(metadata: &serde_json::Value, manifest: &Utf8Path) -> Result<Config> {
+        match metadata.get("mutants") {
+            Some(value) => serde_json::from_value(value.clone()).map_err(|err| {
+                anyhow!(
+                    "invalid [package.metadata.mutants] in {}: {}",
+                    manifest.to_slash_path(),
+                    err
+                )
+            }),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn config_from_package_metadata() {
+        let manifest = Utf8Path::new("Cargo.toml");
+        assert_eq!(
+            Config::from_package_metadata(&json!(null), manifest).unwrap(),
+            Config::default()
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"mutation_kinds": ["loop", "range"]}}),
+                manifest
+            )
+            .unwrap(),
+            Config {
+                mutation_kinds: Some(vec!["loop".to_owned(), "range".to_owned()]),
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"exclude_functions": ["*::fmt"]}}),
+                manifest
+            )
+            .unwrap()
+            .exclude_functions,
+            Some(vec!["*::fmt".to_owned()])
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"copy_exclude": ["fixtures/large", "*.bin"]}}),
+                manifest
+            )
+            .unwrap()
+            .copy_exclude,
+            Some(vec!["fixtures/large".to_owned(), "*.bin".to_owned()])
+        );
+        assert_eq!(
+            Config::from_package_metadata(&json!({"mutants": {"copy_vcs": true}}), manifest)
+                .unwrap()
+                .copy_vcs,
+            Some(true)
+        );
+        assert_eq!(
+            Config::from_package_metadata(&json!({"mutants": {"symlinks": "follow"}}), manifest)
+                .unwrap()
+                .symlinks
+                .as_deref(),
+            Some("follow")
+        );
+        let phase = Config::from_package_metadata(
+            &json!({"mutants": {"phase": {
+                "test": {"args": ["--", "--skip", "slow_"]},
+                "build": {"args": ["--workspace"]},
+                "fuzz": {"mutant_env": {"QUICKCHECK_TESTS": "10"}},
+            }}}),
+            manifest,
+        )
+        .unwrap()
+        .phase
+        .unwrap();
+        assert_eq!(
+            phase.get(Phase::Test).unwrap().args,
+            ["--", "--skip", "slow_"]
+        );
+        assert_eq!(phase.get(Phase::Build).unwrap().args, ["--workspace"]);
+        assert_eq!(phase.get(Phase::Check), None);
+        let fuzz = phase.get(Phase::Fuzz).unwrap();
+        assert!(fuzz.args.is_empty());
+        assert_eq!(
+            fuzz.mutant_env,
+            BTreeMap::from([("QUICKCHECK_TESTS".to_owned(), "10".to_owned())])
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"isolate_nested_cargo": true}}),
+                manifest
+            )
+            .unwrap()
+            .isolate_nested_cargo,
+            Some(true)
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"score": {"src/parser": 90, "crate::lex": 75.5}}}),
+                manifest
+            )
+            .unwrap()
+            .score,
+            Some(BTreeMap::from([
+                ("crate::lex".to_owned(), 75.5),
+                ("src/parser".to_owned(), 90.0)
+            ]))
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"timeout_multipliers": {"src/net/**": 3}}}),
+                manifest
+            )
+            .unwrap()
+            .timeout_multipliers,
+            Some(BTreeMap::from([("src/net/**".to_owned(), 3.0)]))
+        );
+        assert_eq!(
+            Config::from_package_metadata(
+                &json!({"mutants": {"output": "target/mutants"}}),
+                manifest
+            )
+            .unwrap()
+            .output
+            .as_deref(),
+            Some("target/mutants")
+        );
+        let err = Config::from_package_metadata(&json!({"mutants": {"kinds": []}}), manifest)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(
+                "invalid [package.metadata.mutants] in Cargo.toml: unknown field `kinds`"
+            ),
+            "{}",
+            err
+        );
+    }
+}
@@ -7,15 +7,22 @@
 
 use std::borrow::Cow;
 use std::fmt::Write;
+use std::io::{IsTerminal, Write as _};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use ::console::{style, StyledObject};
+use ::console::{style, StyledObject, Term};
 use anyhow::Result;
 
 use crate::*;
 
 /// Overall "run a bunch of experiments activity".
+///
+/// This is cheap to clone, and the clones can be shared between threads, each
+/// running its own scenarios: each scenario in flight gets its own progress row,
+/// under an overall progress line for the lab.
+#[derive(Clone)]
 pub struct LabActivity {
     view: Arc<nutmeg::View<LabModel>>,
 }
@@ -28,24 +35,50 @@ impl LabActivity {
         }
     }
 
-    pub fn start_mutants(&mut self, n_mutants: usize) {
+    pub fn start_mutants(&self, n_mutants: usize) {
         self.view.update(|model| {
             model.n_mutants = n_mutants;
             model.lab_start = Some(Instant::now());
         })
     }
 
-    pub fn start_scenario(&mut self, scenario: &Scenario, log_file: Utf8PathBuf) -> CargoActivity {
+    /// Print a message above the progress bars.
+    pub fn message(&self, message: &str) {
+        self.view.message(message);
+    }
+
+    /// Ask the user a yes-or-no question, with the progress bars hidden.
+    ///
+    /// If there's no user at a terminal to answer, this returns true without asking.
+    pub fn confirm(&self, question: &str) -> Result<bool> {
+        if !(::console::user_attended() && std::io::stdin().is_terminal()) {
+            return Ok(true);
+        }
+        self.view.suspend();
+        print!("{} [y/N] ", question);
+        std::io::stdout().flush()?;
+        let answer = Term::stdout().read_line();
+        self.view.resume();
+        Ok(matches!(
+            answer?.trim().to_lowercase().as_str(),
+            "y" | "yes"
+        ))
+    }
+
+    /// Start showing progress of a scenario, in its own row.
+    pub fn start_scenario(&self, scenario: &Scenario, log_file: Utf8PathBuf) -> CargoActivity {
         let start = Instant::now();
         let cargo_model = CargoModel::new(scenario, start, log_file);
         let name = cargo_model.name.clone();
-        if let Scenario::Mutant { .. } = scenario {
-            self.view.update(|model| model.i_mutant += 1);
-        }
-        self.view
-            .update(|model| model.cargo_model = Some(cargo_model));
+        let id = self.view.update(|model| {
+            if scenario.is_mutant() {
+                model.mutants_started += 1;
+            }
+            model.add_cargo_model(cargo_model)
+        });
         CargoActivity {
             lab_view: self.view.clone(),
+            id,
             name,
             start,
         }
@@ -54,14 +87,17 @@ impl LabActivity {
 
 /// Description of all current activities in the lab.
 ///
-/// At the moment there is either a copy, cargo runs, or nothing.
-/// Later, there might be concurrent activities.
+/// There may be a copy, and any number of scenarios running cargo concurrently.
 #[derive(Default)]
 struct LabModel {
     copy_model: Option<CopyModel>,
-    cargo_model: Option<CargoModel>,
+    /// The scenarios in flight, in the order they started.
+    cargo_models: Vec<CargoModel>,
+    /// The id to be given to the next scenario started.
+    next_cargo_id: usize,
     lab_start: Option<Instant>,
-    i_mutant: usize,
+    mutants_started: usize,
+    mutants_done: usize,
     n_mutants: usize,
     mutants_caught: usize,
     mutants_missed: usize,
@@ -73,53 +109,82 @@ impl nutmeg::Model for LabModel {
         if let Some(copy) = self.copy_model.as_mut() {
             s.push_str(&copy.render(width));
         }
-        if let Some(cargo_model) = self.cargo_model.as_mut() {
-            if !s.is_empty() {
-                s.push('\n')
-            }
-            if let Some(lab_start) = self.lab_start {
-                writeln!(
-                    s,
-                    "Trying mutant {}/{}, {} done, {} caught, {} missed, {} remaining",
-                    self.i_mutant,
-                    self.n_mutants,
-                    nutmeg::percent_done(self.i_mutant, self.n_mutants),
-                    self.mutants_caught,
-                    self.mutants_missed,
-                    nutmeg::estimate_remaining(&lab_start, self.i_mutant, self.n_mutants)
-                )
-                .unwrap();
-            }
-            s.push_str(&cargo_model.render(width));
+        if self.cargo_models.is_empty() {
+            return s;
         }
+        if !s.is_empty() {
+            s.push('\n')
+        }
+        if let Some(lab_start) = self.lab_start {
+            writeln!(
+                s,
+                "Trying mutants: {} started, {}/{} done, {}, {} caught, {} missed, {} remaining",
+                self.mutants_started,
+                self.mutants_done,
+                self.n_mutants,
+                nutmeg::percent_done(self.mutants_done, self.n_mutants),
+                self.mutants_caught,
+                self.mutants_missed,
+                nutmeg::estimate_remaining(&lab_start, self.mutants_done, self.n_mutants)
+            )
+            .unwrap();
+        }
+        let rows: Vec<String> = self
+            .cargo_models
+            .iter_mut()
+            .map(|cargo_model| cargo_model.render(width))
+            .collect();
+        s.push_str(&rows.join("\n"));
         s
     }
 }
 
 impl LabModel {
-    fn cargo_model(&mut self) -> &mut CargoModel {
-        self.cargo_model.as_mut().unwrap()
+    /// Add a row for a scenario, and return its id.
+    fn add_cargo_model(&mut self, mut cargo_model: CargoModel) -> usize {
+        let id = self.next_cargo_id;
+        self.next_cargo_id += 1;
+        cargo_model.id = id;
+        self.cargo_models.push(cargo_model);
+        id
+    }
+
+    fn cargo_model(&mut self, id: usize) -> Option<&mut CargoModel> {
+        self.cargo_models.iter_mut().find(|m| m.id == id)
+    }
+
+    fn remove_cargo_model(&mut self, id: usize) {
+        self.cargo_models.retain(|m| m.id != id);
     }
 }
 
+/// Progress of one scenario running cargo, shown as a row under the lab's progress.
+///
+/// The row is removed when the activity finishes or is dropped.
 pub struct CargoActivity {
     lab_view: Arc<nutmeg::View<LabModel>>,
+    /// Identifies this activity's row in the [LabModel].
+    id: usize,
     name: Cow<'static, str>,
     start: Instant,
 }
 
 impl CargoActivity {
     pub fn set_phase(&mut self, phase: &'static str) {
-        self.lab_view
-            .update(|lab_model| lab_model.cargo_model().phase = Some(phase));
+        let id = self.id;
+        self.lab_view.update(|lab_model| {
+            if let Some(cargo_model) = lab_model.cargo_model(id) {
+                cargo_model.phase = Some(phase)
+            }
+        });
     }
 
     /// Mark this activity as interrupted.
     pub fn interrupted(&mut self) {
         // TODO: Unify with outcomes?
-        self.lab_view.update(|lab_model| {
-            lab_model.cargo_model.take();
-        });
+        let id = self.id;
+        self.lab_view
+            .update(|lab_model| lab_model.remove_cargo_model(id));
         self.lab_view.message(format!(
             "{} ... {}",
             self.name,
@@ -135,7 +200,12 @@ impl CargoActivity {
     ///
     /// Prints the log content if appropriate.
     pub fn outcome(self, outcome: &Outcome, options: &Options) -> Result<()> {
+        let id = self.id;
         self.lab_view.update(|model| {
+            model.remove_cargo_model(id);
+            if outcome.scenario.is_mutant() {
+                model.mutants_done += 1;
+            }
             if outcome.mutant_caught() {
                 model.mutants_caught += 1
             } else if outcome.mutant_missed() {
@@ -153,6 +223,9 @@ impl CargoActivity {
 
         let mut s = String::with_capacity(100);
         write!(s, "{} ... {}", self.name, style_outcome(outcome)).unwrap();
+        if outcome.scenario.is_mutant() && !outcome.error_codes().is_empty() {
+            write!(s, " ({})", outcome.error_codes().join(", ")).unwrap();
+        }
         if options.show_times {
             write!(s, " in {}", format_elapsed_millis(self.start)).unwrap();
         }
@@ -166,10 +239,21 @@ impl CargoActivity {
     }
 }
 
+impl Drop for CargoActivity {
+    fn drop(&mut self) {
+        // If the scenario failed with an error, don't leave its row behind.
+        let id = self.id;
+        self.lab_view
+            .update(|lab_model| lab_model.remove_cargo_model(id));
+    }
+}
+
 /// A Nutmeg progress model for running `cargo test` etc.
 ///
 /// It draws the command and some description of what scenario is being tested.
 struct CargoModel {
+    /// Identifies this model within the [LabModel], assigned when it's added.
+    id: usize,
     name: Cow<'static, str>,
     start: Instant,
     phase: Option<&'static str>,
@@ -199,6 +283,7 @@ impl CargoModel {
             Scenario::Mutant(mutant) => style_mutant(mutant).into(),
         };
         CargoModel {
+            id: 0,
             name,
             phase: None,
             start,
@@ -213,25 +298,40 @@ pub struct CopyActivity {
 
 struct CopyModel {
     bytes_copied: u64,
+    files_copied: usize,
+    /// The number of files to be copied, counted before the copy starts.
+    total_files: Option<usize>,
+    /// The directory containing the most recently copied file, relative to the tree.
+    current_dir: String,
     start: Instant,
     name: &'static str,
     succeeded: bool,
     show_times: bool,
+    quiet: bool,
 }
 
 impl nutmeg::Model for CopyModel {
     fn render(&mut self, _width: usize) -> String {
+        let files = match self.total_files {
+            Some(total) => format!("{}/{} files", self.files_copied, total),
+            None => format!("{} files", self.files_copied),
+        };
         format!(
-            "{} ... {} in {}",
+            "{} ... {}, {} in {}, {}/s {}",
             self.name,
+            style(files).cyan(),
             style_mb(self.bytes_copied),
             format_elapsed_secs(self.start),
+            format_mb(self.bytes_per_sec()),
+            self.current_dir,
         )
     }
 
     fn final_message(&mut self) -> String {
         if self.succeeded {
-            if self.show_times {
+            if self.quiet {
+                String::new()
+            } else if self.show_times {
                 format!(
                     "{} ... {} in {}",
                     self.name,
@@ -247,23 +347,44 @@ impl nutmeg::Model for CopyModel {
     }
 }
 
+impl CopyModel {
+    fn bytes_per_sec(&self) -> u64 {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.bytes_copied as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+}
+
 impl CopyActivity {
-    pub fn new(name: &'static str, options: Options) -> CopyActivity {
+    /// Start showing progress of a copy, of `total_files` if the number is known.
+    pub fn new(name: &'static str, total_files: Option<usize>, options: Options) -> CopyActivity {
         let view = nutmeg::View::new(
             CopyModel {
                 name,
                 start: Instant::now(),
                 bytes_copied: 0,
+                files_copied: 0,
+                total_files,
+                current_dir: String::new(),
                 succeeded: false,
                 show_times: options.show_times,
+                quiet: options.quiet_copy,
             },
-            nutmeg_options(),
+            nutmeg_options().progress_enabled(!options.quiet_copy),
         );
         CopyActivity { view }
     }
 
-    pub fn bytes_copied(&mut self, bytes_copied: u64) {
-        self.view.update(|model| model.bytes_copied = bytes_copied);
+    /// Update progress after a file is copied.
+    pub fn copied(&mut self, bytes_copied: u64, files_copied: usize, current_dir: &Path) {
+        self.view.update(|model| {
+            model.bytes_copied = bytes_copied;
+            model.files_copied = files_copied;
+            model.current_dir = current_dir.to_slash_lossy().to_string();
+        });
     }
 
     pub fn succeed(self, bytes_copied: u64) {
@@ -292,15 +413,19 @@ pub fn style_outcome(outcome: &Outcome) -> StyledObject<&'static str> {
             Success => style("ok").green(),
             Failure => style("FAILED").red().bold(),
             Timeout => style("TIMEOUT").red().bold(),
+            Hung => style("HUNG").red().bold(),
         },
         Mutant { .. } => match (outcome.last_phase(), outcome.last_phase_result()) {
             (Phase::Test, Failure) => style("caught").green(),
             (Phase::Test, Success) => style("NOT CAUGHT").red().bold(),
+            (Phase::Fuzz, Failure) => style("caught by fuzzing").green(),
+            (Phase::Fuzz, Success) => style("NOT CAUGHT").red().bold(),
             (Phase::Build, Success) => style("build ok").green(),
             (Phase::Check, Success) => style("check ok").green(),
             (Phase::Build, Failure) => style("build failed").yellow(),
             (Phase::Check, Failure) => style("check failed").yellow(),
             (_, Timeout) => style("TIMEOUT").red().bold(),
+            (_, Hung) => style("HUNG").red().bold(),
         },
     }
 }
@@ -314,7 +439,45 @@ pub fn list_mutants(mutants: &[Mutant], show_diffs: bool) {
     }
 }
 
+/// Style a mutant, followed by the other mutations in it if it's a higher-order mutant.
 fn style_mutant(mutant: &Mutant) -> String {
+    let mut s = style_mutation(mutant);
+    for other in mutant.also() {
+        s.push_str(" + ");
+        s.push_str(&style_mutation(other));
+    }
+    s
+}
+
+fn style_mutation(mutant: &Mutant) -> String {
+    if let Some(location) = mutant.op.location() {
+        return format!(
+            "{}: insert {} {} in {}",
+            mutant.describe_location(),
+            style(mutant.replacement_text()).yellow(),
+            location,
+            style(mutant.function_name()).bright().magenta(),
+        );
+    }
+    if let Some(original) = mutant.op.original() {
+        let replacement = mutant.replacement_text();
+        return if replacement.is_empty() {
+            format!(
+                "{}: delete {} in {}",
+                mutant.describe_location(),
+                style(original).yellow(),
+                style(mutant.function_name()).bright().magenta(),
+            )
+        } else {
+            format!(
+                "{}: replace {} with {} in {}",
+                mutant.describe_location(),
+                original,
+                style(replacement).yellow(),
+                style(mutant.function_name()).bright().magenta(),
+            )
+        };
+    }
     format!(
         "{}: replace {}{}{} with {}",
         mutant.describe_location(),
@@ -333,6 +496,15 @@ pub fn print_error(msg: &str) {
     println!("{}: {}", style("error").bold().red(), msg);
 }
 
+pub fn print_warning(msg: &str) {
+    println!("{}: {}", style("warning").bold().yellow(), msg);
+}
+
+/// Print a warning to stderr, so that it's not mixed into lists printed on stdout.
+pub fn eprint_warning(msg: &str) {
+    eprintln!("{}: {}", style("warning").bold().yellow(), msg);
+}
+
 fn format_elapsed_secs(since: Instant) -> String {
     style(format!("{}s", since.elapsed().as_secs()))
         .cyan()
@@ -350,3 +522,48 @@ fn format_mb(bytes: u64) -> String {
 fn style_mb(bytes: u64) -> StyledObject<String> {
     style(format_mb(bytes)).cyan()
 }
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn activities_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LabActivity>();
+        assert_send_sync::<CargoActivity>();
+    }
+
+    #[test]
+    fn render_a_row_for_each_scenario_in_flight() {
+        let mut model = LabModel {
+            n_mutants: 4,
+            ..LabModel::default()
+        };
+        let log_file = Utf8PathBuf::from("/nonexistent/log");
+        let start = Instant::now();
+        let baseline = model.add_cargo_model(CargoModel::new(
+            &Scenario::Baseline,
+            start,
+            log_file.clone(),
+        ));
+        let source_tree =
+            model.add_cargo_model(CargoModel::new(&Scenario::SourceTree, start, log_file));
+        assert_ne!(baseline, source_tree);
+        model.cargo_model(source_tree).unwrap().phase = Some("build");
+        assert_eq!(
+            nutmeg::Model::render(&mut model, 80),
+            "Unmutated baseline ... 0s\nFreshen source tree (build) ... 0s"
+        );
+
+        model.remove_cargo_model(baseline);
+        assert_eq!(
+            nutmeg::Model::render(&mut model, 80),
+            "Freshen source tree (build) ... 0s"
+        );
+        model.remove_cargo_model(source_tree);
+        assert_eq!(nutmeg::Model::render(&mut model, 80), "");
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2022 Martin Pool
+
+//! Read line coverage data in lcov format, as produced by for example
+//! `cargo llvm-cov --lcov` or `grcov`, and use it to tag mutants as covered or uncovered.
+//!
+//! A mutant is covered if any line of the function body it replaces was executed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use camino::Utf8Path;
+
+use crate::*;
+
+/// How to use coverage data to order or select mutants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageOrder {
+    /// Test mutants in uncovered code first.
+    UncoveredFirst,
+    /// Test mutants in covered code first.
+    CoveredFirst,
+    /// Test only mutants in uncovered code.
+    UncoveredOnly,
+}
+
+impl FromStr for CoverageOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "uncovered-first" => Ok(CoverageOrder::UncoveredFirst),
+            "covered-first" => Ok(CoverageOrder::CoveredFirst),
+            "uncovered-only" => Ok(CoverageOrder::UncoveredOnly),
+            _ => Err(anyhow!(
+                "unknown coverage order {:?}: expected uncovered-first, covered-first, or uncovered-only",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CoverageOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CoverageOrder::UncoveredFirst => "uncovered-first",
+            CoverageOrder::CoveredFirst => "covered-first",
+            CoverageOrder::UncoveredOnly => "uncovered-only",
+        })
+    }
+}
+
+/// Line execution counts for each source file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Map from source file path, with forward slashes, to a map from 1-based line
+    /// number to execution count.
+    files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Read an lcov file.
+    pub fn read_lcov(path: &Utf8Path) -> Result<Coverage> {
+        let text = fs::read_to_string(path).with_context(|| format!("read {}", path))?;
+        Coverage::parse_lcov(&text).with_context(|| format!("parse lcov file {}", path))
+    }
+
+    /// Parse lcov text, using only the `SF` (source file) and `DA` (line data) records.
+    pub fn parse_lcov(text: &str) -> Result<Coverage> {
+        let mut coverage = Coverage::default();
+        let mut current_file: Option<String> = None;
+        for line in text.lines().map(str::trim) {
+            if let Some(path) = line.strip_prefix("SF:") {
+                current_file = Some(path.replace('\\', "/"));
+            } else if let Some(data) = line.strip_prefix("DA:") {
+                let file = current_file
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("DA record outside of a source file: {:?}", line))?;
+                let mut fields = data.split(',');
+                let (line_number, count) = match (fields.next(), fields.next()) {
+                    (Some(line_number), Some(count)) => (line_number, count),
+                    _ => return Err(anyhow!("invalid DA record: {:?}", line)),
+                };
+                let line_number: usize = line_number
+                    .parse()
+                    .with_context(|| format!("invalid line number in {:?}", line))?;
+                // Some tools write counts as floats or with a leading minus for
+                // unreachable lines; anything that isn't a positive count is zero.
+                let count: u64 = count.parse().unwrap_or(0);
+                *coverage
+                    .files
+                    .entry(file.clone())
+                    .or_default()
+                    .entry(line_number)
+                    .or_default() += count;
+            } else if line == "end_of_record" {
+                current_file = None;
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// Return the line counts for a tree-relative path, matching lcov paths
+    /// that are either the same relative path or an absolute path ending in it.
+    fn file_lines(&self, tree_relative: &str) -> Option<&BTreeMap<usize, u64>> {
+        let suffix = format!("/{}", tree_relative);
+        self.files
+            .iter()
+            .find(|(path, _)| *path == tree_relative || path.ends_with(&suffix))
+            .map(|(_, lines)| lines)
+    }
+
+    /// True if any line in the inclusive range was executed.
+    pub fn is_covered(&self, tree_relative: &str, start_line: usize, end_line: usize) -> bool {
+        self.file_lines(tree_relative).is_some_and(|lines| {
+            lines
+                .range(start_line..=end_line)
+                .any(|(_, &count)| count > 0)
+        })
+    }
+
+    /// Set whether each mutant is covered.
+    pub fn tag_mutants(&self, mutants: &mut [Mutant]) {
+        for mutant in mutants {
+            let span = mutant.span();
+            let covered = self.is_covered(
+                &mutant.source_file().tree_relative_slashes(),
+                span.start.line,
+                span.end.line,
+            );
+            mutant.set_covered(covered);
+        }
+    }
+}
+
+/// Reorder or filter tagged mutants according to the coverage order.
+///
+/// The sort is stable, so mutants that are all covered or all uncovered keep their
+/// relative order.
+pub fn apply_coverage_order(mutants: &mut Vec<Mutant>, coverage_order: CoverageOrder) {
+    match coverage_order {
+        CoverageOrder::UncoveredFirst => mutants.sort_by_key(|m| m.covered() == Some(true)),
+        CoverageOrder::CoveredFirst => mutants.sort_by_key(|m| m.covered() != Some(true)),
+        CoverageOrder::UncoveredOnly => mutants.retain(|m| m.covered() != Some(true)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const LCOV: &str = "TN:
+SF:/home/user/src/factorial/src/bin/main.rs
+FN:7,_RNvCs_4main9factorial
+FNDA:1,_RNvCs_4main9factorial
+DA:1,0
+DA:2,0
+DA:5,0
+DA:7,1
+DA:8,1
+DA:9,6
+DA:13,1
+end_of_record
+";
+
+    #[test]
+    fn parse_and_query() {
+        let coverage = Coverage::parse_lcov(LCOV).unwrap();
+        assert!(!coverage.is_covered("src/bin/main.rs", 1, 5));
+        assert!(coverage.is_covered("src/bin/main.rs", 7, 13));
+        assert!(!coverage.is_covered("src/lib.rs", 7, 13));
+        assert!(!coverage.is_covered("bin/main.rs.old", 7, 13));
+    }
+
+    #[test]
+    fn tag_and_order_factorial_mutants() {
+        let source_file = SourceFile::new(
+            Utf8Path::new("testdata/tree/factorial"),
+            "src/bin/main.rs".parse().unwrap(),
+        )
+        .unwrap();
+        let mut mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        Coverage::parse_lcov(LCOV)
+            .unwrap()
+            .tag_mutants(&mut mutants);
+        let names_and_coverage = |mutants: &[Mutant]| {
+            mutants
+                .iter()
+                .map(|m| (m.function_name().to_owned(), m.covered()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names_and_coverage(&mutants),
+            [
+                ("main".to_owned(), Some(false)),
+                ("main".to_owned(), Some(false)),
+                ("main".to_owned(), Some(false)),
+                ("factorial".to_owned(), Some(true)),
+                ("factorial".to_owned(), Some(true)),
+                ("factorial".to_owned(), Some(true)),
+            ]
+        );
+
+        apply_coverage_order(&mut mutants, CoverageOrder::CoveredFirst);
+        assert_eq!(mutants[0].function_name(), "factorial");
+        apply_coverage_order(&mut mutants, CoverageOrder::UncoveredFirst);
+        assert_eq!(mutants[0].function_name(), "main");
+        apply_coverage_order(&mut mutants, CoverageOrder::UncoveredOnly);
+        assert_eq!(
+            names_and_coverage(&mutants),
+            [
+                ("main".to_owned(), Some(false)),
+                ("main".to_owned(), Some(false)),
+                ("main".to_owned(), Some(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_coverage_order() {
+        for s in ["uncovered-first", "covered-first", "uncovered-only"] {
+            assert_eq!(s.parse::<CoverageOrder>().unwrap().to_string(), s);
+        }
+        assert!("sideways".parse::<CoverageOrder>().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2022 Martin Pool
+
+//! Missed mutants as diagnostics in `mutants.out/diagnostics.json`, in the shape of
+//! Language Server Protocol diagnostics, so that editor plugins can show them inline
+//! on the mutated code.
+
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::lab::Scenario;
+use crate::outcome::LabOutcome;
+use crate::textedit::LineColumn;
+use crate::*;
+
+const DIAGNOSTICS_JSON: &str = "diagnostics.json";
+
+/// The LSP `DiagnosticSeverity` for a warning.
+const SEVERITY_WARNING: u32 = 2;
+
+/// A diagnostic about one missed mutant, like an LSP `Diagnostic` with the file it's in.
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    /// The tree-relative path of the file, with forward slashes.
+    file: String,
+    range: Range,
+    severity: u32,
+    source: &'static str,
+    /// The id of the mutant, as used in the allowlist: see [Mutant::id].
+    code: String,
+    message: String,
+}
+
+/// A range in a file, with 0-based lines and characters, and an exclusive end.
+#[derive(Debug, Serialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+#[derive(Debug, Serialize)]
+struct Position {
+    line: usize,
+    character: usize,
+}
+
+impl Diagnostic {
+    fn for_missed_mutant(mutant: &Mutant) -> Diagnostic {
+        let span = mutant.span();
+        // The span's end is inclusive, so the exclusive end is one character later, except
+        // that the span of a whole function body already covers the character after it.
+        let end_column = if mutant.op.replaces_body() {
+            span.end.column - 1
+        } else {
+            span.end.column
+        };
+        Diagnostic {
+            file: mutant.source_file().tree_relative_slashes(),
+            range: Range {
+                start: Position::from(span.start),
+                end: Position {
+                    line: span.end.line - 1,
+                    character: end_column,
+                },
+            },
+            severity: SEVERITY_WARNING,
+            source: NAME,
+            code: mutant.id(),
+            message: format!("missed mutant: {}", mutant.describe_change()),
+        }
+    }
+}
+
+impl From<LineColumn> for Position {
+    fn from(line_column: LineColumn) -> Position {
+        Position {
+            line: line_column.line - 1,
+            character: line_column.column - 1,
+        }
+    }
+}
+
+/// Write `diagnostics.json` into the `mutants.out` directory, describing each mutant
+/// that was missed and not allowed by the allowlist.
+pub fn write_diagnostics(output_dir: &Utf8Path, lab_outcome: &LabOutcome) -> Result<()> {
+    let diagnostics: Vec<Diagnostic> = lab_outcome
+        .outcomes()
+        .iter()
+        .filter(|outcome| outcome.mutant_missed() && !lab_outcome.is_allowed_miss(outcome))
+        .filter_map(|outcome| match &outcome.scenario {
+            Scenario::Mutant(mutant) => Some(Diagnostic::for_missed_mutant(mutant)),
+            _ => None,
+        })
+        .collect();
+    let path = output_dir.join(DIAGNOSTICS_JSON);
+    fs::write(&path, serde_json::to_string_pretty(&diagnostics)?)
+        .with_context(|| format!("write {}", path))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diagnostic_for_missed_mutant() {
+        let source_file = SourceFile::new(
+            Utf8Path::new("testdata/tree/factorial"),
+            "src/bin/main.rs".parse().unwrap(),
+        )
+        .unwrap();
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        let diagnostic = Diagnostic::for_missed_mutant(&mutants[0]);
+        assert_eq!(
+            serde_json::to_value(&diagnostic).unwrap(),
+            json!({
+                "file": "src/bin/main.rs",
+                "range": {
+                    "start": {"line": 0, "character": 10},
+                    "end": {"line": 4, "character": 1},
+                },
+                "severity": 2,
+                "source": "cargo-mutants",
+                "code": "src/bin/main.rs: replace main with ()",
+                "message": "missed mutant: replace main with ()",
+            })
+        );
+        // Mutants within the body end the same way, just after their last character.
+        let range_mutant = mutants
+            .iter()
+            .find(|mutant| mutant.describe_change() == "replace 1..=6 with 1..6 in main")
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(Diagnostic::for_missed_mutant(range_mutant)).unwrap()["range"],
+            json!({
+                "start": {"line": 1, "character": 13},
+                "end": {"line": 1, "character": 18},
+            })
+        );
+    }
+}
@@ -25,3 +25,9 @@ pub const TIMEOUT: i32 = 3;
 
 /// The tests are already failing in an unmutated tree.
 pub const CLEAN_TESTS_FAILED: i32 = 4;
+
+/// The mutation score of some part of the tree is below the minimum set in the config.
+pub const SCORE_BELOW_MINIMUM: i32 = 5;
+
+/// The tests in the unmutated tree ran no tests, so no mutants were tested.
+pub const NO_TESTS: i32 = 6;
@@ -0,0 +1,188 @@
+// Copyright 2022 Martin Pool
+
+//! Warnings about filters that eliminate everything they apply to.
+//!
+//! `--file`, `--mutation-kinds`, `--exclude-tag`, and `exclude_functions` in the
+//! config combine, so
+//! when a run finds fewer mutants than expected it can be hard to tell which of them
+//! is responsible. These warnings say which filter eliminated what.
+
+use std::fmt;
+
+use crate::options::build_path_glob_set;
+use crate::visit::FilterCounts;
+use crate::*;
+
+/// A filter, or combination of filters, that eliminated everything it applied to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FilterWarning {
+    /// A `--file` pattern matched none of the source files.
+    FileUnmatched { pattern: String },
+
+    /// Every mutant in a file selected by `--file` was filtered out.
+    FileAllFiltered { file: String, counts: FilterCounts },
+
+    /// Mutants were found, but every one was filtered out, and no files were named
+    /// by `--file`.
+    NoMutants { counts: FilterCounts },
+}
+
+impl fmt::Display for FilterWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterWarning::FileUnmatched { pattern } => {
+                write!(f, "--file {:?} matched no source files", pattern)
+            }
+            FilterWarning::FileAllFiltered { file, counts } => write!(
+                f,
+                "all {} mutants in {} were filtered out: {}",
+                counts.found(),
+                file,
+                describe_filtered(counts)
+            ),
+            FilterWarning::NoMutants { counts } => write!(
+                f,
+                "all {} mutants found were filtered out: {}",
+                counts.found(),
+                describe_filtered(counts)
+            ),
+        }
+    }
+}
+
+/// Describe how many mutants each filter removed, leaving out filters that removed none.
+fn describe_filtered(counts: &FilterCounts) -> String {
+    let mut parts = Vec::new();
+    if counts.by_kind > 0 {
+        parts.push(format!("{} by --mutation-kinds", counts.by_kind));
+    }
+    if counts.by_function > 0 {
+        parts.push(format!("{} by exclude_functions", counts.by_function));
+    }
+    if counts.by_tag > 0 {
+        parts.push(format!("{} by --exclude-tag", counts.by_tag));
+    }
+    parts.join(", ")
+}
+
+/// Work out warnings about the filters in `options`, given the tree-relative path of
+/// each source file selected, and the counts of its mutants.
+pub fn filter_warnings(
+    options: &Options,
+    file_counts: &[(String, FilterCounts)],
+) -> Result<Vec<FilterWarning>> {
+    let mut warnings = Vec::new();
+    for pattern in &options.file_patterns {
+        let globset = build_path_glob_set([pattern])?.expect("one glob");
+        if !file_counts.iter().any(|(file, _)| globset.is_match(file)) {
+            warnings.push(FilterWarning::FileUnmatched {
+                pattern: pattern.clone(),
+            });
+        }
+    }
+    if !options.file_patterns.is_empty() {
+        for (file, counts) in file_counts {
+            if counts.kept == 0 && counts.found() > 0 {
+                warnings.push(FilterWarning::FileAllFiltered {
+                    file: file.clone(),
+                    counts: *counts,
+                });
+            }
+        }
+    }
+    let mut total = FilterCounts::default();
+    for (_, counts) in file_counts {
+        total.kept += counts.kept;
+        total.by_kind += counts.by_kind;
+        total.by_function += counts.by_function;
+        total.by_tag += counts.by_tag;
+        total.by_fn_limit += counts.by_fn_limit;
+    }
+    // With `--file`, each file whose mutants were all filtered out is already named.
+    if options.file_patterns.is_empty() && total.kept == 0 && total.found() > 0 {
+        warnings.push(FilterWarning::NoMutants { counts: total });
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn counts(kept: usize, by_kind: usize, by_function: usize) -> FilterCounts {
+        FilterCounts {
+            kept,
+            by_kind,
+            by_function,
+            by_tag: 0,
+            by_fn_limit: 0,
+        }
+    }
+
+    #[test]
+    fn no_warnings_without_filters() {
+        let file_counts = [("src/lib.rs".to_owned(), counts(3, 0, 0))];
+        assert_eq!(
+            filter_warnings(&Options::default(), &file_counts).unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn warn_about_files_and_patterns_that_are_filtered_out() {
+        let mut options = Options::default();
+        options.file_patterns = vec!["lib.rs".to_owned(), "src/missing/*.rs".to_owned()];
+        let file_counts = [("src/lib.rs".to_owned(), counts(0, 2, 5))];
+        let warnings: Vec<String> = filter_warnings(&options, &file_counts)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "--file \"src/missing/*.rs\" matched no source files",
+                "all 7 mutants in src/lib.rs were filtered out: 2 by --mutation-kinds, 5 by exclude_functions",
+            ]
+        );
+    }
+
+    #[test]
+    fn warn_when_all_mutants_are_filtered_out() {
+        let file_counts = [
+            ("src/a.rs".to_owned(), counts(0, 0, 4)),
+            ("src/b.rs".to_owned(), counts(0, 1, 0)),
+        ];
+        assert_eq!(
+            filter_warnings(&Options::default(), &file_counts).unwrap(),
+            [FilterWarning::NoMutants {
+                counts: counts(0, 1, 4)
+            }]
+        );
+        let file_counts = [(
+            "src/a.rs".to_owned(),
+            FilterCounts {
+                by_tag: 2,
+                ..counts(0, 0, 1)
+            },
+        )];
+        assert_eq!(
+            filter_warnings(&Options::default(), &file_counts).unwrap()[0].to_string(),
+            "all 3 mutants found were filtered out: 1 by exclude_functions, 2 by --exclude-tag"
+        );
+    }
+
+    #[test]
+    fn only_warn_about_files_named_by_file_patterns() {
+        let file_counts = [
+            ("src/a.rs".to_owned(), counts(0, 0, 4)),
+            ("src/b.rs".to_owned(), counts(1, 3, 0)),
+        ];
+        assert_eq!(
+            filter_warnings(&Options::default(), &file_counts).unwrap(),
+            []
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2022 Martin Pool
+
+//! Support for `--fuzz-time`: after the tests pass, run each
+//! [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz) target for a while, and
+//! count the mutant as caught if any of them finds a crash.
+//!
+//! This shows how effective the fuzz targets are at finding bugs, for projects that
+//! rely on fuzzing more than on tests.
+
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use camino::Utf8Path;
+
+use crate::*;
+
+/// The directory holding the fuzz targets, relative to the package, as laid out by
+/// `cargo fuzz init`.
+const FUZZ_TARGETS_DIR: &str = "fuzz/fuzz_targets";
+
+/// Return the names of the fuzz targets of the package in `package_dir`, in order.
+pub fn find_fuzz_targets(package_dir: &Utf8Path) -> Result<Vec<String>> {
+    let dir = package_dir.join(FUZZ_TARGETS_DIR);
+    let mut targets = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("read {}", dir))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                targets.push(stem.to_owned());
+            }
+        }
+    }
+    if targets.is_empty() {
+        return Err(anyhow!("no fuzz targets found in {}", dir));
+    }
+    targets.sort();
+    Ok(targets)
+}
+
+/// Return the cargo arguments to run one fuzz target for `fuzz_time`, with
+/// additional arguments to `cargo fuzz run`.
+///
+/// libFuzzer only takes whole seconds, so the time is rounded up.
+pub fn fuzz_args(target: &str, fuzz_time: Duration, additional_args: &[String]) -> Vec<String> {
+    let seconds = (fuzz_time.as_secs_f64().ceil() as u64).max(1);
+    let mut args = vec!["fuzz".to_owned(), "run".to_owned(), target.to_owned()];
+    args.extend(additional_args.iter().cloned());
+    args.push("--".to_owned());
+    args.push(format!("-max_total_time={}", seconds));
+    args
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn find_targets_in_fuzz_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let package_dir = Utf8Path::from_path(tmp.path()).unwrap();
+        assert!(find_fuzz_targets(package_dir).is_err());
+        fs::create_dir_all(package_dir.join(FUZZ_TARGETS_DIR)).unwrap();
+        assert_eq!(
+            find_fuzz_targets(package_dir).unwrap_err().to_string(),
+            format!("no fuzz targets found in {}/fuzz/fuzz_targets", package_dir)
+        );
+        for name in ["parse.rs", "decode.rs", "README.md"] {
+            fs::write(package_dir.join(FUZZ_TARGETS_DIR).join(name), "").unwrap();
+        }
+        assert_eq!(find_fuzz_targets(package_dir).unwrap(), ["decode", "parse"]);
+    }
+
+    #[test]
+    fn fuzz_args_round_up_to_seconds() {
+        assert_eq!(
+            fuzz_args("parse", Duration::from_millis(2500), &[]),
+            ["fuzz", "run", "parse", "--", "-max_total_time=3"]
+        );
+        assert_eq!(
+            fuzz_args("parse", Duration::from_secs(10), &["--release".to_owned()]),
+            [
+                "fuzz",
+                "run",
+                "parse",
+                "--release",
+                "--",
+                "-max_total_time=10"
+            ]
+        );
+        assert_eq!(
+            fuzz_args("parse", Duration::ZERO, &[]).last().unwrap(),
+            "-max_total_time=1"
+        );
+    }
+}
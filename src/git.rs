@@ -0,0 +1,66 @@
+// Copyright 2022 Martin Pool
+
+//! Ask git about the history and state of the source tree.
+//!
+//! All of these are best-effort: if the tree is not in git, or git can't be run, they
+//! return nothing rather than an error.
+
+use std::collections::HashSet;
+use std::process::Command;
+
+use camino::Utf8Path;
+
+/// Run git in the tree and return the non-empty lines of its output, or None if it
+/// failed.
+fn git_lines<S: AsRef<str>>(tree_path: &Utf8Path, args: &[S]) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(args.iter().map(AsRef::as_ref))
+        .current_dir(tree_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    )
+}
+
+/// Return the commit id of `HEAD`.
+pub fn head_commit(tree_path: &Utf8Path) -> Option<String> {
+    git_lines(tree_path, &["rev-parse", "HEAD"])?
+        .into_iter()
+        .next()
+}
+
+/// Return the tree-relative paths, with forward slashes, of files changed in the last
+/// `n_commits` commits or not yet committed.
+pub fn recently_changed_files(tree_path: &Utf8Path, n_commits: usize) -> HashSet<String> {
+    let mut files = HashSet::new();
+    files.extend(
+        git_lines(
+            tree_path,
+            &[
+                "log".to_owned(),
+                format!("-n{}", n_commits),
+                "--name-only".to_owned(),
+                "--format=".to_owned(),
+                "--relative".to_owned(),
+            ],
+        )
+        .unwrap_or_default(),
+    );
+    files.extend(changed_files_since(tree_path, "HEAD").unwrap_or_default());
+    files
+}
+
+/// Return the tree-relative paths of files that differ between a commit and the
+/// working tree, including uncommitted changes, or None if git can't tell.
+pub fn changed_files_since(tree_path: &Utf8Path, commit: &str) -> Option<HashSet<String>> {
+    git_lines(tree_path, &["diff", "--name-only", "--relative", commit])
+        .map(|lines| lines.into_iter().collect())
+}
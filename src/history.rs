@@ -0,0 +1,181 @@
+// Copyright 2022 Martin Pool
+
+//! An append-only history of runs, kept in `mutants.out/history.jsonl`.
+//!
+//! Each line is a JSON object describing one run: when it ran, the overall score,
+//! and the outcome of each mutant keyed by its [Mutant::id]. The history is carried
+//! forward when `mutants.out` is rotated, so that it accumulates across runs.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::outcome::{LabOutcome, RunScore, SummaryOutcome};
+use crate::*;
+
+/// The name of the history file within `mutants.out`.
+pub const HISTORY_JSONL: &str = "history.jsonl";
+
+/// A record of one run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub cargo_mutants_version: String,
+    pub start_time: String,
+    pub end_time: String,
+    /// The git commit of the source tree, if known.
+    #[serde(default)]
+    pub git_commit: Option<String>,
+    /// The toolchain chosen by a `rust-toolchain.toml` file, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_toolchain: Option<String>,
+    pub run_score: RunScore,
+    pub mutants: Vec<MutantHistory>,
+}
+
+/// The outcome of one mutant within a [HistoryEntry].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MutantHistory {
+    pub id: String,
+    pub summary: SummaryOutcome,
+}
+
+impl HistoryEntry {
+    /// Describe a run that started at `start_time` and finished at `end_time`.
+    pub fn new(
+        lab_outcome: &LabOutcome,
+        start_time: &DateTime<Utc>,
+        end_time: &DateTime<Utc>,
+        git_commit: Option<String>,
+        rust_toolchain: Option<String>,
+    ) -> HistoryEntry {
+        let mutants = lab_outcome
+            .outcomes()
+            .iter()
+            .filter_map(|outcome| match &outcome.scenario {
+                Scenario::Mutant(mutant) => Some(MutantHistory {
+                    id: mutant.id(),
+                    summary: outcome.summary(),
+                }),
+                Scenario::SourceTree | Scenario::Baseline => None,
+            })
+            .collect();
+        HistoryEntry {
+            cargo_mutants_version: crate::VERSION.to_owned(),
+            start_time: start_time.to_rfc3339(),
+            end_time: end_time.to_rfc3339(),
+            git_commit,
+            rust_toolchain,
+            run_score: lab_outcome.run_score(),
+            mutants,
+        }
+    }
+
+    /// Add the outcomes of mutants that were not tested in this run but carried
+    /// forward from earlier runs, and recalculate the score to include them.
+    pub fn add_carried_forward(&mut self, mutants: Vec<MutantHistory>, lab_outcome: &LabOutcome) {
+        self.mutants.extend(mutants);
+        self.run_score =
+            lab_outcome.score_summaries(self.mutants.iter().map(|m| (&m.id, m.summary.clone())));
+    }
+}
+
+/// Return the most recent recorded outcome of each mutant id.
+pub fn latest_summaries(history: &[HistoryEntry]) -> HashMap<String, SummaryOutcome> {
+    let mut summaries = HashMap::new();
+    for entry in history.iter().rev() {
+        for mutant in &entry.mutants {
+            summaries
+                .entry(mutant.id.clone())
+                .or_insert_with(|| mutant.summary.clone());
+        }
+    }
+    summaries
+}
+
+/// Append one entry to the history file in the given `mutants.out` directory.
+pub fn append_history(output_dir: &Utf8Path, entry: &HistoryEntry) -> Result<()> {
+    let path = output_dir.join(HISTORY_JSONL);
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("append to {}", path))
+}
+
+/// Read all the entries from the history file in a `mutants.out` directory, oldest first.
+///
+/// A missing history file is treated as empty. Lines that can't be parsed, perhaps
+/// because a previous run was interrupted while writing them, are skipped.
+pub fn read_history(output_dir: &Utf8Path) -> Result<Vec<HistoryEntry>> {
+    let path = output_dir.join(HISTORY_JSONL);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("read {}", path))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn entry(id: &str, summary: SummaryOutcome) -> HistoryEntry {
+        HistoryEntry {
+            cargo_mutants_version: "0.0.0".to_owned(),
+            start_time: "2022-07-01T00:00:00+00:00".to_owned(),
+            end_time: "2022-07-01T00:01:00+00:00".to_owned(),
+            git_commit: None,
+            rust_toolchain: None,
+            run_score: RunScore::from_summaries([&summary]),
+            mutants: vec![MutantHistory {
+                id: id.to_owned(),
+                summary,
+            }],
+        }
+    }
+
+    #[test]
+    fn missing_history_is_empty() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(tmp.path()).unwrap();
+        assert_eq!(read_history(path).unwrap(), []);
+    }
+
+    #[test]
+    fn append_and_read_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(tmp.path()).unwrap();
+        let first = entry(
+            "src/lib.rs: replace a with ()",
+            SummaryOutcome::MissedMutant,
+        );
+        let second = entry(
+            "src/lib.rs: replace a with ()",
+            SummaryOutcome::CaughtMutant,
+        );
+        append_history(path, &first).unwrap();
+        append_history(path, &second).unwrap();
+        // A truncated line from an interrupted run is ignored.
+        OpenOptions::new()
+            .append(true)
+            .open(path.join(HISTORY_JSONL))
+            .unwrap()
+            .write_all(b"{\"cargo_mutants_version\": \"0.")
+            .unwrap();
+        assert_eq!(read_history(path).unwrap(), [first, second]);
+    }
+}
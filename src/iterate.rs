@@ -0,0 +1,128 @@
+// Copyright 2022 Martin Pool
+
+//! Support for `--iterate`: test only the mutants that were missed last time or
+//! that have not been tested before, and carry forward the other outcomes.
+//!
+//! This makes it fast to repeatedly add tests and then check that the missed mutants
+//! are now caught.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::git;
+use crate::history::{latest_summaries, HistoryEntry, MutantHistory};
+use crate::outcome::SummaryOutcome;
+use crate::*;
+
+/// What is known from previous runs about which mutants need to be tested again.
+#[derive(Debug)]
+pub struct Iteration {
+    /// The most recent outcome of each mutant id.
+    previous: HashMap<String, SummaryOutcome>,
+    /// Tree-relative paths of files changed since the last run, whose mutants are
+    /// always retested.
+    changed_files: HashSet<String>,
+}
+
+impl Iteration {
+    /// Learn what was tested from the history and, if the last run recorded its
+    /// git commit, which files have changed since then.
+    ///
+    /// If the files changed since the last run can't be determined, because the
+    /// tree is not in git or the last run didn't record a commit, then only the mutant
+    /// ids are used.
+    pub fn new(source_tree: &SourceTree, history: &[HistoryEntry]) -> Iteration {
+        let changed_files = history
+            .last()
+            .and_then(|entry| entry.git_commit.as_deref())
+            .and_then(|commit| git::changed_files_since(source_tree.path(), commit))
+            .unwrap_or_default();
+        Iteration {
+            previous: latest_summaries(history),
+            changed_files,
+        }
+    }
+
+    /// Remove mutants that don't need to be tested again, because their last outcome
+    /// was something other than missed, and their file has not changed since.
+    ///
+    /// Returns the previous outcomes of the removed mutants, to be carried forward.
+    pub fn remove_already_tested(&self, mutants: &mut Vec<Mutant>) -> Vec<MutantHistory> {
+        let mut carried_forward = Vec::new();
+        mutants.retain(|mutant| {
+            if self
+                .changed_files
+                .contains(&mutant.source_file().tree_relative_slashes())
+            {
+                return true;
+            }
+            let id = mutant.id();
+            match self.previous.get(&id) {
+                None | Some(SummaryOutcome::MissedMutant) => true,
+                Some(summary) => {
+                    carried_forward.push(MutantHistory {
+                        id,
+                        summary: summary.clone(),
+                    });
+                    false
+                }
+            }
+        });
+        carried_forward
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use camino::Utf8Path;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn only_missed_and_new_mutants_are_kept() {
+        let source_tree = SourceTree::new(Utf8Path::new("testdata/tree/well_tested")).unwrap();
+        let mut mutants = source_tree.mutants(&Options::default()).unwrap();
+        let ids: Vec<String> = mutants.iter().map(Mutant::id).collect();
+        let iteration = Iteration {
+            previous: vec![
+                (ids[0].clone(), SummaryOutcome::CaughtMutant),
+                (ids[1].clone(), SummaryOutcome::MissedMutant),
+                (ids[2].clone(), SummaryOutcome::Unviable),
+            ]
+            .into_iter()
+            .collect(),
+            changed_files: HashSet::new(),
+        };
+        let carried_forward = iteration.remove_already_tested(&mut mutants);
+        assert_eq!(
+            carried_forward,
+            [
+                MutantHistory {
+                    id: ids[0].clone(),
+                    summary: SummaryOutcome::CaughtMutant
+                },
+                MutantHistory {
+                    id: ids[2].clone(),
+                    summary: SummaryOutcome::Unviable
+                }
+            ]
+        );
+        assert_eq!(mutants.len(), ids.len() - 2);
+        assert_eq!(mutants[0].id(), ids[1]);
+    }
+
+    #[test]
+    fn mutants_in_changed_files_are_kept() {
+        let source_tree = SourceTree::new(Utf8Path::new("testdata/tree/factorial")).unwrap();
+        let mut mutants = source_tree.mutants(&Options::default()).unwrap();
+        let iteration = Iteration {
+            previous: mutants
+                .iter()
+                .map(|m| (m.id(), SummaryOutcome::CaughtMutant))
+                .collect(),
+            changed_files: std::iter::once("src/bin/main.rs".to_owned()).collect(),
+        };
+        assert_eq!(iteration.remove_already_tested(&mut mutants), []);
+        assert_eq!(mutants.len(), 6);
+    }
+}
@@ -2,10 +2,8 @@
 
 //! Successively apply mutations to the source code and run cargo to check, build, and test them.
 
-use std::cmp::max;
 use std::fmt;
-use std::fs::File;
-use std::io::BufWriter;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
@@ -13,11 +11,28 @@ use camino::Utf8Path;
 use rand::prelude::*;
 use serde::Serialize;
 
-use crate::cargo::run_cargo;
-use crate::console::{self, LabActivity};
-use crate::mutate::Mutant;
+use crate::allowlist::Allowlist;
+use crate::baseline_failure::describe_failure;
+use crate::cargo::{
+    clean_incremental_state, generate_lockfile, infrastructure_problem,
+    looks_like_corrupt_build_cache, needs_nightly_toolchain, run_cargo, CargoEnv, Timeouts,
+    MESSAGE_FORMAT_JSON,
+};
+use crate::console::{self, CargoActivity, LabActivity};
+use crate::coverage::apply_coverage_order;
+use crate::fuzz::{find_fuzz_targets, fuzz_args};
+use crate::git;
+use crate::history::{append_history, read_history, HistoryEntry};
+use crate::iterate::Iteration;
+use crate::mutate::{group_by_file, Mutant};
 use crate::outcome::{LabOutcome, Outcome, Phase};
-use crate::output::OutputDir;
+use crate::output::{BaselineTimes, OutputDir};
+use crate::plan::{self, mutants_in_budget};
+use crate::prioritize::Priorities;
+use crate::relocation::find_relocation_hazards;
+use crate::suggest::TestFiles;
+use crate::toolchain::RustToolchain;
+use crate::user_cache::UserCache;
 use crate::*;
 
 /// What type of build, check, or test was this?
@@ -55,6 +70,31 @@ impl Scenario {
     }
 }
 
+/// Whether to test the unmutated baseline before the mutants.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineStrategy {
+    /// Build and test the baseline, and save its times for later runs.
+    #[default]
+    Run,
+    /// Don't test the baseline, and set the timeout from the times saved by a previous run.
+    Skip,
+}
+
+impl FromStr for BaselineStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "run" => Ok(BaselineStrategy::Run),
+            "skip" => Ok(BaselineStrategy::Skip),
+            _ => Err(anyhow!(
+                "unknown baseline strategy {:?}: expected run or skip",
+                s
+            )),
+        }
+    }
+}
+
 /// Run all possible mutation experiments.
 ///
 /// Before testing the mutants, the lab checks that the source tree passes its tests with no
@@ -63,52 +103,172 @@ pub fn test_unmutated_then_all_mutants(
     source_tree: &SourceTree,
     options: &Options,
 ) -> Result<LabOutcome> {
+    test_mutants_keeping_build_dir(source_tree, options, &mut None)
+}
+
+/// Run mutation experiments like [test_unmutated_then_all_mutants], in the build directory
+/// in `kept_build_dir` if there is one, and otherwise in a new one, which is put there
+/// after the mutants are tested so that later runs can reuse it.
+///
+/// A kept build directory already passed the baseline, so it's not run again: the
+/// baseline times saved by the earlier run are used instead.
+pub fn test_mutants_keeping_build_dir(
+    source_tree: &SourceTree,
+    options: &Options,
+    kept_build_dir: &mut Option<BuildDir>,
+) -> Result<LabOutcome> {
+    let start_time = options.report_time();
     let mut options: Options = options.clone();
-    let mut lab_outcome = LabOutcome::default();
+    let mut lab_outcome =
+        LabOutcome::new(Allowlist::read(source_tree.path())?, options.timeout_policy);
     let output_in_dir = if let Some(o) = &options.output_in_dir {
         o.as_path()
     } else {
         source_tree.path()
     };
-    let output_dir = OutputDir::new(output_in_dir)?;
-    let mut lab_activity = LabActivity::new(&options);
+    let output_dir = OutputDir::new(output_in_dir, &options)?;
+    let project_cache = UserCache::find().map(|cache| cache.project_dir(source_tree.path()));
+    if let Some(project_cache) = &project_cache {
+        if let Err(err) = output_dir.carry_forward_from(project_cache) {
+            console::print_warning(&format!("failed to read the user cache: {:#}", err));
+        }
+    }
+    let lab_activity = LabActivity::new(&options);
+    // A container has its own toolchain.
+    if !options.ignore_rust_toolchain && options.container_image.is_none() {
+        options.rust_toolchain = RustToolchain::find(source_tree.path())?;
+        if let Some(toolchain) = &options.rust_toolchain {
+            lab_activity.message(&format!(
+                "Using toolchain {} from {}\n",
+                toolchain.channel, toolchain.file
+            ));
+        }
+    }
+    if options.fuzz_time.is_some() {
+        options.fuzz_targets = find_fuzz_targets(source_tree.path())?;
+    }
+    options.package = source_tree.package_in_workspace()?;
+    let phases = options.phases();
 
     if options.build_source {
         let outcome =
-            check_and_build_source_tree(source_tree, &output_dir, &options, &mut lab_activity)?;
+            check_and_build_source_tree(source_tree, &output_dir, &options, &lab_activity)?;
         lab_outcome.add(&outcome);
+        output_dir.append_outcome(&outcome)?;
         if !outcome.success() {
-            console::print_error(&format!(
-                "cargo {} failed in source tree, not continuing",
-                outcome.last_phase(),
-            ));
+            explain_unmutated_failure(
+                &format!(
+                    "cargo {} failed in source tree, not continuing",
+                    outcome.last_phase(),
+                ),
+                &outcome,
+                &output_dir,
+            );
+            output_dir.write_outcomes_json(&lab_outcome)?;
             return Ok(lab_outcome); // TODO: Maybe should be Err?
         }
     }
 
-    let build_dir = BuildDir::new(source_tree, &options)?;
-    let build_dir_path = build_dir.path();
-    let outcome = {
-        run_cargo_phases(
-            build_dir_path,
-            &output_dir,
-            &options,
-            &Scenario::Baseline,
-            Phase::ALL,
-            &mut lab_activity,
-        )
-    }?;
-    lab_outcome.add(&outcome);
-    if !outcome.success() {
-        console::print_error(&format!(
-            "cargo {} failed in an unmutated tree, so no mutants were tested",
-            outcome.last_phase(),
+    let mut mutants = source_tree.mutants(&options)?;
+    if mutants.is_empty() {
+        // Stop before copying the tree and running the baseline: a run that tests
+        // nothing should not look like a success.
+        return Err(anyhow!(
+            "No mutants found: check the --file filters, and whether every function is \
+            skipped or has nothing to mutate"
         ));
-        return Ok(lab_outcome); // TODO: Maybe should be Err?
     }
-    if !options.has_test_timeout() {
-        if let Some(baseline_duration) = outcome.test_duration() {
-            let auto_timeout = max(Duration::from_secs(20), baseline_duration.mul_f32(5.0));
+
+    let build_dir = match kept_build_dir.take() {
+        Some(build_dir) => {
+            options.baseline = BaselineStrategy::Skip;
+            build_dir
+        }
+        None => BuildDir::new(source_tree, &options)?,
+    };
+    if options.dry_run {
+        dry_run_mutants(&mutants, &build_dir, &output_dir)?;
+        return Ok(lab_outcome);
+    }
+    let build_dir_path = build_dir.path();
+    if !build_dir.has_lockfile() {
+        generate_lockfile(build_dir_path, build_dir.cargo_env(), &options)?;
+    }
+    let baseline_times = match options.baseline {
+        BaselineStrategy::Run => {
+            let outcome = run_cargo_phases(
+                build_dir_path,
+                build_dir.cargo_env(),
+                &output_dir,
+                &options,
+                &Scenario::Baseline,
+                &phases,
+                &lab_activity,
+            )?;
+            lab_outcome.add(&outcome);
+            output_dir.append_outcome(&outcome)?;
+            if !outcome.success() {
+                explain_unmutated_failure(
+                    &format!(
+                        "cargo {} failed in an unmutated tree, so no mutants were tested",
+                        outcome.last_phase(),
+                    ),
+                    &outcome,
+                    &output_dir,
+                );
+                if outcome.last_phase() == Phase::Test {
+                    explain_relocation_failure(
+                        source_tree,
+                        &build_dir,
+                        &output_dir,
+                        &options,
+                        &lab_activity,
+                    )?;
+                }
+                output_dir.write_outcomes_json(&lab_outcome)?;
+                return Ok(lab_outcome); // TODO: Maybe should be Err?
+            }
+            if outcome.tests_run()? == Some(0) {
+                console::print_error(
+                    "cargo test ran no tests in the unmutated tree, so no mutants were tested",
+                );
+                lab_outcome.set_baseline_ran_no_tests();
+                output_dir.write_outcomes_json(&lab_outcome)?;
+                return Ok(lab_outcome);
+            }
+            options.baseline_failed_tests = outcome.ignored_failed_tests().to_vec();
+            warn_of_baseline_failures(&options);
+            let baseline_times = BaselineTimes::new(&outcome, options.test_tool.min_auto_timeout());
+            output_dir.write_baseline_times(&baseline_times)?;
+            save_to_project_cache(&output_dir, project_cache.as_deref());
+            Some(baseline_times)
+        }
+        BaselineStrategy::Skip => {
+            let baseline_times = output_dir.read_baseline_times()?;
+            if baseline_times.is_none()
+                && !options.has_test_timeout()
+                && options.test_cpu_timeout.is_none()
+            {
+                console::print_warning(
+                    "no baseline times were saved by a previous run, so tests have no timeout",
+                );
+            }
+            if options.allow_baseline_failures {
+                if let Some(baseline_times) = &baseline_times {
+                    options.baseline_failed_tests = baseline_times.failed_tests.clone();
+                    warn_of_baseline_failures(&options);
+                }
+            }
+            baseline_times
+        }
+    };
+    // A limit on CPU time replaces the automatic timeout, because on a loaded machine
+    // the wall-clock time of the tests varies much more than their CPU time.
+    if !options.has_test_timeout() && options.test_cpu_timeout.is_none() {
+        if let Some(auto_timeout) = baseline_times
+            .as_ref()
+            .and_then(BaselineTimes::auto_timeout)
+        {
             options.set_test_timeout(auto_timeout);
             if options.show_times {
                 println!(
@@ -118,16 +278,83 @@ pub fn test_unmutated_then_all_mutants(
             }
         }
     }
+    if options.baseline_only {
+        println!(
+            "Baseline succeeded; not testing {} {} because of --baseline-only",
+            mutants.len(),
+            if mutants.len() == 1 {
+                "mutant"
+            } else {
+                "mutants"
+            }
+        );
+        output_dir.write_outcomes_json(&lab_outcome)?;
+        return Ok(lab_outcome);
+    }
 
-    let mut mutants = source_tree.mutants(&options)?;
+    let history = read_history(output_dir.path())?;
+    let carried_forward = if options.iterate {
+        let carried_forward =
+            Iteration::new(source_tree, &history).remove_already_tested(&mut mutants);
+        if !carried_forward.is_empty() {
+            println!(
+                "Skipping {} {} already tested in previous runs",
+                carried_forward.len(),
+                if carried_forward.len() == 1 {
+                    "mutant"
+                } else {
+                    "mutants"
+                }
+            );
+        }
+        carried_forward
+    } else {
+        Vec::new()
+    };
     if options.shuffle {
         mutants.shuffle(&mut rand::thread_rng());
     }
+    if options.prioritize {
+        Priorities::new(source_tree, &history).sort(&mut mutants);
+    }
+    if !options.shuffle {
+        group_by_file(&mut mutants);
+    }
+    if let Some(coverage_order) = options.coverage_order {
+        // Applied last so that it takes precedence, but the sort is stable so
+        // the earlier ordering is kept within covered and uncovered mutants.
+        apply_coverage_order(&mut mutants, coverage_order);
+    }
+    if options.max_mutants.is_some() || options.max_duration.is_some() {
+        let per_mutant = match &baseline_times {
+            Some(times) => estimate_run_time(times, 1),
+            None => {
+                if options.max_duration.is_some() {
+                    console::print_warning(
+                        "--max-duration is ignored because there are no baseline times to \
+                        estimate how long each mutant will take",
+                    );
+                }
+                Duration::ZERO
+            }
+        };
+        let count = mutants_in_budget(
+            mutants.len(),
+            options.max_mutants,
+            options.max_duration,
+            per_mutant,
+        );
+        if count < mutants.len() {
+            println!(
+                "Selected {} of {} mutants to fit --max-mutants or --max-duration",
+                count,
+                mutants.len()
+            );
+            plan::select(&mut mutants, count, &Priorities::new(source_tree, &history));
+        }
+    }
 
-    serde_json::to_writer_pretty(
-        BufWriter::new(File::create(output_dir.path().join("mutants.json"))?),
-        &mutants,
-    )?;
+    output_dir.write_mutants_json(&mutants)?;
     println!(
         "Found {} {} to test",
         mutants.len(),
@@ -137,35 +364,301 @@ pub fn test_unmutated_then_all_mutants(
             "mutants"
         }
     );
-    if mutants.is_empty() {
-        return Err(anyhow!("No mutants found"));
+    let estimate = baseline_times
+        .as_ref()
+        .map(|times| estimate_run_time(times, mutants.len()));
+    if let Some(estimate) = estimate {
+        if options.show_times {
+            println!(
+                "Estimated time to test all mutants: {}",
+                format_estimate(estimate)
+            );
+        }
+        if options.confirm_long_runs
+            && estimate > CONFIRM_RUN_TIME
+            && !lab_activity.confirm(&format!(
+                "Testing {} mutants may take {}. Continue?",
+                mutants.len(),
+                format_estimate(estimate)
+            ))?
+        {
+            return Err(anyhow!("Cancelled: no mutants were tested"));
+        }
     }
 
     lab_activity.start_mutants(mutants.len());
+    let mut rebuild_times = RebuildTimes::default();
+    let mut previous_file: Option<String> = None;
     for mutant in mutants {
+        let file = mutant.source_file().tree_relative_slashes();
         let scenario = Scenario::Mutant(mutant.clone());
         let outcome = mutant.with_mutation_applied(&build_dir, || {
             run_cargo_phases(
                 build_dir_path,
+                build_dir.cargo_env(),
                 &output_dir,
                 &options,
                 &scenario,
-                Phase::ALL,
-                &mut lab_activity,
+                &phases,
+                &lab_activity,
             )
         })?;
         lab_outcome.add(&outcome);
-
-        // Rewrite outcomes.json every time, so we can watch it and so it's not
-        // lost if the program stops or is interrupted.
-        serde_json::to_writer_pretty(
-            BufWriter::new(File::create(output_dir.path().join("outcomes.json"))?),
-            &lab_outcome,
-        )?;
+        output_dir.append_outcome(&outcome)?;
+        if let Some(previous_file) = &previous_file {
+            rebuild_times.add(*previous_file == file, outcome.build_duration());
+        }
+        previous_file = Some(file);
+    }
+    output_dir.write_outcomes_json(&lab_outcome)?;
+    if !options.check_only {
+        if let Some(message) = lab_outcome.describe_kind_scores() {
+            lab_activity.message(&message);
+        }
+        if let Some(message) = lab_outcome.describe_tag_scores() {
+            lab_activity.message(&message);
+        }
+    }
+    for message in lab_outcome.scores_below_minimum(&options.score_thresholds) {
+        lab_activity.message(&format!("{}\n", message));
+    }
+    if options.slowest > 0 {
+        if let Some(message) = lab_outcome.describe_slowest(options.slowest) {
+            lab_activity.message(&message);
+        }
+    }
+    if options.show_times && !options.shuffle {
+        if let Some(message) = rebuild_times.describe_saving() {
+            lab_activity.message(&message);
+        }
     }
+    let end_time = options.report_time();
+    let mut history_entry = HistoryEntry::new(
+        &lab_outcome,
+        &start_time,
+        &end_time,
+        git::head_commit(source_tree.path()),
+        options.rust_toolchain.as_ref().map(|t| t.channel.clone()),
+    );
+    history_entry.add_carried_forward(carried_forward, &lab_outcome);
+    append_history(output_dir.path(), &history_entry)?;
+    save_to_project_cache(&output_dir, project_cache.as_deref());
+    badge::write_badges(output_dir.path(), &history_entry.run_score)?;
+    summary::write_summary(
+        output_dir.path(),
+        &history_entry.run_score,
+        &lab_outcome,
+        (end_time - start_time).to_std().unwrap_or_default(),
+        options.show_times,
+        &TestFiles::find(source_tree.path()),
+    )?;
+    diagnostics::write_diagnostics(output_dir.path(), &lab_outcome)?;
+    test_efficiency::write_test_efficiency(output_dir.path(), &lab_outcome)?;
+    *kept_build_dir = Some(build_dir);
     Ok(lab_outcome)
 }
 
+/// Report that cargo failed in the unmutated tree, with the compiler errors or failed
+/// tests that caused it, also written to `mutants.out/baseline-failure.txt`.
+///
+/// If the tree needs a nightly toolchain, say so, because the reason can be buried
+/// deep in cargo's output.
+fn explain_unmutated_failure(error: &str, outcome: &Outcome, output_dir: &OutputDir) {
+    console::print_error(error);
+    match describe_failure(outcome) {
+        Ok(description) => {
+            println!("{}", description);
+            let report = format!(
+                "{}\n\n{}\nThe full output is in {}.\n",
+                error,
+                description,
+                outcome.log_file()
+            );
+            if let Err(err) = output_dir.write_baseline_failure(&report) {
+                console::print_warning(&format!("{:#}", err));
+            }
+        }
+        Err(err) => console::print_warning(&format!(
+            "failed to read why the unmutated tree failed: {:#}",
+            err
+        )),
+    }
+    if outcome
+        .get_log_content()
+        .is_ok_and(|log| needs_nightly_toolchain(&log))
+    {
+        console::print_error(
+            "the tree uses features that need a nightly toolchain, such as -Z flags in \
+            .cargo/config.toml, but cargo is using a stable or beta toolchain: choose a \
+            nightly toolchain in rust-toolchain.toml, or run `cargo +nightly mutants`",
+        );
+    }
+}
+
+/// When the tests failed in the unmutated copy of the tree, run them in the source
+/// tree, and if they pass there, explain that they probably depend on where the tree
+/// is, and point at the code that looks responsible.
+fn explain_relocation_failure(
+    source_tree: &SourceTree,
+    build_dir: &BuildDir,
+    output_dir: &OutputDir,
+    options: &Options,
+    lab_activity: &LabActivity,
+) -> Result<()> {
+    let outcome = run_cargo_phases(
+        source_tree.path(),
+        &CargoEnv::default(),
+        output_dir,
+        options,
+        &Scenario::SourceTree,
+        &[Phase::Test],
+        lab_activity,
+    )?;
+    if !outcome.success() {
+        return Ok(());
+    }
+    console::print_error(
+        "the tests pass in the source tree but fail in a copy of it, so they probably \
+        depend on where the tree is: use paths relative to env!(\"CARGO_MANIFEST_DIR\") \
+        rather than absolute paths, and keep test fixtures inside the package and out of \
+        copy_exclude",
+    );
+    match find_relocation_hazards(source_tree.path(), build_dir.path()) {
+        Ok(hazards) => {
+            for hazard in hazards {
+                println!("  {}", hazard);
+            }
+        }
+        Err(err) => console::print_warning(&format!(
+            "failed to look for paths that depend on the source tree: {:#}",
+            err
+        )),
+    }
+    Ok(())
+}
+
+/// Say which tests failed in the baseline and are ignored, if any.
+fn warn_of_baseline_failures(options: &Options) {
+    let failed = &options.baseline_failed_tests;
+    if !failed.is_empty() {
+        console::print_warning(&format!(
+            "{} {} failed in the unmutated tree, and only other tests failing will catch \
+            mutants, because of --allow-baseline-failures: {}",
+            failed.len(),
+            if failed.len() == 1 { "test" } else { "tests" },
+            failed.join(", ")
+        ));
+    }
+}
+
+/// Save the files carried forward between runs into the project's directory in the
+/// user cache, if there is one, warning rather than failing if they can't be saved.
+fn save_to_project_cache(output_dir: &OutputDir, project_cache: Option<&Utf8Path>) {
+    if let Some(project_cache) = project_cache {
+        if let Err(err) = output_dir.save_carried_forward(project_cache) {
+            console::print_warning(&format!("failed to save to the user cache: {:#}", err));
+        }
+    }
+}
+
+/// Apply and revert each mutant in the build directory, and write `mutants.json` and
+/// a log of each planned scenario, without running cargo.
+///
+/// This quickly checks the filters and config, and that the mutants can be applied.
+fn dry_run_mutants(mutants: &[Mutant], build_dir: &BuildDir, output_dir: &OutputDir) -> Result<()> {
+    output_dir.write_mutants_json(mutants)?;
+    for mutant in mutants {
+        let scenario = Scenario::Mutant(mutant.clone());
+        mutant.with_mutation_applied(build_dir, || {
+            let mut log_file = output_dir.create_log(&scenario)?;
+            log_file.message(&scenario.to_string());
+            log_file.message(&mutant.diff());
+            Ok(())
+        })?;
+    }
+    println!(
+        "Dry run: applied and reverted {} {} without running cargo",
+        mutants.len(),
+        if mutants.len() == 1 {
+            "mutant"
+        } else {
+            "mutants"
+        }
+    );
+    Ok(())
+}
+
+/// Runs that are estimated to take longer than this ask for confirmation before
+/// testing any mutants, unless `--yes` is given.
+const CONFIRM_RUN_TIME: Duration = Duration::from_secs(60 * 60);
+
+/// Estimate the time to test `n_mutants`, from the times of the baseline.
+///
+/// Each mutant is built incrementally on top of the baseline build. When the `target`
+/// directory is copied from the source tree the baseline build is also incremental,
+/// so its time is representative. With `--no-copy-target` the baseline also builds
+/// the dependencies, and the estimate will be too high.
+fn estimate_run_time(baseline: &BaselineTimes, n_mutants: usize) -> Duration {
+    (baseline.build_duration() + baseline.test_duration().unwrap_or_default()) * n_mutants as u32
+}
+
+/// Format an estimated duration, rounded to the precision that's meaningful for an
+/// estimate.
+fn format_estimate(estimate: Duration) -> String {
+    let secs = estimate.as_secs();
+    if secs < 60 {
+        "less than a minute".to_owned()
+    } else if secs < 60 * 60 {
+        format!("about {}m", secs / 60)
+    } else {
+        format!("about {}h {}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// Durations of the check and build phases for each mutant, split by whether the previous
+/// mutant was in the same file.
+///
+/// Mutants in the same file as the previous mutant should generally rebuild faster, because
+/// cargo can reuse more of the incremental build.
+#[derive(Default)]
+struct RebuildTimes {
+    same_file: Vec<Duration>,
+    changed_file: Vec<Duration>,
+}
+
+impl RebuildTimes {
+    fn add(&mut self, same_file: bool, duration: Duration) {
+        if same_file {
+            self.same_file.push(duration)
+        } else {
+            self.changed_file.push(duration)
+        }
+    }
+
+    fn mean(durations: &[Duration]) -> Option<Duration> {
+        if durations.is_empty() {
+            None
+        } else {
+            Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+        }
+    }
+
+    /// Describe the estimated time saved by testing mutants in the same file consecutively,
+    /// if there is enough data to estimate it.
+    fn describe_saving(&self) -> Option<String> {
+        let same = RebuildTimes::mean(&self.same_file)?;
+        let changed = RebuildTimes::mean(&self.changed_file)?;
+        let saved = changed.saturating_sub(same) * self.same_file.len() as u32;
+        Some(format!(
+            "Grouping mutants by file saved about {:.1}s of rebuild time \
+            ({:.3}s per mutant after one in the same file, {:.3}s after changing file)\n",
+            saved.as_secs_f64(),
+            same.as_secs_f64(),
+            changed.as_secs_f64(),
+        ))
+    }
+}
+
 /// Successively run cargo check, build, test, and return the overall outcome in a build
 /// directory, which might have a mutation applied or not.
 ///
@@ -177,11 +670,12 @@ pub fn test_unmutated_then_all_mutants(
 /// Return the outcome of the last phase run.
 fn run_cargo_phases(
     in_dir: &Utf8Path,
+    cargo_env: &CargoEnv,
     output_dir: &OutputDir,
     options: &Options,
     scenario: &Scenario,
     phases: &[Phase],
-    lab_activity: &mut LabActivity,
+    lab_activity: &LabActivity,
 ) -> Result<Outcome> {
     let mut log_file = output_dir.create_log(scenario)?;
     log_file.message(&scenario.to_string());
@@ -190,42 +684,215 @@ fn run_cargo_phases(
     }
     let mut cargo_activity = lab_activity.start_scenario(scenario, log_file.path().to_owned());
 
-    let mut outcome = Outcome::new(&log_file, scenario.clone());
-    for &phase in phases {
-        let phase_start = Instant::now();
-        cargo_activity.set_phase(phase.name());
-        let cargo_args = match phase {
-            Phase::Check => vec!["check", "--tests"],
-            Phase::Build => vec!["build", "--tests"],
-            Phase::Test => std::iter::once("test")
-                .chain(
-                    options
-                        .additional_cargo_test_args
-                        .iter()
-                        .map(String::as_str),
-                )
-                .collect(),
+    let log_start = log_file.size()?;
+    let mut outcome = run_phases_once(
+        in_dir,
+        cargo_env,
+        options,
+        scenario,
+        phases,
+        &mut log_file,
+        &mut cargo_activity,
+    )?;
+    if outcome.last_phase_result() == CargoResult::Failure {
+        if let Some(problem) = infrastructure_problem(&log_file.read_from(log_start)?) {
+            // Retry once, rather than blaming the mutant, or the tree, for a problem with
+            // the machine.
+            log_file.message(&format!(
+                "failure looks like {}: retrying the scenario\n",
+                problem
+            ));
+            outcome = run_phases_once(
+                in_dir,
+                cargo_env,
+                options,
+                scenario,
+                phases,
+                &mut log_file,
+                &mut cargo_activity,
+            )?;
+            outcome.set_retried_because(problem);
+        }
+    }
+    if options.allow_baseline_failures {
+        let known_failures = match scenario {
+            Scenario::Baseline => outcome.failed_tests()?,
+            Scenario::Mutant(_) => options.baseline_failed_tests.clone(),
+            Scenario::SourceTree => Vec::new(),
         };
-        let timeout = match phase {
-            Phase::Test => options.test_timeout(),
-            _ => Duration::MAX,
+        if outcome.ignore_known_failures(&known_failures)? {
+            log_file.message(&format!(
+                "ignoring tests that failed in the baseline, because of \
+                --allow-baseline-failures: {}",
+                outcome.ignored_failed_tests().join(", ")
+            ));
+        }
+    }
+    cargo_activity.outcome(&outcome, options)?;
+    Ok(outcome)
+}
+
+/// Run the phases of a scenario once, until one fails, writing to the scenario's log.
+fn run_phases_once(
+    in_dir: &Utf8Path,
+    cargo_env: &CargoEnv,
+    options: &Options,
+    scenario: &Scenario,
+    phases: &[Phase],
+    log_file: &mut LogFile,
+    cargo_activity: &mut CargoActivity,
+) -> Result<Outcome> {
+    let mut outcome = Outcome::new(log_file, scenario.clone());
+    'phases: for &phase in phases {
+        cargo_activity.set_phase(phase.name());
+        let phase_env = match scenario {
+            Scenario::Mutant(_) => cargo_env.with_vars(options.mutant_env(phase)),
+            _ => cargo_env.clone(),
         };
-        let cargo_result = run_cargo(
-            &cargo_args,
-            in_dir,
-            &mut cargo_activity,
-            &mut log_file,
-            timeout,
-        )?;
-        outcome.add_phase_result(phase, phase_start.elapsed(), cargo_result);
-        if (phase == Phase::Check && options.check_only) || !cargo_result.success() {
+        // The fuzz phase runs each target in turn, recording a result for each.
+        for cargo_args in phase_cargo_args(phase, options) {
+            let cargo_args: Vec<&str> = cargo_args.iter().map(String::as_str).collect();
+            let phase_start = Instant::now();
+            let log_start = log_file.size()?;
+            let timeouts = Timeouts {
+                total: match (phase, scenario) {
+                    (Phase::Test, Scenario::Mutant(mutant)) => options.mutant_test_timeout(mutant),
+                    (Phase::Test, _) => options.test_timeout(),
+                    (Phase::Fuzz, _) => options.fuzz_timeout(),
+                    _ => Duration::MAX,
+                },
+                // The compiler can be quiet for a long time while it works, but tests
+                // usually print as each one finishes.
+                idle: options.idle_timeout.filter(|_| phase == Phase::Test),
+                cpu: match (phase, scenario) {
+                    (Phase::Test, Scenario::Mutant(mutant)) => {
+                        options.mutant_test_cpu_timeout(mutant)
+                    }
+                    (Phase::Test, _) => options.test_cpu_timeout,
+                    _ => None,
+                },
+            };
+            let mut cargo_run = run_cargo(
+                &cargo_args,
+                in_dir,
+                &phase_env,
+                cargo_activity,
+                log_file,
+                timeouts,
+                options,
+            )?;
+            // Only the scratch target directory is cleaned: the source tree's belongs
+            // to the developer.
+            if let Some(target_dir) = cargo_env
+                .target_dir()
+                .filter(|_| !phase.is_test() && cargo_run.result == CargoResult::Failure)
+            {
+                if looks_like_corrupt_build_cache(&log_file.read_from(log_start)?) {
+                    // Retry once, so that a corrupt cache doesn't make every later mutant
+                    // unviable too.
+                    log_file.message(
+                        "build failure looks like a corrupt build cache: \
+                        removing incremental state and retrying\n",
+                    );
+                    clean_incremental_state(target_dir)?;
+                    cargo_run = run_cargo(
+                        &cargo_args,
+                        in_dir,
+                        &phase_env,
+                        cargo_activity,
+                        log_file,
+                        timeouts,
+                        options,
+                    )?;
+                }
+            }
+            outcome.add_phase_result(
+                phase,
+                phase_start.elapsed(),
+                cargo_run.result,
+                cargo_run.exit_code,
+                log_start..log_file.size()?,
+            );
+            if let Some(build_messages) = cargo_run.build_messages {
+                outcome.set_build_messages(build_messages);
+            }
+            if let Some(peak_rss) = cargo_run.peak_rss {
+                outcome.set_peak_rss(peak_rss);
+            }
+            outcome.set_command(cargo_run.command);
+            if !cargo_run.result.success() {
+                break 'phases;
+            }
+        }
+        if phase == Phase::Check && options.check_only {
             break;
         }
     }
-    cargo_activity.outcome(&outcome, options)?;
     Ok(outcome)
 }
 
+/// Return the arguments to cargo for each command in a phase, including the
+/// additional arguments from the options.
+fn phase_cargo_args(phase: Phase, options: &Options) -> Vec<Vec<String>> {
+    let additional_args = options.additional_cargo_args(phase);
+    let mut base_args: Vec<String> = match phase {
+        Phase::Build if options.test_tool.build_args().is_some() => {
+            let mut args: Vec<String> = options
+                .test_tool
+                .build_args()
+                .unwrap_or_default()
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect();
+            if let Some(package) = &options.package {
+                args.extend(["--package".to_owned(), package.clone()]);
+            }
+            args
+        }
+        Phase::Check | Phase::Build => {
+            let mut args = vec![
+                phase.name().to_owned(),
+                "--tests".to_owned(),
+                MESSAGE_FORMAT_JSON.to_owned(),
+                // The compiler's messages are in the JSON, so cargo's progress lines
+                // are just noise. The test phase isn't quiet, because that would also
+                // hide the name of each test that passed.
+                "--quiet".to_owned(),
+            ];
+            // Other members of the workspace aren't changed by the mutants, so there's
+            // no need to check them every time.
+            if let Some(package) = &options.package {
+                args.extend(["--package".to_owned(), package.clone()]);
+            }
+            args
+        }
+        Phase::Test => {
+            let mut args: Vec<String> = options
+                .test_tool
+                .test_args()
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect();
+            // Otherwise a test binary with a known failure would stop the tests in any
+            // later binaries from running at all.
+            if options.allow_baseline_failures {
+                args.push("--no-fail-fast".to_owned());
+            }
+            args
+        }
+        Phase::Fuzz => {
+            let fuzz_time = options.fuzz_time.unwrap_or_default();
+            return options
+                .fuzz_targets
+                .iter()
+                .map(|target| fuzz_args(target, fuzz_time, additional_args))
+                .collect();
+        }
+    };
+    base_args.extend(additional_args.iter().cloned());
+    vec![base_args]
+}
+
 /// Build tests in the original source tree.
 ///
 /// This brings the source `target` directory basically up to date with any changes to the source,
@@ -235,7 +902,7 @@ fn check_and_build_source_tree(
     source_tree: &SourceTree,
     output_dir: &OutputDir,
     options: &Options,
-    lab_activity: &mut LabActivity,
+    lab_activity: &LabActivity,
 ) -> Result<Outcome> {
     let phases: &'static [Phase] = if options.check_only {
         &[Phase::Check]
@@ -244,6 +911,7 @@ fn check_and_build_source_tree(
     };
     run_cargo_phases(
         source_tree.path(),
+        &CargoEnv::default(),
         output_dir,
         options,
         &Scenario::SourceTree,
@@ -251,3 +919,34 @@ fn check_and_build_source_tree(
         lab_activity,
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_estimate() {
+        use super::format_estimate as format;
+
+        assert_eq!(format(Duration::from_secs(12)), "less than a minute");
+        assert_eq!(format(Duration::from_secs(125)), "about 2m");
+        assert_eq!(
+            format(Duration::from_secs(3 * 3600 + 7 * 60 + 5)),
+            "about 3h 7m"
+        );
+    }
+
+    #[test]
+    fn rebuild_saving_needs_both_kinds_of_rebuild() {
+        let mut rebuild_times = RebuildTimes::default();
+        rebuild_times.add(true, Duration::from_secs(1));
+        assert_eq!(rebuild_times.describe_saving(), None);
+        rebuild_times.add(true, Duration::from_secs(3));
+        rebuild_times.add(false, Duration::from_secs(5));
+        assert_eq!(
+            rebuild_times.describe_saving().unwrap(),
+            "Grouping mutants by file saved about 6.0s of rebuild time \
+            (2.000s per mutant after one in the same file, 5.000s after changing file)\n"
+        );
+    }
+}
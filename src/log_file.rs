@@ -2,9 +2,13 @@
 
 //! Manage per-scenario log files, which contain the output from cargo
 //! and test cases, mixed with commentary from cargo-mutants.
+//!
+//! Each line of output from cargo is prefixed by the time since cargo started and
+//! the stream it was written to, like `[   0.513 stderr] error[E0308]: ...`.
 
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
+use std::time::Duration;
 
 use anyhow::Context;
 use camino::{Utf8Path, Utf8PathBuf};
@@ -14,6 +18,22 @@ use crate::Result;
 /// Text inserted in log files to make important sections more visible.
 pub const LOG_MARKER: &str = "***";
 
+/// Which stream of a subprocess a line of output was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    fn name(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
 /// A log file for execution of a single scenario.
 #[derive(Debug)]
 pub struct LogFile {
@@ -33,8 +53,9 @@ impl LogFile {
                 format!("{}_{:03}.log", basename, i)
             };
             let path = log_dir.join(t);
+            // Append, so that messages go after output written by cargo through other handles.
             match OpenOptions::new()
-                .write(true)
+                .append(true)
                 .read(true)
                 .create_new(true)
                 .open(&path)
@@ -50,12 +71,24 @@ impl LogFile {
         );
     }
 
-    /// Open the log file to append more content.
-    pub fn open_append(&self) -> Result<File> {
-        OpenOptions::new()
-            .append(true)
-            .open(&self.path)
-            .with_context(|| format!("open {} for append", self.path))
+    /// Append text to the log, as if it had been written by cargo.
+    pub fn append(&mut self, text: &str) {
+        self.write_to
+            .write_all(text.as_bytes())
+            .expect("append to log");
+    }
+
+    /// Append a line of output from a subprocess, prefixed by the time since it started,
+    /// and the stream it was written to.
+    pub fn output_line(&mut self, elapsed: Duration, stream: OutputStream, text: &str) {
+        write!(
+            self.write_to,
+            "\n[{:8.3} {}] {}",
+            elapsed.as_secs_f64(),
+            stream.name(),
+            text
+        )
+        .expect("write output to log");
     }
 
     /// Write a message, with a marker. Ignore errors.
@@ -66,6 +99,21 @@ impl LogFile {
     pub fn path(&self) -> &Utf8Path {
         &self.path
     }
+
+    /// Return the text written to the log after the byte offset `start`, replacing any
+    /// invalid UTF-8.
+    pub fn read_from(&self, start: u64) -> Result<String> {
+        let content = fs::read(&self.path).with_context(|| format!("read {}", self.path))?;
+        let start = (start as usize).min(content.len());
+        Ok(String::from_utf8_lossy(&content[start..]).into_owned())
+    }
+
+    /// Return the number of bytes written to the log so far.
+    pub fn size(&self) -> Result<u64> {
+        Ok(fs::metadata(&self.path)
+            .with_context(|| format!("get size of {}", self.path))?
+            .len())
+    }
 }
 
 /// Return the last non-empty line from a file, if it has any content.
@@ -77,18 +125,29 @@ pub fn last_line(path: &Utf8Path) -> Result<String> {
     // and this should only be called a few times per second...
     Ok(fs::read_to_string(path)?
         .lines()
-        .filter(|s| !s.trim().is_empty())
-        .last()
+        .map(output_text)
+        .rfind(|s| !s.trim().is_empty())
         .unwrap_or_default()
         .to_owned())
 }
 
+/// Return the text of a line in the log, without the prefix added to lines of output
+/// from subprocesses by [LogFile::output_line].
+pub fn output_text(line: &str) -> &str {
+    line.strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(prefix, _)| prefix.ends_with(" stdout") || prefix.ends_with(" stderr"))
+        .map_or(line, |(_, text)| text)
+}
+
+/// Make a file name that's valid on every platform, by replacing anything other
+/// than ASCII letters, digits, `.`, `-` and `_`.
 fn clean_filename(s: &str) -> String {
     let s = s.replace('/', "__");
     s.chars()
         .map(|c| match c {
-            '\\' | ' ' | ':' | '<' | '>' | '?' | '*' | '|' | '"' => '_',
-            c => c,
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
         })
         .collect::<String>()
 }
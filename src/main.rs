@@ -2,23 +2,47 @@
 
 //! `cargo-mutants`: Find inadequately-tested code that can be removed without any tests failing.
 
+mod allowlist;
+mod annotate;
+mod badge;
+mod baseline_failure;
 mod build_dir;
 mod cargo;
+mod compare;
+mod config;
 mod console;
+mod coverage;
+mod diagnostics;
 mod exit_code;
+mod filter_warning;
+mod fuzz;
+mod git;
+mod history;
 mod interrupt;
+mod iterate;
 mod lab;
 mod log_file;
+mod manifest;
 mod mutate;
 mod options;
 mod outcome;
 mod output;
 mod path;
+mod plan;
+mod prioritize;
+mod relocation;
+mod scratch_registry;
 mod source;
+mod suggest;
+mod summary;
+mod test_efficiency;
 mod textedit;
+mod toml_subset;
+mod toolchain;
+mod user_cache;
 mod visit;
+mod watch;
 
-use std::convert::TryFrom;
 use std::env;
 use std::io;
 
@@ -26,22 +50,27 @@ use std::process::exit;
 
 use anyhow::Result;
 use argh::FromArgs;
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 #[allow(unused)]
 use path_slash::PathExt;
 
 // Imports of public names from this crate.
-use crate::build_dir::BuildDir;
-use crate::cargo::CargoResult;
+use crate::build_dir::{BuildDir, SymlinkPolicy};
+use crate::cargo::{CargoResult, TestTool};
+use crate::config::Config;
+use crate::coverage::{apply_coverage_order, Coverage, CoverageOrder};
 use crate::interrupt::check_interrupted;
-use crate::lab::Scenario;
+use crate::lab::{BaselineStrategy, Scenario};
 use crate::log_file::{last_line, LogFile};
-use crate::mutate::{Mutant, MutationOp};
+use crate::mutate::{ItemTag, Mutant, MutationOp};
 use crate::options::Options;
-use crate::outcome::{Outcome, Phase};
+use crate::outcome::{Outcome, Phase, TimeoutPolicy};
 use crate::path::Utf8PathSlashes;
 use crate::source::{SourceFile, SourceTree};
+use crate::user_cache::UserCache;
+#[cfg(test)]
 use crate::visit::discover_mutants;
+use crate::visit::{discover_skipped, SkippedFunction};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const NAME: &str = env!("CARGO_PKG_NAME");
@@ -55,6 +84,26 @@ struct Args {
     #[argh(switch)]
     all_logs: bool,
 
+    /// carry on if some tests fail in the unmutated tree, and count mutants as caught only by other tests failing.
+    #[argh(switch)]
+    allow_baseline_failures: bool,
+
+    /// test the unmutated tree first (run, the default), or skip it and reuse the timeout saved by a previous run (skip).
+    #[argh(option)]
+    baseline: Option<BaselineStrategy>,
+
+    /// build and test the unmutated tree in the scratch directory, then stop.
+    #[argh(switch)]
+    baseline_only: bool,
+
+    /// build with lints capped at allow, so that mutants that only cause warnings aren't unviable in trees that deny warnings.
+    #[argh(switch)]
+    cap_lints: bool,
+
+    /// cargo-compatible command to build and test with instead of cargo, such as `cross`.
+    #[argh(option)]
+    cargo_command: Option<String>,
+
     /// print mutants that were caught by tests.
     #[argh(switch, short = 'v')]
     caught: bool,
@@ -63,20 +112,81 @@ struct Args {
     #[argh(switch)]
     check: bool,
 
+    /// with --container-image, the docker-compatible command used to run containers.
+    #[argh(option, default = r#""docker".to_owned()"#)]
+    container_cli: String,
+
+    /// run cargo in a container from this image, with the build directory mounted.
+    #[argh(option)]
+    container_image: Option<String>,
+
+    /// lcov file of line coverage from the tests, used to tag mutants as covered or uncovered.
+    #[argh(option)]
+    coverage: Option<Utf8PathBuf>,
+
+    /// with --coverage, test mutants in uncovered-first, covered-first, or uncovered-only order.
+    #[argh(option)]
+    coverage_order: Option<CoverageOrder>,
+
+    /// build in the scratch directory without RUSTFLAGS and similar variables from the environment, and with CARGO_INCREMENTAL and SOURCE_DATE_EPOCH fixed.
+    #[argh(switch)]
+    deterministic_env: bool,
+
     /// show the mutation diffs.
     #[argh(switch)]
     diff: bool,
 
+    /// glob for paths not to copy to the scratch directory; globs containing slash match the
+    /// entire path within the tree.
+    #[argh(option)]
+    copy_exclude: Vec<String>,
+
+    /// copy version control directories like .git to the scratch directory, for tests that run git.
+    #[argh(switch)]
+    copy_vcs: bool,
+
+    /// copy the tree and apply and revert each mutant, but don't run cargo.
+    #[argh(switch)]
+    dry_run: bool,
+
     /// rust crate directory to examine.
     #[argh(option, short = 'd', default = r#"Utf8PathBuf::from(".")"#)]
     dir: Utf8PathBuf,
 
+    /// don't mutate code in items marked #[deprecated] (with "deprecated") or #[doc(hidden)] (with "hidden").
+    #[argh(option)]
+    exclude_tag: Vec<ItemTag>,
+
     /// glob for files to examine; with no glob, all files are examined; globs containing
     /// slash match the entire path.
     #[argh(option, short = 'f')]
     file: Vec<String>,
 
-    /// output json (only for --list).
+    /// after the tests pass, run each cargo-fuzz target for this many seconds, and count a crash as catching the mutant.
+    #[argh(option)]
+    fuzz_time: Option<f64>,
+
+    /// stop the tests of a mutant as hung if they write no output for this many seconds.
+    #[argh(option)]
+    idle_timeout: Option<f64>,
+
+    /// build with the toolchain that launched cargo-mutants, ignoring any rust-toolchain.toml.
+    #[argh(switch)]
+    ignore_rust_toolchain: bool,
+
+    /// also mutate code in vendor/ and third_party/ directories, and generated code.
+    #[argh(switch)]
+    include_generated: bool,
+
+    /// give cargo commands run by the tests their own target directory and environment, for crates whose tests run cargo.
+    #[argh(switch)]
+    isolate_nested_cargo: bool,
+
+    /// test only mutants that were missed in the last run or are new, carrying forward other outcomes.
+    #[argh(switch)]
+    iterate: bool,
+
+    /// output json (only for --list, --list-files, and --list-skipped).
     #[argh(switch)]
     json: bool,
 
@@ -88,18 +198,62 @@ struct Args {
     #[argh(switch)]
     list_files: bool,
 
+    /// list functions that won't be mutated and why, don't run anything.
+    #[argh(switch)]
+    list_skipped: bool,
+
+    /// stop after about this many seconds, testing the mutants most worth testing first.
+    #[argh(option)]
+    max_duration: Option<f64>,
+
+    /// test at most this many mutants, choosing those most worth testing.
+    #[argh(option)]
+    max_mutants: Option<usize>,
+
+    /// test at most this many mutants in each function, chosen evenly through it.
+    #[argh(option)]
+    max_mutants_per_fn: Option<usize>,
+
+    /// set an environment variable, as NAME=VALUE, when testing mutants but not the baseline.
+    #[argh(option)]
+    mutant_env: Vec<String>,
+
+    /// comma-separated kinds of mutation to generate, for example "fn-value,loop,early-return".
+    #[argh(option)]
+    mutation_kinds: Option<String>,
+
     /// don't copy the /target directory, and don't build the source tree first.
     #[argh(switch)]
     no_copy_target: bool,
 
-    /// don't print times or tree sizes, to make output deterministic.
+    /// don't print times or tree sizes, and zero times in reports, to make output deterministic.
     #[argh(switch)]
     no_times: bool,
 
-    /// create mutants.out within this directory.
+    /// experimental: 2 to combine pairs of mutants in the same module into second-order mutants.
+    #[argh(option)]
+    order: Option<usize>,
+
+    /// create mutants.out within this directory, such as target/mutants, instead of the crate directory.
     #[argh(option, short = 'o')]
     output: Option<Utf8PathBuf>,
 
+    /// test the mutants most likely to be missed first.
+    #[argh(switch)]
+    prioritize: bool,
+
+    /// set PROPTEST_CASES to this when testing mutants, to run fewer cases than in the baseline.
+    #[argh(option)]
+    proptest_cases: Option<u32>,
+
+    /// set QUICKCHECK_TESTS to this when testing mutants, to run fewer cases than in the baseline.
+    #[argh(option)]
+    quickcheck_tests: Option<u32>,
+
+    /// don't show progress while copying the source tree.
+    #[argh(switch)]
+    quiet_copy: bool,
+
     /// run mutants in random order.
     #[argh(switch)]
     shuffle: bool,
@@ -108,10 +262,30 @@ struct Args {
     #[argh(switch)]
     no_shuffle: bool,
 
+    /// at the end, list this many of the slowest mutants and source files.
+    #[argh(option, default = "0")]
+    slowest: usize,
+
+    /// how to copy symlinks to the scratch directory: follow, preserve (the default), or skip.
+    #[argh(option)]
+    symlinks: Option<SymlinkPolicy>,
+
+    /// tool to run the tests: cargo (the default), miri to run `cargo miri test`, or nextest to run `cargo nextest run`.
+    #[argh(option)]
+    test_tool: Option<TestTool>,
+
     /// maximum run time for all cargo commands, in seconds.
     #[argh(option, short = 't')]
     timeout: Option<f64>,
 
+    /// maximum CPU time for the tests of each mutant, in seconds, counting all their processes, instead of the automatic timeout (Linux only).
+    #[argh(option)]
+    timeout_cpu_seconds: Option<f64>,
+
+    /// count mutants that time out as caught, missed, or inconclusive (the default).
+    #[argh(option)]
+    timeout_counts_as: Option<TimeoutPolicy>,
+
     /// print mutations that failed to check or build.
     #[argh(switch, short = 'V')]
     unviable: bool,
@@ -120,6 +294,17 @@ struct Args {
     #[argh(switch)]
     version: bool,
 
+    /// after testing, watch the tree for changes and test again the mutants in changed files.
+    #[argh(switch)]
+    watch: bool,
+
+    /// don't ask for confirmation before runs that are estimated to take a long time.
+    #[argh(switch, short = 'y')]
+    yes: bool,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+
     // The following option captures all the remaining non-option args, to
     // send to cargo.
     /// pass remaining arguments to cargo test after all options and after `--`.
@@ -127,6 +312,85 @@ struct Args {
     cargo_test_args: Vec<String>,
 }
 
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Annotate(AnnotateArgs),
+    Clean(CleanArgs),
+    Compare(CompareArgs),
+    MinimalTests(MinimalTestsArgs),
+}
+
+/// Write copies of the source files marking each line with its mutants and their outcomes.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "annotate")]
+struct AnnotateArgs {
+    /// rust crate directory containing the source files.
+    #[argh(option, short = 'd', default = r#"Utf8PathBuf::from(".")"#)]
+    dir: Utf8PathBuf,
+
+    /// mutants.out directory, or outcomes file within it, from the run [default: mutants.out in the crate directory, or in the output directory from the config].
+    #[argh(option)]
+    outcomes: Option<Utf8PathBuf>,
+
+    /// directory to write the annotated files into [default: annotated within the mutants.out directory].
+    #[argh(option, short = 'o')]
+    output: Option<Utf8PathBuf>,
+}
+
+/// Remove mutants.out, mutants.out.old, the project's data in the user cache, and scratch directories left by runs that crashed.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "clean")]
+struct CleanArgs {
+    /// rust crate directory containing mutants.out.
+    #[argh(option, short = 'd', default = r#"Utf8PathBuf::from(".")"#)]
+    dir: Utf8PathBuf,
+
+    /// directory containing mutants.out, if it was created elsewhere with --output [default: the output directory from the config, or the crate directory].
+    #[argh(option, short = 'o')]
+    output: Option<Utf8PathBuf>,
+}
+
+/// Compare the outcomes of two runs and report regressions.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "compare")]
+struct CompareArgs {
+    /// output json.
+    #[argh(switch)]
+    json: bool,
+
+    /// outcomes.json, or the mutants.out directory, from the earlier run.
+    #[argh(positional)]
+    old: Utf8PathBuf,
+
+    /// outcomes.json, or the mutants.out directory, from the later run.
+    #[argh(positional)]
+    new: Utf8PathBuf,
+}
+
+/// Choose a small set of tests that still kills every mutant caught by an earlier run.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "minimal-tests")]
+struct MinimalTestsArgs {
+    /// output json.
+    #[argh(switch)]
+    json: bool,
+
+    /// the mutants.out directory from the run.
+    #[argh(positional, default = r#"Utf8PathBuf::from("mutants.out")"#)]
+    dir: Utf8PathBuf,
+}
+
+/// Return the directory containing `mutants.out` for the crate in `dir`: the `output`
+/// directory from its config, if it has one, or else `dir` itself.
+fn configured_output_in_dir(dir: &Utf8Path) -> Utf8PathBuf {
+    SourceTree::new(dir)
+        .and_then(|source_tree| Config::read_tree_config(&source_tree))
+        .ok()
+        .and_then(|config| config.output)
+        .map_or_else(|| dir.to_owned(), |output| dir.join(output))
+}
+
 fn main() -> Result<()> {
     if let Some(subcommand) = env::args().nth(1) {
         if subcommand != "mutants" {
@@ -138,26 +402,89 @@ fn main() -> Result<()> {
         exit(exit_code::USAGE);
     }
     let args: Args = argh::cargo_from_env();
-    let options = Options::try_from(&args)?;
+    if let Some(Command::Annotate(annotate_args)) = &args.command {
+        let outcomes = annotate_args
+            .outcomes
+            .clone()
+            .unwrap_or_else(|| configured_output_in_dir(&annotate_args.dir).join("mutants.out"));
+        for path in annotate::annotate_tree(
+            &annotate_args.dir,
+            &outcomes,
+            annotate_args.output.as_deref(),
+        )? {
+            println!("{}", path);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Clean(clean_args)) = &args.command {
+        let output_in_dir = clean_args
+            .output
+            .clone()
+            .unwrap_or_else(|| configured_output_in_dir(&clean_args.dir));
+        for path in output::remove_output_dirs(&output_in_dir)? {
+            println!("Removed {}", path);
+        }
+        if let Some(user_cache) = UserCache::find() {
+            if let Some(path) = user_cache.remove_project_dir(&clean_args.dir)? {
+                println!("Removed {}", path);
+            }
+            for path in user_cache.scratch_registry().remove_stale()? {
+                println!("Removed stale scratch directory {}", path);
+            }
+        }
+        return Ok(());
+    }
+    if let Some(Command::Compare(compare_args)) = &args.command {
+        let comparison = compare::compare_outcome_files(&compare_args.old, &compare_args.new)?;
+        if compare_args.json {
+            serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &comparison)?;
+        } else {
+            print!("{}", comparison);
+        }
+        if comparison.has_regressions() {
+            exit(exit_code::FOUND_PROBLEMS);
+        }
+        return Ok(());
+    }
+    if let Some(Command::MinimalTests(minimal_tests_args)) = &args.command {
+        let minimal = test_efficiency::minimal_test_set(&minimal_tests_args.dir)?;
+        if minimal_tests_args.json {
+            serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &minimal)?;
+        } else {
+            print!("{}", minimal);
+        }
+        return Ok(());
+    }
     let source_tree = SourceTree::new(&args.dir)?;
+    let config = Config::read_tree_config(&source_tree)?;
+    let options = Options::new(&args, &config)?;
     interrupt::install_handler();
     if args.version {
         println!("{} {}", NAME, VERSION);
     } else if args.list_files {
-        let files: Vec<String> = source_tree
-            .source_paths(&options)?
-            .into_iter()
-            .map(|trp| trp.to_string())
-            .collect();
         if args.json {
+            let files = source_tree.list_files(&options)?;
             serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &files)?;
         } else {
-            for f in files {
+            for f in source_tree.source_paths(&options)? {
                 println!("{}", f);
             }
         }
+    } else if args.list_skipped {
+        let skipped = source_tree.skipped_functions(&options)?;
+        if args.json {
+            serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &skipped)?;
+        } else {
+            for skipped_function in skipped {
+                println!("{}", skipped_function);
+            }
+        }
     } else if args.list {
-        let mutants = source_tree.mutants(&options)?;
+        let mut mutants = source_tree.mutants(&options)?;
+        if let Some(coverage_order) = options.coverage_order {
+            // When testing, this is applied by the lab after the mutants are ordered.
+            apply_coverage_order(&mut mutants, coverage_order);
+        }
         if args.json {
             if args.diff {
                 eprintln!("--list --diff --json is not (yet) supported");
@@ -167,11 +494,13 @@ fn main() -> Result<()> {
         } else {
             console::list_mutants(&mutants, args.diff);
         }
+    } else if args.watch {
+        watch::watch(&source_tree, &options)?;
     } else {
         let lab_outcome = lab::test_unmutated_then_all_mutants(&source_tree, &options)?;
         // TODO: Perhaps print a text summary of how many were tested and whether they were all
         // caught?
-        exit(lab_outcome.exit_code());
+        exit(lab_outcome.exit_code(&options));
     }
     Ok(())
 }
@@ -0,0 +1,217 @@
+// Copyright 2022 Martin Pool
+
+//! Settings from `Cargo.toml` that aren't reported by `cargo metadata`: the
+//! `include` and `exclude` lists of a package, and the `exclude` list of a
+//! workspace.
+//!
+//! The package's lists decide which source files are mutated: they only control
+//! what cargo publishes, so everything is still copied to the scratch directory.
+//! Directories excluded from the workspace aren't copied.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::toml_subset::{expect, parse_string_array};
+use crate::*;
+
+/// Files that cargo always includes in a package.
+const ALWAYS_INCLUDED: &[&str] = &["Cargo.toml", "Cargo.lock"];
+
+/// The files of a package, according to `package.include` and `package.exclude`.
+#[derive(Debug, Default)]
+pub struct PackageFiles {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl PackageFiles {
+    /// Read the `include` and `exclude` fields from the manifest in a package directory.
+    pub fn read(package_dir: &Utf8Path) -> Result<PackageFiles> {
+        let manifest_path = package_dir.join("Cargo.toml");
+        let manifest = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("read {}", manifest_path))?;
+        PackageFiles::from_manifest(&manifest)
+            .with_context(|| format!("parse {}", manifest_path.to_slash_path()))
+    }
+
+    fn from_manifest(manifest: &str) -> Result<PackageFiles> {
+        let glob_set = |key| {
+            read_array(manifest, "package", key)?
+                .map(|patterns| build_glob_set(&patterns))
+                .transpose()
+        };
+        Ok(PackageFiles {
+            include: glob_set("include")?,
+            exclude: glob_set("exclude")?,
+        })
+    }
+
+    /// True if cargo would leave this path, relative to the package directory, out of
+    /// the package.
+    ///
+    /// Directories are only excluded by `exclude`, because `include` might match
+    /// files inside them.
+    pub fn excludes(&self, rel_path: &Path, is_dir: bool) -> bool {
+        let matches_self_or_parent =
+            |globset: &GlobSet| rel_path.ancestors().any(|p| globset.is_match(p));
+        if ALWAYS_INCLUDED
+            .iter()
+            .any(|name| rel_path == Path::new(name))
+        {
+            false
+        } else if self.exclude.as_ref().is_some_and(matches_self_or_parent) {
+            true
+        } else if is_dir {
+            false
+        } else {
+            self.include
+                .as_ref()
+                .is_some_and(|include| !matches_self_or_parent(include))
+        }
+    }
+}
+
+/// Read the `workspace.exclude` paths from the manifest in a workspace root directory.
+pub fn workspace_excludes(workspace_root: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let manifest =
+        fs::read_to_string(&manifest_path).with_context(|| format!("read {}", manifest_path))?;
+    Ok(read_array(&manifest, "workspace", "exclude")
+        .with_context(|| format!("parse {}", manifest_path.to_slash_path()))?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| Utf8PathBuf::from(path.trim_end_matches('/')))
+        .collect())
+}
+
+/// Read an array of strings from `key` in the `[section]` of a manifest, or None if
+/// it's not there.
+///
+/// Other values in the manifest are not parsed at all.
+fn read_array(manifest: &str, section: &str, key: &str) -> Result<Option<Vec<String>>> {
+    let mut current_section = "";
+    let mut offset = 0;
+    for line in manifest.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if let Some(header) = trimmed.strip_prefix('[') {
+            current_section = header.split(']').next().unwrap_or_default().trim();
+        } else if current_section == section {
+            match trimmed.strip_prefix(key) {
+                Some(after_key) if after_key.trim_start().starts_with('=') => (),
+                _ => continue,
+            }
+            let value_start = line_start + (line.len() - line.trim_start().len()) + key.len();
+            let mut chars = manifest[value_start..].chars().peekable();
+            expect(&mut chars, '=')?;
+            return parse_string_array(&mut chars)
+                .with_context(|| format!("{}.{} should be an array of strings", section, key))
+                .map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Build a glob set from patterns in the style of `.gitignore`, as used by cargo.
+///
+/// Patterns without a slash match a file or directory with that name anywhere; others
+/// match from the top of the package.
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
+        let glob = if let Some(anchored) = pattern.strip_prefix('/') {
+            anchored.to_owned()
+        } else if pattern.contains('/') {
+            pattern.to_owned()
+        } else {
+            format!("**/{}", pattern)
+        };
+        builder.add(
+            GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("invalid pattern {:?}", pattern))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "example"
+description = "exclude = ['this is not the key']"
+exclude = [
+    "/fixtures/large",  # Not needed for the tests.
+    "*.bin",
+]
+
+[workspace]
+exclude = ["old/"]
+
+[dependencies]
+include = ["not a package field"]
+"#;
+
+    #[test]
+    fn read_arrays_from_sections() {
+        assert_eq!(
+            read_array(MANIFEST, "package", "exclude").unwrap(),
+            Some(vec!["/fixtures/large".to_owned(), "*.bin".to_owned()])
+        );
+        assert_eq!(read_array(MANIFEST, "package", "include").unwrap(), None);
+        assert_eq!(
+            read_array(MANIFEST, "workspace", "exclude").unwrap(),
+            Some(vec!["old/".to_owned()])
+        );
+        assert_eq!(
+            read_array("[package]\nexclude = \"x\"\n", "package", "exclude")
+                .unwrap_err()
+                .to_string(),
+            "package.exclude should be an array of strings"
+        );
+    }
+
+    #[test]
+    fn package_exclude() {
+        let files = PackageFiles::from_manifest(MANIFEST).unwrap();
+        assert!(files.excludes(Path::new("fixtures/large"), true));
+        assert!(files.excludes(Path::new("fixtures/large/a.txt"), false));
+        assert!(files.excludes(Path::new("src/data/x.bin"), false));
+        assert!(!files.excludes(Path::new("fixtures/small.txt"), false));
+        assert!(!files.excludes(Path::new("tests/fixtures/large"), true));
+        assert!(!files.excludes(Path::new("src/lib.rs"), false));
+    }
+
+    #[test]
+    fn package_include() {
+        let files =
+            PackageFiles::from_manifest("[package]\ninclude = [\"src/**/*.rs\", \"/build.rs\"]\n")
+                .unwrap();
+        assert!(!files.excludes(Path::new("src/lib.rs"), false));
+        assert!(!files.excludes(Path::new("src/a/b.rs"), false));
+        assert!(!files.excludes(Path::new("build.rs"), false));
+        assert!(!files.excludes(Path::new("Cargo.toml"), false));
+        assert!(!files.excludes(Path::new("tests"), true));
+        assert!(files.excludes(Path::new("tests/t.rs"), false));
+        assert!(files.excludes(Path::new("src/README.md"), false));
+    }
+
+    #[test]
+    fn no_include_or_exclude() {
+        let files = PackageFiles::from_manifest("[package]\nname = \"a\"\n").unwrap();
+        assert!(!files.excludes(Path::new("src/lib.rs"), false));
+        assert!(!files.excludes(Path::new("tests"), true));
+    }
+}
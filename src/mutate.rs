@@ -4,22 +4,188 @@
 
 use std::fmt;
 use std::fs;
-
+use std::str::FromStr;
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
+use camino::Utf8Path;
 use serde::ser::{SerializeStruct, Serializer};
 use serde::Serialize;
 use similar::TextDiff;
 
 use crate::build_dir::BuildDir;
 use crate::source::SourceFile;
-use crate::textedit::{replace_region, Span};
+use crate::textedit::{region_text, replace_region, Span};
 
 /// A comment marker inserted next to changes, so they can be easily found.
 const MUTATION_MARKER_COMMENT: &str = "/* ~ changed by cargo-mutants ~ */";
 
+/// A class of mutation operators, which can be selected together.
+#[derive(Debug, Eq, Clone, Copy, PartialEq, Hash, Serialize)]
+pub enum MutationKind {
+    /// Replace the whole function body with a value of the return type.
+    FnValue,
+    /// Change loop conditions and control flow.
+    Loop,
+    /// Insert an early return, as if some logic had been forgotten.
+    EarlyReturn,
+    /// Change the bounds of ranges, to catch off-by-one errors.
+    Range,
+    /// Swap bitwise and shift operators.
+    Bitwise,
+    /// Remove assertions, and don't panic on `unwrap` and `expect`.
+    Guard,
+    /// Panic on, or ignore, errors passed up by the `?` operator.
+    Try,
+    /// Swap function parameters that have the same type.
+    SwapArgs,
+    /// Change the values of `const` and `static` items.
+    ConstValue,
+    /// Delete statements that only call a function or method, discarding its result.
+    DeleteCall,
+    /// Swap the branches of `if` expressions, by negating the condition.
+    SwapIf,
+    /// Change iterator adapters like `filter`, `take`, `skip`, and `rev`.
+    Iterator,
+    /// Flip floating point comparisons, and change their tolerances.
+    Float,
+    /// Change the values of fields in struct expressions built by `Default`, `From`,
+    /// and `Into` impls.
+    StructField,
+}
+
+impl MutationKind {
+    /// All the kinds, in the order they're described to users.
+    pub const ALL: &'static [MutationKind] = &[
+        MutationKind::FnValue,
+        MutationKind::Loop,
+        MutationKind::EarlyReturn,
+        MutationKind::Range,
+        MutationKind::Bitwise,
+        MutationKind::Guard,
+        MutationKind::Try,
+        MutationKind::SwapArgs,
+        MutationKind::ConstValue,
+        MutationKind::DeleteCall,
+        MutationKind::SwapIf,
+        MutationKind::Iterator,
+        MutationKind::Float,
+        MutationKind::StructField,
+    ];
+
+    /// The name of the kind, as shown to users.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MutationKind::FnValue => "fn-value",
+            MutationKind::Loop => "loop",
+            MutationKind::EarlyReturn => "early-return",
+            MutationKind::Range => "range",
+            MutationKind::Bitwise => "bitwise",
+            MutationKind::Guard => "guard",
+            MutationKind::Try => "try",
+            MutationKind::SwapArgs => "swap-args",
+            MutationKind::ConstValue => "const-value",
+            MutationKind::DeleteCall => "delete-call",
+            MutationKind::SwapIf => "swap-if",
+            MutationKind::Iterator => "iterator",
+            MutationKind::Float => "float",
+            MutationKind::StructField => "struct-field",
+        }
+    }
+
+    /// True if this kind is generated when the user doesn't choose which kinds to use.
+    ///
+    /// Kinds that produce many mutants, or many unviable mutants, are opt-in.
+    pub fn is_default(&self) -> bool {
+        !matches!(
+            self,
+            MutationKind::EarlyReturn
+                | MutationKind::Bitwise
+                | MutationKind::Guard
+                | MutationKind::SwapArgs
+                | MutationKind::ConstValue
+                | MutationKind::DeleteCall
+                | MutationKind::SwapIf
+                | MutationKind::Iterator
+                | MutationKind::Float
+                | MutationKind::StructField
+                | MutationKind::Try
+        )
+    }
+}
+
+impl FromStr for MutationKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        MutationKind::ALL
+            .iter()
+            .find(|kind| kind.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown mutation kind {:?}: expected one of {}",
+                    s,
+                    MutationKind::ALL
+                        .iter()
+                        .map(MutationKind::name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for MutationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An attribute of an item containing a mutant, by which mutants can be grouped in
+/// reports, or excluded, because code that's on its way out or not part of the public
+/// API often isn't expected to be tested as thoroughly.
+#[derive(Debug, Eq, Clone, Copy, PartialEq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemTag {
+    /// Inside an item marked `#[deprecated]`.
+    Deprecated,
+    /// Inside an item marked `#[doc(hidden)]`.
+    Hidden,
+}
+
+impl ItemTag {
+    pub const ALL: &'static [ItemTag] = &[ItemTag::Deprecated, ItemTag::Hidden];
+
+    /// The name of the tag, as shown to users.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemTag::Deprecated => "deprecated",
+            ItemTag::Hidden => "hidden",
+        }
+    }
+}
+
+impl FromStr for ItemTag {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ItemTag::ALL
+            .iter()
+            .find(|tag| tag.name() == s)
+            .copied()
+            .ok_or_else(|| anyhow!("unknown tag {:?}: expected deprecated or hidden", s))
+    }
+}
+
+impl fmt::Display for ItemTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// A type of mutation operation that could be applied to a source file.
 #[derive(Debug, Eq, Clone, PartialEq, Serialize)]
 pub enum MutationOp {
@@ -39,11 +205,38 @@ pub enum MutationOp {
     Xyzzy,
     /// Return `Ok(Default::default())`
     OkDefault,
+    /// Return `""`, for functions returning `&str`.
+    EmptyStr,
+    /// Return `"xyzzy"`, for functions returning `&str`.
+    XyzzyStr,
+    /// Return a reference to a leaked default value, for functions returning `&T`.
+    LeakedDefault,
+    /// Return an empty iterator, for functions returning `impl Iterator`.
+    EmptyIter,
+    /// Return a future of a default value, for functions returning `impl Future`.
+    AsyncDefault,
+    /// Replace some code within the function, rather than the whole body.
+    Replace {
+        kind: MutationKind,
+        /// The original code, with whitespace collapsed, or a short description of it.
+        original: String,
+        /// The code that's inserted in place of the mutated span; empty to delete it.
+        replacement: String,
+    },
+    /// Insert code after the single character in the mutated span, for example after
+    /// the opening brace of a block.
+    Insert {
+        kind: MutationKind,
+        /// A short description of where the code is inserted, like "at start of if block".
+        location: String,
+        /// The inserted code.
+        code: String,
+    },
 }
 
 impl MutationOp {
     /// Return the text that replaces the body of the mutated span, without the marker comment.
-    fn replacement(&self) -> &'static str {
+    pub fn replacement(&self) -> &str {
         use MutationOp::*;
         match self {
             Default => "Default::default()",
@@ -53,6 +246,60 @@ impl MutationOp {
             EmptyString => "\"\".into()",
             Xyzzy => "\"xyzzy\".into()",
             OkDefault => "Ok(Default::default())",
+            EmptyStr => "\"\"",
+            XyzzyStr => "\"xyzzy\"",
+            LeakedDefault => "Box::leak(Box::new(Default::default()))",
+            EmptyIter => "std::iter::empty()",
+            AsyncDefault => "async { Default::default() }",
+            Replace { replacement, .. } => replacement,
+            Insert { code, .. } => code,
+        }
+    }
+
+    /// Return the kind of mutation this is.
+    pub fn kind(&self) -> MutationKind {
+        match self {
+            MutationOp::Replace { kind, .. } | MutationOp::Insert { kind, .. } => *kind,
+            _ => MutationKind::FnValue,
+        }
+    }
+
+    /// True if this replaces the whole body of the function, rather than code within it.
+    pub fn replaces_body(&self) -> bool {
+        !matches!(self, MutationOp::Replace { .. } | MutationOp::Insert { .. })
+    }
+
+    /// Return the description of the original code, for operations that replace only part of
+    /// the function.
+    pub fn original(&self) -> Option<&str> {
+        match self {
+            MutationOp::Replace { original, .. } => Some(original),
+            _ => None,
+        }
+    }
+
+    /// Return where code is inserted, for operations that insert code.
+    pub fn location(&self) -> Option<&str> {
+        match self {
+            MutationOp::Insert { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Describe the change, for operations that change only part of a function.
+    ///
+    /// Returns None for operations that replace the whole function body.
+    pub fn describe_within(&self, function: &str) -> Option<String> {
+        match self {
+            MutationOp::Replace {
+                original,
+                replacement,
+                ..
+            } => Some(describe_replacement(original, replacement, function)),
+            MutationOp::Insert { location, code, .. } => {
+                Some(describe_insertion(code, location, function))
+            }
+            _ => None,
         }
     }
 }
@@ -74,6 +321,16 @@ pub struct Mutant {
 
     /// The type of change to apply.
     pub op: MutationOp,
+
+    /// Whether the mutated code was executed according to coverage data, if any was given.
+    covered: Option<bool>,
+
+    /// Attributes of the items containing the mutant, in order, without duplicates.
+    tags: Vec<ItemTag>,
+
+    /// Other mutations applied together with this one, making a higher-order mutant.
+    /// None of their spans overlap this one's or each other's.
+    also: Vec<Mutant>,
 }
 
 impl Mutant {
@@ -90,26 +347,136 @@ impl Mutant {
             function_name,
             return_type,
             span,
+            covered: None,
+            tags: Vec::new(),
+            also: Vec::new(),
+        }
+    }
+
+    /// Combine this mutant with another, so that both are applied together as a
+    /// higher-order mutant.
+    ///
+    /// Returns None if they change overlapping regions of the same file, since the
+    /// changes couldn't both be applied.
+    pub fn combine(&self, other: &Mutant) -> Option<Mutant> {
+        if self.parts().any(|a| other.parts().any(|b| a.overlaps(b))) {
+            return None;
         }
+        let mut combined = self.clone();
+        combined.also.extend(other.parts().map(|part| Mutant {
+            also: Vec::new(),
+            ..part.clone()
+        }));
+        Some(combined)
     }
 
-    /// Return text of the whole file with the mutation applied.
-    pub fn mutated_code(&self) -> String {
-        replace_region(
-            &self.source_file.code,
-            &self.span.start,
-            &self.span.end,
-            &format!(
+    /// Return the other mutations applied together with this one, if it's a
+    /// higher-order mutant.
+    pub fn also(&self) -> &[Mutant] {
+        &self.also
+    }
+
+    /// Return this mutant and the others applied with it.
+    fn parts(&self) -> impl Iterator<Item = &Mutant> {
+        std::iter::once(self).chain(self.also.iter())
+    }
+
+    /// True if the two mutants change overlapping, or adjacent, regions of the same file.
+    fn overlaps(&self, other: &Mutant) -> bool {
+        self.source_file.tree_relative_path() == other.source_file.tree_relative_path()
+            && self.span.start <= other.span.end
+            && other.span.start <= self.span.end
+    }
+
+    /// Return the files changed by this mutant, starting with its own, without duplicates.
+    fn changed_files(&self) -> Vec<&SourceFile> {
+        let mut files: Vec<&SourceFile> = Vec::new();
+        for part in self.parts() {
+            if !files
+                .iter()
+                .any(|file| file.tree_relative_path() == part.source_file.tree_relative_path())
+            {
+                files.push(&part.source_file);
+            }
+        }
+        files
+    }
+
+    /// Return the text of `source_file` with every part of this mutant that's in it
+    /// applied.
+    fn mutated_code(&self, source_file: &SourceFile) -> String {
+        let mut parts: Vec<&Mutant> = self
+            .parts()
+            .filter(|part| {
+                part.source_file.tree_relative_path() == source_file.tree_relative_path()
+            })
+            .collect();
+        // Apply the last change first, so that it doesn't move the positions of the others.
+        parts.sort_by_key(|part| std::cmp::Reverse(part.span.start));
+        parts
+            .iter()
+            .fold(source_file.code.to_string(), |code, part| {
+                replace_region(
+                    &code,
+                    &part.span.start,
+                    &part.span.end,
+                    &part.replacement_code(),
+                )
+            })
+    }
+
+    /// Return the code that replaces the span of this mutant, including the marker comment.
+    fn replacement_code(&self) -> String {
+        if let MutationOp::Insert { code, .. } = &self.op {
+            format!(
+                "{} {} {}",
+                region_text(&self.source_file.code, &self.span.start, &self.span.end),
+                code,
+                MUTATION_MARKER_COMMENT
+            )
+        } else if self.op.original().is_some() {
+            if self.op.replacement().is_empty() {
+                MUTATION_MARKER_COMMENT.to_owned()
+            } else {
+                format!("{} {}", self.op.replacement(), MUTATION_MARKER_COMMENT)
+            }
+        } else {
+            format!(
                 "{{\n{} {}\n}}\n",
                 self.op.replacement(),
                 MUTATION_MARKER_COMMENT
-            ),
-        )
+            )
+        }
+    }
+
+    /// Return the file that this mutant changes.
+    pub fn source_file(&self) -> &SourceFile {
+        &self.source_file
     }
 
-    /// Return the original code for the entire file affected by this mutation.
-    pub fn original_code(&self) -> &str {
-        &self.source_file.code
+    /// Return the region of the source file replaced by this mutation.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Return whether the mutated code was covered by tests, if coverage data was given.
+    pub fn covered(&self) -> Option<bool> {
+        self.covered
+    }
+
+    pub fn set_covered(&mut self, covered: bool) {
+        self.covered = Some(covered);
+    }
+
+    /// Return the attributes of the items containing the mutant.
+    pub fn tags(&self) -> &[ItemTag] {
+        &self.tags
+    }
+
+    pub fn add_tag(&mut self, tag: ItemTag) {
+        if let Err(index) = self.tags.binary_search(&tag) {
+            self.tags.insert(index, tag);
+        }
     }
 
     pub fn return_type(&self) -> &str {
@@ -126,16 +493,55 @@ impl Mutant {
     }
 
     /// Describe the mutant briefly, not including the location.
+    ///
+    /// The changes of a higher-order mutant are joined with `+`.
     pub fn describe_change(&self) -> String {
-        format!(
-            "replace {} with {}",
-            self.function_name(),
-            self.op.replacement()
+        self.parts()
+            .map(Mutant::describe_own_change)
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    /// Describe the change of this mutant, without those applied with it.
+    fn describe_own_change(&self) -> String {
+        self.op
+            .describe_within(&self.function_name)
+            .unwrap_or_else(|| {
+                format!(
+                    "replace {} with {}",
+                    self.function_name(),
+                    self.op.replacement()
+                )
+            })
+    }
+
+    /// Return a stable identifier for this mutant.
+    ///
+    /// The id includes the file, function, and replacement, but not the line number,
+    /// so that it does not change when unrelated edits move the function.
+    ///
+    /// Note that, like [Mutant::function_name], this is not guaranteed to be unique.
+    ///
+    /// The ids of the parts of a higher-order mutant are joined with `+`.
+    pub fn id(&self) -> String {
+        self.parts()
+            .map(Mutant::own_id)
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    fn own_id(&self) -> String {
+        mutant_id(
+            &self.source_file.tree_relative_slashes(),
+            &self.function_name,
+            &self.return_type,
+            self.op.describe_within(&self.function_name).as_deref(),
+            self.op.replacement(),
         )
     }
 
     /// Return the text inserted for this mutation.
-    pub fn replacement_text(&self) -> &'static str {
+    pub fn replacement_text(&self) -> &str {
         self.op.replacement()
     }
 
@@ -147,15 +553,32 @@ impl Mutant {
         &self.function_name
     }
 
-    /// Return a unified diff for the mutant.
+    /// Return the name of the function qualified by the path of its module, like
+    /// `net::retry::backoff`, as matched by function patterns in the config.
+    pub fn function_path(&self) -> String {
+        let mut path: String = self
+            .source_file
+            .module_path()
+            .iter()
+            .map(|name| format!("{}::", name))
+            .collect();
+        path.push_str(&self.function_name);
+        path
+    }
+
+    /// Return a unified diff for the mutant, covering each file it changes.
     pub fn diff(&self) -> String {
-        let old_label = self.source_file.tree_relative_slashes();
         let new_label = self.describe_change();
-        TextDiff::from_lines(self.original_code(), &self.mutated_code())
-            .unified_diff()
-            .context_radius(8)
-            .header(&old_label, &new_label)
-            .to_string()
+        self.changed_files()
+            .into_iter()
+            .map(|file| {
+                TextDiff::from_lines(file.code.as_str(), &self.mutated_code(file))
+                    .unified_diff()
+                    .context_radius(8)
+                    .header(&file.tree_relative_slashes(), &new_label)
+                    .to_string()
+            })
+            .collect()
     }
 
     /// Run a function with this mutation applied, then revert it afterwards, even if the function
@@ -164,42 +587,173 @@ impl Mutant {
     where
         F: FnMut() -> Result<T>,
     {
-        self.write_in_dir(build_dir, &self.mutated_code())?;
-        let r = func();
-        self.write_in_dir(build_dir, self.original_code())?;
-        r
-    }
-
-    fn write_in_dir(&self, build_dir: &BuildDir, code: &str) -> Result<()> {
-        let path = self
-            .source_file
-            .tree_relative_path()
-            .within(build_dir.path());
-        // for safety, don't follow symlinks
-        assert!(path.is_file(), "{:?} is not a file", path);
-        fs::write(&path, code.as_bytes())
-            .with_context(|| format!("failed to write mutated code to {:?}", path))
+        let files = self.changed_files();
+        // If writing one of the files fails, the others are still restored.
+        let r = files
+            .iter()
+            .try_for_each(|file| write_in_dir(build_dir, file, &self.mutated_code(file)))
+            .and_then(|()| func());
+        // Try to restore every file, even if an earlier one fails, and then report the
+        // first failure, which is more serious than the result of the mutant.
+        let mut restored = Ok(());
+        for file in files {
+            let result = write_in_dir(build_dir, file, &file.code)
+                .and_then(|()| check_restored(build_dir, file));
+            if restored.is_ok() {
+                restored = result;
+            }
+        }
+        restored.and(r)
     }
 
+    /// Return the base name of the log file for this mutant, derived from its id.
+    ///
+    /// The name doesn't depend on the line number or the order the mutants are tested.
+    /// Since the id is not guaranteed to be unique, the log file might still need a
+    /// suffix to disambiguate it.
     pub fn log_file_name_base(&self) -> String {
         format!(
-            "{}_line_{}",
+            "{}_{:08x}",
             self.source_file.tree_relative_slashes(),
-            self.span.start.line
+            fnv1a_hash(&self.id())
         )
     }
 }
 
+/// Reorder mutants so that all the mutants in each file are consecutive, which lets
+/// cargo reuse more of the incremental build between them.
+///
+/// Files are ordered by their first appearance, and mutants within a file keep their
+/// relative order.
+pub fn group_by_file(mutants: &mut [Mutant]) {
+    let mut file_order: Vec<String> = Vec::new();
+    mutants.sort_by_cached_key(|mutant| {
+        let file = mutant.source_file.tree_relative_slashes();
+        file_order
+            .iter()
+            .position(|f| *f == file)
+            .unwrap_or_else(|| {
+                file_order.push(file);
+                file_order.len() - 1
+            })
+    });
+}
+
+/// Combine pairs of mutants in the same module into second-order mutants, to see how
+/// the tests respond to several changes at once.
+///
+/// Each mutant is paired with the next mutant in the same module that doesn't overlap
+/// it, so there are about as many second-order mutants as first-order mutants. Mutants
+/// with no such partner are left out.
+pub fn combine_pairs(mutants: &[Mutant]) -> Vec<Mutant> {
+    let modules: Vec<Vec<String>> = mutants
+        .iter()
+        .map(|mutant| mutant.source_file.module_path())
+        .collect();
+    mutants
+        .iter()
+        .enumerate()
+        .filter_map(|(i, first)| {
+            mutants[i + 1..]
+                .iter()
+                .zip(&modules[i + 1..])
+                .filter(|(_, module)| **module == modules[i])
+                .find_map(|(second, _)| first.combine(second))
+        })
+        .collect()
+}
+
+/// Check that the file in the build directory is exactly the original code again,
+/// so that a failed restore can't silently corrupt the results of later mutants.
+fn check_restored(build_dir: &BuildDir, source_file: &SourceFile) -> Result<()> {
+    let path = source_file.tree_relative_path().within(build_dir.path());
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read back restored file {:?}", path))?;
+    check_content(&path, &content, &source_file.code)
+}
+
+fn write_in_dir(build_dir: &BuildDir, source_file: &SourceFile, code: &str) -> Result<()> {
+    let path = source_file.tree_relative_path().within(build_dir.path());
+    // for safety, don't follow symlinks
+    assert!(path.is_file(), "{:?} is not a file", path);
+    fs::write(&path, code.as_bytes())
+        .with_context(|| format!("failed to write mutated code to {:?}", path))
+}
+
+/// Return an error if `content` read back from `path` doesn't match the `expected` code.
+fn check_content(path: &Utf8Path, content: &str, expected: &str) -> Result<()> {
+    if content == expected {
+        Ok(())
+    } else {
+        // Show hashes rather than the whole files, which could be long.
+        Err(anyhow!(
+            "{} was not restored correctly after mutation: content hash is {:08x}, expected {:08x}",
+            path,
+            fnv1a_hash(content),
+            fnv1a_hash(expected)
+        ))
+    }
+}
+
+/// A 32-bit FNV-1a hash, which unlike the standard library's hasher is guaranteed to be
+/// the same across Rust versions and platforms.
+pub fn fnv1a_hash(s: &str) -> u32 {
+    s.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Format the stable identifier of a mutant from its parts.
+///
+/// `partial_change` is the description of mutants that change only part of the function,
+/// from [MutationOp::describe_within].
+pub fn mutant_id(
+    file: &str,
+    function: &str,
+    return_type: &str,
+    partial_change: Option<&str>,
+    replacement: &str,
+) -> String {
+    match partial_change {
+        None => format!(
+            "{}: replace {}{}{} with {}",
+            file,
+            function,
+            if return_type.is_empty() { "" } else { " " },
+            return_type,
+            replacement
+        ),
+        Some(partial_change) => format!("{}: {}", file, partial_change),
+    }
+}
+
+/// Describe a change to part of a function.
+pub fn describe_replacement(original: &str, replacement: &str, function: &str) -> String {
+    if replacement.is_empty() {
+        format!("delete {} in {}", original, function)
+    } else {
+        format!("replace {} with {} in {}", original, replacement, function)
+    }
+}
+
+/// Describe code inserted into a function.
+pub fn describe_insertion(code: &str, location: &str, function: &str) -> String {
+    format!("insert {} {} in {}", code, location, function)
+}
+
 impl fmt::Debug for Mutant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Mutant")
-            .field("op", &self.op)
+        let mut s = f.debug_struct("Mutant");
+        s.field("op", &self.op)
             .field("function_name", &self.function_name())
             .field("return_type", &self.return_type)
             // more concise display of spans
             .field("start", &(self.span.start.line, self.span.start.column))
-            .field("end", &(self.span.end.line, self.span.end.column))
-            .finish()
+            .field("end", &(self.span.end.line, self.span.end.column));
+        if !self.also.is_empty() {
+            s.field("also", &self.also);
+        }
+        s.finish()
     }
 }
 
@@ -226,6 +780,22 @@ impl Serialize for Mutant {
         ss.serialize_field("function", &self.function_name.as_ref())?;
         ss.serialize_field("return_type", &self.return_type.as_ref())?;
         ss.serialize_field("replacement", self.op.replacement())?;
+        ss.serialize_field("kind", &self.op.kind().name())?;
+        if let Some(original) = self.op.original() {
+            ss.serialize_field("original", original)?;
+        }
+        if let Some(location) = self.op.location() {
+            ss.serialize_field("location", location)?;
+        }
+        if let Some(covered) = self.covered {
+            ss.serialize_field("covered", &covered)?;
+        }
+        if !self.tags.is_empty() {
+            ss.serialize_field("tags", &self.tags)?;
+        }
+        if !self.also.is_empty() {
+            ss.serialize_field("also", &self.also)?;
+        }
         ss.end()
     }
 }
@@ -236,6 +806,7 @@ mod test {
     use itertools::Itertools;
     use pretty_assertions::assert_eq;
 
+    use super::{check_content, combine_pairs, group_by_file, MutationKind};
     use crate::*;
 
     #[test]
@@ -245,18 +816,34 @@ mod test {
             "src/bin/main.rs".parse().unwrap(),
         )
         .unwrap();
-        let muts = discover_mutants(source_file.into()).unwrap();
-        assert_eq!(muts.len(), 2);
+        let muts = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        assert_eq!(muts.len(), 6);
         assert_eq!(
             format!("{:?}", muts[0]),
             r#"Mutant { op: Unit, function_name: "main", return_type: "", start: (1, 11), end: (5, 2) }"#
         );
         assert_eq!(
-            format!("{:?}", muts[1]),
+            format!("{:?}", muts[3]),
             r#"Mutant { op: Default, function_name: "factorial", return_type: "-> u32", start: (7, 29), end: (13, 2) }"#
         );
     }
 
+    #[test]
+    fn group_mutants_by_file() {
+        let source_tree = SourceTree::new(Utf8Path::new("testdata/tree/well_tested")).unwrap();
+        let mutants = source_tree.mutants(&Options::default()).unwrap();
+        let file_of = |m: &Mutant| m.source_file().tree_relative_slashes();
+        let (x0, x1) = mutants
+            .iter()
+            .tuple_windows()
+            .find(|(a, b)| file_of(a) == file_of(b))
+            .unwrap();
+        let y = mutants.iter().find(|m| file_of(m) != file_of(x0)).unwrap();
+        let mut interleaved = vec![x0.clone(), y.clone(), x1.clone()];
+        group_by_file(&mut interleaved);
+        assert_eq!(interleaved, [x0.clone(), x1.clone(), y.clone()]);
+    }
+
     #[test]
     fn filter_by_attributes() {
         let source_file = SourceFile::new(
@@ -264,14 +851,26 @@ mod test {
             "src/lib.rs".parse().unwrap(),
         )
         .unwrap();
-        let mutants = discover_mutants(source_file.into()).unwrap();
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
         let descriptions = mutants.iter().map(Mutant::describe_change).collect_vec();
         insta::assert_snapshot!(
             descriptions.join("\n"),
-            @"replace controlled_loop with ()"
+            @r###"
+        replace controlled_loop with ()
+        replace 0.. with 0 + 1.. in controlled_loop
+        delete break in controlled_loop
+        "###
         );
     }
 
+    #[test]
+    fn parse_mutation_kind() {
+        for kind in MutationKind::ALL {
+            assert_eq!(kind.name().parse::<MutationKind>().unwrap(), *kind);
+        }
+        assert!("sideways".parse::<MutationKind>().is_err());
+    }
+
     #[test]
     fn mutate_factorial() {
         let source_file = SourceFile::new(
@@ -279,10 +878,10 @@ mod test {
             "src/bin/main.rs".parse().unwrap(),
         )
         .unwrap();
-        let mutants = discover_mutants(source_file.into()).unwrap();
-        assert_eq!(mutants.len(), 2);
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        assert_eq!(mutants.len(), 6);
 
-        let mut mutated_code = mutants[0].mutated_code();
+        let mut mutated_code = mutants[0].mutated_code(mutants[0].source_file());
         assert_eq!(mutants[0].function_name(), "main");
         mutated_code.retain(|c| c != '\r');
         assert_eq!(
@@ -307,8 +906,8 @@ fn test_factorial() {
 "#
         );
 
-        let mut mutated_code = mutants[1].mutated_code();
-        assert_eq!(mutants[1].function_name(), "factorial");
+        let mut mutated_code = mutants[3].mutated_code(mutants[3].source_file());
+        assert_eq!(mutants[3].function_name(), "factorial");
         mutated_code.retain(|c| c != '\r');
         assert_eq!(
             mutated_code,
@@ -329,5 +928,70 @@ fn test_factorial() {
 }
 "#
         );
+
+        assert_eq!(
+            mutants[4].describe_change(),
+            "replace 2..=n with 2..n in factorial"
+        );
+        assert!(mutants[4]
+            .mutated_code(mutants[4].source_file())
+            .contains("for i in 2..n /* ~ changed by cargo-mutants ~ */ {"));
+    }
+
+    #[test]
+    fn combine_mutants_in_one_file() {
+        let source_file = SourceFile::new(
+            Utf8Path::new("testdata/tree/factorial"),
+            "src/bin/main.rs".parse().unwrap(),
+        )
+        .unwrap();
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        // The body of factorial contains the range.
+        assert_eq!(mutants[3].combine(&mutants[4]), None);
+
+        let combined = mutants[1].combine(&mutants[3]).unwrap();
+        assert_eq!(
+            combined.describe_change(),
+            "replace 1..=6 with 1..6 in main + replace factorial with Default::default()"
+        );
+        assert_eq!(
+            combined.id(),
+            "src/bin/main.rs: replace 1..=6 with 1..6 in main + \
+            src/bin/main.rs: replace factorial -> u32 with Default::default()"
+        );
+        let mut mutated_code = combined.mutated_code(combined.source_file());
+        mutated_code.retain(|c| c != '\r');
+        assert!(mutated_code.starts_with(
+            r#"fn main() {
+    for i in 1..6 /* ~ changed by cargo-mutants ~ */ {
+        println!("{}! = {}", i, factorial(i));
+    }
+}
+
+fn factorial(n: u32) -> u32 {
+Default::default() /* ~ changed by cargo-mutants ~ */
+}
+"#
+        ));
+
+        let pairs = combine_pairs(&mutants);
+        assert_eq!(
+            pairs.iter().map(Mutant::describe_change).collect_vec(),
+            [
+                "replace main with () + replace factorial with Default::default()",
+                "replace 1..=6 with 1..6 in main + replace factorial with Default::default()",
+                "replace 1..=6 with 1 + 1..=6 in main + replace factorial with Default::default()",
+            ]
+        );
+    }
+
+    #[test]
+    fn incorrectly_restored_file_is_an_error() {
+        let path = Utf8Path::new("src/lib.rs");
+        check_content(path, "fn a() {}\n", "fn a() {}\n").unwrap();
+        let err = check_content(path, "fn a() { () }\n", "fn a() {}\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("src/lib.rs was not restored correctly after mutation"));
     }
 }
@@ -4,12 +4,20 @@
 //!
 //! The [Options] structure is built from command-line options and then widely passed around.
 
-use std::convert::TryFrom;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
 
+use anyhow::{anyhow, Context};
 use camino::Utf8PathBuf;
+use chrono::{DateTime, Utc};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 
+use crate::build_dir::SymlinkPolicy;
+use crate::config::Config;
+use crate::mutate::{ItemTag, MutationKind};
+use crate::outcome::{Phase, TimeoutPolicy};
+use crate::toolchain::RustToolchain;
 use crate::*;
 
 /// Options for running experiments.
@@ -23,6 +31,9 @@ pub struct Options {
     pub print_caught: bool,
     pub print_unviable: bool,
 
+    /// Show elapsed times on the console, and record them in reports. If false, times
+    /// are left out of the console output and zeroed in reports, so that both are
+    /// reproducible.
     pub show_times: bool,
 
     /// Show logs even from mutants that were caught, or source/unmutated builds.
@@ -34,20 +45,204 @@ pub struct Options {
     /// interesting results.
     pub shuffle: bool,
 
-    /// Additional arguments to `cargo test`.
-    pub additional_cargo_test_args: Vec<String>,
+    /// Test the mutants most likely to be missed first, based on history, recent
+    /// changes, and the location of tests.
+    pub prioritize: bool,
+
+    /// Line coverage data used to tag mutants as covered or uncovered.
+    pub coverage: Option<Coverage>,
+
+    /// How to order or select mutants using the coverage data.
+    pub coverage_order: Option<CoverageOrder>,
+
+    /// Test only mutants that were missed in the previous run, or that are new, and
+    /// carry forward the outcomes of the others.
+    pub iterate: bool,
+
+    /// The kinds of mutation to generate, or None for the default kinds.
+    pub mutation_kinds: Option<Vec<MutationKind>>,
+
+    /// Test at most this many mutants, chosen by [crate::plan].
+    pub max_mutants: Option<usize>,
+
+    /// Test only as many mutants as are expected to fit in this time, chosen by
+    /// [crate::plan].
+    pub max_duration: Option<Duration>,
+
+    /// Keep at most this many mutants in each function, spread through it.
+    pub max_mutants_per_fn: Option<usize>,
+
+    /// The number of mutations combined in each mutant: 2 to pair up mutants in the
+    /// same module into second-order mutants. 0 or 1 for ordinary mutants.
+    pub order: usize,
+
+    /// Additional arguments to cargo for each phase.
+    additional_cargo_args: HashMap<Phase, Vec<String>>,
+
+    /// Environment variables set in each phase when testing mutants.
+    mutant_env: HashMap<Phase, Vec<(String, String)>>,
 
     /// Copy the `/target/` directory from the source tree.
     pub copy_target: bool,
 
+    /// Paths within the source tree not to copy to the scratch directory.
+    pub copy_exclude: Option<GlobSet>,
+
+    /// Copy version control directories like `.git` to the scratch directory.
+    pub copy_vcs: bool,
+
+    /// How symlinks in the source tree are copied to the scratch directory.
+    pub symlinks: SymlinkPolicy,
+
+    /// Don't show progress while copying the source tree.
+    pub quiet_copy: bool,
+
+    /// Cap lints at `allow` when building in the scratch directory.
+    pub cap_lints: bool,
+
+    /// A cargo-compatible command, such as `cross`, to run instead of cargo to check,
+    /// build, and test.
+    pub cargo_command: Option<String>,
+
+    /// Build in the scratch directory without the developer's rustc flags, and with
+    /// fixed values for other variables that affect the build.
+    pub deterministic_env: bool,
+
     /// Build the source directory before copying it.
     pub build_source: bool,
 
+    /// Run cargo in a container from this image.
+    pub container_image: Option<String>,
+
+    /// The docker-compatible command used to run containers.
+    pub container_cli: String,
+
+    /// Give cargo commands run by the tests their own target directory.
+    pub isolate_nested_cargo: bool,
+
+    /// Don't look for a `rust-toolchain.toml` file choosing the toolchain.
+    pub ignore_rust_toolchain: bool,
+
+    /// The toolchain to build with, chosen by a toolchain file in or above the
+    /// source tree, found when the lab starts.
+    pub rust_toolchain: Option<RustToolchain>,
+
+    /// Whether to test the unmutated baseline, or reuse the times saved by a previous run.
+    pub baseline: BaselineStrategy,
+
+    /// Stop after testing the unmutated baseline.
+    pub baseline_only: bool,
+
+    /// Carry on if some tests fail in the baseline, and count a mutant as caught only
+    /// if other tests fail.
+    pub allow_baseline_failures: bool,
+
+    /// With `allow_baseline_failures`, the tests that failed in the baseline, found
+    /// when it's tested, or read from `baseline.json` with `--baseline skip`.
+    pub baseline_failed_tests: Vec<String>,
+
+    /// Apply and revert mutants in the build directory, but don't run cargo.
+    pub dry_run: bool,
+
+    /// The number of slowest mutants and source files to list at the end of the run.
+    pub slowest: usize,
+
+    /// How timeouts count towards the mutation score.
+    pub timeout_policy: TimeoutPolicy,
+
+    /// The tool that runs the tests.
+    pub test_tool: TestTool,
+
+    /// Run each fuzz target for this long after the tests pass.
+    pub fuzz_time: Option<Duration>,
+
+    /// Stop the tests as hung if they write no output for this long.
+    pub idle_timeout: Option<Duration>,
+
+    /// Stop the tests if they use this much CPU time, counting all their processes.
+    pub test_cpu_timeout: Option<Duration>,
+
+    /// The package being mutated, if it's in a workspace with other packages, found
+    /// when the lab starts. Only this package is checked and built.
+    pub package: Option<String>,
+
+    /// The names of the fuzz targets, found when the lab starts if fuzzing is enabled.
+    pub fuzz_targets: Vec<String>,
+
+    /// Multipliers for the test timeout of mutants in some files or functions, from
+    /// the config.
+    pub timeout_multipliers: Vec<TimeoutMultiplier>,
+
+    /// Minimum mutation scores for source paths or modules, from the config.
+    pub score_thresholds: Vec<(String, f64)>,
+
     /// Files to examine.
     pub globset: Option<GlobSet>,
 
+    /// The patterns from `--file` that make up [Options::globset], to explain which of
+    /// them matched nothing.
+    pub file_patterns: Vec<String>,
+
+    /// Also mutate vendored and generated code, which is skipped by default.
+    pub include_generated: bool,
+
+    /// Don't mutate code in items with these attributes.
+    pub exclude_tags: Vec<ItemTag>,
+
+    /// Fully-qualified paths of functions not to mutate.
+    pub exclude_functions: Option<GlobSet>,
+
     /// Create `mutants.out` within this directory (by default, the source directory).
     pub output_in_dir: Option<Utf8PathBuf>,
+
+    /// Ask the user before starting runs that are estimated to take a long time.
+    pub confirm_long_runs: bool,
+}
+
+/// A multiplier for the test timeout of mutants whose source path or function matches
+/// a pattern.
+#[derive(Debug, Clone)]
+pub struct TimeoutMultiplier {
+    /// Matches the fully-qualified function path if the pattern contains `::`, and
+    /// otherwise the source path.
+    by_function: bool,
+    globset: GlobSet,
+    multiplier: f64,
+}
+
+impl TimeoutMultiplier {
+    pub fn new(pattern: &str, multiplier: f64) -> Result<TimeoutMultiplier> {
+        if !(multiplier > 0.0 && multiplier.is_finite()) {
+            return Err(anyhow!(
+                "timeout multiplier for {:?} should be a positive number, not {}",
+                pattern,
+                multiplier
+            ));
+        }
+        let by_function = pattern.contains("::");
+        let globset = if by_function {
+            Glob::new(pattern)
+                .and_then(|glob| GlobSetBuilder::new().add(glob).build())
+                .map_err(anyhow::Error::from)
+        } else {
+            build_path_glob_set([pattern]).map(|globset| globset.expect("one glob was added"))
+        }
+        .with_context(|| format!("invalid timeout_multipliers pattern {:?}", pattern))?;
+        Ok(TimeoutMultiplier {
+            by_function,
+            globset,
+            multiplier,
+        })
+    }
+
+    fn matches(&self, mutant: &Mutant) -> bool {
+        if self.by_function {
+            self.globset.is_match(mutant.function_path())
+        } else {
+            self.globset
+                .is_match(mutant.source_file().tree_relative_slashes())
+        }
+    }
 }
 
 impl Options {
@@ -58,6 +253,82 @@ impl Options {
         self.test_timeout
     }
 
+    /// Return the maximum run time for `cargo test` with this mutant applied: the test
+    /// timeout, multiplied by the largest of the config's timeout multipliers that
+    /// match the mutant.
+    pub fn mutant_test_timeout(&self, mutant: &Mutant) -> Duration {
+        match self.timeout_multiplier(mutant) {
+            Some(multiplier) if self.has_test_timeout() => {
+                multiply_timeout(self.test_timeout, multiplier)
+            }
+            _ => self.test_timeout,
+        }
+    }
+
+    /// Return the CPU time limit for `cargo test` with this mutant applied, if any,
+    /// multiplied in the same way as [Options::mutant_test_timeout].
+    pub fn mutant_test_cpu_timeout(&self, mutant: &Mutant) -> Option<Duration> {
+        self.test_cpu_timeout.map(|cpu_timeout| {
+            self.timeout_multiplier(mutant)
+                .map_or(cpu_timeout, |multiplier| {
+                    multiply_timeout(cpu_timeout, multiplier)
+                })
+        })
+    }
+
+    /// Return the largest of the config's timeout multipliers that match the mutant.
+    fn timeout_multiplier(&self, mutant: &Mutant) -> Option<f64> {
+        self.timeout_multipliers
+            .iter()
+            .filter(|tm| tm.matches(mutant))
+            .map(|tm| tm.multiplier)
+            .reduce(f64::max)
+    }
+
+    /// Return the current time to record in reports, or the Unix epoch if times aren't
+    /// shown, so that reports are reproducible.
+    pub fn report_time(&self) -> DateTime<Utc> {
+        if self.show_times {
+            Utc::now()
+        } else {
+            DateTime::from(UNIX_EPOCH)
+        }
+    }
+
+    /// Return the additional arguments to cargo for a phase, after the arguments that
+    /// cargo-mutants always uses.
+    pub fn additional_cargo_args(&self, phase: Phase) -> &[String] {
+        self.additional_cargo_args
+            .get(&phase)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Return the phases to run for the baseline and each mutant.
+    pub fn phases(&self) -> Vec<Phase> {
+        let mut phases = vec![Phase::Check, Phase::Build, Phase::Test];
+        if self.fuzz_time.is_some() {
+            phases.push(Phase::Fuzz);
+        }
+        phases
+    }
+
+    /// Return the maximum run time for each fuzz target: it runs for the fuzz time,
+    /// and should take no longer than the tests to start and stop.
+    pub fn fuzz_timeout(&self) -> Duration {
+        self.fuzz_time
+            .unwrap_or_default()
+            .saturating_add(self.test_timeout)
+    }
+
+    /// Return the environment variables to set in a phase when testing a mutant.
+    pub fn mutant_env(&self, phase: Phase) -> &[(String, String)] {
+        self.mutant_env
+            .get(&phase)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     pub fn has_test_timeout(&self) -> bool {
         self.test_timeout < Duration::MAX
     }
@@ -65,42 +336,396 @@ impl Options {
     pub fn set_test_timeout(&mut self, test_timeout: Duration) {
         self.test_timeout = test_timeout;
     }
+
+    /// True if mutants of this kind should be generated.
+    pub fn is_kind_enabled(&self, kind: MutationKind) -> bool {
+        match &self.mutation_kinds {
+            Some(kinds) => kinds.contains(&kind),
+            None => kind.is_default(),
+        }
+    }
+
+    /// True if the function with this fully-qualified path should not be mutated.
+    pub fn is_function_excluded(&self, function_path: &str) -> bool {
+        self.exclude_functions
+            .as_ref()
+            .is_some_and(|globset| globset.is_match(function_path))
+    }
 }
 
-impl TryFrom<&Args> for Options {
-    type Error = anyhow::Error;
+impl Options {
+    /// Build options from the command line arguments and the config of the source tree.
+    ///
+    /// Command line arguments take precedence over the config.
+    pub fn new(args: &Args, config: &Config) -> Result<Options> {
+        let globset = build_path_glob_set(&args.file)?;
+        let copy_exclude = build_path_glob_set(
+            config
+                .copy_exclude
+                .iter()
+                .flatten()
+                .chain(args.copy_exclude.iter()),
+        )
+        .context("invalid copy_exclude pattern")?;
 
-    fn try_from(args: &Args) -> std::result::Result<Options, anyhow::Error> {
-        let globset = if args.file.is_empty() {
-            None
-        } else {
-            let mut builder = GlobSetBuilder::new();
-            for glob_str in &args.file {
-                if glob_str.contains('/') {
-                    builder.add(Glob::new(glob_str)?);
-                } else {
-                    builder.add(Glob::new(&format!("**/{}", glob_str))?);
+        let exclude_functions = config
+            .exclude_functions
+            .as_ref()
+            .map(|globs| {
+                let mut builder = GlobSetBuilder::new();
+                for glob_str in globs {
+                    builder.add(Glob::new(glob_str).with_context(|| {
+                        format!("invalid exclude_functions pattern {:?}", glob_str)
+                    })?);
                 }
+                builder.build().map_err(anyhow::Error::from)
+            })
+            .transpose()?;
+
+        let symlinks = match args.symlinks {
+            Some(symlinks) => symlinks,
+            None => config
+                .symlinks
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .context("invalid symlinks")?
+                .unwrap_or_default(),
+        };
+
+        let mut exclude_tags = args.exclude_tag.clone();
+        for name in config.exclude_tags.iter().flatten() {
+            exclude_tags.push(name.parse().context("invalid exclude_tags")?);
+        }
+
+        let coverage = args
+            .coverage
+            .as_deref()
+            .map(Coverage::read_lcov)
+            .transpose()?;
+        if args.baseline_only && args.baseline == Some(BaselineStrategy::Skip) {
+            return Err(anyhow!(
+                "--baseline-only can't be used with --baseline skip"
+            ));
+        }
+        if args.allow_baseline_failures && args.test_tool == Some(TestTool::Nextest) {
+            return Err(anyhow!(
+                "--allow-baseline-failures can't be used with --test-tool nextest, \
+                because the failed tests are read from the output of libtest"
+            ));
+        }
+        if args.coverage_order.is_some() && coverage.is_none() {
+            return Err(anyhow!("--coverage-order requires --coverage"));
+        }
+
+        let mutation_kind_names: Option<Vec<&str>> = match &args.mutation_kinds {
+            Some(kinds) => Some(kinds.split(',').map(str::trim).collect()),
+            None => config
+                .mutation_kinds
+                .as_ref()
+                .map(|kinds| kinds.iter().map(String::as_str).collect()),
+        };
+        let mutation_kinds = mutation_kind_names
+            .map(|names| {
+                names
+                    .into_iter()
+                    .map(MutationKind::from_str)
+                    .collect::<Result<Vec<MutationKind>>>()
+            })
+            .transpose()?;
+        if mutation_kinds.as_ref().is_some_and(Vec::is_empty) {
+            return Err(anyhow!("no mutation kinds are enabled"));
+        }
+
+        if args.max_mutants == Some(0) {
+            return Err(anyhow!("--max-mutants should be at least 1"));
+        }
+        let max_duration = match args.max_duration {
+            Some(seconds) if !(seconds > 0.0 && seconds.is_finite()) => {
+                return Err(anyhow!(
+                    "--max-duration should be a positive number of seconds, not {}",
+                    seconds
+                ));
             }
-            Some(builder.build()?)
+            max_duration => max_duration.map(Duration::from_secs_f64),
         };
+        let max_mutants_per_fn = args.max_mutants_per_fn.or(config.max_mutants_per_fn);
+        if max_mutants_per_fn == Some(0) {
+            return Err(anyhow!("max mutants per function should be at least 1"));
+        }
+        let order = match args.order {
+            None | Some(1) => 1,
+            Some(2) => 2,
+            Some(order) => return Err(anyhow!("--order should be 1 or 2, not {}", order)),
+        };
+
+        let timeout_multipliers = config
+            .timeout_multipliers
+            .iter()
+            .flatten()
+            .map(|(pattern, &multiplier)| TimeoutMultiplier::new(pattern, multiplier))
+            .collect::<Result<Vec<_>>>()?;
+
+        let score_thresholds: Vec<(String, f64)> = config
+            .score
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        if let Some((area, minimum)) = score_thresholds
+            .iter()
+            .find(|(_, minimum)| !(0.0..=100.0).contains(minimum))
+        {
+            return Err(anyhow!(
+                "minimum score for {:?} should be between 0 and 100, not {}",
+                area,
+                minimum
+            ));
+        }
+
+        let fuzz_time = match args.fuzz_time.or(config.fuzz_time) {
+            Some(seconds) if !(seconds > 0.0 && seconds.is_finite()) => {
+                return Err(anyhow!(
+                    "fuzz time should be a positive number of seconds, not {}",
+                    seconds
+                ));
+            }
+            fuzz_time => fuzz_time.map(Duration::from_secs_f64),
+        };
+
+        let idle_timeout = match args.idle_timeout.or(config.idle_timeout) {
+            Some(seconds) if !(seconds > 0.0 && seconds.is_finite()) => {
+                return Err(anyhow!(
+                    "idle timeout should be a positive number of seconds, not {}",
+                    seconds
+                ));
+            }
+            idle_timeout => idle_timeout.map(Duration::from_secs_f64),
+        };
+
+        let test_cpu_timeout = match args.timeout_cpu_seconds {
+            Some(seconds) if !(seconds > 0.0 && seconds.is_finite()) => {
+                return Err(anyhow!(
+                    "CPU timeout should be a positive number of seconds, not {}",
+                    seconds
+                ));
+            }
+            Some(_) if !cfg!(target_os = "linux") => {
+                return Err(anyhow!("--timeout-cpu-seconds is only supported on Linux"));
+            }
+            test_cpu_timeout => test_cpu_timeout.map(Duration::from_secs_f64),
+        };
+
+        // Arguments after `--` on the command line replace the config for the test phase.
+        let mut additional_cargo_args = HashMap::new();
+        for &phase in Phase::ALL {
+            let phase_args = if phase == Phase::Test && !args.cargo_test_args.is_empty() {
+                args.cargo_test_args.clone()
+            } else if let Some(phase_config) =
+                config.phase.as_ref().and_then(|phases| phases.get(phase))
+            {
+                phase_config.args.clone()
+            } else {
+                continue;
+            };
+            additional_cargo_args.insert(phase, phase_args);
+        }
+
+        // Variables from the command line are set in the test phase, and replace
+        // those of the same name in the config.
+        let mut mutant_env = HashMap::new();
+        for &phase in Phase::ALL {
+            let mut vars = config
+                .phase
+                .as_ref()
+                .and_then(|phases| phases.get(phase))
+                .map(|phase_config| phase_config.mutant_env.clone())
+                .unwrap_or_default();
+            if phase == Phase::Test {
+                if let Some(cases) = args.proptest_cases {
+                    vars.insert("PROPTEST_CASES".to_owned(), cases.to_string());
+                }
+                if let Some(tests) = args.quickcheck_tests {
+                    vars.insert("QUICKCHECK_TESTS".to_owned(), tests.to_string());
+                }
+                for var in &args.mutant_env {
+                    match var.split_once('=') {
+                        Some((name, value)) if !name.is_empty() => {
+                            vars.insert(name.to_owned(), value.to_owned());
+                        }
+                        _ => {
+                            return Err(anyhow!("--mutant-env should be NAME=VALUE, not {:?}", var))
+                        }
+                    }
+                }
+            }
+            if !vars.is_empty() {
+                mutant_env.insert(phase, vars.into_iter().collect());
+            }
+        }
 
         Ok(Options {
-            build_source: !args.no_copy_target,
+            allow_baseline_failures: args.allow_baseline_failures,
+            baseline: args.baseline.unwrap_or_default(),
+            baseline_failed_tests: Vec::new(),
+            baseline_only: args.baseline_only,
+            build_source: !args.no_copy_target && !args.dry_run,
+            cap_lints: args.cap_lints || config.cap_lints.unwrap_or(false),
+            cargo_command: args
+                .cargo_command
+                .clone()
+                .or_else(|| config.cargo_command.clone()),
             check_only: args.check,
+            confirm_long_runs: !args.yes,
+            container_cli: args.container_cli.clone(),
+            container_image: args
+                .container_image
+                .clone()
+                .or_else(|| config.container_image.clone()),
+            copy_exclude,
             copy_target: !args.no_copy_target,
+            copy_vcs: args.copy_vcs || config.copy_vcs.unwrap_or(false),
+            coverage,
+            coverage_order: args.coverage_order,
+            deterministic_env: args.deterministic_env || config.deterministic_env.unwrap_or(false),
+            dry_run: args.dry_run,
+            exclude_functions,
+            exclude_tags,
             globset,
-            output_in_dir: args.output.clone(),
+            file_patterns: args.file.clone(),
+            fuzz_targets: Vec::new(),
+            fuzz_time,
+            idle_timeout,
+            test_cpu_timeout,
+            ignore_rust_toolchain: args.ignore_rust_toolchain,
+            include_generated: args.include_generated,
+            isolate_nested_cargo: args.isolate_nested_cargo
+                || config.isolate_nested_cargo.unwrap_or(false),
+            iterate: args.iterate,
+            max_duration,
+            max_mutants: args.max_mutants,
+            max_mutants_per_fn,
+            mutation_kinds,
+            order,
+            // A relative output directory in the config is relative to the tree.
+            output_in_dir: args
+                .output
+                .clone()
+                .or_else(|| config.output.as_ref().map(|output| args.dir.join(output))),
+            package: None,
             print_caught: args.caught,
             print_unviable: args.unviable,
+            prioritize: args.prioritize,
+            quiet_copy: args.quiet_copy,
+            rust_toolchain: None,
             shuffle: !args.no_shuffle,
             show_times: !args.no_times,
             show_all_logs: args.all_logs,
+            score_thresholds,
+            timeout_multipliers,
+            slowest: args.slowest,
+            symlinks,
+            timeout_policy: args.timeout_counts_as.unwrap_or_default(),
+            test_tool: args.test_tool.unwrap_or_default(),
             test_timeout: args
                 .timeout
                 .map(Duration::from_secs_f64)
                 .unwrap_or(Duration::MAX),
-            additional_cargo_test_args: args.cargo_test_args.clone(),
+            additional_cargo_args,
+            mutant_env,
         })
     }
 }
+
+/// Multiply a timeout, saturating rather than overflowing.
+fn multiply_timeout(timeout: Duration, multiplier: f64) -> Duration {
+    Duration::try_from_secs_f64(timeout.as_secs_f64() * multiplier).unwrap_or(Duration::MAX)
+}
+
+/// Build a set of globs matching paths within the tree, or None if there are no globs.
+///
+/// Globs containing a slash match the entire path; others match the last component.
+pub fn build_path_glob_set<I, S>(globs: I) -> Result<Option<GlobSet>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut builder = GlobSetBuilder::new();
+    let mut empty = true;
+    for glob_str in globs {
+        let glob_str = glob_str.as_ref();
+        if glob_str.contains('/') {
+            builder.add(Glob::new(glob_str)?);
+        } else {
+            builder.add(Glob::new(&format!("**/{}", glob_str))?);
+        }
+        empty = false;
+    }
+    if empty {
+        Ok(None)
+    } else {
+        Ok(Some(builder.build()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use camino::Utf8Path;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn timeout_multipliers_match_paths_and_functions() {
+        let source_tree = SourceTree::new(Utf8Path::new("testdata/tree/well_tested")).unwrap();
+        let mut options = Options::default();
+        let mutants = source_tree.mutants(&options).unwrap();
+        let find = |file: &str, function: &str| {
+            mutants
+                .iter()
+                .find(|m| {
+                    m.source_file().tree_relative_slashes() == file && m.function_name() == function
+                })
+                .unwrap()
+        };
+        let count_odd = find("src/loops.rs", "count_odd");
+        let halvings = find("src/loops.rs", "halvings");
+        let has_nested = find("src/nested_function.rs", "has_nested");
+        assert_eq!(count_odd.function_path(), "loops::count_odd");
+
+        options.set_test_timeout(Duration::from_secs(10));
+        options.timeout_multipliers = vec![
+            TimeoutMultiplier::new("loops.rs", 2.0).unwrap(),
+            TimeoutMultiplier::new("loops::count_*", 3.0).unwrap(),
+        ];
+        assert_eq!(
+            options.mutant_test_timeout(count_odd),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            options.mutant_test_timeout(halvings),
+            Duration::from_secs(20)
+        );
+        assert_eq!(
+            options.mutant_test_timeout(has_nested),
+            Duration::from_secs(10)
+        );
+
+        options.set_test_timeout(Duration::MAX);
+        assert_eq!(options.mutant_test_timeout(count_odd), Duration::MAX);
+        assert_eq!(options.mutant_test_cpu_timeout(count_odd), None);
+
+        options.test_cpu_timeout = Some(Duration::from_secs(5));
+        assert_eq!(
+            options.mutant_test_cpu_timeout(count_odd),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            options.mutant_test_cpu_timeout(has_nested),
+            Some(Duration::from_secs(5))
+        );
+
+        assert!(TimeoutMultiplier::new("src/net/**", 0.0).is_err());
+        assert!(TimeoutMultiplier::new("src/net/[", 2.0).is_err());
+    }
+}
@@ -2,17 +2,23 @@
 
 //! The outcome of running a command.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs;
+use std::ops::Range;
+use std::str::FromStr;
 use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use serde::ser::SerializeStruct;
-use serde::Serialize;
 use serde::Serializer;
+use serde::{Deserialize, Serialize};
 
+use crate::allowlist::Allowlist;
+use crate::cargo::{BuildMessages, CargoCommand};
 use crate::exit_code;
-use crate::log_file::LogFile;
+use crate::log_file::{output_text, LogFile};
+use crate::mutate::MutationKind;
 use crate::*;
 
 /// What phase of evaluating a tree?
@@ -21,6 +27,8 @@ pub enum Phase {
     Check,
     Build,
     Test,
+    /// Run each fuzz target for a while, after the tests pass.
+    Fuzz,
 }
 
 impl Phase {
@@ -29,10 +37,17 @@ impl Phase {
             Phase::Check => "check",
             Phase::Build => "build",
             Phase::Test => "test",
+            Phase::Fuzz => "fuzz",
         }
     }
 
-    pub const ALL: &'static [Phase] = &[Phase::Check, Phase::Build, Phase::Test];
+    /// True for the phases that look for bugs in a tree that builds: a mutant that
+    /// fails these is caught.
+    pub fn is_test(&self) -> bool {
+        matches!(self, Phase::Test | Phase::Fuzz)
+    }
+
+    pub const ALL: &'static [Phase] = &[Phase::Check, Phase::Build, Phase::Test, Phase::Fuzz];
 }
 
 impl fmt::Display for Phase {
@@ -41,20 +56,330 @@ impl fmt::Display for Phase {
     }
 }
 
+/// How mutants that caused the tests to time out count towards the mutation score.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPolicy {
+    /// The tests detected a change in behavior, by hanging, so the mutant was caught.
+    Caught,
+    /// The tests didn't fail, so the mutant was missed.
+    Missed,
+    /// Timeouts don't count towards the score either way.
+    #[default]
+    Inconclusive,
+}
+
+impl FromStr for TimeoutPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "caught" => Ok(TimeoutPolicy::Caught),
+            "missed" => Ok(TimeoutPolicy::Missed),
+            "inconclusive" => Ok(TimeoutPolicy::Inconclusive),
+            _ => Err(anyhow!(
+                "unknown timeout policy {:?}: expected caught, missed, or inconclusive",
+                s
+            )),
+        }
+    }
+}
+
 /// The outcome from a whole lab run containing multiple mutants.
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone)]
 pub struct LabOutcome {
     outcomes: Vec<Outcome>,
+    /// Mutants that are allowed to be missed without counting against the score.
+    allowlist: Allowlist,
+    /// How timeouts count towards the score.
+    timeout_policy: TimeoutPolicy,
+    /// True if the baseline test ran no tests, so no mutants were tested.
+    baseline_ran_no_tests: bool,
 }
 
 impl LabOutcome {
+    pub fn new(allowlist: Allowlist, timeout_policy: TimeoutPolicy) -> LabOutcome {
+        LabOutcome {
+            outcomes: Vec::new(),
+            allowlist,
+            timeout_policy,
+            baseline_ran_no_tests: false,
+        }
+    }
+
+    /// Record that the baseline ran no tests, so no mutants were tested.
+    pub fn set_baseline_ran_no_tests(&mut self) {
+        self.baseline_ran_no_tests = true;
+    }
+
+    /// Return a copy with all the durations set to zero, so that reports are
+    /// reproducible.
+    pub fn without_times(&self) -> LabOutcome {
+        LabOutcome {
+            outcomes: self.outcomes.iter().map(Outcome::without_times).collect(),
+            ..self.clone()
+        }
+    }
+
     /// Record the event of one test.
     pub fn add(&mut self, outcome: &Outcome) {
         self.outcomes.push(outcome.clone());
     }
 
+    /// Return all the outcomes recorded so far.
+    pub fn outcomes(&self) -> &[Outcome] {
+        &self.outcomes
+    }
+
+    /// Return counts of mutants in each state, and the mutation score.
+    pub fn run_score(&self) -> RunScore {
+        self.score(&self.outcomes)
+    }
+
+    /// True if this outcome is a missed mutant that's in the allowlist.
+    pub fn is_allowed_miss(&self, outcome: &Outcome) -> bool {
+        match &outcome.scenario {
+            Scenario::Mutant(mutant) => {
+                outcome.mutant_missed() && self.allowlist.contains(&mutant.id())
+            }
+            _ => false,
+        }
+    }
+
+    /// Count up the mutants among some outcomes.
+    fn score<'a, I>(&self, outcomes: I) -> RunScore
+    where
+        I: IntoIterator<Item = &'a Outcome>,
+    {
+        self.score_summaries(outcomes.into_iter().filter_map(|o| match &o.scenario {
+            Scenario::Mutant(mutant) => Some((mutant.id(), o.summary())),
+            _ => None,
+        }))
+    }
+
+    /// Count up the summaries of some mutants, given with their ids, counting allowed
+    /// missed mutants separately, and counting timeouts according to the policy for
+    /// this run.
+    pub fn score_summaries<I, S>(&self, mutants: I) -> RunScore
+    where
+        I: IntoIterator<Item = (S, SummaryOutcome)>,
+        S: AsRef<str>,
+    {
+        self.allowlist
+            .score(mutants)
+            .with_timeout_policy(self.timeout_policy)
+    }
+
+    /// Return the counts and score for each kind of mutation that was tested, in the
+    /// order of [MutationKind::ALL].
+    pub fn kind_scores(&self) -> Vec<(MutationKind, RunScore)> {
+        MutationKind::ALL
+            .iter()
+            .filter_map(|&kind| {
+                let outcomes: Vec<&Outcome> = self
+                    .outcomes
+                    .iter()
+                    .filter(|o| match &o.scenario {
+                        Scenario::Mutant(mutant) => mutant.op.kind() == kind,
+                        _ => false,
+                    })
+                    .collect();
+                if outcomes.is_empty() {
+                    None
+                } else {
+                    Some((kind, self.score(outcomes)))
+                }
+            })
+            .collect()
+    }
+
+    /// Return the counts and score for the mutants in each source file, keyed by the
+    /// file's tree-relative path.
+    pub fn file_scores(&self) -> BTreeMap<String, RunScore> {
+        self.scores_by(|mutant| vec![mutant.source_file().tree_relative_slashes()])
+    }
+
+    /// Return the counts and score for the mutants in each module, keyed by the module
+    /// path, like `crate::telemetry::span`.
+    ///
+    /// Each module's score includes the mutants in its submodules, so the score for
+    /// `crate` covers the whole tree.
+    pub fn module_scores(&self) -> BTreeMap<String, RunScore> {
+        self.scores_by(|mutant| {
+            let mut path = "crate".to_owned();
+            let mut paths = vec![path.clone()];
+            for component in mutant.source_file().module_path() {
+                path = format!("{}::{}", path, component);
+                paths.push(path.clone());
+            }
+            paths
+        })
+    }
+
+    /// Return the counts and score for the mutants with each [ItemTag], keyed by the
+    /// tag name, like `deprecated`. Mutants with no tags aren't counted.
+    pub fn tag_scores(&self) -> BTreeMap<String, RunScore> {
+        self.scores_by(|mutant| {
+            mutant
+                .tags()
+                .iter()
+                .map(|tag| tag.name().to_owned())
+                .collect()
+        })
+    }
+
+    /// Count up the mutants under each of the keys returned by `keys_fn`.
+    fn scores_by<F>(&self, keys_fn: F) -> BTreeMap<String, RunScore>
+    where
+        F: Fn(&Mutant) -> Vec<String>,
+    {
+        let mut outcomes: BTreeMap<String, Vec<&Outcome>> = BTreeMap::new();
+        for outcome in &self.outcomes {
+            if let Scenario::Mutant(mutant) = &outcome.scenario {
+                for key in keys_fn(mutant) {
+                    outcomes.entry(key).or_default().push(outcome);
+                }
+            }
+        }
+        outcomes
+            .into_iter()
+            .map(|(key, outcomes)| (key, self.score(outcomes)))
+            .collect()
+    }
+
+    /// Return the counts and score for an area of the tree named in the config: either
+    /// a module path like `crate::parser`, or a directory or file like `src/parser`.
+    ///
+    /// Returns None if no mutants in that area were tested.
+    pub fn area_score(&self, area: &str) -> Option<RunScore> {
+        if area.contains("::") || area == "crate" {
+            return self.module_scores().remove(area);
+        }
+        let area = area.trim_end_matches('/');
+        let outcomes: Vec<&Outcome> = self
+            .outcomes
+            .iter()
+            .filter(|o| match &o.scenario {
+                Scenario::Mutant(mutant) => {
+                    let path = mutant.source_file().tree_relative_slashes();
+                    path == area
+                        || path
+                            .strip_prefix(area)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                }
+                _ => false,
+            })
+            .collect();
+        if outcomes.is_empty() {
+            None
+        } else {
+            Some(self.score(outcomes))
+        }
+    }
+
+    /// Describe each area whose score is below its minimum, one message per area.
+    ///
+    /// Areas where no viable mutants were tested have no score, and so don't fail.
+    pub fn scores_below_minimum(&self, thresholds: &[(String, f64)]) -> Vec<String> {
+        thresholds
+            .iter()
+            .filter_map(|(area, minimum)| {
+                let score = self.area_score(area)?.score?;
+                if score < *minimum {
+                    Some(format!(
+                        "Mutation score for {} is {:.0}%, below the minimum of {}%",
+                        area,
+                        score.floor(),
+                        minimum
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Describe the outcomes for each kind of mutation, one per line, or None if no
+    /// mutants were tested.
+    pub fn describe_kind_scores(&self) -> Option<String> {
+        let kind_scores = self.kind_scores();
+        if kind_scores.is_empty() {
+            return None;
+        }
+        let mut s = "Outcomes by kind of mutation:\n".to_owned();
+        for (kind, run_score) in kind_scores {
+            s.push_str(&format!("  {}: {}\n", kind, run_score));
+        }
+        Some(s)
+    }
+
+    /// Describe the outcomes of the mutants with each tag, one per line, or None if
+    /// no tagged mutants were tested.
+    pub fn describe_tag_scores(&self) -> Option<String> {
+        let tag_scores = self.tag_scores();
+        if tag_scores.is_empty() {
+            return None;
+        }
+        let mut s = "Outcomes by tag:\n".to_owned();
+        for (tag, run_score) in tag_scores {
+            s.push_str(&format!("  {}: {}\n", tag, run_score));
+        }
+        Some(s)
+    }
+
+    /// Return the outcomes of up to `n` mutants that took longest to build and test,
+    /// slowest first.
+    pub fn slowest_mutants(&self, n: usize) -> Vec<&Outcome> {
+        let mut mutant_outcomes: Vec<&Outcome> = self
+            .outcomes
+            .iter()
+            .filter(|o| o.scenario.is_mutant())
+            .collect();
+        mutant_outcomes.sort_by_key(|o| std::cmp::Reverse(o.total_duration()));
+        mutant_outcomes.truncate(n);
+        mutant_outcomes
+    }
+
+    /// Return up to `n` source files whose mutants took longest in total to build and
+    /// test, with that total, slowest first.
+    pub fn slowest_files(&self, n: usize) -> Vec<(String, Duration)> {
+        let mut file_durations: BTreeMap<String, Duration> = BTreeMap::new();
+        for outcome in &self.outcomes {
+            if let Scenario::Mutant(mutant) = &outcome.scenario {
+                *file_durations
+                    .entry(mutant.source_file().tree_relative_slashes())
+                    .or_default() += outcome.total_duration();
+            }
+        }
+        let mut file_durations: Vec<(String, Duration)> = file_durations.into_iter().collect();
+        file_durations.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        file_durations.truncate(n);
+        file_durations
+    }
+
+    /// Describe the `n` slowest mutants and source files, or None if no mutants were
+    /// tested.
+    pub fn describe_slowest(&self, n: usize) -> Option<String> {
+        let slowest_mutants = self.slowest_mutants(n);
+        if slowest_mutants.is_empty() {
+            return None;
+        }
+        let mut s = "Slowest mutants:\n".to_owned();
+        for outcome in slowest_mutants {
+            s.push_str(&format!(
+                "  {:.1}s {}\n",
+                outcome.total_duration().as_secs_f64(),
+                outcome.scenario
+            ));
+        }
+        s.push_str("Slowest files:\n");
+        for (file, duration) in self.slowest_files(n) {
+            s.push_str(&format!("  {:.1}s {}\n", duration.as_secs_f64(), file));
+        }
+        Some(s)
+    }
+
     /// Return the overall program exit code reflecting this outcome.
-    pub fn exit_code(&self) -> i32 {
+    pub fn exit_code(&self, options: &Options) -> i32 {
         // TODO: Maybe move this into an error returned from experiment()?
         if self
             .outcomes
@@ -62,9 +387,27 @@ impl LabOutcome {
             .any(|o| !o.scenario.is_mutant() && !o.success())
         {
             exit_code::CLEAN_TESTS_FAILED
-        } else if self.outcomes.iter().any(|o| o.has_timeout()) {
+        } else if self.baseline_ran_no_tests {
+            exit_code::NO_TESTS
+        } else if self.timeout_policy == TimeoutPolicy::Inconclusive
+            && self.outcomes.iter().any(|o| o.has_timeout())
+        {
             exit_code::TIMEOUT
-        } else if self.outcomes.iter().any(|o| o.mutant_missed()) {
+        } else if !self
+            .scores_below_minimum(&options.score_thresholds)
+            .is_empty()
+        {
+            exit_code::SCORE_BELOW_MINIMUM
+        } else if self
+            .outcomes
+            .iter()
+            .any(|o| o.mutant_missed() && !self.is_allowed_miss(o))
+            || (self.timeout_policy == TimeoutPolicy::Missed
+                && self
+                    .outcomes
+                    .iter()
+                    .any(|o| o.scenario.is_mutant() && o.has_timeout()))
+        {
             exit_code::FOUND_PROBLEMS
         } else {
             exit_code::SUCCESS
@@ -72,6 +415,96 @@ impl LabOutcome {
     }
 }
 
+impl Serialize for LabOutcome {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let kinds: BTreeMap<&str, RunScore> = self
+            .kind_scores()
+            .into_iter()
+            .map(|(kind, run_score)| (kind.name(), run_score))
+            .collect();
+        let mut ss = serializer.serialize_struct("LabOutcome", 5)?;
+        ss.serialize_field("outcomes", &self.outcomes)?;
+        ss.serialize_field("kinds", &kinds)?;
+        ss.serialize_field("tags", &self.tag_scores())?;
+        ss.serialize_field("files", &self.file_scores())?;
+        ss.serialize_field("modules", &self.module_scores())?;
+        ss.end()
+    }
+}
+
+/// Counts of mutants by their outcome in one run.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunScore {
+    pub caught: usize,
+    pub missed: usize,
+    pub unviable: usize,
+    pub timeout: usize,
+    /// Missed mutants that are in the allowlist, which don't count towards the score.
+    #[serde(default)]
+    pub allowed: usize,
+    /// Percentage of viable mutants that were caught, or None if there were none.
+    pub score: Option<f64>,
+}
+
+impl RunScore {
+    /// Count up the summaries of some mutant outcomes.
+    pub fn from_summaries<I, S>(summaries: I) -> RunScore
+    where
+        I: IntoIterator<Item = S>,
+        S: std::borrow::Borrow<SummaryOutcome>,
+    {
+        let mut run_score = RunScore::default();
+        for summary in summaries {
+            match summary.borrow() {
+                SummaryOutcome::CaughtMutant => run_score.caught += 1,
+                SummaryOutcome::MissedMutant => run_score.missed += 1,
+                SummaryOutcome::Unviable => run_score.unviable += 1,
+                SummaryOutcome::Timeout => run_score.timeout += 1,
+                SummaryOutcome::Success | SummaryOutcome::Failure => {}
+            }
+        }
+        run_score.with_timeout_policy(TimeoutPolicy::Inconclusive)
+    }
+
+    /// Recalculate the score, counting timeouts according to the policy.
+    pub fn with_timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> RunScore {
+        let (detected, viable) = match timeout_policy {
+            TimeoutPolicy::Caught => (
+                self.caught + self.timeout,
+                self.caught + self.missed + self.timeout,
+            ),
+            TimeoutPolicy::Missed => (self.caught, self.caught + self.missed + self.timeout),
+            TimeoutPolicy::Inconclusive => (self.caught, self.caught + self.missed),
+        };
+        self.score = if viable > 0 {
+            Some(100.0 * detected as f64 / viable as f64)
+        } else {
+            None
+        };
+        self
+    }
+}
+
+impl fmt::Display for RunScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} caught, {} missed, {} unviable, {} timed out",
+            self.caught, self.missed, self.unviable, self.timeout
+        )?;
+        if self.allowed > 0 {
+            write!(f, ", {} allowed", self.allowed)?;
+        }
+        if let Some(score) = self.score {
+            write!(f, ", score {:.0}%", score.floor())?;
+        }
+        Ok(())
+    }
+}
+
 /// The result of running one mutation scenario.
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[must_use]
@@ -83,6 +516,12 @@ pub struct Outcome {
     pub scenario: Scenario,
     /// For each phase, the duration and the cargo result.
     phase_results: Vec<PhaseResult>,
+    /// The problem with the machine that made the first attempt at this scenario fail,
+    /// if it was retried.
+    retried_because: Option<String>,
+    /// Tests that failed, but were ignored because they also failed in the baseline,
+    /// with `--allow-baseline-failures`.
+    ignored_failed_tests: Vec<String>,
 }
 
 impl Serialize for Outcome {
@@ -91,16 +530,28 @@ impl Serialize for Outcome {
         S: Serializer,
     {
         // custom serialize to omit inessential info
-        let mut ss = serializer.serialize_struct("Outcome", 4)?;
+        let mut ss = serializer.serialize_struct("Outcome", 6)?;
         ss.serialize_field("scenario", &self.scenario)?;
         ss.serialize_field("log_path", &self.log_path)?;
+        ss.serialize_field("log_file", &self.log_file())?;
+        ss.serialize_field("status", &self.status())?;
+        // Kept for readers of older outcomes; `status` is simpler to interpret.
         ss.serialize_field("summary", &self.summary())?;
         ss.serialize_field("phase_results", &self.phase_results)?;
+        if let Some(retried_because) = &self.retried_because {
+            ss.serialize_field("retried_because", retried_because)?;
+        }
+        if !self.ignored_failed_tests.is_empty() {
+            ss.serialize_field("ignored_failed_tests", &self.ignored_failed_tests)?;
+        }
+        if let Some(peak_rss) = self.peak_rss() {
+            ss.serialize_field("peak_rss", &peak_rss)?;
+        }
         ss.end()
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SummaryOutcome {
     Success,
     CaughtMutant,
@@ -110,13 +561,77 @@ pub enum SummaryOutcome {
     Timeout,
 }
 
+/// The status of a scenario, as recorded in `outcomes.json`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub enum Status {
+    /// The source tree or baseline built and passed its tests.
+    Passed,
+    /// The mutant was caught by failing tests.
+    Caught,
+    /// The mutant built and passed the tests.
+    Missed,
+    /// The mutant failed to check or build.
+    Unviable,
+    /// Cargo was stopped by a timeout.
+    Timeout,
+    /// The tests wrote no output for longer than the idle timeout, and were stopped.
+    /// This counts as a timeout in the scores.
+    Hung,
+    /// The source tree or baseline failed to build or pass its tests.
+    Failed,
+}
+
+impl From<&SummaryOutcome> for Status {
+    fn from(summary: &SummaryOutcome) -> Status {
+        match summary {
+            SummaryOutcome::Success => Status::Passed,
+            SummaryOutcome::CaughtMutant => Status::Caught,
+            SummaryOutcome::MissedMutant => Status::Missed,
+            SummaryOutcome::Unviable => Status::Unviable,
+            SummaryOutcome::Timeout => Status::Timeout,
+            SummaryOutcome::Failure => Status::Failed,
+        }
+    }
+}
+
 impl Outcome {
     pub fn new(log_file: &LogFile, scenario: Scenario) -> Outcome {
         Outcome {
             log_path: log_file.path().to_owned(),
             scenario,
             phase_results: Vec::new(),
+            retried_because: None,
+            ignored_failed_tests: Vec::new(),
+        }
+    }
+
+    /// Record that this outcome is from a second attempt, after the first failed
+    /// because of a problem with the machine.
+    pub fn set_retried_because(&mut self, problem: &str) {
+        self.retried_because = Some(problem.to_owned());
+    }
+
+    /// If the tests failed, but every test that failed is in `known_failures`, count
+    /// the test phase as a success, and remember which tests were ignored.
+    ///
+    /// Returns true if the failure was ignored. The exit code of cargo is kept, so the
+    /// phase result still shows that cargo failed.
+    pub fn ignore_known_failures(&mut self, known_failures: &[String]) -> Result<bool> {
+        if self.last_phase() != Phase::Test || self.last_phase_result() != CargoResult::Failure {
+            return Ok(false);
+        }
+        let failed_tests = self.failed_tests()?;
+        if failed_tests.is_empty() || !failed_tests.iter().all(|t| known_failures.contains(t)) {
+            return Ok(false);
         }
+        self.phase_results.last_mut().unwrap().cargo_result = CargoResult::Success;
+        self.ignored_failed_tests = failed_tests;
+        Ok(true)
+    }
+
+    /// Tests that failed but were ignored by [Outcome::ignore_known_failures].
+    pub fn ignored_failed_tests(&self) -> &[String] {
+        &self.ignored_failed_tests
     }
 
     pub fn add_phase_result(
@@ -124,18 +639,128 @@ impl Outcome {
         phase: Phase,
         duration: Duration,
         cargo_result: CargoResult,
+        exit_code: Option<i32>,
+        log_range: Range<u64>,
     ) {
         self.phase_results.push(PhaseResult {
             phase,
             duration,
             cargo_result,
+            exit_code,
+            log_range,
+            build_messages: None,
+            peak_rss: None,
+            command: None,
         });
     }
 
+    /// Record what cargo said about the build in the most recent phase.
+    pub fn set_build_messages(&mut self, build_messages: BuildMessages) {
+        if let Some(phase_result) = self.phase_results.last_mut() {
+            phase_result.build_messages = Some(build_messages);
+        }
+    }
+
+    /// Record the cargo command run in the most recent phase.
+    pub fn set_command(&mut self, command: CargoCommand) {
+        if let Some(phase_result) = self.phase_results.last_mut() {
+            phase_result.command = Some(command);
+        }
+    }
+
+    /// Record the peak memory use of cargo in the most recent phase.
+    pub fn set_peak_rss(&mut self, peak_rss: u64) {
+        if let Some(phase_result) = self.phase_results.last_mut() {
+            phase_result.peak_rss = Some(peak_rss);
+        }
+    }
+
+    /// The most resident memory, in bytes, used at once by cargo and its subprocesses
+    /// in any phase, if it was measured.
+    pub fn peak_rss(&self) -> Option<u64> {
+        self.phase_results.iter().filter_map(|pr| pr.peak_rss).max()
+    }
+
+    /// Return the codes of the compiler errors from the phase that failed, like
+    /// `E0308`, if cargo reported them.
+    pub fn error_codes(&self) -> &[String] {
+        self.phase_results
+            .last()
+            .and_then(|phase_result| phase_result.build_messages.as_ref())
+            .map(|messages| messages.error_codes.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Return the compiler's errors from the phase that failed, as they'd be shown
+    /// without JSON, if cargo reported them.
+    pub fn compiler_errors(&self) -> &[String] {
+        self.phase_results
+            .last()
+            .and_then(|phase_result| phase_result.build_messages.as_ref())
+            .map(|messages| messages.errors.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Return a copy of this outcome with all the durations set to zero, and without
+    /// memory measurements, so that reports are reproducible.
+    pub fn without_times(&self) -> Outcome {
+        let mut outcome = self.clone();
+        for phase_result in &mut outcome.phase_results {
+            phase_result.duration = Duration::ZERO;
+            phase_result.peak_rss = None;
+        }
+        outcome
+    }
+
+    /// The path of the log file relative to the output directory, with forward slashes.
+    pub fn log_file(&self) -> String {
+        format!("log/{}", self.log_path.file_name().unwrap_or_default())
+    }
+
     pub fn get_log_content(&self) -> Result<String> {
         fs::read_to_string(&self.log_path).context("read log file")
     }
 
+    /// Return the part of the log written during the last phase.
+    pub fn last_phase_log(&self) -> Result<String> {
+        let log = self.get_log_content()?;
+        let range = &self.phase_results.last().unwrap().log_range;
+        Ok(log
+            .get(range.start as usize..range.end as usize)
+            .unwrap_or(&log)
+            .to_owned())
+    }
+
+    /// Return the total number of tests that libtest reported running, or None if
+    /// the tests weren't run or the count can't be found in the log.
+    pub fn tests_run(&self) -> Result<Option<usize>> {
+        if self.test_duration().is_none() {
+            return Ok(None);
+        }
+        Ok(count_tests_run(&self.get_log_content()?))
+    }
+
+    /// Return the names of the tests that libtest reported as passing, or an empty
+    /// list if the tests weren't run.
+    pub fn passed_tests(&self) -> Result<Vec<String>> {
+        self.tests_with_result("ok")
+    }
+
+    /// Return the names of the tests that libtest reported as failing, or an empty
+    /// list if the tests weren't run.
+    ///
+    /// For a caught mutant, these are the tests that killed it.
+    pub fn failed_tests(&self) -> Result<Vec<String>> {
+        self.tests_with_result("FAILED")
+    }
+
+    fn tests_with_result(&self, result: &str) -> Result<Vec<String>> {
+        if self.test_duration().is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(tests_with_result(&self.get_log_content()?, result))
+    }
+
     pub fn last_phase(&self) -> Phase {
         self.phase_results.last().unwrap().phase
     }
@@ -157,37 +782,65 @@ impl Outcome {
     pub fn has_timeout(&self) -> bool {
         self.phase_results
             .iter()
-            .any(|pr| pr.cargo_result == CargoResult::Timeout)
+            .any(|pr| matches!(pr.cargo_result, CargoResult::Timeout | CargoResult::Hung))
+    }
+
+    /// True if cargo was stopped because it wrote no output for too long.
+    pub fn is_hung(&self) -> bool {
+        self.phase_results
+            .iter()
+            .any(|pr| pr.cargo_result == CargoResult::Hung)
     }
 
     pub fn check_or_build_failed(&self) -> bool {
         self.phase_results
             .iter()
-            .any(|pr| pr.phase != Phase::Test && pr.cargo_result == CargoResult::Failure)
+            .any(|pr| !pr.phase.is_test() && pr.cargo_result == CargoResult::Failure)
     }
 
-    /// True if this outcome is a caught mutant: it's a mutant and the tests failed.
+    /// True if this outcome is a caught mutant: it's a mutant and the tests or a fuzz
+    /// target failed.
     pub fn mutant_caught(&self) -> bool {
         self.scenario.is_mutant()
-            && self.last_phase() == Phase::Test
+            && self.last_phase().is_test()
             && self.last_phase_result() == CargoResult::Failure
     }
 
-    /// True if this outcome is a missed mutant: it's a mutant and the tests succeeded.
+    /// True if this outcome is a missed mutant: it's a mutant and the tests, and any
+    /// fuzz targets, succeeded.
     pub fn mutant_missed(&self) -> bool {
         self.scenario.is_mutant()
-            && self.last_phase() == Phase::Test
+            && self.last_phase().is_test()
             && self.last_phase_result().success()
     }
 
+    /// Total duration of the check and build phases.
+    pub fn build_duration(&self) -> Duration {
+        self.phase_results
+            .iter()
+            .filter(|pr| !pr.phase.is_test())
+            .map(|pr| pr.duration)
+            .sum()
+    }
+
     /// Duration of the test phase, if tests were run.
     pub fn test_duration(&self) -> Option<Duration> {
-        if let Some(phase_result) = self.phase_results.last() {
-            if phase_result.phase == Phase::Test {
-                return Some(phase_result.duration);
-            }
+        self.phase_results
+            .iter()
+            .find(|pr| pr.phase == Phase::Test)
+            .map(|pr| pr.duration)
+    }
+
+    /// Total duration of all phases.
+    pub fn total_duration(&self) -> Duration {
+        self.phase_results.iter().map(|pr| pr.duration).sum()
+    }
+
+    pub fn status(&self) -> Status {
+        match self.summary() {
+            SummaryOutcome::Timeout if self.is_hung() => Status::Hung,
+            summary => Status::from(&summary),
         }
-        None
     }
 
     pub fn summary(&self) -> SummaryOutcome {
@@ -225,6 +878,16 @@ struct PhaseResult {
     phase: Phase,
     duration: Duration,
     cargo_result: CargoResult,
+    /// The exit code of cargo, or None if it was killed by a timeout or signal.
+    exit_code: Option<i32>,
+    /// The byte offsets of the section of the log written during this phase.
+    log_range: Range<u64>,
+    /// What cargo said about the build, for phases that ask it for JSON messages.
+    build_messages: Option<BuildMessages>,
+    /// The most resident memory used at once by cargo and its subprocesses, in bytes.
+    peak_rss: Option<u64>,
+    /// The command that was run, so that it can be reproduced.
+    command: Option<CargoCommand>,
 }
 
 impl Serialize for PhaseResult {
@@ -233,10 +896,267 @@ impl Serialize for PhaseResult {
         S: Serializer,
     {
         // custom serialize to omit inessential info
-        let mut ss = serializer.serialize_struct("PhaseResult", 3)?;
+        let mut ss = serializer.serialize_struct("PhaseResult", 6)?;
         ss.serialize_field("phase", &self.phase)?;
         ss.serialize_field("duration", &self.duration.as_secs_f64())?;
         ss.serialize_field("cargo_result", &self.cargo_result)?;
+        ss.serialize_field("exit_code", &self.exit_code)?;
+        ss.serialize_field("log_start", &self.log_range.start)?;
+        ss.serialize_field("log_end", &self.log_range.end)?;
+        if let Some(build_messages) = &self.build_messages {
+            ss.serialize_field("rebuilt", &build_messages.rebuilt)?;
+            ss.serialize_field("error_codes", &build_messages.error_codes)?;
+        }
+        if let Some(peak_rss) = self.peak_rss {
+            ss.serialize_field("peak_rss", &peak_rss)?;
+        }
+        if let Some(command) = &self.command {
+            ss.serialize_field("command", command)?;
+        }
         ss.end()
     }
 }
+
+/// Sum the counts from libtest's "running N tests" lines in test output, or return
+/// None if there are no such lines, for example because a different test harness was
+/// used.
+fn count_tests_run(log: &str) -> Option<usize> {
+    let mut total = None;
+    for line in log.lines().map(output_text) {
+        let mut words = line.split_whitespace();
+        if let (Some("running"), Some(count), Some("test" | "tests"), None) =
+            (words.next(), words.next(), words.next(), words.next())
+        {
+            if let Ok(count) = count.parse::<usize>() {
+                *total.get_or_insert(0) += count;
+            }
+        }
+    }
+    total
+}
+
+/// Return the names of tests from libtest's "test NAME ... RESULT" lines in test
+/// output that have the given result, without duplicates.
+fn tests_with_result(log: &str, result: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in log.lines().map(output_text) {
+        if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(result))
+            .and_then(|rest| rest.strip_suffix(" ... "))
+        {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use camino::Utf8Path;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn serialize_outcome_status_and_exit_codes() {
+        let mut outcome = Outcome {
+            log_path: Utf8PathBuf::from("mutants.out/log/baseline.log"),
+            scenario: Scenario::Baseline,
+            phase_results: Vec::new(),
+            retried_because: None,
+            ignored_failed_tests: Vec::new(),
+        };
+        outcome.add_phase_result(
+            Phase::Build,
+            Duration::from_secs(2),
+            CargoResult::Success,
+            Some(0),
+            100..2000,
+        );
+        outcome.add_phase_result(
+            Phase::Test,
+            Duration::from_secs(1),
+            CargoResult::Failure,
+            Some(101),
+            2000..2500,
+        );
+        assert_eq!(outcome.status(), Status::Failed);
+        assert_eq!(
+            serde_json::to_value(&outcome).unwrap(),
+            json!({
+                "scenario": "Baseline",
+                "log_path": "mutants.out/log/baseline.log",
+                "log_file": "log/baseline.log",
+                "status": "Failed",
+                "summary": "Failure",
+                "phase_results": [
+                    {"phase": "Build", "duration": 2.0, "cargo_result": "Success", "exit_code": 0,
+                        "log_start": 100, "log_end": 2000},
+                    {"phase": "Test", "duration": 1.0, "cargo_result": "Failure", "exit_code": 101,
+                        "log_start": 2000, "log_end": 2500},
+                ],
+            })
+        );
+        outcome.set_peak_rss(50_000_000);
+        assert_eq!(outcome.peak_rss(), Some(50_000_000));
+        let value = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(value["peak_rss"], 50_000_000);
+        assert_eq!(value["phase_results"][1]["peak_rss"], 50_000_000);
+        assert_eq!(
+            serde_json::to_value(outcome.without_times()).unwrap()["peak_rss"],
+            serde_json::Value::Null
+        );
+        outcome.set_retried_because("the disk is full");
+        assert_eq!(
+            serde_json::to_value(&outcome).unwrap()["retried_because"],
+            "the disk is full"
+        );
+    }
+
+    #[test]
+    fn mutant_caught_by_fuzzing() {
+        let tree = Utf8Path::new("testdata/tree/factorial");
+        let source_file = SourceFile::new(tree, "src/bin/main.rs".parse().unwrap()).unwrap();
+        let mutant = discover_mutants(source_file.into(), &Options::default())
+            .unwrap()
+            .remove(0);
+        let fuzzed = |fuzz_result| {
+            let mut outcome = Outcome {
+                log_path: Utf8PathBuf::from("mutants.out/log/mutant.log"),
+                scenario: Scenario::Mutant(mutant.clone()),
+                phase_results: Vec::new(),
+                retried_because: None,
+                ignored_failed_tests: Vec::new(),
+            };
+            outcome.add_phase_result(
+                Phase::Build,
+                Duration::from_secs(2),
+                CargoResult::Success,
+                Some(0),
+                0..10,
+            );
+            outcome.add_phase_result(
+                Phase::Test,
+                Duration::from_secs(3),
+                CargoResult::Success,
+                Some(0),
+                10..20,
+            );
+            outcome.add_phase_result(
+                Phase::Fuzz,
+                Duration::from_secs(5),
+                fuzz_result,
+                Some(0),
+                20..30,
+            );
+            outcome
+        };
+        let caught = fuzzed(CargoResult::Failure);
+        assert_eq!(caught.summary(), SummaryOutcome::CaughtMutant);
+        assert_eq!(caught.build_duration(), Duration::from_secs(2));
+        assert_eq!(caught.test_duration(), Some(Duration::from_secs(3)));
+        assert_eq!(
+            fuzzed(CargoResult::Success).summary(),
+            SummaryOutcome::MissedMutant
+        );
+    }
+
+    #[test]
+    fn timeout_policy_changes_score() {
+        let run_score = RunScore::from_summaries([
+            SummaryOutcome::CaughtMutant,
+            SummaryOutcome::MissedMutant,
+            SummaryOutcome::Timeout,
+            SummaryOutcome::Timeout,
+        ]);
+        assert_eq!(run_score.score, Some(50.0));
+        assert_eq!(
+            run_score
+                .clone()
+                .with_timeout_policy(TimeoutPolicy::Caught)
+                .score,
+            Some(75.0)
+        );
+        assert_eq!(
+            run_score
+                .clone()
+                .with_timeout_policy(TimeoutPolicy::Missed)
+                .score,
+            Some(25.0)
+        );
+        assert_eq!(
+            "missed".parse::<TimeoutPolicy>().unwrap(),
+            TimeoutPolicy::Missed
+        );
+        assert!("hung".parse::<TimeoutPolicy>().is_err());
+    }
+
+    #[test]
+    fn display_run_score() {
+        let run_score = RunScore::from_summaries([
+            SummaryOutcome::CaughtMutant,
+            SummaryOutcome::CaughtMutant,
+            SummaryOutcome::MissedMutant,
+            SummaryOutcome::Unviable,
+        ]);
+        assert_eq!(
+            run_score.to_string(),
+            "2 caught, 1 missed, 1 unviable, 0 timed out, score 66%"
+        );
+        assert_eq!(
+            RunScore::from_summaries([SummaryOutcome::Timeout]).to_string(),
+            "0 caught, 0 missed, 0 unviable, 1 timed out"
+        );
+    }
+
+    #[test]
+    fn count_libtest_tests() {
+        let log = "
+     Running unittests src/lib.rs (target/debug/deps/x-1234)
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+     Running tests/cli.rs (target/debug/deps/cli-5678)
+
+running 1 test
+test it_works ... ok
+";
+        assert_eq!(count_tests_run(log), Some(1));
+        assert_eq!(
+            count_tests_run("running 0 tests\n   Doc-tests x\n\nrunning 0 tests\n"),
+            Some(0)
+        );
+        assert_eq!(count_tests_run("Starting 3 tests across 1 binary\n"), None);
+        assert_eq!(
+            count_tests_run("\n[   0.412 stdout] running 2 tests\n"),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn libtest_test_names_by_result() {
+        let log = "
+running 4 tests
+[   0.120 stdout] test tests::add ... ok
+test tests::sub ... FAILED
+test tests::slow ... ignored
+test src/lib.rs - add (line 3) ... ok
+
+failures:
+
+---- tests::sub stdout ----
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        assert_eq!(
+            tests_with_result(log, "ok"),
+            ["tests::add", "src/lib.rs - add (line 3)"]
+        );
+        assert_eq!(tests_with_result(log, "FAILED"), ["tests::sub"]);
+    }
+}
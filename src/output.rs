@@ -2,25 +2,100 @@
 
 //! A `mutants.out` directory holding logs and other output.
 
+use std::cmp::max;
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::thread::sleep;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use camino::Utf8Path;
-use chrono::{DateTime, Utc};
 use fs2::FileExt;
 use path_slash::PathExt;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::history::HISTORY_JSONL;
+use crate::outcome::LabOutcome;
 use crate::*;
 
 const OUTDIR_NAME: &str = "mutants.out";
 const ROTATED_NAME: &str = "mutants.out.old";
 const LOCK_JSON: &str = "lock.json";
 const LOCK_POLL: Duration = Duration::from_millis(100);
+const MUTANTS_JSON: &str = "mutants.json";
+const OUTCOMES_JSON: &str = "outcomes.json";
+const OUTCOMES_NDJSON: &str = "outcomes.ndjson";
+const LOGS_NDJSON: &str = "logs.ndjson";
+const BASELINE_JSON: &str = "baseline.json";
+const ARTIFACTS_JSON: &str = "artifacts.json";
+const BASELINE_FAILURE_TXT: &str = "baseline-failure.txt";
+
+/// Files copied from the previous output directory when it's rotated, so that they
+/// accumulate or can be reused across runs.
+const CARRIED_FORWARD: &[&str] = &[HISTORY_JSONL, BASELINE_JSON];
+
+/// The durations of the baseline, and the timeout chosen from them, saved in
+/// `baseline.json` so that a later run with `--baseline skip` can reuse them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineTimes {
+    /// Seconds to check and build the unmutated tree.
+    pub build_duration: f64,
+    /// Seconds to run the tests, if they were run.
+    pub test_duration: Option<f64>,
+    /// The automatic timeout for tests, in seconds, derived from the test duration.
+    pub auto_timeout: Option<f64>,
+    /// The tests that failed, and were ignored because of `--allow-baseline-failures`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_tests: Vec<String>,
+}
+
+impl BaselineTimes {
+    /// Record the times of a successful baseline, and the automatic timeout derived from
+    /// them, which is at least `min_auto_timeout`.
+    pub fn new(baseline: &Outcome, min_auto_timeout: Duration) -> BaselineTimes {
+        let test_duration = baseline.test_duration();
+        BaselineTimes {
+            build_duration: baseline.build_duration().as_secs_f64(),
+            test_duration: test_duration.map(|d| d.as_secs_f64()),
+            auto_timeout: test_duration
+                .map(|d| max(min_auto_timeout, d.mul_f32(5.0)).as_secs_f64()),
+            failed_tests: baseline.ignored_failed_tests().to_vec(),
+        }
+    }
+
+    pub fn build_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.build_duration)
+    }
+
+    pub fn test_duration(&self) -> Option<Duration> {
+        self.test_duration.map(Duration::from_secs_f64)
+    }
+
+    pub fn auto_timeout(&self) -> Option<Duration> {
+        self.auto_timeout.map(Duration::from_secs_f64)
+    }
+}
+
+/// A line in `logs.ndjson`, recording which scenario a log file is for.
+#[derive(Debug, Serialize)]
+struct LogIndexEntry {
+    /// The path of the log relative to the output directory, with forward slashes.
+    log_file: String,
+    /// A description of the scenario.
+    scenario: String,
+    /// The stable id of the mutant, if this is a mutant.
+    id: Option<String>,
+}
+
+/// The contents of `artifacts.json`, listing the files the run created in the output
+/// directory.
+#[derive(Debug, Serialize)]
+struct ArtifactsManifest {
+    cargo_mutants_version: String,
+    /// Paths relative to the output directory, with forward slashes.
+    files: Vec<String>,
+}
 
 /// The contents of a `lock.json` written into the output directory and used as a lock file.
 #[derive(Debug, Serialize)]
@@ -32,9 +107,8 @@ struct LockFile {
 }
 
 impl LockFile {
-    fn new() -> LockFile {
-        let now: DateTime<Utc> = Utc::now();
-        let start_time = now.to_rfc3339();
+    fn new(options: &Options) -> LockFile {
+        let start_time = options.report_time().to_rfc3339();
         LockFile {
             cargo_mutants_version: crate::VERSION.to_string(),
             start_time,
@@ -47,10 +121,11 @@ impl LockFile {
     /// directory.
     ///
     /// Return the `File` whose lifetime controls the file lock.
-    pub fn acquire_lock(output_dir: &Path) -> Result<File> {
+    pub fn acquire_lock(output_dir: &Path, options: &Options) -> Result<File> {
         let lock_path = output_dir.join(LOCK_JSON);
         let mut lock_file = File::options()
             .create(true)
+            .truncate(false)
             .write(true)
             .open(&lock_path)
             .context("open or create lock.json in existing directory")?;
@@ -72,7 +147,7 @@ impl LockFile {
         }
         lock_file.set_len(0)?;
         lock_file
-            .write_all(serde_json::to_string_pretty(&LockFile::new())?.as_bytes())
+            .write_all(serde_json::to_string_pretty(&LockFile::new(options))?.as_bytes())
             .context("write lock.json")?;
         Ok(lock_file)
     }
@@ -85,6 +160,9 @@ pub struct OutputDir {
     log_dir: Utf8PathBuf,
     #[allow(unused)] // Lifetime controls the file lock
     lock_file: File,
+    /// Record durations in outcomes; if false they're zeroed, so that reports are
+    /// reproducible.
+    show_times: bool,
 }
 
 impl OutputDir {
@@ -95,10 +173,18 @@ impl OutputDir {
     ///
     /// If the directory already exists and `lock.json` exists and is locked, this waits for
     /// the lock to be released. The returned `OutputDir` holds a lock for its lifetime.
-    pub fn new(in_dir: &Utf8Path) -> Result<OutputDir> {
+    ///
+    /// Any `history.jsonl` from the previous directory is copied into the new directory,
+    /// so that history accumulates across runs, and so is `baseline.json`, so that the
+    /// baseline can be skipped.
+    pub fn new(in_dir: &Utf8Path, options: &Options) -> Result<OutputDir> {
+        // The directory might be inside one that doesn't exist yet, like `target/mutants`
+        // before the first build.
+        fs::create_dir_all(in_dir).with_context(|| format!("create {:?}", in_dir))?;
         let output_dir = in_dir.join(OUTDIR_NAME);
+        let mut carried_forward = Vec::new();
         if output_dir.exists() {
-            LockFile::acquire_lock(output_dir.as_ref())?;
+            LockFile::acquire_lock(output_dir.as_ref(), options)?;
             // Now release the lock for a bit while we move the directory. This might be
             // slightly racy.
 
@@ -108,10 +194,22 @@ impl OutputDir {
             }
             fs::rename(&output_dir, &rotated)
                 .with_context(|| format!("move {:?} to {:?}", &output_dir, &rotated))?;
+            carried_forward = CARRIED_FORWARD
+                .iter()
+                .map(|name| rotated.join(name))
+                .filter(|p| p.is_file())
+                .collect();
         }
         fs::create_dir(&output_dir)
             .with_context(|| format!("create output directory {:?}", &output_dir))?;
-        let lock_file = LockFile::acquire_lock(output_dir.as_std_path())
+        for previous in carried_forward {
+            let name = previous
+                .file_name()
+                .expect("carried forward file has a name");
+            fs::copy(&previous, output_dir.join(name))
+                .with_context(|| format!("copy {:?} into new output directory", previous))?;
+        }
+        let lock_file = LockFile::acquire_lock(output_dir.as_std_path(), options)
             .context("create lock.json lock file")?;
         let log_dir = output_dir.join("log");
         fs::create_dir(&log_dir).with_context(|| format!("create log directory {:?}", &log_dir))?;
@@ -119,15 +217,158 @@ impl OutputDir {
             path: output_dir,
             log_dir,
             lock_file,
+            show_times: options.show_times,
         })
     }
 
-    /// Create a new log for a given scenario.
+    /// Create a new log for a given scenario, and record which scenario it's for in
+    /// `logs.ndjson`.
     ///
     /// Returns the [File] to which subprocess output should be sent, and a LogFile to read it
     /// later.
     pub fn create_log(&self, scenario: &Scenario) -> Result<LogFile> {
-        LogFile::create_in(&self.log_dir, &scenario.log_file_name_base())
+        let log_file = LogFile::create_in(&self.log_dir, &scenario.log_file_name_base())?;
+        let entry = LogIndexEntry {
+            log_file: format!("log/{}", log_file.path().file_name().unwrap_or_default()),
+            scenario: scenario.to_string(),
+            id: match scenario {
+                Scenario::Mutant(mutant) => Some(mutant.id()),
+                _ => None,
+            },
+        };
+        self.append_json_line(LOGS_NDJSON, &entry)?;
+        Ok(log_file)
+    }
+
+    /// Append one outcome as a line of `outcomes.ndjson`, as soon as it's known.
+    ///
+    /// Appending is cheap even for thousands of mutants, and the results of a run that
+    /// is interrupted or crashes are kept.
+    pub fn append_outcome(&self, outcome: &Outcome) -> Result<()> {
+        if self.show_times {
+            self.append_json_line(OUTCOMES_NDJSON, outcome)
+        } else {
+            self.append_json_line(OUTCOMES_NDJSON, &outcome.without_times())
+        }
+    }
+
+    /// Append a value as one line of JSON to a file in the output directory.
+    fn append_json_line<T: Serialize>(&self, file_name: &str, value: &T) -> Result<()> {
+        let path = self.path.join(file_name);
+        let mut line = serde_json::to_string(value)?;
+        line.push('\n');
+        File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("append to {}", path))
+    }
+
+    /// Write the list of mutants that will be tested into `mutants.json`.
+    pub fn write_mutants_json(&self, mutants: &[Mutant]) -> Result<()> {
+        let path = self.path.join(MUTANTS_JSON);
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::create(&path).with_context(|| format!("create {}", path))?),
+            mutants,
+        )
+        .with_context(|| format!("write {}", path))
+    }
+
+    /// Write all the outcomes of the run into `outcomes.json`, when it's finished.
+    pub fn write_outcomes_json(&self, lab_outcome: &LabOutcome) -> Result<()> {
+        let path = self.path.join(OUTCOMES_JSON);
+        let without_times;
+        let lab_outcome = if self.show_times {
+            lab_outcome
+        } else {
+            without_times = lab_outcome.without_times();
+            &without_times
+        };
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::create(&path).with_context(|| format!("create {}", path))?),
+            lab_outcome,
+        )
+        .with_context(|| format!("write {}", path))
+    }
+
+    /// Write the baseline times into `baseline.json`, for later runs that skip the
+    /// baseline.
+    pub fn write_baseline_times(&self, baseline_times: &BaselineTimes) -> Result<()> {
+        let path = self.path.join(BASELINE_JSON);
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::create(&path).with_context(|| format!("create {}", path))?),
+            baseline_times,
+        )
+        .with_context(|| format!("write {}", path))
+    }
+
+    /// Write `baseline-failure.txt`, describing why the unmutated tree failed.
+    pub fn write_baseline_failure(&self, report: &str) -> Result<()> {
+        let path = self.path.join(BASELINE_FAILURE_TXT);
+        fs::write(&path, report).with_context(|| format!("write {}", path))
+    }
+
+    /// Read the baseline times saved by a previous run, if there are any.
+    pub fn read_baseline_times(&self) -> Result<Option<BaselineTimes>> {
+        let path = self.path.join(BASELINE_JSON);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("read {}", path))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("parse {}", path))
+    }
+
+    /// Copy files that are carried forward between runs from another directory, such
+    /// as the project's directory in the user cache, if this directory doesn't already
+    /// have them.
+    pub fn carry_forward_from(&self, dir: &Utf8Path) -> Result<()> {
+        for name in CARRIED_FORWARD {
+            let (from, to) = (dir.join(name), self.path.join(name));
+            if from.is_file() && !to.exists() {
+                fs::copy(&from, &to).with_context(|| format!("copy {} to {}", from, to))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the files that are carried forward between runs into another directory,
+    /// such as the project's directory in the user cache.
+    pub fn save_carried_forward(&self, dir: &Utf8Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir))?;
+        for name in CARRIED_FORWARD {
+            let (from, to) = (self.path.join(name), dir.join(name));
+            if from.is_file() {
+                fs::copy(&from, &to).with_context(|| format!("copy {} to {}", from, to))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `artifacts.json`, listing every other file in the output directory.
+    fn write_artifacts_manifest(&self) -> Result<()> {
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(&self.path).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let relative = entry.path().strip_prefix(&self.path)?;
+                if relative != Path::new(ARTIFACTS_JSON) {
+                    files.push(relative.to_slash_lossy().into_owned());
+                }
+            }
+        }
+        let manifest = ArtifactsManifest {
+            cargo_mutants_version: crate::VERSION.to_owned(),
+            files,
+        };
+        let path = self.path.join(ARTIFACTS_JSON);
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::create(&path).with_context(|| format!("create {}", path))?),
+            &manifest,
+        )
+        .with_context(|| format!("write {}", path))
     }
 
     #[allow(dead_code)]
@@ -137,6 +378,40 @@ impl OutputDir {
     }
 }
 
+impl Drop for OutputDir {
+    /// Write the manifest when the run is finished with the directory, however it
+    /// stopped, and before the lock is released.
+    fn drop(&mut self) {
+        let _ = self.write_artifacts_manifest();
+    }
+}
+
+/// Remove the `mutants.out` directory within a directory, and the rotated
+/// `mutants.out.old`, and return those that existed.
+///
+/// This fails if `mutants.out` is locked by a run that's still going.
+pub fn remove_output_dirs(in_dir: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let output_dir = in_dir.join(OUTDIR_NAME);
+    let lock_path = output_dir.join(LOCK_JSON);
+    if lock_path.is_file() {
+        let lock_file = File::open(&lock_path).with_context(|| format!("open {}", lock_path))?;
+        if lock_file.try_lock_exclusive().is_err() {
+            return Err(anyhow!(
+                "{} is in use by another cargo-mutants process",
+                output_dir
+            ));
+        }
+    }
+    let mut removed = Vec::new();
+    for dir in [output_dir, in_dir.join(ROTATED_NAME)] {
+        if dir.is_dir() {
+            fs::remove_dir_all(&dir).with_context(|| format!("remove {}", dir))?;
+            removed.push(dir);
+        }
+    }
+    Ok(removed)
+}
+
 #[cfg(test)]
 mod test {
     use std::convert::TryInto;
@@ -176,7 +451,7 @@ mod test {
         let tmp = minimal_source_tree();
         let tmp_path = tmp.path().try_into().unwrap();
         let src_tree = SourceTree::new(tmp_path).unwrap();
-        let output_dir = OutputDir::new(src_tree.path()).unwrap();
+        let output_dir = OutputDir::new(src_tree.path(), &Options::default()).unwrap();
         assert_eq!(
             list_recursive(tmp.path()),
             &[
@@ -198,7 +473,7 @@ mod test {
         let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
 
         // Create an initial output dir with one log.
-        let output_dir = OutputDir::new(temp_dir_path).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
         output_dir.create_log(&Scenario::SourceTree).unwrap();
         assert!(temp_dir
             .path()
@@ -207,7 +482,7 @@ mod test {
         drop(output_dir); // release the lock.
 
         // The second time we create it in the same directory, the old one is moved away.
-        let output_dir = OutputDir::new(temp_dir_path).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
         output_dir.create_log(&Scenario::SourceTree).unwrap();
         output_dir.create_log(&Scenario::Baseline).unwrap();
         assert!(temp_dir
@@ -225,7 +500,7 @@ mod test {
         drop(output_dir);
 
         // The third time (and later), the .old directory is removed.
-        let output_dir = OutputDir::new(temp_dir_path).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
         output_dir.create_log(&Scenario::SourceTree).unwrap();
         assert!(temp_dir
             .path()
@@ -244,4 +519,117 @@ mod test {
             .join("mutants.out.old/log/baseline.log")
             .is_file());
     }
+
+    #[test]
+    fn history_is_carried_forward_when_rotating() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        fs::write(output_dir.path().join(HISTORY_JSONL), "{}\n").unwrap();
+        drop(output_dir);
+
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join(HISTORY_JSONL)).unwrap(),
+            "{}\n"
+        );
+        assert!(temp_dir
+            .path()
+            .join("mutants.out.old")
+            .join(HISTORY_JSONL)
+            .is_file());
+    }
+
+    #[test]
+    fn artifacts_manifest_is_written_when_closed() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        output_dir.create_log(&Scenario::Baseline).unwrap();
+        drop(output_dir);
+        let manifest: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(temp_dir.path().join("mutants.out/artifacts.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            manifest["files"],
+            serde_json::json!(["lock.json", "log/baseline.log", "logs.ndjson"])
+        );
+    }
+
+    #[test]
+    fn remove_output_dirs_unless_locked() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
+        drop(OutputDir::new(temp_dir_path, &Options::default()).unwrap());
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        assert!(remove_output_dirs(temp_dir_path)
+            .unwrap_err()
+            .to_string()
+            .contains("is in use by another cargo-mutants process"));
+        drop(output_dir);
+        assert_eq!(
+            remove_output_dirs(temp_dir_path).unwrap(),
+            [
+                temp_dir_path.join("mutants.out"),
+                temp_dir_path.join("mutants.out.old")
+            ]
+        );
+        assert_eq!(list_recursive(temp_dir.path()), [""]);
+        assert_eq!(
+            remove_output_dirs(temp_dir_path).unwrap(),
+            Vec::<Utf8PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn carried_forward_files_are_saved_and_restored() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let cache_dir = temp_dir_path.join("cache/project");
+        fs::create_dir(temp_dir_path.join("a")).unwrap();
+        fs::create_dir(temp_dir_path.join("b")).unwrap();
+        let output_dir = OutputDir::new(&temp_dir_path.join("a"), &Options::default()).unwrap();
+        fs::write(output_dir.path().join(HISTORY_JSONL), "{}\n").unwrap();
+        output_dir.save_carried_forward(&cache_dir).unwrap();
+        assert_eq!(list_recursive(cache_dir.as_std_path()), ["", HISTORY_JSONL]);
+
+        // An output directory elsewhere gets the files from the cache.
+        let output_dir = OutputDir::new(&temp_dir_path.join("b"), &Options::default()).unwrap();
+        output_dir.carry_forward_from(&cache_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join(HISTORY_JSONL)).unwrap(),
+            "{}\n"
+        );
+
+        // Files already in the output directory aren't replaced.
+        fs::write(cache_dir.join(HISTORY_JSONL), "{}\n{}\n").unwrap();
+        output_dir.carry_forward_from(&cache_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join(HISTORY_JSONL)).unwrap(),
+            "{}\n"
+        );
+    }
+
+    #[test]
+    fn baseline_times_are_carried_forward_when_rotating() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir_path = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        assert_eq!(output_dir.read_baseline_times().unwrap(), None);
+        let baseline_times = BaselineTimes {
+            build_duration: 2.5,
+            test_duration: Some(1.5),
+            auto_timeout: Some(20.0),
+            failed_tests: Vec::new(),
+        };
+        output_dir.write_baseline_times(&baseline_times).unwrap();
+        drop(output_dir);
+
+        let output_dir = OutputDir::new(temp_dir_path, &Options::default()).unwrap();
+        let read = output_dir.read_baseline_times().unwrap().unwrap();
+        assert_eq!(read, baseline_times);
+        assert_eq!(read.auto_timeout(), Some(Duration::from_secs(20)));
+        assert_eq!(read.test_duration(), Some(Duration::from_millis(1500)));
+    }
 }
@@ -0,0 +1,163 @@
+// Copyright 2022 Martin Pool
+
+//! Choose which mutants to test when the run is limited by `--max-mutants` or
+//! `--max-duration`.
+//!
+//! Rather than testing an arbitrary sample, the mutants are ranked by how much is likely
+//! to be learned from testing them: the kind of mutation, whether the mutated code is
+//! covered by the tests, and the heuristics in [crate::prioritize], which look at the
+//! run history. The highest-ranked mutants that fit in the budget are tested, in the
+//! order they would otherwise have been tested.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::mutate::MutationKind;
+use crate::prioritize::Priorities;
+use crate::*;
+
+/// Return how many mutants can be tested within the limits, given the estimated time
+/// to test each one.
+///
+/// At least one mutant is tested, even if it's expected to take longer than
+/// `max_duration`.
+pub fn mutants_in_budget(
+    n_mutants: usize,
+    max_mutants: Option<usize>,
+    max_duration: Option<Duration>,
+    per_mutant: Duration,
+) -> usize {
+    let mut count = n_mutants;
+    if let Some(max_mutants) = max_mutants {
+        count = count.min(max_mutants);
+    }
+    if let Some(max_duration) = max_duration {
+        if !per_mutant.is_zero() {
+            let fit = (max_duration.as_secs_f64() / per_mutant.as_secs_f64()) as usize;
+            count = count.min(fit.max(1));
+        }
+    }
+    count
+}
+
+/// Keep only the `count` most valuable mutants, without changing their order.
+pub fn select(mutants: &mut Vec<Mutant>, count: usize, priorities: &Priorities) {
+    select_by_value(mutants, count, |mutant| {
+        kind_value(mutant.op.kind()) + coverage_value(mutant) + priorities.score(mutant)
+    })
+}
+
+/// How much is learned from testing a mutant of this kind.
+///
+/// Replacing a whole function is the strongest sign that the function isn't tested, and
+/// operators that change control flow come next. Other kinds more often produce
+/// mutants that are unviable or equivalent to the original code.
+fn kind_value(kind: MutationKind) -> u32 {
+    match kind {
+        MutationKind::FnValue => 3,
+        MutationKind::Loop
+        | MutationKind::Try
+        | MutationKind::DeleteCall
+        | MutationKind::SwapIf => 2,
+        _ => 1,
+    }
+}
+
+/// Mutants of code that the tests don't run will almost certainly be missed, which the
+/// coverage data already shows, so covered mutants are more worth testing.
+fn coverage_value(mutant: &Mutant) -> u32 {
+    match mutant.covered() {
+        Some(true) => 2,
+        None => 1,
+        Some(false) => 0,
+    }
+}
+
+/// Keep the `count` mutants with the highest values, and of those with the same value,
+/// the ones that come first.
+fn select_by_value<F>(mutants: &mut Vec<Mutant>, count: usize, value: F)
+where
+    F: Fn(&Mutant) -> u32,
+{
+    let mut ranked: Vec<usize> = (0..mutants.len()).collect();
+    ranked.sort_by_cached_key(|&i| Reverse(value(&mutants[i])));
+    let kept: HashSet<usize> = ranked.into_iter().take(count).collect();
+    let mut i = 0;
+    mutants.retain(|_| {
+        i += 1;
+        kept.contains(&(i - 1))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use camino::Utf8Path;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn budget_limits_the_count() {
+        let per_mutant = Duration::from_secs(10);
+        assert_eq!(mutants_in_budget(50, None, None, per_mutant), 50);
+        assert_eq!(mutants_in_budget(50, Some(20), None, per_mutant), 20);
+        assert_eq!(mutants_in_budget(5, Some(20), None, per_mutant), 5);
+        assert_eq!(
+            mutants_in_budget(50, None, Some(Duration::from_secs(95)), per_mutant),
+            9
+        );
+        assert_eq!(
+            mutants_in_budget(50, Some(5), Some(Duration::from_secs(95)), per_mutant),
+            5
+        );
+        assert_eq!(
+            mutants_in_budget(50, None, Some(Duration::from_secs(1)), per_mutant),
+            1
+        );
+        assert_eq!(
+            mutants_in_budget(50, None, Some(Duration::from_secs(1)), Duration::ZERO),
+            50
+        );
+    }
+
+    #[test]
+    fn select_keeps_the_most_valuable_in_order() {
+        let source_tree = SourceTree::new(Utf8Path::new("testdata/tree/well_tested")).unwrap();
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::FnValue, MutationKind::Loop]);
+        let all = source_tree.mutants(&options).unwrap();
+        let n_fn_value = all
+            .iter()
+            .filter(|mutant| mutant.op.kind() == MutationKind::FnValue)
+            .count();
+        assert!(n_fn_value > 2);
+
+        let mut mutants = all.clone();
+        select_by_value(&mut mutants, 2, |mutant| kind_value(mutant.op.kind()));
+        let first_fn_values: Vec<Mutant> = all
+            .iter()
+            .filter(|mutant| mutant.op.kind() == MutationKind::FnValue)
+            .take(2)
+            .cloned()
+            .collect();
+        assert_eq!(mutants, first_fn_values);
+
+        let mut mutants = all.clone();
+        select_by_value(&mut mutants, n_fn_value + 1, |mutant| {
+            kind_value(mutant.op.kind())
+        });
+        assert_eq!(mutants.len(), n_fn_value + 1);
+        let first_loop = all
+            .iter()
+            .find(|mutant| mutant.op.kind() == MutationKind::Loop)
+            .unwrap();
+        assert!(mutants.contains(first_loop));
+        // The order of the original list is kept.
+        let positions: Vec<usize> = mutants
+            .iter()
+            .map(|mutant| all.iter().position(|m| m == mutant).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2022 Martin Pool
+
+//! Order mutants so that those most likely to be missed are tested first.
+//!
+//! This is heuristic: it looks at whether the mutant was missed in a previous run,
+//! whether its file was recently changed, and whether there are any tests near the
+//! mutated code. Testing likely-missed mutants first means that runs that are stopped
+//! early are more likely to have found something interesting.
+
+use std::collections::HashSet;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::git;
+use crate::history::{latest_summaries, HistoryEntry};
+use crate::outcome::SummaryOutcome;
+use crate::*;
+
+/// How many recent commits count as "recently changed" code.
+const RECENT_COMMITS: usize = 20;
+
+/// Weights for each heuristic, chosen so that a previous miss is the strongest signal.
+const PREVIOUSLY_MISSED_WEIGHT: u32 = 4;
+const RECENTLY_CHANGED_WEIGHT: u32 = 2;
+const NO_NEARBY_TESTS_WEIGHT: u32 = 1;
+
+/// Information about the tree used to prioritize mutants.
+#[derive(Debug)]
+pub struct Priorities {
+    /// The root of the source tree.
+    tree_path: Utf8PathBuf,
+    /// Ids of mutants that were missed in the most recent run that tested them.
+    previously_missed: HashSet<String>,
+    /// Tree-relative paths, with forward slashes, of recently changed files.
+    recently_changed: HashSet<String>,
+}
+
+impl Priorities {
+    /// Gather priority information from the run history and from git.
+    pub fn new(source_tree: &SourceTree, history: &[HistoryEntry]) -> Priorities {
+        Priorities {
+            tree_path: source_tree.path().to_owned(),
+            previously_missed: previously_missed(history),
+            recently_changed: git::recently_changed_files(source_tree.path(), RECENT_COMMITS),
+        }
+    }
+
+    /// Return a score for a mutant: higher scores are more likely to be missed.
+    pub fn score(&self, mutant: &Mutant) -> u32 {
+        let mut score = 0;
+        if self.previously_missed.contains(&mutant.id()) {
+            score += PREVIOUSLY_MISSED_WEIGHT;
+        }
+        if self
+            .recently_changed
+            .contains(&mutant.source_file().tree_relative_slashes())
+        {
+            score += RECENTLY_CHANGED_WEIGHT;
+        }
+        if !has_nearby_tests(&self.tree_path, mutant.source_file()) {
+            score += NO_NEARBY_TESTS_WEIGHT;
+        }
+        score
+    }
+
+    /// Sort mutants so that the most likely to be missed come first.
+    ///
+    /// The sort is stable, so mutants with the same priority keep their existing
+    /// (possibly shuffled) order.
+    pub fn sort(&self, mutants: &mut [Mutant]) {
+        mutants.sort_by_cached_key(|mutant| std::cmp::Reverse(self.score(mutant)));
+    }
+}
+
+/// Return the ids of mutants whose most recent recorded outcome was missed.
+fn previously_missed(history: &[HistoryEntry]) -> HashSet<String> {
+    latest_summaries(history)
+        .into_iter()
+        .filter(|(_, summary)| *summary == SummaryOutcome::MissedMutant)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// True if the source file contains tests, or has a sibling file of tests.
+fn has_nearby_tests(tree_path: &Utf8Path, source_file: &SourceFile) -> bool {
+    if source_file.code.contains("#[test]") || source_file.code.contains("#[cfg(test)]") {
+        return true;
+    }
+    // Look for conventional `tests.rs` or `test.rs` siblings.
+    let path = source_file.tree_relative_slashes();
+    let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    ["tests.rs", "test.rs"]
+        .iter()
+        .any(|name| tree_path.join(dir).join(name).is_file())
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::history::MutantHistory;
+    use crate::outcome::RunScore;
+
+    fn history_entry(mutants: &[(&str, SummaryOutcome)]) -> HistoryEntry {
+        HistoryEntry {
+            cargo_mutants_version: "0.0.0".to_owned(),
+            start_time: String::new(),
+            end_time: String::new(),
+            git_commit: None,
+            rust_toolchain: None,
+            run_score: RunScore::default(),
+            mutants: mutants
+                .iter()
+                .map(|(id, summary)| MutantHistory {
+                    id: id.to_string(),
+                    summary: summary.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn only_most_recent_outcome_counts() {
+        let history = [
+            history_entry(&[
+                ("a", SummaryOutcome::MissedMutant),
+                ("b", SummaryOutcome::MissedMutant),
+            ]),
+            history_entry(&[("a", SummaryOutcome::CaughtMutant)]),
+        ];
+        assert_eq!(previously_missed(&history).into_iter().collect_vec(), ["b"]);
+    }
+
+    #[test]
+    fn previously_missed_mutants_come_first() {
+        let tree_path = Utf8Path::new("testdata/tree/well_tested");
+        let source_tree = SourceTree::new(tree_path).unwrap();
+        let mut mutants = source_tree.mutants(&Options::default()).unwrap();
+        let last = mutants.last().unwrap().clone();
+        let priorities = Priorities {
+            tree_path: tree_path.to_owned(),
+            previously_missed: std::iter::once(last.id()).collect(),
+            recently_changed: HashSet::new(),
+        };
+        priorities.sort(&mut mutants);
+        assert_eq!(mutants[0], last);
+    }
+
+    #[test]
+    fn files_containing_tests_have_nearby_tests() {
+        let tree_path = Utf8Path::new("testdata/tree/factorial");
+        let source_file = SourceFile::new(tree_path, "src/bin/main.rs".parse().unwrap()).unwrap();
+        assert!(has_nearby_tests(tree_path, &source_file));
+    }
+}
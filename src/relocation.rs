@@ -0,0 +1,248 @@
+// Copyright 2022 Martin Pool
+
+//! Find code that depends on where the source tree is, and so can break when the tree
+//! is copied to a scratch directory.
+//!
+//! Tests sometimes use the absolute path of the tree they were written in, or read
+//! fixtures relative to `env!("CARGO_MANIFEST_DIR")` that are outside the package or
+//! excluded from the copy. They pass in the source tree but fail in the copy, which
+//! otherwise looks like a broken baseline with no explanation.
+
+use std::fmt;
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+
+use crate::*;
+
+/// Directories that aren't searched, because they hold build products or output
+/// rather than code.
+const SKIP_DIRS: &[&str] = &["target", "mutants.out", "mutants.out.old"];
+
+/// Literals within this many lines after a mention of `CARGO_MANIFEST_DIR` are taken
+/// to be relative to it, because a call like `Path::new(env!("CARGO_MANIFEST_DIR"))`
+/// is often split over several lines before the path is joined on.
+const MANIFEST_DIR_LINES: usize = 2;
+
+/// Some code that probably depends on where the source tree is.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelocationHazard {
+    /// The tree-relative path of the source file, with forward slashes.
+    pub file: String,
+    pub line: usize,
+    pub kind: HazardKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HazardKind {
+    /// A string that's an absolute path within the source tree.
+    AbsolutePath { path: String },
+    /// A string that's a path relative to `CARGO_MANIFEST_DIR`, which exists in the
+    /// source tree but not in the copy.
+    MissingFixture { path: String },
+}
+
+impl fmt::Display for RelocationHazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.file, self.line)?;
+        match &self.kind {
+            HazardKind::AbsolutePath { path } => {
+                write!(f, "{:?} is an absolute path in the source tree", path)
+            }
+            HazardKind::MissingFixture { path } => write!(
+                f,
+                "{:?} relative to CARGO_MANIFEST_DIR is not in the scratch directory",
+                path
+            ),
+        }
+    }
+}
+
+/// Find code in the Rust files of `source_dir` that refers to the source tree in a way
+/// that doesn't work in its copy in `build_dir`.
+pub fn find_relocation_hazards(
+    source_dir: &Utf8Path,
+    build_dir: &Utf8Path,
+) -> Result<Vec<RelocationHazard>> {
+    let absolute_source = source_dir
+        .canonicalize_utf8()
+        .with_context(|| format!("canonicalize {}", source_dir))?;
+    let mut hazards = Vec::new();
+    for entry in walkdir::WalkDir::new(source_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(name.starts_with('.') || SKIP_DIRS.contains(&name.as_ref()))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let path = match Utf8Path::from_path(entry.path()) {
+            Some(path) if path.extension() == Some("rs") => path,
+            _ => continue,
+        };
+        let code = match fs::read_to_string(path) {
+            Ok(code) => code,
+            Err(_) => continue,
+        };
+        let file = path
+            .strip_prefix(source_dir)
+            .unwrap_or(path)
+            .to_slash_path();
+        for (line, kind) in code_hazards(&code, source_dir, &absolute_source, build_dir) {
+            hazards.push(RelocationHazard {
+                file: file.clone(),
+                line,
+                kind,
+            });
+        }
+    }
+    Ok(hazards)
+}
+
+/// Find the hazards in the text of one file, with their 1-based line numbers.
+fn code_hazards(
+    code: &str,
+    source_dir: &Utf8Path,
+    absolute_source: &Utf8Path,
+    build_dir: &Utf8Path,
+) -> Vec<(usize, HazardKind)> {
+    let mut hazards = Vec::new();
+    let mut manifest_dir_until = None;
+    for (i, line) in code.lines().enumerate() {
+        if line.contains("CARGO_MANIFEST_DIR") {
+            manifest_dir_until = Some(i + MANIFEST_DIR_LINES);
+        }
+        for literal in string_literals(line) {
+            if Utf8Path::new(literal).starts_with(absolute_source) {
+                hazards.push((
+                    i + 1,
+                    HazardKind::AbsolutePath {
+                        path: literal.to_owned(),
+                    },
+                ));
+            } else if manifest_dir_until.is_some_and(|until| i <= until) {
+                let relative = literal.trim_start_matches('/');
+                if !relative.is_empty()
+                    && relative != "CARGO_MANIFEST_DIR"
+                    && source_dir.join(relative).exists()
+                    && !build_dir.join(relative).exists()
+                {
+                    hazards.push((
+                        i + 1,
+                        HazardKind::MissingFixture {
+                            path: literal.to_owned(),
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    hazards
+}
+
+/// Return the contents of the simple string literals in a line of code.
+///
+/// This doesn't fully lex Rust, so raw strings and literals that span lines are
+/// missed, and literals containing escapes are skipped, since they're unlikely to be
+/// paths.
+fn string_literals(line: &str) -> Vec<&str> {
+    let mut literals = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('"') {
+        let after = &rest[start + 1..];
+        let mut end = None;
+        let mut escaped = false;
+        let mut chars = after.char_indices();
+        while let Some((j, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    escaped = true;
+                    chars.next();
+                }
+                '"' => {
+                    end = Some(j);
+                    break;
+                }
+                _ => (),
+            }
+        }
+        match end {
+            Some(end) => {
+                if !escaped {
+                    literals.push(&after[..end]);
+                }
+                rest = &after[end + 1..];
+            }
+            None => break,
+        }
+    }
+    literals
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn string_literals_in_line() {
+        assert_eq!(
+            string_literals(r#"let p = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");"#),
+            ["CARGO_MANIFEST_DIR", "/fixtures"]
+        );
+        assert_eq!(string_literals(r#"println!("a\"b", "c");"#), ["c"]);
+        assert_eq!(
+            string_literals(r#"let s = "unterminated"#),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn absolute_paths_and_missing_fixtures() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        let source_dir = tmp_path.join("source");
+        let build_dir = tmp_path.join("build");
+        for dir in [
+            source_dir.join("fixtures/large"),
+            source_dir.join("fixtures/small"),
+            build_dir.join("fixtures/small"),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        let absolute_source = source_dir.canonicalize_utf8().unwrap();
+        let code = format!(
+            r#"
+            let elsewhere = "fixtures/large";
+            let large = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("fixtures/large");
+            let small = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/small");
+            let here = "{}/fixtures";
+            "#,
+            absolute_source
+        );
+        assert_eq!(
+            code_hazards(&code, &source_dir, &absolute_source, &build_dir),
+            [
+                (
+                    4,
+                    HazardKind::MissingFixture {
+                        path: "fixtures/large".to_owned()
+                    }
+                ),
+                (
+                    6,
+                    HazardKind::AbsolutePath {
+                        path: format!("{}/fixtures", absolute_source)
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2022 Martin Pool
+
+//! A registry of scratch directories, kept in the user's cache directory, so that
+//! directories left behind by runs that crashed can be found and removed by
+//! `cargo mutants clean`.
+//!
+//! Each scratch directory has an entry file in the registry, which is locked for as
+//! long as the run is using the directory. An entry that isn't locked was left by a
+//! run that's no longer running.
+
+use std::fs::{self, File};
+use std::io::Write;
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// The contents of an entry in the registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RegistryEntry {
+    /// The scratch directory.
+    scratch_dir: Utf8PathBuf,
+    /// The source tree that was copied into it.
+    source_dir: Utf8PathBuf,
+    cargo_mutants_version: String,
+}
+
+/// A directory of entries describing scratch directories in use.
+#[derive(Debug, Clone)]
+pub struct ScratchRegistry {
+    dir: Utf8PathBuf,
+}
+
+/// The registration of a scratch directory, which is removed when this is dropped.
+#[derive(Debug)]
+pub struct ScratchRegistration {
+    entry_path: Utf8PathBuf,
+    /// Holds the lock that shows the directory is in use.
+    lock_file: Option<File>,
+}
+
+impl ScratchRegistry {
+    pub fn new(dir: Utf8PathBuf) -> ScratchRegistry {
+        ScratchRegistry { dir }
+    }
+
+    /// Record that a scratch directory is in use, until the returned registration is
+    /// dropped.
+    pub fn register(
+        &self,
+        scratch_dir: &Utf8Path,
+        source_dir: &Utf8Path,
+    ) -> Result<ScratchRegistration> {
+        fs::create_dir_all(&self.dir).with_context(|| format!("create {}", self.dir))?;
+        let entry_path = self.dir.join(format!(
+            "{}.json",
+            scratch_dir
+                .file_name()
+                .context("scratch directory has no name")?
+        ));
+        let mut lock_file =
+            File::create(&entry_path).with_context(|| format!("create {}", entry_path))?;
+        lock_file
+            .try_lock_exclusive()
+            .with_context(|| format!("lock {}", entry_path))?;
+        let entry = RegistryEntry {
+            scratch_dir: scratch_dir.to_owned(),
+            source_dir: source_dir.to_owned(),
+            cargo_mutants_version: crate::VERSION.to_owned(),
+        };
+        lock_file
+            .write_all(serde_json::to_string_pretty(&entry)?.as_bytes())
+            .with_context(|| format!("write {}", entry_path))?;
+        Ok(ScratchRegistration {
+            entry_path,
+            lock_file: Some(lock_file),
+        })
+    }
+
+    /// Remove scratch directories whose runs are no longer running, and their entries,
+    /// and return the directories that were removed.
+    pub fn remove_stale(&self) -> Result<Vec<Utf8PathBuf>> {
+        let mut removed = Vec::new();
+        if !self.dir.is_dir() {
+            return Ok(removed);
+        }
+        for dir_entry in fs::read_dir(&self.dir).with_context(|| format!("read {}", self.dir))? {
+            let entry_path = match Utf8PathBuf::from_path_buf(dir_entry?.path()) {
+                Ok(path) if path.extension() == Some("json") => path,
+                _ => continue,
+            };
+            let file = File::open(&entry_path).with_context(|| format!("open {}", entry_path))?;
+            if file.try_lock_exclusive().is_err() {
+                continue; // Still in use.
+            }
+            let content =
+                fs::read_to_string(&entry_path).with_context(|| format!("read {}", entry_path))?;
+            // The lock is released before removing the entry, because Windows can't
+            // remove open files.
+            drop(file);
+            // An entry might be empty or partly written if the run crashed while
+            // registering, in which case there's nothing to remove but the entry.
+            if let Ok(entry) = serde_json::from_str::<RegistryEntry>(&content) {
+                if entry.scratch_dir.is_dir() {
+                    fs::remove_dir_all(&entry.scratch_dir)
+                        .with_context(|| format!("remove {}", entry.scratch_dir))?;
+                    removed.push(entry.scratch_dir);
+                }
+            }
+            fs::remove_file(&entry_path).with_context(|| format!("remove {}", entry_path))?;
+        }
+        Ok(removed)
+    }
+}
+
+impl Drop for ScratchRegistration {
+    fn drop(&mut self) {
+        drop(self.lock_file.take());
+        let _ = fs::remove_file(&self.entry_path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn only_directories_of_finished_runs_are_removed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        let registry = ScratchRegistry::new(tmp_path.join("registry"));
+        let running = tmp_path.join("cargo-mutants-running.tmp");
+        let crashed = tmp_path.join("cargo-mutants-crashed.tmp");
+        fs::create_dir(&running).unwrap();
+        fs::create_dir(&crashed).unwrap();
+        let source_dir = Utf8Path::new("src");
+
+        let _running_registration = registry.register(&running, source_dir).unwrap();
+        let mut crashed_registration = registry.register(&crashed, source_dir).unwrap();
+        // Simulate a crash by releasing the lock but leaving the entry and the directory.
+        drop(crashed_registration.lock_file.take());
+        mem::forget(crashed_registration);
+        fs::write(registry.dir.join("partly-written.json"), "").unwrap();
+
+        assert_eq!(registry.remove_stale().unwrap(), vec![crashed.clone()]);
+        assert!(running.is_dir());
+        assert!(!crashed.exists());
+        assert_eq!(
+            registry.dir.read_dir().unwrap().count(),
+            1,
+            "only the running entry is left"
+        );
+    }
+
+    #[test]
+    fn registration_is_removed_when_dropped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        let registry = ScratchRegistry::new(tmp_path.join("registry"));
+        let registration = registry
+            .register(&tmp_path.join("scratch.tmp"), Utf8Path::new("src"))
+            .unwrap();
+        assert!(registration.entry_path.is_file());
+        let entry_path = registration.entry_path.clone();
+        drop(registration);
+        assert!(!entry_path.exists());
+        assert_eq!(registry.remove_stale().unwrap(), Vec::<Utf8PathBuf>::new());
+    }
+}
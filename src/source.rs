@@ -12,9 +12,19 @@ use std::str::FromStr;
 use anyhow::{anyhow, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use globset::GlobSet;
+use serde::Serialize;
 
+use crate::cargo::cargo_bin;
+use crate::filter_warning::filter_warnings;
+use crate::manifest::PackageFiles;
+use crate::mutate::combine_pairs;
+use crate::visit::discover_and_count_filtered;
 use crate::*;
 
+/// Names of directories containing code copied from elsewhere, which isn't mutated by
+/// default.
+const VENDORED_DIRS: &[&str] = &["vendor", "third_party"];
+
 /// A path relative to the top of the source tree.
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
 pub struct TreeRelativePathBuf(Utf8PathBuf);
@@ -93,6 +103,14 @@ pub struct SourceFile {
 
     /// Full copy of the source.
     pub code: Rc<String>,
+
+    /// The top source file of the target containing this file, like `src/main.rs`,
+    /// if it's known.
+    target_root: Option<TreeRelativePathBuf>,
+
+    /// True if this file is only built into binaries of a package with no integration
+    /// tests, so that `cargo test` never builds or runs code marked `#[cfg(not(test))]`.
+    pub only_unit_tested: bool,
 }
 
 impl SourceFile {
@@ -110,9 +128,18 @@ impl SourceFile {
         Ok(SourceFile {
             tree_relative_path,
             code: Rc::new(code),
+            target_root: None,
+            only_unit_tested: false,
         })
     }
 
+    /// Record which target this file is part of.
+    fn in_target(mut self, target: &SourceTarget, targets: &PackageTargets) -> SourceFile {
+        self.target_root = Some(target.root.clone());
+        self.only_unit_tested = target.kind == "bin" && !targets.has_integration_tests;
+        self
+    }
+
     /// Return the path of this file relative to the tree root, with forward slashes.
     pub fn tree_relative_slashes(&self) -> String {
         self.tree_relative_path.to_string()
@@ -122,6 +149,44 @@ impl SourceFile {
     pub fn tree_relative_path(&self) -> &TreeRelativePathBuf {
         &self.tree_relative_path
     }
+
+    /// Return the path of the module defined by this file, such as `["telemetry", "span"]`
+    /// for `src/telemetry/span.rs`, or an empty path for a crate root.
+    ///
+    /// This is guessed from the file name, relative to the top source file of its
+    /// target if that's known, so `#[path]` attributes are not respected.
+    pub fn module_path(&self) -> Vec<String> {
+        let path = &self.tree_relative_path.0;
+        let mut components: Vec<&str>;
+        if let Some(target_root) = &self.target_root {
+            if path == &target_root.0 {
+                return Vec::new();
+            }
+            components = path
+                .strip_prefix(&target_root.parent().0)
+                .unwrap_or(path)
+                .components()
+                .map(|c| c.as_str())
+                .collect();
+        } else {
+            components = path.components().map(|c| c.as_str()).collect();
+            if components.first() == Some(&"src") {
+                components.remove(0);
+            }
+            if components.first() == Some(&"bin") && components.len() == 2 {
+                return Vec::new();
+            }
+        }
+        if let Some(last) = components.pop() {
+            let stem = last.strip_suffix(".rs").unwrap_or(last);
+            match stem {
+                "lib" | "main" if components.is_empty() => (),
+                "mod" => (),
+                stem => components.push(stem),
+            }
+        }
+        components.into_iter().map(str::to_owned).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -145,9 +210,32 @@ impl SourceTree {
     /// Return all the mutations that could possibly be applied to this tree.
     pub fn mutants(&self, options: &Options) -> Result<Vec<Mutant>> {
         let mut r = Vec::new();
+        let mut file_counts = Vec::new();
         for sf in self.source_files(options)? {
             check_interrupted()?;
-            r.extend(discover_mutants(sf.into())?);
+            let path = sf.tree_relative_slashes();
+            let (mutants, counts) = discover_and_count_filtered(sf.into(), options)?;
+            file_counts.push((path, counts));
+            r.extend(mutants);
+        }
+        for warning in filter_warnings(options, &file_counts)? {
+            console::eprint_warning(&warning.to_string());
+        }
+        if options.order == 2 {
+            r = combine_pairs(&r);
+        }
+        if let Some(coverage) = &options.coverage {
+            coverage.tag_mutants(&mut r);
+        }
+        Ok(r)
+    }
+
+    /// Return the functions and items in this tree that aren't mutated, and why.
+    pub fn skipped_functions(&self, options: &Options) -> Result<Vec<SkippedFunction>> {
+        let mut r = Vec::new();
+        for sf in self.source_files(options)? {
+            check_interrupted()?;
+            r.extend(discover_skipped(sf.into(), options)?);
         }
         Ok(r)
     }
@@ -157,17 +245,58 @@ impl SourceTree {
         &self,
         options: &Options,
     ) -> Result<impl IntoIterator<Item = TreeRelativePathBuf>> {
-        let top_sources = cargo_metadata_sources(&self.root)?;
-        indirect_sources(&self.root, top_sources, &options.globset)
+        self.source_paths_of_targets(&cargo_metadata_targets(&self.root)?, options)
+    }
+
+    fn source_paths_of_targets(
+        &self,
+        targets: &PackageTargets,
+        options: &Options,
+    ) -> Result<BTreeSet<TreeRelativePathBuf>> {
+        let package_files = PackageFiles::read(&self.root)?;
+        let mut sources = indirect_sources(
+            &self.root,
+            targets.sources.iter().map(|target| target.root.clone()),
+            &options.globset,
+            options.include_generated,
+        )?;
+        sources.retain(|path| !package_files.excludes(path.0.as_std_path(), false));
+        Ok(sources)
+    }
+
+    /// Describe each source file: the package and target it belongs to, and how many
+    /// mutants it has, so that work can be divided up by file.
+    pub fn list_files(&self, options: &Options) -> Result<Vec<ListedFile>> {
+        let targets = cargo_metadata_targets(&self.root)?;
+        let mut r = Vec::new();
+        for trp in self.source_paths_of_targets(&targets, options)? {
+            check_interrupted()?;
+            let target = target_of_file(&targets.sources, &trp)
+                .ok_or_else(|| anyhow!("no target contains {}", trp))?;
+            let source_file = SourceFile::new(&self.root, trp.clone())?.in_target(target, &targets);
+            let (_mutants, counts) = discover_and_count_filtered(source_file.into(), options)?;
+            r.push(ListedFile {
+                path: trp.to_string(),
+                package: target.package.clone(),
+                kind: target.kind.clone(),
+                mutants: counts.kept,
+            });
+        }
+        Ok(r)
     }
 
     /// Return an iterator of [SourceFile] object, eagerly loading their content.
     pub fn source_files(&self, options: &Options) -> Result<impl Iterator<Item = SourceFile> + '_> {
         // TODO: Maybe don't eagerly read them here...?
-        let source_paths = self.source_paths(options)?;
+        let targets = cargo_metadata_targets(&self.root)?;
+        let source_paths = self.source_paths_of_targets(&targets, options)?;
         let root = self.root.clone();
         Ok(source_paths.into_iter().filter_map(move |trp| {
             SourceFile::new(&root, trp.clone())
+                .map(|source_file| match target_of_file(&targets.sources, &trp) {
+                    Some(target) => source_file.in_target(target, &targets),
+                    None => source_file,
+                })
                 .map_err(|err| {
                     eprintln!("error reading source {}: {}", trp, err);
                 })
@@ -179,12 +308,64 @@ impl SourceTree {
     pub fn path(&self) -> &Utf8Path {
         &self.root
     }
+
+    /// Return the root of the workspace containing this tree, and the path of this tree
+    /// relative to it, which is empty if the tree is not in a larger workspace.
+    ///
+    /// When the tree is a member of a workspace, the whole workspace is copied so that
+    /// path dependencies and inherited settings resolve, but only this package is mutated.
+    pub fn workspace_root(&self) -> Result<(Utf8PathBuf, Utf8PathBuf)> {
+        let manifest = self.root.join("Cargo.toml");
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&manifest)
+            .no_deps()
+            .exec()
+            .with_context(|| format!("run {} metadata", cargo_bin()))?;
+        let workspace_root = metadata
+            .workspace_root
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {}", metadata.workspace_root))?;
+        let package_root = self
+            .root
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {}", self.root))?;
+        match package_root.strip_prefix(&workspace_root) {
+            Ok(relative) => Ok((workspace_root.clone(), relative.to_owned())),
+            Err(_) => Ok((package_root, Utf8PathBuf::new())),
+        }
+    }
+
+    /// Return the name of the package in this tree, if it's one of several packages in
+    /// a workspace, or None if it's the only one.
+    ///
+    /// All the mutated files belong to this package, so only it needs to be checked
+    /// and built after each mutation.
+    pub fn package_in_workspace(&self) -> Result<Option<String>> {
+        let manifest = self.root.join("Cargo.toml");
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&manifest)
+            .no_deps()
+            .exec()
+            .with_context(|| format!("run {} metadata", cargo_bin()))?;
+        if metadata.workspace_members.len() < 2 {
+            return Ok(None);
+        }
+        let manifest = manifest
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {}", manifest))?;
+        Ok(metadata
+            .packages
+            .iter()
+            .find(|package| package.manifest_path == manifest)
+            .map(|package| package.name.clone()))
+    }
 }
 
 fn indirect_sources(
     root_dir: &Utf8Path,
     top_sources: impl IntoIterator<Item = TreeRelativePathBuf>,
     globset: &Option<GlobSet>,
+    include_vendored: bool,
 ) -> Result<BTreeSet<TreeRelativePathBuf>> {
     let dirs: BTreeSet<TreeRelativePathBuf> = top_sources.into_iter().map(|p| p.parent()).collect();
     let mut files: BTreeSet<TreeRelativePathBuf> = BTreeSet::new();
@@ -200,15 +381,16 @@ fn indirect_sources(
             .map(|entry| entry.into_path())
             .filter(|path| {
                 path.extension()
-                    .map_or(false, |p| p.eq_ignore_ascii_case("rs"))
+                    .is_some_and(|p| p.eq_ignore_ascii_case("rs"))
             })
             .map(move |full_path| {
                 full_path
-                    .strip_prefix(&root_dir)
+                    .strip_prefix(root_dir)
                     .expect("strip prefix")
                     .to_owned()
             })
-            .filter(|rel_path| globset.as_ref().map_or(true, |gs| gs.is_match(rel_path)))
+            .filter(|rel_path| include_vendored || !is_vendored(rel_path))
+            .filter(|rel_path| globset.as_ref().is_none_or(|gs| gs.is_match(rel_path)))
         {
             files.insert(p.into());
         }
@@ -216,21 +398,84 @@ fn indirect_sources(
     Ok(files)
 }
 
+/// True if the path is inside a directory of code copied from elsewhere, like `vendor/`.
+fn is_vendored(rel_path: &Path) -> bool {
+    rel_path.components().any(|component| {
+        VENDORED_DIRS
+            .iter()
+            .any(|dir| component.as_os_str() == *dir)
+    })
+}
+
 /// Given a path to a cargo manifest, find all the directly-referenced source files.
-fn cargo_metadata_sources(source_dir: &Utf8Path) -> Result<BTreeSet<TreeRelativePathBuf>> {
+/// A source file in the tree, as listed by `--list-files --json`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ListedFile {
+    /// The path relative to the tree root, with forward slashes.
+    pub path: String,
+    /// The name of the package containing the file.
+    pub package: String,
+    /// The kind of target the file is part of, like `lib` or `bin`.
+    pub kind: String,
+    /// The number of mutants in the file, after filtering.
+    pub mutants: usize,
+}
+
+/// A lib or bin target of the package, from `cargo metadata`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct SourceTarget {
+    package: String,
+    kind: String,
+    /// The top source file of the target, like `src/lib.rs`.
+    root: TreeRelativePathBuf,
+}
+
+/// The targets of the package in the tree, from `cargo metadata`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct PackageTargets {
+    /// The lib and bin targets, whose source is mutated.
+    sources: Vec<SourceTarget>,
+    /// True if the package has integration tests, which can run its binaries.
+    has_integration_tests: bool,
+}
+
+/// Find the target that a source file is part of: the target whose top source it is,
+/// or otherwise the target with the deepest directory containing it, preferring a lib.
+fn target_of_file<'a>(
+    targets: &'a [SourceTarget],
+    path: &TreeRelativePathBuf,
+) -> Option<&'a SourceTarget> {
+    if let Some(target) = targets.iter().find(|target| &target.root == path) {
+        return Some(target);
+    }
+    targets
+        .iter()
+        .filter(|target| path.0.starts_with(&target.root.parent().0))
+        .max_by_key(|target| (target.root.0.components().count(), target.kind == "lib"))
+}
+
+fn cargo_metadata_targets(source_dir: &Utf8Path) -> Result<PackageTargets> {
     let manifest = source_dir.join("Cargo.toml");
-    let mut found = BTreeSet::new();
+    let mut found = PackageTargets {
+        sources: Vec::new(),
+        has_integration_tests: false,
+    };
     let cmd = cargo_metadata::MetadataCommand::new()
         .manifest_path(&manifest)
         .exec()
-        .context("run cargo metadata")?;
+        .with_context(|| format!("run {} metadata", cargo_bin()))?;
     if let Some(pkg) = cmd.root_package() {
         let pkg_dir = pkg.manifest_path.parent().unwrap();
         for target in &pkg.targets {
-            if target.kind == ["lib"] || target.kind == ["bin"] {
-                if let Ok(relpath) = target.src_path.strip_prefix(&pkg_dir) {
-                    let relpath = TreeRelativePathBuf::new(relpath.into());
-                    found.insert(relpath);
+            if target.kind == ["test"] {
+                found.has_integration_tests = true;
+            } else if target.kind == ["lib"] || target.kind == ["bin"] {
+                if let Ok(relpath) = target.src_path.strip_prefix(pkg_dir) {
+                    found.sources.push(SourceTarget {
+                        package: pkg.name.clone(),
+                        kind: target.kind[0].clone(),
+                        root: TreeRelativePathBuf::new(relpath.into()),
+                    });
                 } else {
                     eprintln!("{:?} is not in {:?}", target.src_path, pkg_dir);
                 }
@@ -242,7 +487,7 @@ fn cargo_metadata_sources(source_dir: &Utf8Path) -> Result<BTreeSet<TreeRelative
 
 #[cfg(test)]
 mod test {
-    use std::fs::File;
+    use std::fs::{self, File};
     use std::io::Write;
 
     use pretty_assertions::assert_eq;
@@ -263,6 +508,162 @@ mod test {
         );
     }
 
+    #[test]
+    fn vendored_paths() {
+        assert!(is_vendored(Path::new("src/vendor/zlib.rs")));
+        assert!(is_vendored(Path::new("third_party/x/lib.rs")));
+        assert!(!is_vendored(Path::new("src/vendors.rs")));
+        assert!(!is_vendored(Path::new("src/lib.rs")));
+    }
+
+    #[test]
+    fn module_path_from_file_name() {
+        let module_path = |path: &str| {
+            SourceFile {
+                tree_relative_path: path.parse().unwrap(),
+                code: Rc::new(String::new()),
+                target_root: None,
+                only_unit_tested: false,
+            }
+            .module_path()
+        };
+        assert_eq!(module_path("src/lib.rs"), Vec::<String>::new());
+        assert_eq!(module_path("src/main.rs"), Vec::<String>::new());
+        assert_eq!(module_path("src/bin/tool.rs"), Vec::<String>::new());
+        assert_eq!(module_path("src/telemetry.rs"), ["telemetry"]);
+        assert_eq!(module_path("src/telemetry/mod.rs"), ["telemetry"]);
+        assert_eq!(module_path("src/telemetry/span.rs"), ["telemetry", "span"]);
+    }
+
+    #[test]
+    fn module_path_relative_to_target_root() {
+        let module_path = |path: &str, target_root: &str| {
+            SourceFile {
+                tree_relative_path: path.parse().unwrap(),
+                code: Rc::new(String::new()),
+                target_root: Some(target_root.parse().unwrap()),
+                only_unit_tested: false,
+            }
+            .module_path()
+        };
+        assert_eq!(
+            module_path("src/main.rs", "src/main.rs"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            module_path("src/net/retry.rs", "src/main.rs"),
+            ["net", "retry"]
+        );
+        assert_eq!(
+            module_path("src/bin/tool/main.rs", "src/bin/tool/main.rs"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            module_path("src/bin/tool/args.rs", "src/bin/tool/main.rs"),
+            ["args"]
+        );
+        assert_eq!(module_path("cli/args/mod.rs", "cli/main.rs"), ["args"]);
+    }
+
+    #[test]
+    fn bin_only_package_without_integration_tests_is_only_unit_tested() {
+        let source_files = SourceTree::new(Utf8Path::new("testdata/tree/bin_only"))
+            .unwrap()
+            .source_files(&Options::default())
+            .unwrap()
+            .map(|sf| {
+                (
+                    sf.tree_relative_slashes(),
+                    sf.module_path(),
+                    sf.only_unit_tested,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            source_files,
+            [
+                (
+                    "src/bin/tool/args.rs".to_owned(),
+                    vec!["args".to_owned()],
+                    true
+                ),
+                ("src/bin/tool/main.rs".to_owned(), vec![], true),
+                ("src/main.rs".to_owned(), vec![], true),
+            ]
+        );
+    }
+
+    #[test]
+    fn bin_only_package_with_integration_tests_is_not_only_unit_tested() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::write(
+            tmp_path.join("Cargo.toml"),
+            "[package]\nname = \"tool\"\nversion = \"0.0.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir(tmp_path.join("src")).unwrap();
+        fs::write(tmp_path.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir(tmp_path.join("tests")).unwrap();
+        fs::write(tmp_path.join("tests/cli.rs"), "").unwrap();
+        let source_files = SourceTree::new(tmp_path)
+            .unwrap()
+            .source_files(&Options::default())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(source_files.len(), 1);
+        assert!(!source_files[0].only_unit_tested);
+    }
+
+    #[test]
+    fn workspace_root_of_member_package() {
+        let (workspace_root, relative) = SourceTree::new(Utf8Path::new("testdata/tree/factorial"))
+            .unwrap()
+            .workspace_root()
+            .unwrap();
+        assert_eq!(
+            workspace_root,
+            Utf8Path::new(".").canonicalize_utf8().unwrap()
+        );
+        assert_eq!(relative, "testdata/tree/factorial");
+    }
+
+    #[test]
+    fn package_in_workspace_is_named() {
+        assert_eq!(
+            SourceTree::new(Utf8Path::new("testdata/tree/factorial"))
+                .unwrap()
+                .package_in_workspace()
+                .unwrap()
+                .as_deref(),
+            Some("cargo-mutants-testdata-factorial")
+        );
+    }
+
+    #[test]
+    fn workspace_root_of_standalone_package() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::write(
+            tmp_path.join("Cargo.toml"),
+            "[package]\nname = \"standalone\"\nversion = \"0.0.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir(tmp_path.join("src")).unwrap();
+        fs::write(tmp_path.join("src/lib.rs"), "").unwrap();
+        let (workspace_root, relative) =
+            SourceTree::new(tmp_path).unwrap().workspace_root().unwrap();
+        assert_eq!(workspace_root, tmp_path.canonicalize_utf8().unwrap());
+        assert_eq!(relative, "");
+        assert_eq!(
+            SourceTree::new(tmp_path)
+                .unwrap()
+                .package_in_workspace()
+                .unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn error_opening_subdirectory_of_crate() {
         let result = SourceTree::new(Utf8Path::new("testdata/tree/factorial/src"));
@@ -288,4 +689,26 @@ mod test {
         let source_file = SourceFile::new(temp_dir_path, file_name.parse().unwrap()).unwrap();
         assert_eq!(*source_file.code, "fn main() {\n    640 << 10;\n}\n");
     }
+
+    #[test]
+    fn target_of_file() {
+        let target = |kind: &str, root: &str| SourceTarget {
+            package: "p".to_owned(),
+            kind: kind.to_owned(),
+            root: root.parse().unwrap(),
+        };
+        let targets = [
+            target("bin", "src/main.rs"),
+            target("lib", "src/lib.rs"),
+            target("bin", "src/bin/tool.rs"),
+        ];
+        let root_of = |path: &str| {
+            super::target_of_file(&targets, &path.parse().unwrap()).map(|t| t.root.to_string())
+        };
+        assert_eq!(root_of("src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(root_of("src/lib.rs").unwrap(), "src/lib.rs");
+        assert_eq!(root_of("src/visit.rs").unwrap(), "src/lib.rs");
+        assert_eq!(root_of("src/bin/tool/args.rs").unwrap(), "src/bin/tool.rs");
+        assert_eq!(root_of("build.rs"), None);
+    }
 }
@@ -0,0 +1,162 @@
+// Copyright 2022 Martin Pool
+
+//! Suggestions of which tests to extend to catch each missed mutant.
+//!
+//! The suggestions are found by simple text search, not by understanding the code:
+//! the tests in the mutated file itself, and then other files containing tests
+//! that mention the name of the mutated function.
+
+use camino::Utf8Path;
+
+use crate::watch::IGNORED_DIRS;
+use crate::*;
+
+/// The most test files suggested for each mutant.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// The Rust files in a tree that contain tests.
+#[derive(Debug)]
+pub struct TestFiles {
+    /// The tree-relative path, with forward slashes, and the text of each file.
+    files: Vec<(String, String)>,
+}
+
+impl TestFiles {
+    /// Find the files in the tree that contain tests: those under a `tests` directory,
+    /// and those containing a `#[test]` attribute.
+    pub fn find(root: &Utf8Path) -> TestFiles {
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                !(entry.file_type().is_dir()
+                    && IGNORED_DIRS.iter().any(|name| entry.file_name() == *name))
+            })
+            .filter_map(|r| r.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let rel_path = match entry
+                .path()
+                .strip_prefix(root)
+                .ok()
+                .and_then(Utf8Path::from_path)
+            {
+                Some(rel_path) if rel_path.extension() == Some("rs") => rel_path,
+                _ => continue,
+            };
+            let code = match std::fs::read_to_string(entry.path()) {
+                Ok(code) => code,
+                Err(_) => continue,
+            };
+            let in_tests_dir = rel_path.components().any(|c| c.as_str() == "tests");
+            if in_tests_dir || has_tests(&code) {
+                files.push((rel_path.to_slash_path(), code));
+            }
+        }
+        TestFiles { files }
+    }
+
+    /// Suggest test files that could be extended to catch a missed mutant: the file
+    /// containing the mutant, if it has tests, and then other test files that mention
+    /// the name of the mutated function.
+    pub fn suggest(&self, mutant: &Mutant) -> Vec<String> {
+        let mutant_file = mutant.source_file().tree_relative_slashes();
+        let mut suggestions = Vec::new();
+        if has_tests(&mutant.source_file().code) {
+            suggestions.push(mutant_file.clone());
+        }
+        // Methods are named by their type and then the function: search for the function.
+        let function = mutant
+            .function_name()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default();
+        for (path, code) in &self.files {
+            if suggestions.len() == MAX_SUGGESTIONS {
+                break;
+            }
+            if *path != mutant_file && mentions_word(code, function) {
+                suggestions.push(path.clone());
+            }
+        }
+        suggestions
+    }
+}
+
+fn has_tests(code: &str) -> bool {
+    code.contains("#[test]")
+}
+
+/// True if `word` occurs in `code` other than as part of a longer identifier.
+fn mentions_word(code: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    code.match_indices(word).any(|(start, _)| {
+        let before = code[..start].chars().next_back();
+        let after = code[start + word.len()..].chars().next();
+        !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn find_words_in_code() {
+        assert!(mentions_word("assert_eq!(factorial(3), 6);", "factorial"));
+        assert!(mentions_word("factorial", "factorial"));
+        assert!(!mentions_word("factorial_of(3)", "factorial"));
+        assert!(!mentions_word("my_factorial(3)", "factorial"));
+        assert!(!mentions_word("anything", ""));
+    }
+
+    #[test]
+    fn suggest_test_files_mentioning_the_function() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "pub fn double(x: u32) -> u32 {\n    x * 2\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("tests/api.rs"),
+            "#[test]\nfn twice() {\n    assert!(mylib::double(2) > 3);\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("tests/other.rs"),
+            "#[test]\nfn other() {\n    assert!(mylib::doubled());\n}\n",
+        )
+        .unwrap();
+        let source_file = SourceFile::new(root, "src/lib.rs".parse().unwrap()).unwrap();
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        let test_files = TestFiles::find(root);
+        assert_eq!(test_files.suggest(&mutants[0]), ["tests/api.rs"]);
+    }
+
+    #[test]
+    fn suggest_tests_in_the_same_file() {
+        let tree = Utf8Path::new("testdata/tree/factorial");
+        let source_file = SourceFile::new(tree, "src/bin/main.rs".parse().unwrap()).unwrap();
+        let mutants = discover_mutants(source_file.into(), &Options::default()).unwrap();
+        let factorial_mutant = mutants
+            .iter()
+            .find(|m| m.function_name() == "factorial")
+            .unwrap();
+        assert_eq!(
+            TestFiles::find(tree).suggest(factorial_mutant),
+            ["src/bin/main.rs"]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2022 Martin Pool
+
+//! A compact summary of a run, in `mutants.out/summary.json` and
+//! `mutants.out/summary.txt`, for quick consumption by CI steps that
+//! don't want to parse the full `outcomes.json`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::time::Duration;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::lab::Scenario;
+use crate::outcome::{LabOutcome, RunScore};
+use crate::suggest::TestFiles;
+use crate::*;
+
+const SUMMARY_JSON: &str = "summary.json";
+const SUMMARY_TXT: &str = "summary.txt";
+
+/// The number of slowest mutants listed in the summary.
+const N_SLOWEST: usize = 10;
+
+/// The end-of-run summary.
+#[derive(Debug, Serialize)]
+struct Summary {
+    run_score: RunScore,
+    /// Wall-clock duration of the whole run, in seconds.
+    total_duration: f64,
+    /// The slowest mutants to build and test, slowest first.
+    slowest_mutants: Vec<TimedMutant>,
+    /// Descriptions of the mutants that were not caught by the tests.
+    missed: Vec<String>,
+    /// For each missed mutant, test files that might be extended to catch it.
+    suggested_tests: BTreeMap<String, Vec<String>>,
+    /// Counts and scores for each module, including its submodules.
+    modules: BTreeMap<String, RunScore>,
+    /// Counts and scores for each source file.
+    files: BTreeMap<String, RunScore>,
+    /// Counts and scores for mutants in deprecated or hidden code, by tag.
+    tags: BTreeMap<String, RunScore>,
+    /// Include times in the text summary.
+    #[serde(skip)]
+    show_times: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TimedMutant {
+    mutant: String,
+    /// Total duration of all phases, in seconds.
+    duration: f64,
+}
+
+/// Write `summary.json` and `summary.txt` into the `mutants.out` directory.
+///
+/// If `show_times` is false, durations are zeroed in the JSON and left out of the text,
+/// so that the summary is reproducible.
+pub fn write_summary(
+    output_dir: &Utf8Path,
+    run_score: &RunScore,
+    lab_outcome: &LabOutcome,
+    total_duration: Duration,
+    show_times: bool,
+    test_files: &TestFiles,
+) -> Result<()> {
+    let summary = if show_times {
+        Summary::new(run_score, lab_outcome, total_duration, true, test_files)
+    } else {
+        Summary::new(
+            run_score,
+            &lab_outcome.without_times(),
+            Duration::ZERO,
+            false,
+            test_files,
+        )
+    };
+    let json_path = output_dir.join(SUMMARY_JSON);
+    fs::write(&json_path, serde_json::to_string_pretty(&summary)?)
+        .with_context(|| format!("write {}", json_path))?;
+    let txt_path = output_dir.join(SUMMARY_TXT);
+    fs::write(&txt_path, summary.to_text()).with_context(|| format!("write {}", txt_path))
+}
+
+impl Summary {
+    fn new(
+        run_score: &RunScore,
+        lab_outcome: &LabOutcome,
+        total_duration: Duration,
+        show_times: bool,
+        test_files: &TestFiles,
+    ) -> Summary {
+        let slowest_mutants = lab_outcome
+            .slowest_mutants(N_SLOWEST)
+            .into_iter()
+            .map(|outcome| TimedMutant {
+                mutant: outcome.scenario.to_string(),
+                duration: outcome.total_duration().as_secs_f64(),
+            })
+            .collect();
+        let mut missed = Vec::new();
+        let mut suggested_tests = BTreeMap::new();
+        for outcome in lab_outcome
+            .outcomes()
+            .iter()
+            .filter(|outcome| outcome.mutant_missed() && !lab_outcome.is_allowed_miss(outcome))
+        {
+            let name = outcome.scenario.to_string();
+            if let Scenario::Mutant(mutant) = &outcome.scenario {
+                let suggestions = test_files.suggest(mutant);
+                if !suggestions.is_empty() {
+                    suggested_tests.insert(name.clone(), suggestions);
+                }
+            }
+            missed.push(name);
+        }
+        Summary {
+            run_score: run_score.clone(),
+            total_duration: total_duration.as_secs_f64(),
+            slowest_mutants,
+            missed,
+            suggested_tests,
+            modules: lab_outcome.module_scores(),
+            files: lab_outcome.file_scores(),
+            tags: lab_outcome.tag_scores(),
+            show_times,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "{}", self.run_score).unwrap();
+        if self.show_times {
+            writeln!(s, "Total time: {:.1}s", self.total_duration).unwrap();
+        }
+        if self.show_times && !self.slowest_mutants.is_empty() {
+            writeln!(s, "\nSlowest mutants:").unwrap();
+            for timed in &self.slowest_mutants {
+                writeln!(s, "  {:.1}s {}", timed.duration, timed.mutant).unwrap();
+            }
+        }
+        if !self.missed.is_empty() {
+            writeln!(s, "\nMissed mutants:").unwrap();
+            for missed in &self.missed {
+                writeln!(s, "  {}", missed).unwrap();
+                if let Some(suggestions) = self.suggested_tests.get(missed) {
+                    writeln!(s, "    tests to extend: {}", suggestions.join(", ")).unwrap();
+                }
+            }
+        }
+        if !self.modules.is_empty() {
+            writeln!(s, "\nScores by module:").unwrap();
+            for (module, run_score) in &self.modules {
+                writeln!(s, "  {}: {}", module, run_score).unwrap();
+            }
+        }
+        if !self.tags.is_empty() {
+            writeln!(s, "\nScores by tag:").unwrap();
+            for (tag, run_score) in &self.tags {
+                writeln!(s, "  {}: {}", tag, run_score).unwrap();
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn summary_text() {
+        let summary = Summary {
+            run_score: RunScore {
+                caught: 3,
+                missed: 1,
+                unviable: 2,
+                timeout: 0,
+                allowed: 0,
+                score: Some(75.0),
+            },
+            total_duration: 65.0,
+            slowest_mutants: vec![
+                TimedMutant {
+                    mutant: "src/lib.rs:2: replace a -> bool with true".to_owned(),
+                    duration: 12.5,
+                },
+                TimedMutant {
+                    mutant: "src/lib.rs:8: replace b with ()".to_owned(),
+                    duration: 3.0,
+                },
+            ],
+            missed: vec!["src/lib.rs:8: replace b with ()".to_owned()],
+            suggested_tests: BTreeMap::from([(
+                "src/lib.rs:8: replace b with ()".to_owned(),
+                vec!["src/lib.rs".to_owned(), "tests/b.rs".to_owned()],
+            )]),
+            modules: BTreeMap::from([(
+                "crate".to_owned(),
+                RunScore {
+                    caught: 3,
+                    missed: 1,
+                    unviable: 2,
+                    timeout: 0,
+                    allowed: 0,
+                    score: Some(75.0),
+                },
+            )]),
+            files: BTreeMap::new(),
+            tags: BTreeMap::from([(
+                "deprecated".to_owned(),
+                RunScore {
+                    caught: 0,
+                    missed: 1,
+                    unviable: 0,
+                    timeout: 0,
+                    allowed: 0,
+                    score: Some(0.0),
+                },
+            )]),
+            show_times: true,
+        };
+        assert_eq!(
+            summary.to_text(),
+            "\
+3 caught, 1 missed, 2 unviable, 0 timed out, score 75%
+Total time: 65.0s
+
+Slowest mutants:
+  12.5s src/lib.rs:2: replace a -> bool with true
+  3.0s src/lib.rs:8: replace b with ()
+
+Missed mutants:
+  src/lib.rs:8: replace b with ()
+    tests to extend: src/lib.rs, tests/b.rs
+
+Scores by module:
+  crate: 3 caught, 1 missed, 2 unviable, 0 timed out, score 75%
+
+Scores by tag:
+  deprecated: 0 caught, 1 missed, 0 unviable, 0 timed out, score 0%
+"
+        );
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["run_score"]["missed"], 1);
+        assert_eq!(json["slowest_mutants"][0]["duration"], 12.5);
+        assert_eq!(json["missed"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2022 Martin Pool
+
+//! A report of how many mutants each test killed, in `mutants.out/test_efficiency.json`
+//! and `mutants.out/test_efficiency.txt`, to help find high-value tests, and tests
+//! that may be redundant because they killed no mutants.
+//!
+//! The tests are found from libtest's output in the logs: the tests that passed in the
+//! baseline, and the tests that failed under each caught mutant. Tests run by other
+//! harnesses aren't counted.
+//!
+//! `cargo mutants minimal-tests` reads the report back and picks a small set of tests
+//! that still kills every caught mutant, as a guide to pruning slow test suites.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
+
+use crate::outcome::LabOutcome;
+use crate::*;
+
+const TEST_EFFICIENCY_JSON: &str = "test_efficiency.json";
+const TEST_EFFICIENCY_TXT: &str = "test_efficiency.txt";
+
+/// The mutants killed by each test.
+#[derive(Debug, Serialize, Deserialize)]
+struct TestEfficiency {
+    /// Every test that passed in the baseline or killed a mutant, those that killed
+    /// the most mutants first.
+    tests: Vec<TestKills>,
+    /// The names of the tests that killed no mutants.
+    killed_none: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct TestKills {
+    test: String,
+    /// The number of mutants this test killed.
+    killed: usize,
+    /// The number of mutants killed by this test and no other.
+    unique: usize,
+    /// The mutants this test killed.
+    mutants: Vec<String>,
+}
+
+/// Write `test_efficiency.json` and `test_efficiency.txt` into the `mutants.out`
+/// directory.
+pub fn write_test_efficiency(output_dir: &Utf8Path, lab_outcome: &LabOutcome) -> Result<()> {
+    let mut baseline_tests = Vec::new();
+    let mut kills = Vec::new();
+    for outcome in lab_outcome.outcomes() {
+        if outcome.scenario.is_mutant() {
+            if outcome.mutant_caught() {
+                kills.push((outcome.scenario.to_string(), outcome.failed_tests()?));
+            }
+        } else {
+            baseline_tests.extend(outcome.passed_tests()?);
+        }
+    }
+    let efficiency = TestEfficiency::new(&baseline_tests, &kills);
+    let json_path = output_dir.join(TEST_EFFICIENCY_JSON);
+    fs::write(&json_path, serde_json::to_string_pretty(&efficiency)?)
+        .with_context(|| format!("write {}", json_path))?;
+    let txt_path = output_dir.join(TEST_EFFICIENCY_TXT);
+    fs::write(&txt_path, efficiency.to_text()).with_context(|| format!("write {}", txt_path))
+}
+
+impl TestEfficiency {
+    /// Count up the kills given the tests that passed in the baseline, and for each
+    /// caught mutant, the tests that failed.
+    fn new(baseline_tests: &[String], kills: &[(String, Vec<String>)]) -> TestEfficiency {
+        let mut by_test: BTreeMap<&str, Vec<&str>> = baseline_tests
+            .iter()
+            .map(|test| (test.as_str(), Vec::new()))
+            .collect();
+        for (mutant, failed_tests) in kills {
+            for test in failed_tests {
+                by_test.entry(test).or_default().push(mutant);
+            }
+        }
+        let mut tests: Vec<TestKills> = by_test
+            .into_iter()
+            .map(|(test, mutants)| TestKills {
+                test: test.to_owned(),
+                killed: mutants.len(),
+                unique: mutants
+                    .iter()
+                    .filter(|mutant| {
+                        kills
+                            .iter()
+                            .any(|(m, failed)| m == *mutant && failed.len() == 1)
+                    })
+                    .count(),
+                mutants: mutants.into_iter().map(str::to_owned).collect(),
+            })
+            .collect();
+        tests.sort_by(|a, b| b.killed.cmp(&a.killed).then_with(|| a.test.cmp(&b.test)));
+        let killed_none = tests
+            .iter()
+            .filter(|t| t.killed == 0)
+            .map(|t| t.test.clone())
+            .collect();
+        TestEfficiency { tests, killed_none }
+    }
+
+    fn to_text(&self) -> String {
+        let mut s = String::new();
+        if self.tests.is_empty() {
+            writeln!(s, "No libtest test results were found in the logs.").unwrap();
+            return s;
+        }
+        writeln!(s, "Mutants killed by each test:").unwrap();
+        writeln!(s, "{:>8} {:>8}  test", "killed", "unique").unwrap();
+        for test in &self.tests {
+            writeln!(s, "{:>8} {:>8}  {}", test.killed, test.unique, test.test).unwrap();
+        }
+        if !self.killed_none.is_empty() {
+            writeln!(s, "\nTests that killed no mutants:").unwrap();
+            for test in &self.killed_none {
+                writeln!(s, "  {}", test).unwrap();
+            }
+        }
+        s
+    }
+
+    /// Choose a small set of tests that together kill every mutant killed by any test.
+    ///
+    /// Finding the smallest such set is the NP-hard set cover problem, so this
+    /// greedily takes the test that kills the most mutants not yet killed, until
+    /// none are left. The result is at most a logarithmic factor larger than the
+    /// smallest.
+    fn minimal_test_set(&self) -> MinimalTestSet {
+        let mut remaining: BTreeSet<&str> = self
+            .tests
+            .iter()
+            .flat_map(|t| t.mutants.iter().map(String::as_str))
+            .collect();
+        let caught_mutants = remaining.len();
+        let mut tests = Vec::new();
+        while !remaining.is_empty() {
+            // Ties go to the earliest test, which kills the most mutants overall.
+            let (best, newly_killed) = self
+                .tests
+                .iter()
+                .map(|t| {
+                    let newly_killed = t
+                        .mutants
+                        .iter()
+                        .filter(|m| remaining.contains(m.as_str()))
+                        .count();
+                    (t, newly_killed)
+                })
+                .rev()
+                .max_by_key(|(_, newly_killed)| *newly_killed)
+                .expect("some test kills each remaining mutant");
+            for mutant in &best.mutants {
+                remaining.remove(mutant.as_str());
+            }
+            tests.push(CoveringTest {
+                test: best.test.clone(),
+                newly_killed,
+            });
+        }
+        MinimalTestSet {
+            tests,
+            total_tests: self.tests.len(),
+            caught_mutants,
+        }
+    }
+}
+
+/// A small set of tests that kills every mutant caught by the whole test suite.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct MinimalTestSet {
+    /// The chosen tests, in the order they were chosen.
+    tests: Vec<CoveringTest>,
+    /// The number of tests in the whole suite.
+    total_tests: usize,
+    /// The number of mutants killed by at least one test.
+    caught_mutants: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct CoveringTest {
+    test: String,
+    /// The number of mutants killed by this test and by none chosen before it.
+    newly_killed: usize,
+}
+
+impl fmt::Display for MinimalTestSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} tests kill all {} caught mutants:",
+            self.tests.len(),
+            self.total_tests,
+            self.caught_mutants
+        )?;
+        for test in &self.tests {
+            writeln!(f, "  {} (+{})", test.test, test.newly_killed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read `test_efficiency.json` from a `mutants.out` directory, and choose a small set
+/// of tests that kills every mutant caught by the run.
+pub fn minimal_test_set(mutants_out: &Utf8Path) -> Result<MinimalTestSet> {
+    let json_path = mutants_out.join(TEST_EFFICIENCY_JSON);
+    let json = fs::read_to_string(&json_path).with_context(|| format!("read {}", json_path))?;
+    let efficiency: TestEfficiency =
+        serde_json::from_str(&json).with_context(|| format!("parse {}", json_path))?;
+    Ok(efficiency.minimal_test_set())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn count_kills_by_test() {
+        let baseline = strings(&["a", "b", "c", "d"]);
+        let kills = [
+            ("m1".to_owned(), strings(&["a", "b"])),
+            ("m2".to_owned(), strings(&["b"])),
+            ("m3".to_owned(), strings(&["b", "c"])),
+        ];
+        let efficiency = TestEfficiency::new(&baseline, &kills);
+        assert_eq!(
+            efficiency.tests,
+            [
+                TestKills {
+                    test: "b".to_owned(),
+                    killed: 3,
+                    unique: 1,
+                    mutants: strings(&["m1", "m2", "m3"]),
+                },
+                TestKills {
+                    test: "a".to_owned(),
+                    killed: 1,
+                    unique: 0,
+                    mutants: strings(&["m1"]),
+                },
+                TestKills {
+                    test: "c".to_owned(),
+                    killed: 1,
+                    unique: 0,
+                    mutants: strings(&["m3"]),
+                },
+                TestKills {
+                    test: "d".to_owned(),
+                    killed: 0,
+                    unique: 0,
+                    mutants: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(efficiency.killed_none, ["d"]);
+        assert_eq!(
+            efficiency.to_text(),
+            "\
+Mutants killed by each test:
+  killed   unique  test
+       3        1  b
+       1        0  a
+       1        0  c
+       0        0  d
+
+Tests that killed no mutants:
+  d
+"
+        );
+    }
+
+    #[test]
+    fn greedy_minimal_test_set() {
+        let baseline = strings(&["a", "b", "c", "d", "e"]);
+        let kills = [
+            ("m1".to_owned(), strings(&["a", "b"])),
+            ("m2".to_owned(), strings(&["a", "c"])),
+            ("m3".to_owned(), strings(&["a"])),
+            ("m4".to_owned(), strings(&["b", "d"])),
+            ("m5".to_owned(), strings(&["c", "d"])),
+        ];
+        let minimal = TestEfficiency::new(&baseline, &kills).minimal_test_set();
+        assert_eq!(
+            minimal.to_string(),
+            "\
+2 of 5 tests kill all 5 caught mutants:
+  a (+3)
+  d (+2)
+"
+        );
+    }
+
+    #[test]
+    fn no_tests_found() {
+        assert_eq!(
+            TestEfficiency::new(&[], &[]).to_text(),
+            "No libtest test results were found in the logs.\n"
+        );
+    }
+}
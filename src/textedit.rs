@@ -5,7 +5,7 @@
 use serde::Serialize;
 
 /// A (line, column) position in a source file.
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize)]
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Debug, Serialize)]
 pub struct LineColumn {
     /// 1-based line number.
     pub line: usize,
@@ -34,6 +34,24 @@ pub struct Span {
     pub end: LineColumn,
 }
 
+impl Span {
+    /// Return a span covering exactly the text of some tokens.
+    ///
+    /// Unlike the `From` conversions, which also cover the character after the end
+    /// (typically the newline after a function body), the end of this span is
+    /// the last character of the tokens.
+    pub fn exact(s: proc_macro2::Span) -> Span {
+        let end = s.end();
+        Span {
+            start: s.start().into(),
+            end: LineColumn {
+                line: end.line,
+                column: end.column,
+            },
+        }
+    }
+}
+
 impl From<proc_macro2::Span> for Span {
     fn from(s: proc_macro2::Span) -> Self {
         Span {
@@ -88,6 +106,27 @@ pub(crate) fn replace_region(
     r
 }
 
+/// Return the text of the region between `start` and `end` inclusive.
+pub(crate) fn region_text(s: &str, start: &LineColumn, end: &LineColumn) -> String {
+    let mut r = String::new();
+    let mut line_no = 1;
+    let mut col_no = 1;
+    for c in s.chars() {
+        if (line_no > start.line || (line_no == start.line && col_no >= start.column))
+            && (line_no < end.line || (line_no == end.line && col_no <= end.column))
+        {
+            r.push(c);
+        }
+        if c == '\n' {
+            line_no += 1;
+            col_no = 1;
+        } else if c != '\r' {
+            col_no += 1;
+        }
+    }
+    r
+}
+
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
@@ -111,6 +150,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn region_text_is_inclusive() {
+        let source = "fn foo() {\n    while a < b {\n    }\n}\n";
+        assert_eq!(
+            region_text(
+                source,
+                &LineColumn { line: 2, column: 5 },
+                &LineColumn {
+                    line: 2,
+                    column: 15
+                }
+            ),
+            "while a < b"
+        );
+    }
+
     #[test]
     fn test_replace_region() {
         let source = "
@@ -0,0 +1,115 @@
+// Copyright 2022 Martin Pool
+
+//! A parser for the small subset of TOML in the files that cargo-mutants reads
+//! itself, because there's no TOML parser in our dependencies: arrays of
+//! strings, with comments.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::anyhow;
+
+use crate::*;
+
+/// Skip whitespace and comments.
+pub fn skip_blank(chars: &mut Peekable<Chars>) {
+    loop {
+        match chars.peek() {
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+            }
+            Some('#') => while chars.next_if(|c| *c != '\n').is_some() {},
+            _ => return,
+        }
+    }
+}
+
+/// Skip blanks, and then consume the expected character or return an error.
+pub fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<()> {
+    skip_blank(chars);
+    if chars.next() == Some(expected) {
+        Ok(())
+    } else {
+        Err(anyhow!("expected `{}`", expected))
+    }
+}
+
+/// Parse an array of strings, like `["a", 'b']`, possibly over several lines.
+pub fn parse_string_array(chars: &mut Peekable<Chars>) -> Result<Vec<String>> {
+    expect(chars, '[')?;
+    let mut strings = Vec::new();
+    loop {
+        skip_blank(chars);
+        match chars.peek() {
+            Some(']') => {
+                chars.next();
+                return Ok(strings);
+            }
+            Some('"') | Some('\'') => {
+                strings.push(parse_string(chars)?);
+                skip_blank(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some(']') => return Ok(strings),
+                    _ => return Err(anyhow!("expected `,` or `]` after string")),
+                }
+            }
+            _ => return Err(anyhow!("expected a quoted string or `]`")),
+        }
+    }
+}
+
+/// Parse a TOML basic (double-quoted) or literal (single-quoted) string.
+pub fn parse_string(chars: &mut Peekable<Chars>) -> Result<String> {
+    let quote = chars.next().unwrap();
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            None | Some('\n') => return Err(anyhow!("unterminated string")),
+            Some(c) if c == quote => return Ok(s),
+            Some('\\') if quote == '"' => match chars.next() {
+                Some('"') => s.push('"'),
+                Some('\\') => s.push('\\'),
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| anyhow!("invalid unicode escape \\u{}", hex))?;
+                    s.push(c);
+                }
+                other => return Err(anyhow!("unsupported escape \\{}", other.unwrap_or(' '))),
+            },
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_arrays_of_strings() {
+        let parse = |s: &str| parse_string_array(&mut s.chars().peekable());
+        assert_eq!(parse("[]").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            parse("[\n  \"a\", # first\n  'b\\\\c',\n  \"\\u00e9\\\"\",\n]").unwrap(),
+            ["a", "b\\\\c", "é\""]
+        );
+        assert_eq!(
+            parse("[\"a\" \"b\"]").unwrap_err().to_string(),
+            "expected `,` or `]` after string"
+        );
+        assert_eq!(
+            parse("[\"a]").unwrap_err().to_string(),
+            "unterminated string"
+        );
+        assert_eq!(parse("\"a\"").unwrap_err().to_string(), "expected `[`");
+    }
+}
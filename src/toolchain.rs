@@ -0,0 +1,148 @@
+// Copyright 2022 Martin Pool
+
+//! Support for `rust-toolchain.toml` and `rust-toolchain` files, which tell rustup
+//! which toolchain to use for a tree.
+//!
+//! When cargo-mutants is run as a cargo subcommand, `$CARGO` is the cargo binary of
+//! the toolchain that launched it, which doesn't read toolchain files, and rustup
+//! sets `$RUSTUP_TOOLCHAIN` for it, which would override the files anyway. So the
+//! toolchain is resolved from the source tree, and cargo is run through the rustup
+//! proxy with `$RUSTUP_TOOLCHAIN` set to it. This also applies to the scratch
+//! directory, which might not be inside the directory holding the toolchain file.
+
+use std::fs;
+
+use anyhow::{anyhow, Context};
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::toml_subset::{expect, parse_string, skip_blank};
+use crate::*;
+
+/// The names of the files that choose a toolchain, in rustup's order of precedence.
+pub const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain", "rust-toolchain.toml"];
+
+/// A toolchain chosen by a toolchain file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustToolchain {
+    /// The toolchain name, such as `nightly-2022-06-01` or `1.62`.
+    pub channel: String,
+    /// The file that chose it.
+    pub file: Utf8PathBuf,
+}
+
+impl RustToolchain {
+    /// Find the toolchain file that rustup would use in `dir`, searching it and then
+    /// its parents, and read the toolchain from it.
+    pub fn find(dir: &Utf8Path) -> Result<Option<RustToolchain>> {
+        let dir = dir
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {}", dir))?;
+        for ancestor in dir.ancestors() {
+            for name in TOOLCHAIN_FILES {
+                let file = ancestor.join(name);
+                if !file.is_file() {
+                    continue;
+                }
+                let content =
+                    fs::read_to_string(&file).with_context(|| format!("read {}", file))?;
+                let channel =
+                    parse_toolchain_file(&content).with_context(|| format!("parse {}", file))?;
+                return Ok(Some(RustToolchain { channel, file }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Return the toolchain named by a toolchain file: either the `channel` or `path` in
+/// the `[toolchain]` section of a TOML file, or the whole of an old-style file
+/// holding only the name.
+fn parse_toolchain_file(content: &str) -> Result<String> {
+    let trimmed = content.trim();
+    if !trimmed.is_empty() && !trimmed.contains(['\n', '=', '[']) {
+        return Ok(trimmed.to_owned());
+    }
+    let mut in_toolchain = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            in_toolchain = header.split(']').next().unwrap_or_default().trim() == "toolchain";
+        } else if in_toolchain {
+            for key in ["channel", "path"] {
+                if let Some(value) = line.strip_prefix(key) {
+                    let mut chars = value.chars().peekable();
+                    if expect(&mut chars, '=').is_ok() {
+                        skip_blank(&mut chars);
+                        return parse_string(&mut chars)
+                            .with_context(|| format!("toolchain.{} should be a string", key));
+                    }
+                }
+            }
+        }
+    }
+    Err(anyhow!("no toolchain.channel"))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_toolchain_files() {
+        assert_eq!(
+            parse_toolchain_file("nightly-2022-06-01\n").unwrap(),
+            "nightly-2022-06-01"
+        );
+        assert_eq!(
+            parse_toolchain_file(
+                "# Pinned for the MSRV.\n[toolchain]\nchannel = \"1.62\"\ncomponents = [\"rustfmt\"]\n"
+            )
+            .unwrap(),
+            "1.62"
+        );
+        assert_eq!(
+            parse_toolchain_file("[toolchain]\npath = '/opt/rust'\n").unwrap(),
+            "/opt/rust"
+        );
+        assert_eq!(
+            parse_toolchain_file("[toolchain]\ncomponents = [\"miri\"]\n")
+                .unwrap_err()
+                .to_string(),
+            "no toolchain.channel"
+        );
+    }
+
+    #[test]
+    fn find_toolchain_file_in_parent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(tmp.path())
+            .unwrap()
+            .canonicalize_utf8()
+            .unwrap();
+        let package = root.join("crates/a");
+        fs::create_dir_all(&package).unwrap();
+        assert_eq!(RustToolchain::find(&package).unwrap(), None);
+
+        fs::write(
+            root.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"nightly\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            RustToolchain::find(&package).unwrap(),
+            Some(RustToolchain {
+                channel: "nightly".to_owned(),
+                file: root.join("rust-toolchain.toml"),
+            })
+        );
+
+        // The old-style file takes precedence, as it does in rustup.
+        fs::write(root.join("rust-toolchain"), "stable\n").unwrap();
+        assert_eq!(
+            RustToolchain::find(&package).unwrap().unwrap().channel,
+            "stable"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2022 Martin Pool
+
+//! A directory in the user's cache directory, `~/.cache/cargo-mutants` by default,
+//! holding data that's kept across runs even if `mutants.out` isn't.
+//!
+//! Each project has a directory keyed by the path of its source tree, holding copies
+//! of its `history.jsonl` and `baseline.json`. A run whose `mutants.out` doesn't
+//! have them, for example because the checkout is fresh or read-only and the output
+//! goes somewhere new, starts from these copies, so `--iterate`, `--prioritize`, and
+//! `--baseline skip` still work. The history records the git commit of each run,
+//! so `--iterate` can tell which files changed since.
+
+use std::env;
+use std::fs;
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::mutate::fnv1a_hash;
+use crate::scratch_registry::ScratchRegistry;
+use crate::*;
+
+/// The cache directory of cargo-mutants for this user.
+#[derive(Debug, Clone)]
+pub struct UserCache {
+    dir: Utf8PathBuf,
+}
+
+impl UserCache {
+    pub fn new(dir: Utf8PathBuf) -> UserCache {
+        UserCache { dir }
+    }
+
+    /// Find the cache in the user's cache directory, or return None if the cache
+    /// directory can't be found.
+    pub fn find() -> Option<UserCache> {
+        user_cache_dir().map(|cache| UserCache::new(cache.join("cargo-mutants")))
+    }
+
+    /// Return the registry of scratch directories in use.
+    pub fn scratch_registry(&self) -> ScratchRegistry {
+        ScratchRegistry::new(self.dir.join("scratch"))
+    }
+
+    /// Return the directory for data about the project whose source is in
+    /// `source_dir`, which might not exist yet.
+    ///
+    /// The name of the directory includes the name of the source directory, to make
+    /// it recognizable, and a hash of its absolute path, so that different checkouts
+    /// are kept apart.
+    pub fn project_dir(&self, source_dir: &Utf8Path) -> Utf8PathBuf {
+        let absolute = source_dir
+            .canonicalize_utf8()
+            .unwrap_or_else(|_| source_dir.to_owned());
+        self.dir.join("projects").join(format!(
+            "{}-{:08x}",
+            absolute.file_name().unwrap_or("root"),
+            fnv1a_hash(absolute.as_str())
+        ))
+    }
+
+    /// Remove the directory for a project, if it exists, and return its path.
+    pub fn remove_project_dir(&self, source_dir: &Utf8Path) -> Result<Option<Utf8PathBuf>> {
+        let project_dir = self.project_dir(source_dir);
+        if !project_dir.is_dir() {
+            return Ok(None);
+        }
+        fs::remove_dir_all(&project_dir).with_context(|| format!("remove {}", project_dir))?;
+        Ok(Some(project_dir))
+    }
+}
+
+/// Return the user's cache directory, following the XDG convention if
+/// `XDG_CACHE_HOME` is set, and otherwise each platform's usual location.
+fn user_cache_dir() -> Option<Utf8PathBuf> {
+    let var = |name: &str| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(Utf8PathBuf::from)
+    };
+    if let Some(cache) = var("XDG_CACHE_HOME") {
+        Some(cache)
+    } else if cfg!(windows) {
+        var("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Caches"))
+    } else {
+        var("HOME").map(|home| home.join(".cache"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn project_dirs_are_distinct_and_named_after_the_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_path = Utf8Path::from_path(tmp.path()).unwrap();
+        for dir in ["a/factorial", "b/factorial"] {
+            fs::create_dir_all(tmp_path.join(dir)).unwrap();
+        }
+        let cache = UserCache::new(tmp_path.join("cache"));
+        let a = cache.project_dir(&tmp_path.join("a/factorial"));
+        let b = cache.project_dir(&tmp_path.join("b/factorial"));
+        assert_ne!(a, b);
+        assert!(a.starts_with(tmp_path.join("cache/projects")));
+        assert!(a.file_name().unwrap().starts_with("factorial-"));
+        // The same directory reached by a different path has the same cache.
+        assert_eq!(cache.project_dir(&tmp_path.join("b/../a/factorial")), a);
+    }
+}
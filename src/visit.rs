@@ -4,25 +4,279 @@
 //!
 //! Knowledge of the syn API is localized here.
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::Arc;
 
 use quote::ToTokens;
+use serde::Serialize;
+use syn::spanned::Spanned;
 use syn::visit::Visit;
 use syn::Attribute;
 use syn::ItemFn;
 
+use crate::mutate::{ItemTag, MutationKind};
+use crate::textedit::{region_text, LineColumn, Span};
 use crate::*;
 
-/// Find all possible mutants in a source file.
-pub fn discover_mutants(source_file: Arc<SourceFile>) -> Result<Vec<Mutant>> {
+/// The marker in a comment at the start of a file that shows it was generated by a tool.
+const GENERATED_MARKER: &str = "@generated";
+
+/// Traits whose functions construct a value, and so aren't replaced by
+/// `Default::default()`: for `Default` that's equivalent, and for the others it's
+/// often either recursive or equivalent to a trivial conversion.
+const VALUE_TRAITS: &[&str] = &["Default", "From", "Into"];
+
+/// The text of a comment that marks the next item, or the function containing it,
+/// to be skipped.
+const SKIP_COMMENT: &str = "cargo-mutants: skip";
+
+/// A function, or an item containing functions, that was not mutated, and why.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SkippedFunction {
+    /// The tree-relative path of the source file, with forward slashes.
+    pub file: String,
+    /// The name of the function or item, including its enclosing namespaces within
+    /// the file, or empty if the whole file was skipped.
+    pub function: String,
+    /// The line where the function or item starts.
+    pub line: usize,
+    pub reason: SkipReason,
+}
+
+impl fmt::Display for SkippedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.function.is_empty() {
+            write!(f, "{}: {}", self.file, self.reason)
+        } else {
+            write!(
+                f,
+                "{}:{}: {}: {}",
+                self.file, self.line, self.function, self.reason
+            )
+        }
+    }
+}
+
+/// Why a function was not mutated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// Marked `#[test]` or `#[cfg(test)]`.
+    Test,
+    /// Marked `#[mutants::skip]` or `#[allow(dead_code)]`.
+    Attribute,
+    /// Marked by a `// cargo-mutants: skip` comment.
+    SkipComment,
+    /// The body is empty, so there's nothing to replace.
+    Empty,
+    /// A method named `new`.
+    Constructor,
+    /// Generated code: a file marked `@generated`, or an `#[automatically_derived]` impl.
+    Generated,
+    /// Excluded by `exclude_functions` in the config.
+    Excluded,
+    /// In an item whose attributes are excluded by `--exclude-tag`.
+    ExcludedTag,
+    /// The body isn't replaced because there's no value of the return type that would build.
+    ReturnType,
+    /// A macro definition or invocation, whose contents aren't visited.
+    Macro,
+    /// Marked `#[cfg(not(test))]` in a binary with no integration tests, so it's never
+    /// built by `cargo test`.
+    NotTested,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::Test => "test code",
+            SkipReason::Attribute => "skipped by attribute",
+            SkipReason::SkipComment => "skipped by comment",
+            SkipReason::Empty => "empty body",
+            SkipReason::Constructor => "constructor",
+            SkipReason::Generated => "generated code",
+            SkipReason::Excluded => "excluded by filter",
+            SkipReason::ExcludedTag => "excluded by tag",
+            SkipReason::ReturnType => "unsupported return type",
+            SkipReason::Macro => "macro",
+            SkipReason::NotTested => "not built for tests",
+        })
+    }
+}
+
+/// The number of mutants found in a file, and the number removed by each filter in
+/// the options.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct FilterCounts {
+    /// Mutants that passed all the filters.
+    pub kept: usize,
+    /// Mutants of kinds that weren't chosen by `--mutation-kinds` or the config.
+    pub by_kind: usize,
+    /// Mutants in functions matching `exclude_functions`.
+    pub by_function: usize,
+    /// Mutants in items with attributes excluded by `--exclude-tag` or `exclude_tags`.
+    pub by_tag: usize,
+    /// Mutants left out of functions with more than `--max-mutants-per-fn`.
+    pub by_fn_limit: usize,
+}
+
+impl FilterCounts {
+    /// The number of mutants found before filtering.
+    pub fn found(&self) -> usize {
+        self.kept + self.by_kind + self.by_function + self.by_tag + self.by_fn_limit
+    }
+}
+
+/// Find all possible mutants in a source file, of the kinds enabled by the options.
+#[cfg(test)]
+pub fn discover_mutants(source_file: Arc<SourceFile>, options: &Options) -> Result<Vec<Mutant>> {
+    discover(source_file, options).map(|discovery| discovery.mutants)
+}
+
+/// Find the mutants in a source file, and count how many were removed by each filter.
+pub fn discover_and_count_filtered(
+    source_file: Arc<SourceFile>,
+    options: &Options,
+) -> Result<(Vec<Mutant>, FilterCounts)> {
+    discover(source_file, options).map(|discovery| (discovery.mutants, discovery.filter_counts))
+}
+
+/// Find the functions and items in a source file that are not mutated, and why.
+pub fn discover_skipped(
+    source_file: Arc<SourceFile>,
+    options: &Options,
+) -> Result<Vec<SkippedFunction>> {
+    discover(source_file, options).map(|discovery| discovery.skipped)
+}
+
+/// Everything found by visiting one file.
+struct Discovery {
+    mutants: Vec<Mutant>,
+    skipped: Vec<SkippedFunction>,
+    filter_counts: FilterCounts,
+}
+
+fn discover(source_file: Arc<SourceFile>, options: &Options) -> Result<Discovery> {
+    if !options.include_generated && is_generated(&source_file.code) {
+        let skipped = SkippedFunction {
+            file: source_file.tree_relative_slashes(),
+            function: String::new(),
+            line: 1,
+            reason: SkipReason::Generated,
+        };
+        return Ok(Discovery {
+            mutants: Vec::new(),
+            skipped: vec![skipped],
+            filter_counts: FilterCounts::default(),
+        });
+    }
     let syn_file = syn::parse_str::<syn::File>(&source_file.code)?;
     let mut visitor = DiscoveryVisitor {
         source_file,
+        include_generated: options.include_generated,
+        in_value_impl: false,
+        impl_type: None,
+        default_impl_calls: default_impl_calls(&syn_file),
         mutants: Vec::new(),
+        skipped: Vec::new(),
         namespace_stack: Vec::new(),
+        return_type_stack: Vec::new(),
+        early_return_stack: Vec::new(),
     };
     visitor.visit_file(&syn_file);
-    Ok(visitor.mutants)
+    let module_prefix: String = visitor
+        .source_file
+        .module_path()
+        .iter()
+        .map(|name| format!("{}::", name))
+        .collect();
+    let mut skipped = visitor.skipped;
+    let mut excluded_functions = HashSet::new();
+    let mut filter_counts = FilterCounts::default();
+    let mut mutants = visitor.mutants;
+    mutants.retain(|mutant| {
+        if !options.is_kind_enabled(mutant.op.kind()) {
+            // Kinds that are off by default aren't counted unless the user chose the kinds.
+            if options.mutation_kinds.is_some() {
+                filter_counts.by_kind += 1;
+            }
+            return false;
+        }
+        let function_name = mutant.function_name();
+        if options.is_function_excluded(&format!("{}{}", module_prefix, function_name)) {
+            if excluded_functions.insert(function_name.to_owned()) {
+                skipped.push(SkippedFunction {
+                    file: mutant.source_file().tree_relative_slashes(),
+                    function: function_name.to_owned(),
+                    line: mutant.span().start.line,
+                    reason: SkipReason::Excluded,
+                });
+            }
+            filter_counts.by_function += 1;
+            return false;
+        }
+        if mutant
+            .tags()
+            .iter()
+            .any(|tag| options.exclude_tags.contains(tag))
+        {
+            if excluded_functions.insert(function_name.to_owned()) {
+                skipped.push(SkippedFunction {
+                    file: mutant.source_file().tree_relative_slashes(),
+                    function: function_name.to_owned(),
+                    line: mutant.span().start.line,
+                    reason: SkipReason::ExcludedTag,
+                });
+            }
+            filter_counts.by_tag += 1;
+            return false;
+        }
+        true
+    });
+    if let Some(max) = options.max_mutants_per_fn {
+        filter_counts.by_fn_limit = limit_mutants_per_function(&mut mutants, max);
+    }
+    skipped.sort_by_key(|skipped| skipped.line);
+    filter_counts.kept = mutants.len();
+    Ok(Discovery {
+        mutants,
+        skipped,
+        filter_counts,
+    })
+}
+
+/// Keep at most `max` mutants in each function, and return how many were removed.
+///
+/// The mutants kept are spread evenly through the function, in source order, so that
+/// every part of a long function is still tested, and the choice is the same every run.
+fn limit_mutants_per_function(mutants: &mut Vec<Mutant>, max: usize) -> usize {
+    let mut by_function: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, mutant) in mutants.iter().enumerate() {
+        by_function
+            .entry(mutant.function_name())
+            .or_default()
+            .push(i);
+    }
+    let mut removed = HashSet::new();
+    for mut indexes in by_function.into_values() {
+        let len = indexes.len();
+        if len <= max {
+            continue;
+        }
+        indexes.sort_by_key(|&i| {
+            let start = mutants[i].span().start;
+            (start.line, start.column)
+        });
+        let kept: HashSet<usize> = (0..max).map(|k| indexes[k * len / max]).collect();
+        removed.extend(indexes.into_iter().filter(|i| !kept.contains(i)));
+    }
+    let mut i = 0;
+    mutants.retain(|_| {
+        i += 1;
+        !removed.contains(&(i - 1))
+    });
+    removed.len()
 }
 
 /// `syn` visitor that recursively traverses the syntax tree, accumulating places
@@ -31,18 +285,62 @@ struct DiscoveryVisitor {
     /// All the mutants generated by visiting the file.
     mutants: Vec<Mutant>,
 
+    /// Functions and items that were not mutated.
+    skipped: Vec<SkippedFunction>,
+
     /// The file being visited.
     source_file: Arc<SourceFile>,
 
+    /// Also visit `#[automatically_derived]` impls.
+    include_generated: bool,
+
+    /// True inside an impl of `Default`, `From`, or `Into`, whose functions aren't
+    /// replaced by a default value, because that would be equivalent or recursive.
+    /// Literal values in struct expressions are changed instead.
+    in_value_impl: bool,
+
+    /// The name of the type of the `impl` block we're currently inside, if any.
+    impl_type: Option<String>,
+
+    /// For each type with an `impl Default` in this file, the associated functions of
+    /// that type called by `default`: see [default_impl_calls].
+    default_impl_calls: HashMap<String, HashSet<String>>,
+
     /// The stack of namespaces we're currently inside.
     namespace_stack: Vec<String>,
+
+    /// The return types of the functions we're currently inside, innermost last.
+    return_type_stack: Vec<Arc<String>>,
+
+    /// For the innermost function, closure, or async block we're inside, the `return`
+    /// statements that could be inserted to exit it early.
+    ///
+    /// The type of a closure or async block is generally not written out, so inside them
+    /// only `return Default::default();` is inserted.
+    early_return_stack: Vec<Vec<String>>,
 }
 
 impl DiscoveryVisitor {
-    fn collect_fn_mutants(&mut self, return_type: &syn::ReturnType, span: &proc_macro2::Span) {
+    /// Add mutants that replace the whole body of a function, except those that are
+    /// equivalent to the existing body, or that would call the function itself.
+    fn collect_fn_mutants(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let return_type = &sig.output;
         let full_function_name = Arc::new(self.namespace_stack.join("::"));
         let return_type_str = Arc::new(return_type_to_string(return_type));
-        for op in ops_for_return_type(return_type) {
+        let span = &block.brace_token.span;
+        let default_recurses = self.default_impl_calls_function(sig);
+        let ops = ops_for_return_type(return_type);
+        if ops.is_empty() {
+            self.record_skip(
+                full_function_name.to_string(),
+                sig.span(),
+                SkipReason::ReturnType,
+            );
+        }
+        for op in ops.into_iter().filter(|op| {
+            let recurses = default_recurses && *op == MutationOp::Default;
+            !(recurses || op_is_equivalent_to_body(op, block))
+        }) {
             self.mutants.push(Mutant::new(
                 self.source_file.clone(),
                 op,
@@ -53,6 +351,471 @@ impl DiscoveryVisitor {
         }
     }
 
+    /// True if `sig` is an associated function returning the type of the `impl` we're
+    /// inside, and that type's `Default` impl calls it, so that replacing its body with
+    /// `Default::default()` would recurse forever, and the mutant could only time out.
+    fn default_impl_calls_function(&self, sig: &syn::Signature) -> bool {
+        let impl_type = match &self.impl_type {
+            Some(impl_type) => impl_type,
+            None => return false,
+        };
+        let returns_impl_type = match &sig.output {
+            syn::ReturnType::Type(_, ty) => match &**ty {
+                syn::Type::Path(syn::TypePath { path, .. }) => {
+                    path.is_ident("Self") || path.is_ident(impl_type)
+                }
+                _ => false,
+            },
+            syn::ReturnType::Default => false,
+        };
+        returns_impl_type
+            && self
+                .default_impl_calls
+                .get(impl_type)
+                .is_some_and(|calls| calls.contains(&sig.ident.to_string()))
+    }
+
+    /// Add a mutant that replaces part of the function we're currently inside.
+    ///
+    /// Nothing is generated outside of a function, for example in a `const` initializer.
+    fn collect_replace_mutant(
+        &mut self,
+        kind: MutationKind,
+        span: Span,
+        original: String,
+        replacement: &str,
+    ) {
+        let return_type = match self.return_type_stack.last() {
+            Some(return_type) => return_type.clone(),
+            None => return,
+        };
+        self.mutants.push(Mutant::new(
+            self.source_file.clone(),
+            MutationOp::Replace {
+                kind,
+                original,
+                replacement: replacement.to_owned(),
+            },
+            Arc::new(self.namespace_stack.join("::")),
+            return_type,
+            span,
+        ))
+    }
+
+    /// Add mutants that insert each possible early return after the character at `anchor`.
+    fn collect_early_return_mutants(&mut self, anchor: LineColumn, location: &str) {
+        let codes = match self.early_return_stack.last() {
+            Some(codes) => codes.clone(),
+            None => return,
+        };
+        for code in codes {
+            self.collect_insert_mutant(MutationKind::EarlyReturn, anchor, location, code);
+        }
+    }
+
+    /// Add a mutant that inserts code after the character at `anchor`, in the function
+    /// we're currently inside.
+    fn collect_insert_mutant(
+        &mut self,
+        kind: MutationKind,
+        anchor: LineColumn,
+        location: &str,
+        code: String,
+    ) {
+        let return_type = match self.return_type_stack.last() {
+            Some(return_type) => return_type.clone(),
+            None => return,
+        };
+        self.mutants.push(Mutant::new(
+            self.source_file.clone(),
+            MutationOp::Insert {
+                kind,
+                location: location.to_owned(),
+                code,
+            },
+            Arc::new(self.namespace_stack.join("::")),
+            return_type,
+            Span {
+                start: anchor,
+                end: anchor,
+            },
+        ))
+    }
+
+    /// Add mutants that swap each pair of parameters with the same type, by rebinding
+    /// them at the start of the function body.
+    fn collect_swap_args_mutants(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        // The name, whether it's `mut`, and the type of each simple parameter.
+        let params: Vec<(String, bool, String)> = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident)
+                        if pat_ident.subpat.is_none() && pat_ident.by_ref.is_none() =>
+                    {
+                        Some((
+                            pat_ident.ident.to_string(),
+                            pat_ident.mutability.is_some(),
+                            pat_type.ty.to_token_stream().to_string(),
+                        ))
+                    }
+                    _ => None,
+                },
+                syn::FnArg::Receiver(_) => None,
+            })
+            .collect();
+        let binding = |name: &str, mutable: bool| {
+            if mutable {
+                format!("mut {}", name)
+            } else {
+                name.to_owned()
+            }
+        };
+        let anchor = Span::exact(block.brace_token.span).start;
+        for (i, (a, a_mut, a_type)) in params.iter().enumerate() {
+            for (b, b_mut, b_type) in &params[i + 1..] {
+                if a_type == b_type {
+                    self.collect_insert_mutant(
+                        MutationKind::SwapArgs,
+                        anchor,
+                        "at start of function",
+                        format!(
+                            "let ({}, {}) = ({}, {});",
+                            binding(a, *a_mut),
+                            binding(b, *b_mut),
+                            b,
+                            a
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Add early-return mutants after the first statement of a function body, if
+    /// there is anything after it.
+    fn collect_early_return_after_first_statement(&mut self, block: &syn::Block) {
+        if block.stmts.len() < 2 {
+            return;
+        }
+        let semi = match &block.stmts[0] {
+            syn::Stmt::Local(local) => local.semi_token.span,
+            syn::Stmt::Semi(_, semi) => semi.span,
+            _ => return,
+        };
+        self.collect_early_return_mutants(Span::exact(semi).start, "after first statement");
+    }
+
+    /// Add mutants that change the value of a `const` or `static` item, if it's a number,
+    /// a bool, or a `Duration` of a literal number.
+    fn collect_const_value_mutants(&mut self, ident: &syn::Ident, expr: &syn::Expr) {
+        let (span, replacements) = match perturbed_values(expr) {
+            Some(values) => values,
+            None => return,
+        };
+        let span = Span::exact(span);
+        let original = self.original_text(&span);
+        let name = Arc::new(self.qualified_name(&ident.to_string()));
+        for replacement in replacements {
+            self.mutants.push(Mutant::new(
+                self.source_file.clone(),
+                MutationOp::Replace {
+                    kind: MutationKind::ConstValue,
+                    original: original.clone(),
+                    replacement,
+                },
+                name.clone(),
+                Arc::new(String::new()),
+                span,
+            ))
+        }
+    }
+
+    /// True if a `const` or `static` item is marked to be skipped.
+    fn const_item_skipped(&self, attrs: &[Attribute], span: proc_macro2::Span) -> bool {
+        self.skip_reason(attrs, span, Span::from(span).end)
+            .is_some()
+    }
+
+    /// Return why an item should be skipped because of its attributes, or a skip
+    /// comment up to `marked_until`: see [DiscoveryVisitor::skip_comment_marks].
+    fn skip_reason(
+        &self,
+        attrs: &[Attribute],
+        item_span: proc_macro2::Span,
+        marked_until: LineColumn,
+    ) -> Option<SkipReason> {
+        attrs_skip_reason(attrs)
+            .or_else(|| {
+                (self.source_file.only_unit_tested && attrs.iter().any(attr_is_cfg_not_test))
+                    .then_some(SkipReason::NotTested)
+            })
+            .or_else(|| {
+                self.skip_comment_marks(item_span, marked_until)
+                    .then_some(SkipReason::SkipComment)
+            })
+    }
+
+    /// Return why a function should be skipped, if it should.
+    fn fn_skip_reason(
+        &self,
+        attrs: &[Attribute],
+        span: proc_macro2::Span,
+        block: &syn::Block,
+    ) -> Option<SkipReason> {
+        self.skip_reason(attrs, span, Span::from(span).end)
+            .or_else(|| {
+                // Only the function itself: an `impl` or `mod` might allow dead code in
+                // a few of its items, while the rest are used.
+                attrs
+                    .iter()
+                    .any(attr_is_allow_dead_code)
+                    .then_some(SkipReason::Attribute)
+            })
+            .or_else(|| block_is_empty(block).then_some(SkipReason::Empty))
+    }
+
+    /// Record that a function or item, named including its namespaces, is not mutated.
+    fn record_skip(&mut self, function: String, span: proc_macro2::Span, reason: SkipReason) {
+        self.skipped.push(SkippedFunction {
+            file: self.source_file.tree_relative_slashes(),
+            function,
+            line: Span::from(span).start.line,
+            reason,
+        });
+    }
+
+    /// The name of an item within the current namespace.
+    fn qualified_name(&self, name: &str) -> String {
+        self.namespace_stack
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(name))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    /// Add mutants that make `filter` keep everything or nothing, make `take` and `skip`
+    /// use no items, and remove `rev()`.
+    ///
+    /// These are only recognized by name, so they may also change methods of other types
+    /// that have the same names, but such mutants will usually be unviable.
+    fn collect_iterator_mutants(&mut self, i: &syn::ExprMethodCall) {
+        if i.turbofish.is_some() {
+            return;
+        }
+        let call_span = Span {
+            start: Span::exact(i.method.span()).start,
+            end: Span::exact(i.paren_token.span).end,
+        };
+        let method = i.method.to_string();
+        match (method.as_str(), i.args.len()) {
+            ("filter", 1) => {
+                let original = self.original_text(&call_span);
+                for replacement in ["filter(|_| true)", "filter(|_| false)"] {
+                    self.collect_replace_mutant(
+                        MutationKind::Iterator,
+                        call_span,
+                        original.clone(),
+                        replacement,
+                    );
+                }
+            }
+            ("take" | "skip", 1) => {
+                let is_zero = matches!(
+                    &i.args[0],
+                    syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. })
+                        if int.base10_digits() == "0"
+                );
+                if !is_zero {
+                    let original = self.original_text(&call_span);
+                    self.collect_replace_mutant(
+                        MutationKind::Iterator,
+                        call_span,
+                        original,
+                        &format!("{}(0)", method),
+                    );
+                }
+            }
+            ("rev", 0) => {
+                let span = Span {
+                    start: Span::exact(i.dot_token.span).start,
+                    end: call_span.end,
+                };
+                let original = self.original_text(&span);
+                self.collect_replace_mutant(MutationKind::Iterator, span, original, "");
+            }
+            _ => (),
+        }
+    }
+
+    /// Add a mutant that swaps the operator of a binary expression, if it's a bitwise or
+    /// shift operator.
+    fn collect_bitwise_mutant(
+        &mut self,
+        span: proc_macro2::Span,
+        left: &syn::Expr,
+        op: &syn::BinOp,
+        right: &syn::Expr,
+    ) {
+        if let Some(replacement_op) = swapped_bitwise_op(op) {
+            let span = Span::exact(span);
+            let original = self.original_text(&span);
+            let replacement = format!(
+                "{} {} {}",
+                self.original_text(&Span::exact(left.span())),
+                replacement_op,
+                self.original_text(&Span::exact(right.span())),
+            );
+            self.collect_replace_mutant(MutationKind::Bitwise, span, original, &replacement);
+        }
+    }
+
+    /// Add mutants that flip a comparison of floats, and that tighten and loosen the
+    /// tolerance of checks like `(a - b).abs() < eps`.
+    ///
+    /// Since types aren't known, comparisons are taken to be of floats if one side is
+    /// evidently a float: see [is_float_expr]. `abs()` alone isn't enough, because
+    /// integers have it too.
+    fn collect_float_mutants(&mut self, i: &syn::ExprBinary) {
+        let flipped_op = match flipped_comparison_op(&i.op) {
+            Some(op) => op,
+            None => return,
+        };
+        let left_is_abs = is_abs_call(&i.left);
+        let right_is_abs = is_abs_call(&i.right);
+        if !(is_float_expr(&i.left) || is_float_expr(&i.right)) {
+            return;
+        }
+        let span = Span::exact(i.span());
+        let original = self.original_text(&span);
+        let left = self.original_text(&Span::exact(i.left.span()));
+        let op = self.original_text(&Span::exact(i.op.span()));
+        let right = self.original_text(&Span::exact(i.right.span()));
+        self.collect_replace_mutant(
+            MutationKind::Float,
+            span,
+            original.clone(),
+            &format!("{} {} {}", left, flipped_op, right),
+        );
+        let tolerance = match (left_is_abs, right_is_abs) {
+            (true, false) => &i.right,
+            (false, true) => &i.left,
+            _ => return,
+        };
+        let tolerance_text = self.original_text(&Span::exact(tolerance.span()));
+        let loosened = if needs_parens(tolerance) {
+            format!("({}) * 1000.0", tolerance_text)
+        } else {
+            format!("{} * 1000.0", tolerance_text)
+        };
+        for new_tolerance in ["0.0".to_owned(), loosened] {
+            let replacement = if right_is_abs {
+                format!("{} {} {}", new_tolerance, op, right)
+            } else {
+                format!("{} {} {}", left, op, new_tolerance)
+            };
+            self.collect_replace_mutant(MutationKind::Float, span, original.clone(), &replacement);
+        }
+    }
+
+    /// Return the source text between two spans, inclusive, with whitespace collapsed.
+    fn original_text(&self, span: &Span) -> String {
+        region_text(&self.source_file.code, &span.start, &span.end)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Visit the body of a function, recording its return type while inside.
+    fn in_function<F>(&mut self, return_type: &syn::ReturnType, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.return_type_stack
+            .push(Arc::new(return_type_to_string(return_type)));
+        self.early_return_stack.push(
+            ops_for_return_type(return_type)
+                .iter()
+                .map(|op| match op {
+                    MutationOp::Unit => "return;".to_owned(),
+                    op => format!("return {};", op.replacement()),
+                })
+                .collect(),
+        );
+        f(self);
+        self.early_return_stack.pop();
+        self.return_type_stack.pop();
+    }
+
+    /// Add a mutant that replaces the body of a loop statement with `{ break; }`.
+    fn collect_loop_mutant(&mut self, i: &syn::ExprLoop) {
+        // If the loop breaks with a value, `break;` would not type-check.
+        if !contains_break_with_value(&i.body) {
+            self.collect_replace_mutant(
+                MutationKind::Loop,
+                Span::exact(i.body.brace_token.span),
+                "loop body".to_owned(),
+                "{ break; }",
+            );
+        }
+    }
+
+    /// Visit a closure or async block, where `return` exits only the closure or block,
+    /// and its type is inferred.
+    fn in_nested_body<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.early_return_stack
+            .push(vec!["return Default::default();".to_owned()]);
+        f(self);
+        self.early_return_stack.pop();
+    }
+
+    /// True if a `// cargo-mutants: skip` comment marks the item with span `item_span`,
+    /// either in the comments directly above it, or anywhere from the start of the
+    /// item up to `marked_until`.
+    ///
+    /// For functions `marked_until` is the end of the body, so that a marker
+    /// inside the function skips the whole function. For `impl` blocks and
+    /// modules it's the opening brace, so that markers on their contents only skip
+    /// those contents.
+    fn skip_comment_marks(&self, item_span: proc_macro2::Span, marked_until: LineColumn) -> bool {
+        let code = &self.source_file.code;
+        let start = Span::from(item_span).start;
+        let line_start = LineColumn {
+            line: start.line,
+            column: 1,
+        };
+        if contains_skip_comment(&region_text(code, &line_start, &marked_until)) {
+            return true;
+        }
+        let lines_above: Vec<&str> = code.lines().take(start.line - 1).collect();
+        lines_above
+            .into_iter()
+            .rev()
+            .map(str::trim)
+            .take_while(|line| {
+                line.starts_with("//")
+                    || line.starts_with("/*")
+                    || line.starts_with('*')
+                    || line.ends_with("*/")
+            })
+            .any(contains_skip_comment)
+    }
+
+    /// Tag the mutants found since there were `first_mutant` mutants with the
+    /// attributes of the item that contains them.
+    fn tag_mutants_since(&mut self, first_mutant: usize, attrs: &[Attribute]) {
+        for tag in attrs_tags(attrs) {
+            for mutant in &mut self.mutants[first_mutant..] {
+                mutant.add_tag(tag);
+            }
+        }
+    }
+
     /// Call a function with a namespace pushed onto the stack.
     ///
     /// This is used when recursively descending into a namespace.
@@ -72,43 +835,101 @@ impl<'ast> Visit<'ast> for DiscoveryVisitor {
     /// Visit top-level `fn foo()`.
     fn visit_item_fn(&mut self, i: &'ast ItemFn) {
         // TODO: Filter out more inapplicable fns.
-        if attrs_excluded(&i.attrs) || block_is_empty(&i.block) {
+        let function_name = remove_excess_spaces(&i.sig.ident.to_token_stream().to_string());
+        if let Some(reason) = self.fn_skip_reason(&i.attrs, i.span(), &i.block) {
+            self.record_skip(self.qualified_name(&function_name), i.span(), reason);
             return; // don't look inside it either
         }
-        let function_name = remove_excess_spaces(&i.sig.ident.to_token_stream().to_string());
+        // A function nested inside a method of a value impl is an ordinary function.
+        let in_value_impl = std::mem::replace(&mut self.in_value_impl, false);
+        let first_mutant = self.mutants.len();
         self.in_namespace(&function_name, |self_| {
-            self_.collect_fn_mutants(&i.sig.output, &i.block.brace_token.span);
-            syn::visit::visit_item_fn(self_, i);
+            self_.collect_fn_mutants(&i.sig, &i.block);
+            self_.in_function(&i.sig.output, |self_| {
+                self_.collect_swap_args_mutants(&i.sig, &i.block);
+                self_.collect_early_return_after_first_statement(&i.block);
+                syn::visit::visit_item_fn(self_, i)
+            });
         });
+        self.tag_mutants_since(first_mutant, &i.attrs);
+        self.in_value_impl = in_value_impl;
     }
 
     /// Visit `fn foo()` within an `impl`.
     fn visit_impl_item_method(&mut self, i: &'ast syn::ImplItemMethod) {
         // Don't look inside constructors (called "new") because there's often no good
         // alternative.
-        if attrs_excluded(&i.attrs) || i.sig.ident == "new" || block_is_empty(&i.block) {
+        let function_name = remove_excess_spaces(&i.sig.ident.to_token_stream().to_string());
+        let skip_reason = if i.sig.ident == "new" {
+            attrs_skip_reason(&i.attrs).or(Some(SkipReason::Constructor))
+        } else {
+            self.fn_skip_reason(&i.attrs, i.span(), &i.block)
+        };
+        if let Some(reason) = skip_reason {
+            self.record_skip(self.qualified_name(&function_name), i.span(), reason);
             return;
         }
+        let first_mutant = self.mutants.len();
+        self.in_namespace(&function_name, |self_| {
+            if !self_.in_value_impl {
+                self_.collect_fn_mutants(&i.sig, &i.block);
+            }
+            self_.in_function(&i.sig.output, |self_| {
+                self_.collect_swap_args_mutants(&i.sig, &i.block);
+                self_.collect_early_return_after_first_statement(&i.block);
+                syn::visit::visit_impl_item_method(self_, i)
+            });
+        });
+        self.tag_mutants_since(first_mutant, &i.attrs);
+    }
+
+    /// Visit `trait Foo { ... }`, whose methods might have default bodies.
+    fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+        if let Some(reason) =
+            self.skip_reason(&i.attrs, i.span(), Span::exact(i.brace_token.span).start)
+        {
+            self.record_skip(self.qualified_name(&i.ident.to_string()), i.span(), reason);
+            return;
+        }
+        let in_value_impl = std::mem::replace(&mut self.in_value_impl, false);
+        let impl_type = self.impl_type.take();
+        let first_mutant = self.mutants.len();
+        self.in_namespace(&i.ident.to_string(), |v| syn::visit::visit_item_trait(v, i));
+        self.tag_mutants_since(first_mutant, &i.attrs);
+        self.in_value_impl = in_value_impl;
+        self.impl_type = impl_type;
+    }
+
+    /// Visit `fn foo()` within a `trait`, and mutate its default body if it has one.
+    fn visit_trait_item_method(&mut self, i: &'ast syn::TraitItemMethod) {
+        let block = match &i.default {
+            Some(block) => block,
+            None => return,
+        };
         let function_name = remove_excess_spaces(&i.sig.ident.to_token_stream().to_string());
+        if let Some(reason) = self.fn_skip_reason(&i.attrs, i.span(), block) {
+            self.record_skip(self.qualified_name(&function_name), i.span(), reason);
+            return;
+        }
+        let first_mutant = self.mutants.len();
         self.in_namespace(&function_name, |self_| {
-            self_.collect_fn_mutants(&i.sig.output, &i.block.brace_token.span);
-            syn::visit::visit_impl_item_method(self_, i)
+            self_.collect_fn_mutants(&i.sig, block);
+            self_.in_function(&i.sig.output, |self_| {
+                self_.collect_swap_args_mutants(&i.sig, block);
+                self_.collect_early_return_after_first_statement(block);
+                syn::visit::visit_trait_item_method(self_, i)
+            });
         });
+        self.tag_mutants_since(first_mutant, &i.attrs);
     }
 
     /// Visit `impl Foo { ...}` or `impl Debug for Foo { ... }`.
     fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
-        if attrs_excluded(&i.attrs) {
-            return;
-        }
         let type_name = type_name_string(&i.self_ty);
+        let mut is_value_impl = false;
         let name = if let Some((_, trait_path, _)) = &i.trait_ {
             let trait_name = &trait_path.segments.last().unwrap().ident;
-            if trait_name == "Default" {
-                // We don't know (yet) how to generate an interestingly-broken
-                // Default::default.
-                return;
-            }
+            is_value_impl = VALUE_TRAITS.iter().any(|name| trait_name == name);
             format!(
                 "<impl {} for {}>",
                 trait_name,
@@ -117,20 +938,355 @@ impl<'ast> Visit<'ast> for DiscoveryVisitor {
         } else {
             type_name
         };
+        let skip_reason = self
+            .skip_reason(&i.attrs, i.span(), Span::exact(i.brace_token.span).start)
+            .or_else(|| {
+                (!self.include_generated && i.attrs.iter().any(attr_is_automatically_derived))
+                    .then_some(SkipReason::Generated)
+            });
+        if let Some(reason) = skip_reason {
+            self.record_skip(self.qualified_name(&name), i.span(), reason);
+            return;
+        }
         // Make an approximately-right namespace.
         // TODO: For `impl X for Y` get both X and Y onto the namespace
         // stack so that we can show a more descriptive name.
+        let in_value_impl = std::mem::replace(&mut self.in_value_impl, is_value_impl);
+        let impl_type = std::mem::replace(&mut self.impl_type, type_path_name(&i.self_ty));
+        let first_mutant = self.mutants.len();
         self.in_namespace(&name, |v| syn::visit::visit_item_impl(v, i));
+        self.tag_mutants_since(first_mutant, &i.attrs);
+        self.in_value_impl = in_value_impl;
+        self.impl_type = impl_type;
+    }
+
+    /// Visit a struct expression like `Foo { a: 1, ..Default::default() }`, and within
+    /// impls of `Default`, `From`, and `Into` change the literal values of its fields.
+    fn visit_expr_struct(&mut self, i: &'ast syn::ExprStruct) {
+        if self.in_value_impl {
+            for field in &i.fields {
+                if let Some((span, replacements)) = perturbed_values(&field.expr) {
+                    let span = Span::exact(span);
+                    let original = self.original_text(&span);
+                    for replacement in replacements {
+                        self.collect_replace_mutant(
+                            MutationKind::StructField,
+                            span,
+                            original.clone(),
+                            &replacement,
+                        );
+                    }
+                }
+            }
+        }
+        syn::visit::visit_expr_struct(self, i);
+    }
+
+    /// Visit `if cond { ... } else { ... }`, insert early returns at the start of
+    /// each block, and swap the branches by negating the condition.
+    ///
+    /// `if let` can't be negated, so its condition is not changed.
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        self.collect_early_return_mutants(
+            Span::exact(i.then_branch.brace_token.span).start,
+            "at start of if block",
+        );
+        if let Some((_, else_expr)) = &i.else_branch {
+            if let syn::Expr::Block(else_block) = &**else_expr {
+                self.collect_early_return_mutants(
+                    Span::exact(else_block.block.brace_token.span).start,
+                    "at start of else block",
+                );
+            }
+        }
+        let cond = &*i.cond;
+        if !matches!(cond, syn::Expr::Let(_)) {
+            let span = Span::exact(cond.span());
+            let original = self.original_text(&span);
+            let replacement = match cond {
+                syn::Expr::Unary(syn::ExprUnary {
+                    op: syn::UnOp::Not(_),
+                    expr,
+                    ..
+                }) => self.original_text(&Span::exact(expr.span())),
+                _ if needs_parens(cond) => format!("!({})", original),
+                _ => format!("!{}", original),
+            };
+            self.collect_replace_mutant(MutationKind::SwapIf, span, original, &replacement);
+        }
+        syn::visit::visit_expr_if(self, i);
+    }
+
+    /// Visit a closure, where `return` exits only the closure.
+    ///
+    /// The body is replaced with `Default::default()`, like the body of a function,
+    /// if it's a block of more than one statement. Shorter closures are often passed
+    /// where the type has no default, and replacing them mostly repeats the mutant of
+    /// the enclosing function.
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        let is_long = matches!(&*i.body, syn::Expr::Block(body) if body.block.stmts.len() > 1);
+        if is_long {
+            // If the return type is written then the body must be a block.
+            let replacement = match i.output {
+                syn::ReturnType::Default => "Default::default()",
+                syn::ReturnType::Type(..) => "{ Default::default() }",
+            };
+            self.collect_replace_mutant(
+                MutationKind::FnValue,
+                Span::exact(i.body.span()),
+                "closure body".to_owned(),
+                replacement,
+            );
+        }
+        self.in_nested_body(|self_| syn::visit::visit_expr_closure(self_, i));
+    }
+
+    /// Visit an `async` block, where `return` exits only the block.
+    ///
+    /// The body is replaced with `Default::default()`, unless it's empty.
+    fn visit_expr_async(&mut self, i: &'ast syn::ExprAsync) {
+        if !block_is_empty(&i.block) {
+            self.collect_replace_mutant(
+                MutationKind::FnValue,
+                Span::exact(i.block.brace_token.span),
+                "async block body".to_owned(),
+                "{ Default::default() }",
+            );
+        }
+        self.in_nested_body(|self_| syn::visit::visit_expr_async(self_, i));
+    }
+
+    /// Visit `while cond { ... }`, and mutate it to `while false`.
+    ///
+    /// `while let` is not changed, because the body uses the bindings from the pattern.
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        if !matches!(*i.cond, syn::Expr::Let(_)) {
+            let span = Span {
+                start: Span::exact(i.while_token.span).start,
+                end: Span::exact(i.cond.span()).end,
+            };
+            let original = self.original_text(&span);
+            self.collect_replace_mutant(MutationKind::Loop, span, original, "while false");
+        }
+        syn::visit::visit_expr_while(self, i);
+    }
+
+    /// Visit a block, and mutate `loop { ... }` statements to exit immediately.
+    ///
+    /// A loop at the end of the block gives the block its type, which might not be `()`
+    /// if the loop only exits by `return`, so only loops followed by other statements
+    /// or a semicolon are changed.
+    fn visit_block(&mut self, i: &'ast syn::Block) {
+        for (index, stmt) in i.stmts.iter().enumerate() {
+            match stmt {
+                syn::Stmt::Semi(syn::Expr::Loop(expr_loop), _) => {
+                    self.collect_loop_mutant(expr_loop)
+                }
+                syn::Stmt::Expr(syn::Expr::Loop(expr_loop)) if index + 1 < i.stmts.len() => {
+                    self.collect_loop_mutant(expr_loop)
+                }
+                _ => {}
+            }
+            self.visit_stmt(stmt);
+        }
+    }
+
+    /// Visit `a..b` or `a..=b`, and change whether the end is inclusive, or move the start
+    /// up by one.
+    ///
+    /// `..=` can't be written without an end, and `..` alone is not changed.
+    fn visit_expr_range(&mut self, i: &'ast syn::ExprRange) {
+        let span = Span::exact(i.span());
+        let original = self.original_text(&span);
+        let from = i
+            .from
+            .as_ref()
+            .map(|from| self.original_text(&Span::exact(from.span())));
+        let to =
+            i.to.as_ref()
+                .map(|to| self.original_text(&Span::exact(to.span())));
+        if let Some(to) = &to {
+            let swapped_limits = match i.limits {
+                syn::RangeLimits::HalfOpen(_) => "..=",
+                syn::RangeLimits::Closed(_) => "..",
+            };
+            let replacement = format!(
+                "{}{}{}",
+                from.as_deref().unwrap_or_default(),
+                swapped_limits,
+                to
+            );
+            self.collect_replace_mutant(MutationKind::Range, span, original.clone(), &replacement);
+        }
+        if let Some(from) = &from {
+            let limits = match i.limits {
+                syn::RangeLimits::HalfOpen(_) => "..",
+                syn::RangeLimits::Closed(_) => "..=",
+            };
+            let replacement = format!(
+                "{} + 1{}{}",
+                from,
+                limits,
+                to.as_deref().unwrap_or_default()
+            );
+            self.collect_replace_mutant(MutationKind::Range, span, original, &replacement);
+        }
+        syn::visit::visit_expr_range(self, i);
+    }
+
+    /// Visit binary operators, swap bitwise and shift operators, and change float
+    /// comparisons.
+    fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+        self.collect_bitwise_mutant(i.span(), &i.left, &i.op, &i.right);
+        self.collect_float_mutants(i);
+        syn::visit::visit_expr_binary(self, i);
+    }
+
+    /// Visit compound assignments like `a |= b`, and swap bitwise and shift operators.
+    fn visit_expr_assign_op(&mut self, i: &'ast syn::ExprAssignOp) {
+        self.collect_bitwise_mutant(i.span(), &i.left, &i.op, &i.right);
+        syn::visit::visit_expr_assign_op(self, i);
+    }
+
+    /// Visit `expr?`, and replace it with `expr.unwrap()`, to check that errors are tested,
+    /// and with `expr.unwrap_or_default()`, to check that errors are not ignored.
+    fn visit_expr_try(&mut self, i: &'ast syn::ExprTry) {
+        let span = Span::exact(i.span());
+        let original = self.original_text(&span);
+        let inner = self.original_text(&Span::exact(i.expr.span()));
+        for method in ["unwrap()", "unwrap_or_default()"] {
+            self.collect_replace_mutant(
+                MutationKind::Try,
+                span,
+                original.clone(),
+                &format!("{}.{}", inner, method),
+            );
+        }
+        syn::visit::visit_expr_try(self, i);
+    }
+
+    /// Visit method calls, replace `unwrap()` and `expect(..)` with
+    /// `unwrap_or_default()`, and change iterator adapters.
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        let is_guard = i.turbofish.is_none()
+            && ((i.method == "unwrap" && i.args.is_empty())
+                || (i.method == "expect" && i.args.len() == 1));
+        if is_guard {
+            let span = Span {
+                start: Span::exact(i.method.span()).start,
+                end: Span::exact(i.paren_token.span).end,
+            };
+            let original = self.original_text(&span);
+            self.collect_replace_mutant(MutationKind::Guard, span, original, "unwrap_or_default()");
+        }
+        self.collect_iterator_mutants(i);
+        syn::visit::visit_expr_method_call(self, i);
+    }
+
+    /// Visit statements, and delete `break` and `continue` statements, assertions, and
+    /// statements that only call a function or method.
+    fn visit_stmt(&mut self, i: &'ast syn::Stmt) {
+        if let Some(mac) = stmt_macro(i) {
+            if is_assert_macro(mac) {
+                let span = Span::exact(i.span());
+                let original = self.original_text(&span);
+                self.collect_replace_mutant(MutationKind::Guard, span, original, "");
+            }
+        }
+        let (expr, semi) = match i {
+            syn::Stmt::Semi(expr, semi) => (Some(expr), Some(semi)),
+            syn::Stmt::Expr(expr) => (Some(expr), None),
+            _ => (None, None),
+        };
+        if let Some(
+            expr @ (syn::Expr::Break(syn::ExprBreak { expr: None, .. }) | syn::Expr::Continue(_)),
+        ) = expr
+        {
+            let expr_span = Span::exact(expr.span());
+            let original = self.original_text(&expr_span);
+            let span = match semi {
+                Some(semi) => Span {
+                    start: expr_span.start,
+                    end: Span::exact(semi.span).end,
+                },
+                None => expr_span,
+            };
+            self.collect_replace_mutant(MutationKind::Loop, span, original, "");
+        }
+        // The result is discarded, so the call is only made for its side effects, which
+        // the tests should notice are missing. Macros like `log::warn!` aren't calls,
+        // so they're not deleted.
+        if let syn::Stmt::Semi(expr @ (syn::Expr::Call(_) | syn::Expr::MethodCall(_)), semi) = i {
+            let span = Span {
+                start: Span::exact(expr.span()).start,
+                end: Span::exact(semi.span).end,
+            };
+            let original = self.original_text(&span);
+            self.collect_replace_mutant(MutationKind::DeleteCall, span, original, "");
+        }
+        syn::visit::visit_stmt(self, i);
+    }
+
+    /// Visit `const X: T = ...;`, and change its value.
+    fn visit_item_const(&mut self, i: &'ast syn::ItemConst) {
+        if !self.const_item_skipped(&i.attrs, i.span()) {
+            let first_mutant = self.mutants.len();
+            self.collect_const_value_mutants(&i.ident, &i.expr);
+            syn::visit::visit_item_const(self, i);
+            self.tag_mutants_since(first_mutant, &i.attrs);
+        }
+    }
+
+    /// Visit `static X: T = ...;`, and change its value.
+    fn visit_item_static(&mut self, i: &'ast syn::ItemStatic) {
+        if !self.const_item_skipped(&i.attrs, i.span()) {
+            let first_mutant = self.mutants.len();
+            self.collect_const_value_mutants(&i.ident, &i.expr);
+            syn::visit::visit_item_static(self, i);
+            self.tag_mutants_since(first_mutant, &i.attrs);
+        }
+    }
+
+    /// Visit an associated `const X: T = ...;` within an `impl`, and change its value.
+    fn visit_impl_item_const(&mut self, i: &'ast syn::ImplItemConst) {
+        if !self.const_item_skipped(&i.attrs, i.span()) {
+            let first_mutant = self.mutants.len();
+            self.collect_const_value_mutants(&i.ident, &i.expr);
+            syn::visit::visit_impl_item_const(self, i);
+            self.tag_mutants_since(first_mutant, &i.attrs);
+        }
     }
 
     /// Visit `mod foo { ... }`.
     fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
-        if !attrs_excluded(&node.attrs) {
-            self.in_namespace(&node.ident.to_string(), |v| {
-                syn::visit::visit_item_mod(v, node)
-            });
+        let marked_until = match &node.content {
+            Some((brace, _)) => Span::exact(brace.span).start,
+            None => Span::from(node.span()).end,
+        };
+        let name = node.ident.to_string();
+        if let Some(reason) = self.skip_reason(&node.attrs, node.span(), marked_until) {
+            self.record_skip(self.qualified_name(&name), node.span(), reason);
+        } else {
+            let first_mutant = self.mutants.len();
+            self.in_namespace(&name, |v| syn::visit::visit_item_mod(v, node));
+            self.tag_mutants_since(first_mutant, &node.attrs);
         }
     }
+
+    /// Visit a macro definition or invocation at item level, like `macro_rules! m {}`.
+    ///
+    /// Their contents aren't parsed, so nothing in them is mutated.
+    fn visit_item_macro(&mut self, i: &'ast syn::ItemMacro) {
+        let name = match &i.ident {
+            Some(ident) => ident.to_string(),
+            None => format!(
+                "{}!",
+                remove_excess_spaces(&i.mac.path.to_token_stream().to_string())
+            ),
+        };
+        let reason = attrs_skip_reason(&i.attrs).unwrap_or(SkipReason::Macro);
+        self.record_skip(self.qualified_name(&name), i.span(), reason);
+    }
 }
 
 fn ops_for_return_type(return_type: &syn::ReturnType) -> Vec<MutationOp> {
@@ -144,7 +1300,6 @@ fn ops_for_return_type(return_type: &syn::ReturnType) -> Vec<MutationOp> {
                     ops.push(MutationOp::True);
                     ops.push(MutationOp::False);
                 } else if path.is_ident("String") {
-                    // TODO: Detect &str etc.
                     ops.push(MutationOp::EmptyString);
                     ops.push(MutationOp::Xyzzy);
                 } else if path_is_result(path) {
@@ -155,12 +1310,110 @@ fn ops_for_return_type(return_type: &syn::ReturnType) -> Vec<MutationOp> {
                     ops.push(MutationOp::Default)
                 }
             }
+            syn::Type::Reference(syn::TypeReference { elem, .. }) => match &**elem {
+                syn::Type::Path(syn::TypePath { path, .. }) if path.is_ident("str") => {
+                    ops.push(MutationOp::EmptyStr);
+                    ops.push(MutationOp::XyzzyStr);
+                }
+                // Slices have a default reference.
+                syn::Type::Slice(_) => ops.push(MutationOp::Default),
+                // A leaked box needs a sized type with a default, so there's nothing
+                // that could build for trait objects and other unsized types.
+                syn::Type::TraitObject(_) => (),
+                syn::Type::Path(syn::TypePath { path, .. })
+                    if path.segments.last().is_some_and(|segment| {
+                        UNSIZED_TYPES.iter().any(|name| segment.ident == name)
+                    }) => {}
+                _ => ops.push(MutationOp::LeakedDefault),
+            },
+            syn::Type::ImplTrait(syn::TypeImplTrait { bounds, .. }) => {
+                // `Default::default()` can't build for an `impl Trait` type, because its
+                // concrete type can't be inferred.
+                let trait_name = |names: &[&str]| {
+                    bounds.iter().any(|bound| match bound {
+                        syn::TypeParamBound::Trait(trait_bound) => {
+                            trait_bound.path.segments.last().is_some_and(|segment| {
+                                names.iter().any(|name| segment.ident == name)
+                            })
+                        }
+                        _ => false,
+                    })
+                };
+                if trait_name(&["Iterator", "DoubleEndedIterator", "ExactSizeIterator"]) {
+                    ops.push(MutationOp::EmptyIter);
+                } else if trait_name(&["Future"]) {
+                    ops.push(MutationOp::AsyncDefault);
+                }
+            }
             _ => ops.push(MutationOp::Default),
         },
     }
     ops
 }
 
+/// Unsized types that are commonly returned by reference.
+const UNSIZED_TYPES: &[&str] = &["Path", "Utf8Path", "OsStr", "CStr"];
+
+/// Expressions that always evaluate to the default value of their type, whatever
+/// type that is.
+const DEFAULT_EXPRS: &[&str] = &[
+    "Default::default()",
+    "None",
+    "Vec::new()",
+    "vec![]",
+    "String::new()",
+    "HashMap::new()",
+    "HashSet::new()",
+    "BTreeMap::new()",
+    "BTreeSet::new()",
+    "VecDeque::new()",
+    "0",
+    "0.0",
+    "false",
+    "\"\"",
+    "()",
+];
+
+/// Expressions that evaluate to an empty `String`.
+const EMPTY_STRING_EXPRS: &[&str] = &[
+    "\"\".into()",
+    "\"\".to_owned()",
+    "\"\".to_string()",
+    "String::new()",
+    "Default::default()",
+];
+
+/// True if the function body is just an expression that always has the same value as
+/// the code inserted by this mutation, so that the mutant can't possibly be caught.
+fn op_is_equivalent_to_body(op: &MutationOp, block: &syn::Block) -> bool {
+    let expr = match block.stmts.as_slice() {
+        [syn::Stmt::Expr(expr)] => expr,
+        _ => return false,
+    };
+    match op {
+        MutationOp::Default => expr_is_any_of(expr, DEFAULT_EXPRS),
+        MutationOp::EmptyString => expr_is_any_of(expr, EMPTY_STRING_EXPRS),
+        MutationOp::OkDefault => match expr {
+            syn::Expr::Call(call)
+                if call.args.len() == 1 && expr_is_any_of(&call.func, &["Ok"]) =>
+            {
+                expr_is_any_of(&call.args[0], DEFAULT_EXPRS)
+            }
+            _ => false,
+        },
+        op => expr_is_any_of(expr, &[op.replacement()]),
+    }
+}
+
+/// True if the expression has the same tokens as any of the given snippets of code.
+fn expr_is_any_of(expr: &syn::Expr, codes: &[&str]) -> bool {
+    let expr_tokens = expr.to_token_stream().to_string();
+    codes.iter().any(|code| {
+        code.parse::<proc_macro2::TokenStream>()
+            .is_ok_and(|tokens| tokens.to_string() == expr_tokens)
+    })
+}
+
 fn type_name_string(ty: &syn::Type) -> String {
     ty.to_token_stream().to_string()
 }
@@ -194,11 +1447,8 @@ fn remove_excess_spaces(s: &str) -> String {
             ' ' if r.ends_with("->") => {}
             ' ' => {
                 // drop spaces following any of these chars
-                if let Some(a) = r.chars().next_back() {
-                    match a {
-                        ':' | '&' | '<' | '>' => continue, // drop the space
-                        _ => {}
-                    }
+                if let Some(':' | '&' | '<' | '>') = r.chars().next_back() {
+                    continue; // drop the space
                 }
             }
             ':' | ',' | '<' | '>' if r.ends_with(' ') => {
@@ -219,11 +1469,311 @@ fn path_is_result(path: &syn::Path) -> bool {
         .unwrap_or_default()
 }
 
-/// True if any of the attrs indicate that we should skip this node and everything inside it.
-fn attrs_excluded(attrs: &[Attribute]) -> bool {
-    attrs
+/// True if the expression must be wrapped in parentheses before it can be an operand
+/// of `!` or of a binary operator.
+fn needs_parens(expr: &syn::Expr) -> bool {
+    !matches!(
+        expr,
+        syn::Expr::Call(_)
+            | syn::Expr::Field(_)
+            | syn::Expr::Lit(_)
+            | syn::Expr::MethodCall(_)
+            | syn::Expr::Paren(_)
+            | syn::Expr::Path(_)
+    )
+}
+
+/// Return the operator that gives the opposite result of a comparison, if `op` is an
+/// ordering comparison.
+fn flipped_comparison_op(op: &syn::BinOp) -> Option<&'static str> {
+    use syn::BinOp::*;
+    match op {
+        Lt(_) => Some(">="),
+        Le(_) => Some(">"),
+        Gt(_) => Some("<="),
+        Ge(_) => Some("<"),
+        _ => None,
+    }
+}
+
+/// True if the expression is a call of `abs()`.
+fn is_abs_call(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::MethodCall(call) if call.method == "abs" && call.args.is_empty())
+}
+
+/// True if the expression is a float literal like `0.5` or `1e-9`.
+fn is_float_lit(expr: &syn::Expr) -> bool {
+    matches!(
+        expr,
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Float(_),
+            ..
+        })
+    )
+}
+
+/// True if the expression is evidently a float: a float literal, an associated constant
+/// of `f32` or `f64` like `f64::EPSILON`, or arithmetic on them.
+fn is_float_expr(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Path(syn::ExprPath { path, .. }) => {
+            path.segments.len() == 2
+                && (path.segments[0].ident == "f32" || path.segments[0].ident == "f64")
+        }
+        syn::Expr::Binary(binary) => is_float_expr(&binary.left) || is_float_expr(&binary.right),
+        syn::Expr::Paren(paren) => is_float_expr(&paren.expr),
+        syn::Expr::Unary(unary) => is_float_expr(&unary.expr),
+        expr => is_float_lit(expr),
+    }
+}
+
+/// Return the operator to use in place of a bitwise or shift operator, or None for
+/// other operators.
+fn swapped_bitwise_op(op: &syn::BinOp) -> Option<&'static str> {
+    use syn::BinOp::*;
+    match op {
+        BitAnd(_) => Some("|"),
+        BitOr(_) => Some("&"),
+        BitXor(_) => Some("&"),
+        Shl(_) => Some(">>"),
+        Shr(_) => Some("<<"),
+        BitAndEq(_) => Some("|="),
+        BitOrEq(_) => Some("&="),
+        BitXorEq(_) => Some("&="),
+        ShlEq(_) => Some(">>="),
+        ShrEq(_) => Some("<<="),
+        _ => None,
+    }
+}
+
+/// Return the span of a value, such as the initializer of a `const` or a field in a
+/// struct expression, and nearby values to replace it with, if it's a literal number
+/// or bool, or a `Duration::from_*` of a literal number.
+fn perturbed_values(expr: &syn::Expr) -> Option<(proc_macro2::Span, Vec<String>)> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit, .. }) => {
+            let values = match lit {
+                syn::Lit::Bool(b) => vec![(!b.value).to_string()],
+                lit => perturbed_numbers(lit, false)?,
+            };
+            Some((expr.span(), values))
+        }
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr: inner,
+            ..
+        }) => match &**inner {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => {
+                Some((expr.span(), perturbed_numbers(lit, true)?))
+            }
+            _ => None,
+        },
+        syn::Expr::Call(call) if call.args.len() == 1 => match &*call.func {
+            syn::Expr::Path(syn::ExprPath { path, .. })
+                if path.segments.len() >= 2
+                    && path.segments[path.segments.len() - 2].ident == "Duration"
+                    && path
+                        .segments
+                        .last()
+                        .unwrap()
+                        .ident
+                        .to_string()
+                        .starts_with("from_") =>
+            {
+                match &call.args[0] {
+                    arg @ syn::Expr::Lit(syn::ExprLit { lit, .. }) => {
+                        Some((arg.span(), perturbed_numbers(lit, false)?))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Return the code for values near a numeric literal, which is negated if `negative`:
+/// zero, and one more or less for integers, or twice the value for floats.
+///
+/// Values that would be negative are only generated if the literal is negated, since
+/// the type might be unsigned.
+fn perturbed_numbers(lit: &syn::Lit, negative: bool) -> Option<Vec<String>> {
+    let sign = if negative { -1 } else { 1 };
+    match lit {
+        syn::Lit::Int(int) => {
+            let value = sign * int.base10_parse::<i128>().ok()?;
+            let suffix = int.suffix();
+            let mut values: Vec<i128> = Vec::new();
+            for v in [0, value + 1, value - 1] {
+                if v != value && (v >= 0 || negative) && !values.contains(&v) {
+                    values.push(v);
+                }
+            }
+            Some(
+                values
+                    .into_iter()
+                    .map(|v| format!("{}{}", v, suffix))
+                    .collect(),
+            )
+        }
+        syn::Lit::Float(float) => {
+            let value = sign as f64 * float.base10_parse::<f64>().ok()?;
+            let suffix = float.suffix();
+            let mut values = Vec::new();
+            if value != 0.0 {
+                values.push(format!("0.0{}", suffix));
+                values.push(format!("{:?}{}", value * 2.0, suffix));
+            } else {
+                values.push(format!("1.0{}", suffix));
+            }
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// Return the name of a type like `Foo` or `module::Foo<T>`, without its path or
+/// generic parameters, or None if it's not a path.
+fn type_path_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(syn::TypePath { path, .. }) => path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// For each type with an `impl Default` in the file, find the associated functions of
+/// that type that are called by `default`, like `Self::with_capacity(8)` or
+/// `Foo::create()`.
+///
+/// Replacing the body of one of these functions with `Default::default()` would make it
+/// call itself forever. Only direct calls from `impl`s in the same file are found.
+fn default_impl_calls(file: &syn::File) -> HashMap<String, HashSet<String>> {
+    struct CallVisitor {
+        impl_type: Option<String>,
+        calls: HashMap<String, HashSet<String>>,
+    }
+
+    impl<'ast> Visit<'ast> for CallVisitor {
+        fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+            let is_default = i.trait_.as_ref().is_some_and(|(_, trait_path, _)| {
+                trait_path.segments.last().unwrap().ident == "Default"
+            });
+            let impl_type = if is_default {
+                type_path_name(&i.self_ty)
+            } else {
+                None
+            };
+            let outer = std::mem::replace(&mut self.impl_type, impl_type);
+            syn::visit::visit_item_impl(self, i);
+            self.impl_type = outer;
+        }
+
+        fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+            if let (Some(impl_type), syn::Expr::Path(syn::ExprPath { path, .. })) =
+                (&self.impl_type, &*i.func)
+            {
+                let segments = &path.segments;
+                if segments.len() >= 2 {
+                    let type_segment = &segments[segments.len() - 2].ident;
+                    if type_segment == "Self" || type_segment == impl_type {
+                        self.calls
+                            .entry(impl_type.clone())
+                            .or_default()
+                            .insert(segments.last().unwrap().ident.to_string());
+                    }
+                }
+            }
+            syn::visit::visit_expr_call(self, i);
+        }
+    }
+
+    let mut visitor = CallVisitor {
+        impl_type: None,
+        calls: HashMap::new(),
+    };
+    visitor.visit_file(file);
+    visitor.calls
+}
+
+/// Return the macro invoked by a statement like `println!(...);`, if it is one.
+fn stmt_macro(stmt: &syn::Stmt) -> Option<&syn::Macro> {
+    match stmt {
+        syn::Stmt::Item(syn::Item::Macro(item_macro)) if item_macro.ident.is_none() => {
+            Some(&item_macro.mac)
+        }
+        syn::Stmt::Semi(syn::Expr::Macro(expr_macro), _) => Some(&expr_macro.mac),
+        _ => None,
+    }
+}
+
+/// True if the macro is one of the standard assertions, like `assert!` or `debug_assert_eq!`.
+fn is_assert_macro(mac: &syn::Macro) -> bool {
+    mac.path.segments.last().is_some_and(|segment| {
+        [
+            "assert",
+            "assert_eq",
+            "assert_ne",
+            "debug_assert",
+            "debug_assert_eq",
+            "debug_assert_ne",
+        ]
         .iter()
-        .any(|attr| attr_is_cfg_test(attr) || attr_is_test(attr) || attr_is_mutants_skip(attr))
+        .any(|name| segment.ident == name)
+    })
+}
+
+/// True if a loop body contains `break` with a value, not counting nested closures or items.
+fn contains_break_with_value(block: &syn::Block) -> bool {
+    struct BreakVisitor {
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for BreakVisitor {
+        fn visit_expr_break(&mut self, i: &'ast syn::ExprBreak) {
+            if i.expr.is_some() {
+                self.found = true;
+            }
+            syn::visit::visit_expr_break(self, i);
+        }
+
+        fn visit_expr_closure(&mut self, _i: &'ast syn::ExprClosure) {}
+
+        fn visit_item(&mut self, _i: &'ast syn::Item) {}
+    }
+
+    let mut visitor = BreakVisitor { found: false };
+    visitor.visit_block(block);
+    visitor.found
+}
+
+/// If any of the attrs indicate that we should skip this node and everything inside it, say why.
+fn attrs_skip_reason(attrs: &[Attribute]) -> Option<SkipReason> {
+    if attrs
+        .iter()
+        .any(|attr| attr_is_cfg_test(attr) || attr_is_test(attr))
+    {
+        Some(SkipReason::Test)
+    } else if attrs.iter().any(attr_is_mutants_skip) {
+        Some(SkipReason::Attribute)
+    } else {
+        None
+    }
+}
+
+/// True if the text contains a `// cargo-mutants: skip` or `/* cargo-mutants: skip */`
+/// comment.
+///
+/// This doesn't fully lex the text, but it requires the marker to directly follow
+/// the start of a comment, so that it isn't matched in for example a string.
+fn contains_skip_comment(text: &str) -> bool {
+    text.match_indices(SKIP_COMMENT).any(|(index, _)| {
+        let before = text[..index].trim_end();
+        before.ends_with("//") || before.ends_with("/*")
+    })
 }
 
 /// True if the block (e.g. the contents of a function) is empty.
@@ -250,6 +1800,84 @@ fn attr_is_cfg_test(attr: &Attribute) -> bool {
     false
 }
 
+/// True if the attribute is `#[cfg(not(test))]`.
+fn attr_is_cfg_not_test(attr: &Attribute) -> bool {
+    if !attr.path.is_ident("cfg") {
+        return false;
+    }
+    if let Ok(syn::Meta::List(meta_list)) = attr.parse_meta() {
+        if let Some(syn::NestedMeta::Meta(syn::Meta::List(not_list))) = meta_list.nested.first() {
+            return meta_list.nested.len() == 1
+                && not_list.path.is_ident("not")
+                && not_list.nested.len() == 1
+                && matches!(
+                    not_list.nested.first(),
+                    Some(syn::NestedMeta::Meta(syn::Meta::Path(path))) if path.is_ident("test")
+                );
+        }
+    }
+    false
+}
+
+/// True if the attribute is `#[allow(dead_code)]`, possibly among other lints.
+///
+/// Code that's allowed to be dead probably isn't reached by the tests, so mutants
+/// in it would be reported as missed without telling us anything.
+fn attr_is_allow_dead_code(attr: &Attribute) -> bool {
+    if !attr.path.is_ident("allow") {
+        return false;
+    }
+    if let Ok(syn::Meta::List(meta_list)) = attr.parse_meta() {
+        return meta_list.nested.iter().any(|nested| {
+            matches!(nested, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("dead_code"))
+        });
+    }
+    false
+}
+
+/// Return the tags for mutants inside an item with these attributes.
+fn attrs_tags(attrs: &[Attribute]) -> Vec<ItemTag> {
+    let mut tags = Vec::new();
+    if attrs.iter().any(|attr| attr.path.is_ident("deprecated")) {
+        tags.push(ItemTag::Deprecated);
+    }
+    if attrs.iter().any(attr_is_doc_hidden) {
+        tags.push(ItemTag::Hidden);
+    }
+    tags
+}
+
+/// True if the attribute is `#[doc(hidden)]`.
+fn attr_is_doc_hidden(attr: &Attribute) -> bool {
+    if !attr.path.is_ident("doc") {
+        return false;
+    }
+    if let Ok(syn::Meta::List(meta_list)) = attr.parse_meta() {
+        return meta_list.nested.iter().any(|nested| {
+            matches!(nested, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("hidden"))
+        });
+    }
+    false
+}
+
+/// True if the attribute is `#[automatically_derived]`, as on impls generated by derive macros.
+fn attr_is_automatically_derived(attr: &Attribute) -> bool {
+    attr.path.is_ident("automatically_derived")
+}
+
+/// True if the comments at the start of the file mark it as `@generated`.
+fn is_generated(code: &str) -> bool {
+    code.lines()
+        .map(str::trim)
+        .take_while(|line| {
+            line.is_empty()
+                || line.starts_with("//")
+                || line.starts_with("/*")
+                || line.starts_with('*')
+        })
+        .any(|line| line.contains(GENERATED_MARKER))
+}
+
 /// True if the attribute is `#[test]`.
 fn attr_is_test(attr: &Attribute) -> bool {
     attr.path.is_ident("test")
@@ -287,12 +1915,518 @@ fn attr_is_mutants_skip(attr: &Attribute) -> bool {
 
 #[cfg(test)]
 mod test {
+    /// Write `code` to `path` in a temporary source tree, and read it back as a source file.
+    fn write_source_file(path: &str, code: &str) -> crate::SourceFile {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = camino::Utf8Path::from_path(tmp.path()).unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join(path), code).unwrap();
+        crate::SourceFile::new(root, path.parse().unwrap()).unwrap()
+    }
+
+    /// Return the descriptions of the mutants found in `code` as `src/lib.rs`.
+    fn discover_changes(code: &str, options: &crate::Options) -> Vec<String> {
+        super::discover_mutants(write_source_file("src/lib.rs", code).into(), options)
+            .unwrap()
+            .iter()
+            .map(|mutant| mutant.describe_change())
+            .collect()
+    }
+
     #[test]
     fn path_is_result() {
         let path: syn::Path = syn::parse_quote! { Result<(), ()> };
         assert!(super::path_is_result(&path));
     }
 
+    #[test]
+    fn early_return_mutants_are_opt_in() {
+        use std::sync::Arc;
+
+        use camino::Utf8Path;
+
+        use crate::mutate::MutationKind;
+        use crate::*;
+
+        let source_file: Arc<SourceFile> = SourceFile::new(
+            Utf8Path::new("testdata/tree/well_tested"),
+            "src/result.rs".parse().unwrap(),
+        )
+        .unwrap()
+        .into();
+        let insertions = |options: &Options| -> Vec<String> {
+            super::discover_mutants(source_file.clone(), options)
+                .unwrap()
+                .iter()
+                .filter(|mutant| mutant.function_name() == "error_if_negative")
+                .map(|mutant| mutant.describe_change())
+                .filter(|change| change.starts_with("insert "))
+                .collect()
+        };
+        assert_eq!(insertions(&Options::default()), Vec::<String>::new());
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::EarlyReturn]);
+        assert_eq!(
+            insertions(&options),
+            [
+                "insert return Ok(Default::default()); at start of if block in error_if_negative",
+                "insert return Ok(Default::default()); at start of else block in error_if_negative",
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_field_mutants_are_opt_in() {
+        use std::sync::Arc;
+
+        use camino::Utf8Path;
+
+        use crate::mutate::MutationKind;
+        use crate::*;
+
+        let source_file: Arc<SourceFile> = SourceFile::new(
+            Utf8Path::new("testdata/tree/well_tested"),
+            "src/conversions.rs".parse().unwrap(),
+        )
+        .unwrap()
+        .into();
+        let field_changes = |options: &Options| -> Vec<String> {
+            super::discover_mutants(source_file.clone(), options)
+                .unwrap()
+                .iter()
+                .filter(|mutant| mutant.op.kind() == MutationKind::StructField)
+                .map(|mutant| mutant.describe_change())
+                .collect()
+        };
+        assert_eq!(field_changes(&Options::default()), Vec::<String>::new());
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::StructField]);
+        assert_eq!(
+            field_changes(&options),
+            [
+                "replace 3 with 0 in <impl Default for Settings>::default",
+                "replace 3 with 4 in <impl Default for Settings>::default",
+                "replace 3 with 2 in <impl Default for Settings>::default",
+                "replace false with true in <impl Default for Settings>::default",
+                "replace true with false in <impl From for Settings>::from",
+            ]
+        );
+    }
+
+    #[test]
+    fn ops_for_return_type() {
+        use crate::mutate::MutationOp::*;
+
+        let ops = |return_type: syn::ReturnType| super::ops_for_return_type(&return_type);
+        assert_eq!(
+            ops(syn::parse_quote! { -> &'static str }),
+            [EmptyStr, XyzzyStr]
+        );
+        assert_eq!(ops(syn::parse_quote! { -> &mut Config }), [LeakedDefault]);
+        assert_eq!(ops(syn::parse_quote! { -> &[u8] }), [Default]);
+        assert_eq!(ops(syn::parse_quote! { -> &std::path::Path }), []);
+        assert_eq!(ops(syn::parse_quote! { -> &dyn Display }), []);
+        assert_eq!(
+            ops(syn::parse_quote! { -> impl Iterator<Item = u32> + '_ }),
+            [EmptyIter]
+        );
+        assert_eq!(
+            ops(syn::parse_quote! { -> impl Future<Output = u32> }),
+            [AsyncDefault]
+        );
+        assert_eq!(ops(syn::parse_quote! { -> impl Fn(u32) -> u32 }), []);
+    }
+
+    #[test]
+    fn swapped_bitwise_op() {
+        use super::swapped_bitwise_op as swap;
+
+        assert_eq!(swap(&syn::parse_quote! { & }), Some("|"));
+        assert_eq!(swap(&syn::parse_quote! { ^= }), Some("&="));
+        assert_eq!(swap(&syn::parse_quote! { >> }), Some("<<"));
+        assert_eq!(swap(&syn::parse_quote! { && }), None);
+        assert_eq!(swap(&syn::parse_quote! { + }), None);
+    }
+
+    #[test]
+    fn needs_parens() {
+        use super::needs_parens;
+
+        assert!(needs_parens(&syn::parse_quote! { a < b }));
+        assert!(needs_parens(&syn::parse_quote! { a && b.is_empty() }));
+        assert!(needs_parens(&syn::parse_quote! { *flag }));
+        assert!(!needs_parens(&syn::parse_quote! { ready }));
+        assert!(!needs_parens(&syn::parse_quote! { self.done }));
+        assert!(!needs_parens(&syn::parse_quote! { v.is_empty() }));
+        assert!(!needs_parens(&syn::parse_quote! { (a < b) }));
+    }
+
+    #[test]
+    fn perturbed_const_values() {
+        let values = |expr: syn::Expr| super::perturbed_values(&expr).map(|(_, values)| values);
+
+        assert_eq!(
+            values(syn::parse_quote! { 4096 }).unwrap(),
+            ["0", "4097", "4095"]
+        );
+        assert_eq!(values(syn::parse_quote! { 0u8 }).unwrap(), ["1u8"]);
+        assert_eq!(
+            values(syn::parse_quote! { -3i32 }).unwrap(),
+            ["0i32", "-2i32", "-4i32"]
+        );
+        assert_eq!(
+            values(syn::parse_quote! { 0x10 }).unwrap(),
+            ["0", "17", "15"]
+        );
+        assert_eq!(values(syn::parse_quote! { 0.25 }).unwrap(), ["0.0", "0.5"]);
+        assert_eq!(values(syn::parse_quote! { true }).unwrap(), ["false"]);
+        assert_eq!(
+            values(syn::parse_quote! { Duration::from_secs(30) }).unwrap(),
+            ["0", "31", "29"]
+        );
+        assert_eq!(
+            values(syn::parse_quote! { std::time::Duration::from_millis(1) }).unwrap(),
+            ["0", "2"]
+        );
+        assert_eq!(values(syn::parse_quote! { "name" }), None);
+        assert_eq!(values(syn::parse_quote! { A + 1 }), None);
+        assert_eq!(values(syn::parse_quote! { Foo::from_secs(30) }), None);
+    }
+
+    #[test]
+    fn default_impl_calls() {
+        let file: syn::File = syn::parse_quote! {
+            impl Pool {
+                fn with_size(size: usize) -> Self { Pool { size } }
+            }
+            impl Default for Pool {
+                fn default() -> Self {
+                    Self::with_size(Pool::default_size())
+                }
+            }
+            impl Default for Other {
+                fn default() -> Other {
+                    Other { pool: Pool::with_size(1), name: String::new() }
+                }
+            }
+        };
+        let calls = super::default_impl_calls(&file);
+        assert_eq!(calls.len(), 1);
+        let mut pool_calls: Vec<&String> = calls["Pool"].iter().collect();
+        pool_calls.sort();
+        assert_eq!(pool_calls, ["default_size", "with_size"]);
+    }
+
+    #[test]
+    fn tag_mutants_in_deprecated_and_hidden_items() {
+        use std::sync::Arc;
+
+        use crate::mutate::ItemTag::{self, *};
+        use crate::*;
+
+        let code = r#"
+            pub fn current() -> bool { true }
+
+            #[deprecated(since = "0.2.0", note = "use current")]
+            pub fn old() -> bool { true }
+
+            #[doc(hidden)]
+            pub mod internal {
+                #[deprecated]
+                pub fn both() -> bool { true }
+            }
+        "#;
+        let source_file: Arc<SourceFile> = write_source_file("src/lib.rs", code).into();
+        let tags = |options: &Options| -> Vec<(String, Vec<ItemTag>)> {
+            super::discover_mutants(source_file.clone(), options)
+                .unwrap()
+                .iter()
+                .filter(|mutant| mutant.replacement_text() == "false")
+                .map(|mutant| (mutant.function_name().to_owned(), mutant.tags().to_vec()))
+                .collect()
+        };
+        assert_eq!(
+            tags(&Options::default()),
+            [
+                ("current".to_owned(), vec![]),
+                ("old".to_owned(), vec![Deprecated]),
+                ("internal::both".to_owned(), vec![Deprecated, Hidden]),
+            ]
+        );
+
+        let mut options = Options::default();
+        options.exclude_tags = vec![Hidden];
+        assert_eq!(
+            discover_changes(code, &options),
+            ["replace current with false", "replace old with false"]
+        );
+        let (_, counts) =
+            super::discover_and_count_filtered(source_file.clone(), &options).unwrap();
+        assert_eq!(counts.by_tag, 1);
+        let skipped = super::discover_skipped(source_file, &options).unwrap();
+        assert_eq!(
+            skipped[0].to_string(),
+            "src/lib.rs:10: internal::both: excluded by tag"
+        );
+    }
+
+    #[test]
+    fn allow_dead_code_skips_only_functions() {
+        let changes = discover_changes(
+            r#"
+                #[allow(dead_code)]
+                fn unused() -> u32 {
+                    7
+                }
+
+                #[allow(dead_code)]
+                mod m {
+                    pub fn used() -> u32 {
+                        7
+                    }
+                }
+
+                pub struct S;
+
+                #[allow(dead_code)]
+                impl S {
+                    pub fn used(&self) -> bool {
+                        true
+                    }
+                }
+            "#,
+            &crate::Options::default(),
+        );
+        assert_eq!(
+            changes,
+            [
+                "replace m::used with Default::default()",
+                "replace S::used with false",
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_adapters() {
+        use crate::mutate::MutationKind;
+        use crate::Options;
+
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::Iterator]);
+        let changes = discover_changes(
+            r#"
+                pub fn last_evens(v: &[u32], n: usize) -> Vec<u32> {
+                    v.iter().rev().filter(|x| *x % 2 == 0).skip(1).take(n).skip(0).cloned().collect()
+                }
+            "#,
+            &options,
+        );
+        assert_eq!(
+            changes,
+            [
+                "replace take(n) with take(0) in last_evens",
+                "replace skip(1) with skip(0) in last_evens",
+                "replace filter(|x| *x % 2 == 0) with filter(|_| true) in last_evens",
+                "replace filter(|x| *x % 2 == 0) with filter(|_| false) in last_evens",
+                "delete .rev() in last_evens",
+            ]
+        );
+    }
+
+    #[test]
+    fn float_comparisons() {
+        use crate::mutate::MutationKind;
+        use crate::Options;
+
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::Float]);
+        let changes = discover_changes(
+            r#"
+                pub fn close(a: f64, b: f64) -> bool {
+                    (a - b).abs() < f64::EPSILON + 1e-9
+                }
+
+                pub fn positive(x: f64, n: u32) -> bool {
+                    x > 0.0 && n < 10
+                }
+
+                pub fn near(a: i32, b: i32) -> bool {
+                    (a - b).abs() < 2
+                }
+            "#,
+            &options,
+        );
+        // `near` compares integers, so nothing in it is mutated.
+        assert_eq!(
+            changes,
+            [
+                "replace (a - b).abs() < f64::EPSILON + 1e-9 with (a - b).abs() >= f64::EPSILON + 1e-9 in close",
+                "replace (a - b).abs() < f64::EPSILON + 1e-9 with (a - b).abs() < 0.0 in close",
+                "replace (a - b).abs() < f64::EPSILON + 1e-9 with (a - b).abs() < (f64::EPSILON + 1e-9) * 1000.0 in close",
+                "replace x > 0.0 with x <= 0.0 in positive",
+            ]
+        );
+    }
+
+    #[test]
+    fn cfg_not_test_is_skipped_only_when_untested() {
+        use crate::*;
+
+        let code = r#"
+            #[cfg(not(test))]
+            fn main() {
+                println!("{}", double(2));
+            }
+
+            #[cfg(not(feature = "slow"))]
+            fn double(a: u32) -> u32 {
+                a * 2
+            }
+        "#;
+        // In a library, code that's only built outside of tests is still mutated.
+        assert!(discover_changes(code, &Options::default())
+            .contains(&"replace main with ()".to_owned()));
+        let mut source_file = write_source_file("src/main.rs", code);
+        let skipped = |source_file: &SourceFile| {
+            super::discover_skipped(source_file.clone().into(), &Options::default())
+                .unwrap()
+                .into_iter()
+                .map(|skipped| (skipped.function, skipped.reason))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(skipped(&source_file), []);
+        source_file.only_unit_tested = true;
+        assert_eq!(
+            skipped(&source_file),
+            [("main".to_owned(), super::SkipReason::NotTested)]
+        );
+    }
+
+    #[test]
+    fn limit_mutants_per_function() {
+        use crate::mutate::MutationKind;
+        use crate::Options;
+
+        let code = r#"
+            pub fn ranges() -> usize {
+                (0..1).count()
+                    + (0..2).count()
+                    + (0..3).count()
+                    + (0..4).count()
+            }
+
+            pub fn one() -> usize {
+                (0..5).count()
+            }
+        "#;
+        let mut options = Options::default();
+        options.mutation_kinds = Some(vec![MutationKind::Range]);
+        options.max_mutants_per_fn = Some(3);
+        let changes = discover_changes(code, &options);
+        // Each range has two mutants, and of the eight in `ranges` the first, third, and
+        // sixth are kept.
+        assert_eq!(
+            changes,
+            [
+                "replace 0..1 with 0..=1 in ranges",
+                "replace 0..2 with 0..=2 in ranges",
+                "replace 0..3 with 0 + 1..3 in ranges",
+                "replace 0..5 with 0..=5 in one",
+                "replace 0..5 with 0 + 1..5 in one",
+            ]
+        );
+        let (_, counts) = super::discover_and_count_filtered(
+            write_source_file("src/lib.rs", code).into(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(counts.by_fn_limit, 5);
+    }
+
+    #[test]
+    fn assert_statements() {
+        use super::{is_assert_macro, stmt_macro};
+
+        let is_assert = |stmt: syn::Stmt| stmt_macro(&stmt).is_some_and(is_assert_macro);
+        assert!(is_assert(syn::parse_quote! { assert!(a > 0); }));
+        assert!(is_assert(
+            syn::parse_quote! { std::debug_assert_eq!(a, b, "oops"); }
+        ));
+        assert!(!is_assert(syn::parse_quote! { println!("{}", a); }));
+        assert!(!is_assert(syn::parse_quote! { assert(a); }));
+    }
+
+    #[test]
+    fn contains_skip_comment() {
+        use super::contains_skip_comment as check;
+
+        assert!(check("    // cargo-mutants: skip"));
+        assert!(check("/* cargo-mutants: skip */ fn a() {}"));
+        assert!(check("let a = 1; //cargo-mutants: skip because it's slow"));
+        assert!(!check("// This is not cargo-mutants: skip"));
+        assert!(!check("let s = \"cargo-mutants: skip\";"));
+    }
+
+    #[test]
+    fn is_generated() {
+        use super::is_generated;
+
+        assert!(is_generated(
+            "// @generated by prost-build\npub fn a() {}\n"
+        ));
+        assert!(is_generated(
+            "//! Bindings.\n\n/* This file is @generated. */\nfn a() {}\n"
+        ));
+        assert!(!is_generated("fn a() {}\n// @generated\n"));
+        assert!(!is_generated("fn a() -> &'static str { \"@generated\" }\n"));
+    }
+
+    #[test]
+    fn contains_break_with_value() {
+        use super::contains_break_with_value as check;
+
+        assert!(check(&syn::parse_quote! {{ if a { break 3; } }}));
+        assert!(!check(&syn::parse_quote! {{ if a { break; } }}));
+        assert!(!check(
+            &syn::parse_quote! {{ let f = || loop { break 3; }; }}
+        ));
+    }
+
+    #[test]
+    fn op_is_equivalent_to_body() {
+        use crate::mutate::MutationOp;
+        let check =
+            |op: &MutationOp, block: syn::Block| super::op_is_equivalent_to_body(op, &block);
+        assert!(check(&MutationOp::Default, syn::parse_quote! {{ None }}));
+        assert!(check(&MutationOp::Default, syn::parse_quote! {{ vec![] }}));
+        assert!(!check(
+            &MutationOp::Default,
+            syn::parse_quote! {{ vec![1] }}
+        ));
+        assert!(!check(
+            &MutationOp::Default,
+            syn::parse_quote! {{ let a = 1; 0 }}
+        ));
+        assert!(check(&MutationOp::True, syn::parse_quote! {{ true }}));
+        assert!(!check(&MutationOp::False, syn::parse_quote! {{ true }}));
+        assert!(check(
+            &MutationOp::EmptyString,
+            syn::parse_quote! {{ "".to_owned() }}
+        ));
+        assert!(check(
+            &MutationOp::OkDefault,
+            syn::parse_quote! {{ Ok(()) }}
+        ));
+        assert!(check(
+            &MutationOp::OkDefault,
+            syn::parse_quote! {{ Ok(Default::default()) }}
+        ));
+        assert!(!check(
+            &MutationOp::OkDefault,
+            syn::parse_quote! {{ Ok(7) }}
+        ));
+    }
+
     #[test]
     fn remove_excess_spaces() {
         use super::remove_excess_spaces as rem;
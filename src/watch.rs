@@ -0,0 +1,179 @@
+// Copyright 2022 Martin Pool
+
+//! Support for `--watch`: test the mutants, then watch the source tree, and after each
+//! change test again, only the mutants in the source files that changed.
+//!
+//! There's no file notification library in our dependencies, so the tree is polled
+//! for changes to the modification times of its files.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+use camino::Utf8Path;
+
+use crate::options::build_path_glob_set;
+use crate::*;
+
+/// How often to look for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Directories that are never watched, because they're written by builds or by
+/// cargo-mutants itself.
+pub const IGNORED_DIRS: &[&str] = &[".git", "target", "mutants.out", "mutants.out.old"];
+
+/// The modification time of every file in the tree, by tree-relative path with
+/// forward slashes.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Snapshot(BTreeMap<String, SystemTime>);
+
+impl Snapshot {
+    fn take(root: &Utf8Path) -> Snapshot {
+        let mut files = BTreeMap::new();
+        for entry in walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| {
+                !(entry.file_type().is_dir()
+                    && IGNORED_DIRS.iter().any(|name| entry.file_name() == *name))
+            })
+            .filter_map(|r| r.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let rel_path = entry
+                .path()
+                .strip_prefix(root)
+                .ok()
+                .and_then(Utf8Path::from_path);
+            let mtime = entry.metadata().ok().and_then(|m| m.modified().ok());
+            if let (Some(rel_path), Some(mtime)) = (rel_path, mtime) {
+                files.insert(rel_path.to_slash_path(), mtime);
+            }
+        }
+        Snapshot(files)
+    }
+
+    /// Return the files that were added, removed, or modified between this snapshot
+    /// and a later one.
+    fn changed_files(&self, later: &Snapshot) -> BTreeSet<String> {
+        let added_or_modified = later
+            .0
+            .iter()
+            .filter(|(path, mtime)| self.0.get(*path) != Some(mtime))
+            .map(|(path, _)| path.clone());
+        let removed = self
+            .0
+            .keys()
+            .filter(|path| !later.0.contains_key(*path))
+            .cloned();
+        added_or_modified.chain(removed).collect()
+    }
+}
+
+/// Test mutants, then watch for changes and test again, until interrupted.
+///
+/// The same build directory is used for every run, with only the changed files copied
+/// into it, so that the tree isn't copied again and its baseline isn't rebuilt.
+pub fn watch(source_tree: &SourceTree, options: &Options) -> Result<()> {
+    let mut build_dir = None;
+    // Taken before each run, so that files saved while the tests are running are
+    // tested in the next one.
+    let mut snapshot = Snapshot::take(source_tree.path());
+    run_once(source_tree, options, &mut build_dir)?;
+    loop {
+        println!("Watching for changes (ctrl-c to stop)...");
+        let changed = loop {
+            check_interrupted()?;
+            sleep(POLL_INTERVAL);
+            let latest = Snapshot::take(source_tree.path());
+            let changed = snapshot.changed_files(&latest);
+            snapshot = latest;
+            if !changed.is_empty() {
+                break changed;
+            }
+        };
+        let source_paths: BTreeSet<String> = source_tree
+            .source_paths(options)?
+            .into_iter()
+            .map(|trp| trp.to_string())
+            .collect();
+        if let Some(dir) = &build_dir {
+            if let Err(err) = dir.refresh(source_tree, &changed) {
+                console::print_error(&format!("{:#}", err));
+                // Start again from a fresh copy of the tree.
+                build_dir = None;
+            }
+        }
+        let changed_sources: Vec<&String> = changed.intersection(&source_paths).collect();
+        if changed_sources.is_empty() {
+            // Only tests or other files changed, which could affect any mutant.
+            println!("Changed: {}", join_paths(changed.iter()));
+            run_once(source_tree, options, &mut build_dir)?;
+        } else {
+            println!("Changed: {}", join_paths(changed_sources.iter().copied()));
+            let mut scoped_options = options.clone();
+            scoped_options.globset = build_path_glob_set(&changed_sources)?;
+            scoped_options.file_patterns = changed_sources.into_iter().cloned().collect();
+            run_once(source_tree, &scoped_options, &mut build_dir)?;
+        }
+    }
+}
+
+fn join_paths<'a>(paths: impl Iterator<Item = &'a String>) -> String {
+    paths.map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// Test mutants once, in `build_dir` if it has one, reporting rather than returning errors
+/// other than interruption, so that watching continues.
+fn run_once(
+    source_tree: &SourceTree,
+    options: &Options,
+    build_dir: &mut Option<BuildDir>,
+) -> Result<()> {
+    if let Err(err) = lab::test_mutants_keeping_build_dir(source_tree, options, build_dir) {
+        check_interrupted()?;
+        console::print_error(&format!("{:#}", err));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn changed_files_between_snapshots() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/a.rs"), "").unwrap();
+        let before = Snapshot::take(root);
+        assert_eq!(
+            before.0.keys().collect::<Vec<_>>(),
+            ["src/a.rs", "src/lib.rs"]
+        );
+        assert_eq!(before.changed_files(&Snapshot::take(root)), BTreeSet::new());
+
+        fs::write(root.join("target/debug/out"), "").unwrap();
+        fs::remove_file(root.join("src/a.rs")).unwrap();
+        fs::write(root.join("src/b.rs"), "").unwrap();
+        let file = fs::File::options()
+            .write(true)
+            .open(root.join("src/lib.rs"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(
+            before
+                .changed_files(&Snapshot::take(root))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["src/a.rs", "src/b.rs", "src/lib.rs"]
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Parse the arguments of the tool.
+
+/// True if any of the arguments asks for verbose output.
+pub fn verbose(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == "-v" || arg == "--verbose")
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn verbose() {
+        assert!(super::verbose(vec!["-v".to_owned()].into_iter()));
+        assert!(!super::verbose(vec!["-q".to_owned()].into_iter()));
+    }
+}
@@ -0,0 +1,7 @@
+//! A second binary, whose modules are in its own directory.
+
+mod args;
+
+fn main() {
+    println!("verbose: {}", args::verbose(std::env::args().skip(1)));
+}
@@ -0,0 +1,23 @@
+//! A package with only binary targets and no integration tests, so `cargo test`
+//! never builds the code that's marked `#[cfg(not(test))]`.
+//!
+//! `main` is skipped, since none of its mutants could be caught, but `greeting` is
+//! mutated as usual.
+
+#[cfg(not(test))]
+fn main() {
+    let name = std::env::args().nth(1);
+    println!("{}", greeting(name.as_deref().unwrap_or("world")));
+}
+
+fn greeting(name: &str) -> String {
+    format!("Hello, {}!", name)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn greeting() {
+        assert_eq!(super::greeting("tests"), "Hello, tests!");
+    }
+}
@@ -0,0 +1,47 @@
+//! Functions whose bodies are already equivalent to some of the mutations.
+//!
+//! Only the mutants that change the function's behavior are generated.
+
+use std::collections::HashMap;
+
+pub fn returns_none() -> Option<u32> {
+    None
+}
+
+pub fn returns_zero() -> u32 {
+    0
+}
+
+pub fn returns_empty_vec() -> Vec<String> {
+    vec![]
+}
+
+pub fn returns_empty_map() -> HashMap<String, u32> {
+    HashMap::new()
+}
+
+pub fn returns_true() -> bool {
+    true
+}
+
+pub fn returns_empty_string() -> String {
+    String::new()
+}
+
+pub fn returns_ok_unit() -> Result<(), String> {
+    Ok(())
+}
+
+pub fn returns_ok_seven() -> Result<u32, String> {
+    Ok(7)
+}
+
+#[allow(dead_code)]
+fn unused() -> u32 {
+    7
+}
+
+#[allow(unused_variables, dead_code)]
+fn unused_with_other_lints(a: u32) -> u32 {
+    7
+}
@@ -0,0 +1,12 @@
+// This file is @generated by a code generator. Do not edit.
+
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn is_origin(&self) -> bool {
+        self.x == 0 && self.y == 0
+    }
+}
@@ -0,0 +1,26 @@
+mod bindings;
+pub mod vendor;
+
+pub use bindings::Point;
+
+pub fn double(x: u32) -> u32 {
+    x * 2
+}
+
+pub struct Meters(pub f64);
+
+// As written by `#[derive(PartialEq)]`.
+#[automatically_derived]
+impl PartialEq for Meters {
+    fn eq(&self, other: &Meters) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn double() {
+        assert_eq!(super::double(2), 4);
+    }
+}
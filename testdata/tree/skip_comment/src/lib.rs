@@ -0,0 +1,86 @@
+//! Functions, impls, and modules marked with `// cargo-mutants: skip` comments
+//! are not mutated.
+
+// cargo-mutants: skip
+pub fn marked_above() -> bool {
+    true
+}
+
+/// Comments can be mixed with docs and attributes.
+// cargo-mutants: skip because it's slow
+#[inline]
+pub fn marked_above_attrs() -> bool {
+    true
+}
+
+/* cargo-mutants: skip */
+pub fn marked_with_block_comment() -> bool {
+    true
+}
+
+pub fn marked_inside() -> u32 {
+    // cargo-mutants: skip, since every mutant hangs
+    let mut a = 0;
+    while a < 10 {
+        a += 1;
+    }
+    a
+}
+
+pub fn mentions_marker_in_string() -> &'static str {
+    "cargo-mutants: skip"
+}
+
+pub fn not_marked() -> bool {
+    false
+}
+
+pub struct Counter {
+    count: u32,
+}
+
+// cargo-mutants: skip
+impl Counter {
+    pub fn increment(&mut self) {
+        self.count += 1;
+    }
+}
+
+impl Counter {
+    pub fn get(&self) -> u32 {
+        self.count
+    }
+
+    // cargo-mutants: skip
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+}
+
+// cargo-mutants: skip
+pub mod skipped {
+    pub fn double(a: u32) -> u32 {
+        a * 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn all() {
+        assert!(marked_above());
+        assert!(marked_above_attrs());
+        assert!(marked_with_block_comment());
+        assert_eq!(marked_inside(), 10);
+        assert_eq!(mentions_marker_in_string(), "cargo-mutants: skip");
+        assert!(!not_marked());
+        let mut counter = Counter { count: 0 };
+        counter.increment();
+        assert_eq!(counter.get(), 1);
+        counter.reset();
+        assert_eq!(counter.get(), 0);
+        assert_eq!(skipped::double(2), 4);
+    }
+}
@@ -0,0 +1,27 @@
+//! Bit manipulation that can be mutated by swapping bitwise and shift operators.
+
+/// Pack two nibbles into a byte.
+fn pack_nibbles(high: u8, low: u8) -> u8 {
+    (high << 4) | (low & 0x0f)
+}
+
+/// Flip the bits selected by the mask.
+fn toggle(mut flags: u8, mask: u8) -> u8 {
+    flags ^= mask;
+    flags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack() {
+        assert_eq!(pack_nibbles(0xa, 0x35), 0xa5);
+    }
+
+    #[test]
+    fn toggle_flags() {
+        assert_eq!(toggle(0b1100, 0b1010), 0b0110);
+    }
+}
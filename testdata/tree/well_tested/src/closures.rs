@@ -0,0 +1,76 @@
+//! Closures and async blocks, which are mutated like function bodies.
+//!
+//! The body of a closure is only replaced if it has more than one statement.
+
+use std::future::Future;
+
+/// Take 10% off each price.
+fn apply_discount(prices: &mut [u32]) {
+    prices.iter_mut().for_each(|p| {
+        let discount = *p / 10;
+        *p -= discount;
+    });
+}
+
+/// Double a number and add one, using a closure with a declared return type.
+fn double_plus_one(n: u32) -> u32 {
+    let double = |x: u32| -> u32 {
+        debug_assert!(x <= u32::MAX / 2);
+        x * 2
+    };
+    double(n) + 1
+}
+
+/// Return a future that increments a number.
+///
+/// This is deliberately not an `async fn`, so that the async block is mutated.
+#[allow(clippy::manual_async_fn)]
+fn increment_later(n: u32) -> impl Future<Output = u32> {
+    async move { n + 1 }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use super::*;
+
+    /// Run a future that never waits to completion.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn noop_raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                noop_raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = Pin::as_mut(&mut future).poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn discount() {
+        let mut prices = [100, 250];
+        apply_discount(&mut prices);
+        assert_eq!(prices, [90, 225]);
+    }
+
+    #[test]
+    fn double() {
+        assert_eq!(double_plus_one(4), 9);
+    }
+
+    #[test]
+    fn increment() {
+        assert_eq!(block_on(increment_later(41)), 42);
+    }
+}
@@ -0,0 +1,31 @@
+//! Constants and statics, whose values can be mutated by the `const-value` kind.
+
+use std::time::Duration;
+
+const BUFFER_SIZE: usize = 4096;
+
+static VERBOSE: bool = true;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+const OFFSET: i32 = -3;
+
+struct Limits;
+
+impl Limits {
+    const MAX_DEPTH: u8 = 0;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(BUFFER_SIZE, 4096);
+        assert!(VERBOSE);
+        assert_eq!(TIMEOUT.as_secs(), 30);
+        assert_eq!(OFFSET, -3);
+        assert_eq!(Limits::MAX_DEPTH, 0);
+    }
+}
@@ -0,0 +1,69 @@
+//! Impls of `Default` and `From`, whose bodies aren't replaced with
+//! `Default::default()`, but whose literal field values are changed by
+//! `struct-field` mutants.
+//!
+//! Functions called by a `Default` impl also aren't replaced with
+//! `Default::default()`, because that would recurse forever.
+
+struct Settings {
+    retries: u32,
+    verbose: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            retries: 3,
+            verbose: false,
+        }
+    }
+}
+
+impl From<u32> for Settings {
+    fn from(retries: u32) -> Self {
+        Settings {
+            retries,
+            verbose: true,
+        }
+    }
+}
+
+struct Pool {
+    size: usize,
+}
+
+impl Pool {
+    fn with_size(size: usize) -> Pool {
+        Pool { size }
+    }
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::with_size(8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_settings() {
+        let settings = Settings::default();
+        assert_eq!(settings.retries, 3);
+        assert!(!settings.verbose);
+    }
+
+    #[test]
+    fn settings_from_retries() {
+        let settings = Settings::from(5);
+        assert_eq!(settings.retries, 5);
+        assert!(settings.verbose);
+    }
+
+    #[test]
+    fn default_pool() {
+        assert_eq!(Pool::default().size, 8);
+    }
+}
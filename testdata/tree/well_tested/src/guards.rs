@@ -0,0 +1,39 @@
+//! Functions with assertions and `expect` guards that can be removed.
+
+/// Divide, panicking with a clear message on division by zero.
+fn checked_div(a: u32, b: u32) -> u32 {
+    assert!(b != 0, "division by zero");
+    a / b
+}
+
+/// Parse a TCP port number.
+fn parse_port(s: &str) -> u16 {
+    s.parse().expect("invalid port")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn divide() {
+        assert_eq!(checked_div(6, 3), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "division by zero")]
+    fn divide_by_zero() {
+        checked_div(1, 0);
+    }
+
+    #[test]
+    fn parse_valid_port() {
+        assert_eq!(parse_port("8080"), 8080);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid port")]
+    fn parse_invalid_port() {
+        parse_port("http");
+    }
+}
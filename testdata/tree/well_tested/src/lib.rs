@@ -8,11 +8,18 @@
 
 #![allow(unused, dead_code)]
 
+mod bits;
+mod closures;
+mod consts;
+mod conversions;
 mod empty_fns;
+mod guards;
 mod inside_mod;
 mod item_mod;
+mod loops;
 mod methods;
 mod nested_function;
 mod result;
 pub mod simple_fns;
 mod struct_with_lifetime;
+mod traits;
@@ -0,0 +1,89 @@
+//! Loops that can be mutated by changing their conditions and control flow.
+
+/// Count the zero bytes at the start of a slice.
+fn leading_zeros(bytes: &[u8]) -> usize {
+    let mut count = 0;
+    for b in bytes {
+        if *b != 0 {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+/// Count the odd numbers in a slice.
+fn count_odd(numbers: &[u32]) -> usize {
+    let mut count = 0;
+    for n in numbers {
+        if n % 2 == 0 {
+            continue;
+        }
+        count += 1;
+    }
+    count
+}
+
+/// Return how many times `n` can be halved before it reaches zero.
+fn halvings(mut n: u32) -> u32 {
+    let mut count = 0;
+    while n > 0 {
+        n /= 2;
+        count += 1;
+    }
+    count
+}
+
+/// Return the smallest power of two that is at least `n`.
+///
+/// If the `break` is deleted, the loop overflows and panics.
+fn next_power_of_two(n: u32) -> u32 {
+    let mut p = 1;
+    loop {
+        if p >= n {
+            break;
+        }
+        p *= 2;
+    }
+    p
+}
+
+/// Add up the numbers on a stack.
+///
+/// `while let` is not changed to `while false`, because the body uses `n`.
+fn sum_stack(mut stack: Vec<u32>) -> u32 {
+    let mut sum = 0;
+    while let Some(n) = stack.pop() {
+        sum += n;
+    }
+    sum
+}
+
+/// Return the smallest square greater than `n`.
+///
+/// The loop only exits by `return`, so its type is `!`, and it is not changed to exit
+/// with `break`.
+fn next_square(n: u32) -> u32 {
+    let mut i = 1;
+    loop {
+        if i * i > n {
+            return i * i;
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loops() {
+        assert_eq!(leading_zeros(&[0, 0, 1, 0]), 2);
+        assert_eq!(count_odd(&[1, 2, 3, 4, 5]), 3);
+        assert_eq!(halvings(8), 4);
+        assert_eq!(next_power_of_two(5), 8);
+        assert_eq!(sum_stack(vec![1, 2, 3]), 6);
+        assert_eq!(next_square(10), 16);
+    }
+}
@@ -14,6 +14,12 @@ fn error_if_negative(a: i32) -> Result<(), ()> {
     }
 }
 
+/// Parse a number and double it, passing parse errors to the caller.
+fn parse_and_double(s: &str) -> Result<u32, std::num::ParseIntError> {
+    let n: u32 = s.parse()?;
+    Ok(n * 2)
+}
+
 mod test {
     use super::*;
 
@@ -30,4 +36,10 @@ mod test {
         assert_eq!(error_if_negative(-1), Err(()));
         assert_eq!(error_if_negative(1), Ok(()));
     }
+
+    #[test]
+    fn parse_and_double() {
+        assert_eq!(super::parse_and_double("21"), Ok(42));
+        assert!(super::parse_and_double("twenty").is_err());
+    }
 }
@@ -0,0 +1,29 @@
+//! Default method bodies in traits, which are mutated like other functions.
+
+trait Shape {
+    fn sides(&self) -> u32;
+
+    /// The sum of the interior angles, in degrees.
+    fn angle_sum(&self) -> u32 {
+        (self.sides() - 2) * 180
+    }
+}
+
+struct Triangle;
+
+impl Shape for Triangle {
+    fn sides(&self) -> u32 {
+        3
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn triangle() {
+        assert_eq!(Triangle.sides(), 3);
+        assert_eq!(Triangle.angle_sum(), 180);
+    }
+}
@@ -0,0 +1,10 @@
+use cargo_mutants_testdata_workspace_utils::add;
+
+pub fn double(x: u32) -> u32 {
+    add(x, x)
+}
+
+#[test]
+fn double_two() {
+    assert_eq!(double(2), 4);
+}
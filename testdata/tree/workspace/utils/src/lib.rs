@@ -0,0 +1,6 @@
+//! Not tested by the `main` package, so its mutants would be missed if they
+//! were tested from there.
+
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
@@ -2,6 +2,7 @@
 
 //! Tests for cargo-mutants CLI layer.
 
+use std::env;
 use std::fs::{self, read_dir};
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -20,6 +21,7 @@ lazy_static! {
     static ref MAIN_BINARY: PathBuf = assert_cmd::cargo::cargo_bin("cargo-mutants");
     static ref DURATION_RE: Regex = Regex::new(r"\d+\.\d{1,3}s").unwrap();
     static ref SIZE_RE: Regex = Regex::new(r"\d+ MB").unwrap();
+    static ref ESTIMATE_RE: Regex = Regex::new(r"Estimated time to test all mutants: .*").unwrap();
 }
 
 fn run_assert_cmd() -> assert_cmd::Command {
@@ -61,6 +63,7 @@ fn copy_of_testdata(tree_name: &str) -> TempDir {
 fn redact_timestamps_sizes(s: &str) -> String {
     // TODO: Maybe match the number of digits?
     let s = DURATION_RE.replace_all(s, "x.xxxs");
+    let s = ESTIMATE_RE.replace_all(&s, "Estimated time to test all mutants: xxx");
     SIZE_RE.replace_all(&s, "xxx MB").to_string()
 }
 
@@ -115,6 +118,24 @@ fn uses_cargo_env_var_to_run_cargo_so_invalid_value_fails() {
     // clean build failing.
 }
 
+#[test]
+fn container_cli_is_used_to_run_cargo() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let bogus_cli = "NOTHING_NONEXISTENT_CONTAINER_CLI";
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--container-image",
+            "rust:latest",
+            "--container-cli",
+            bogus_cli,
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .stderr(contains(format!("failed to spawn {} run --rm", bogus_cli)))
+        .code(1);
+}
+
 #[test]
 fn list_diff_json_not_yet_supported() {
     run_assert_cmd()
@@ -195,6 +216,67 @@ fn list_mutants_in_cfg_attr_mutants_skip_json() {
         .assert_insta("list_mutants_in_cfg_attr_mutants_skip_json");
 }
 
+#[test]
+fn list_mutants_in_skip_comment() {
+    run()
+        .arg("mutants")
+        .arg("--list")
+        .current_dir("testdata/tree/skip_comment")
+        .assert_insta("list_mutants_in_skip_comment");
+}
+
+#[test]
+fn list_mutants_in_equivalent() {
+    run()
+        .arg("mutants")
+        .arg("--list")
+        .current_dir("testdata/tree/equivalent")
+        .assert_insta("list_mutants_in_equivalent");
+}
+
+#[test]
+fn list_mutants_skips_vendored_and_generated_code() {
+    run()
+        .arg("mutants")
+        .arg("--list")
+        .current_dir("testdata/tree/generated")
+        .assert_insta("list_mutants_skips_vendored_and_generated_code");
+    run()
+        .args(["mutants", "--list", "--include-generated"])
+        .current_dir("testdata/tree/generated")
+        .assert_insta("list_mutants_with_include_generated");
+}
+
+#[test]
+fn list_mutants_skips_files_excluded_from_package() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let old_dir = tmp_src_dir.path().join("src/bin/old");
+    fs::create_dir(&old_dir).unwrap();
+    fs::write(
+        old_dir.join("helper.rs"),
+        "pub fn one() -> u32 {\n    1\n}\n",
+    )
+    .unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("src/bin/old/helper.rs"));
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .unwrap()
+        .replace("[package]\n", "[package]\nexclude = [\"/src/bin/old\"]\n");
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("src/bin/old/helper.rs").not())
+        .stdout(contains("src/bin/main.rs"));
+}
+
 #[test]
 fn list_mutants_in_cfg_attr_test_skip() {
     let tmp_src_dir = copy_of_testdata("cfg_attr_test_skip");
@@ -254,6 +336,269 @@ fn list_mutants_well_tested_name_filter() {
         .assert_insta("list_mutants_well_tested_name_filter");
 }
 
+#[test]
+fn list_early_return_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "early-return"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_early_return_mutants_well_tested");
+}
+
+#[test]
+fn list_bitwise_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "bitwise"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_bitwise_mutants_well_tested");
+}
+
+#[test]
+fn mutation_kinds_from_config_and_command_line() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str("\n[package.metadata.mutants]\nmutation_kinds = [\"fn-value\"]\n");
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(
+            "src/bin/main.rs:1: replace main with ()\n\
+            src/bin/main.rs:7: replace factorial -> u32 with Default::default()\n",
+        );
+    // The command line takes precedence over the config.
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--list",
+            "--mutation-kinds",
+            "range",
+            "--file",
+            "main.rs",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("replace 2..=n with 2..n in factorial"))
+        .stdout(contains("replace factorial -> u32").not());
+}
+
+#[test]
+fn exclude_functions_from_config() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str(
+        "\n[package.metadata.mutants]\n\
+        exclude_functions = [\"*::fmt\", \"loops::*\", \"inside_mod::outer::*\"]\n",
+    );
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("replace Foo::double with ()"))
+        .stdout(contains("fmt -> fmt::Result").not())
+        .stdout(contains("src/loops.rs").not())
+        .stdout(contains("src/inside_mod.rs").not())
+        .stdout(contains("replace has_nested::inner -> u32"));
+    run_assert_cmd()
+        .args(["mutants", "--list-skipped"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "src/loops.rs:4: leading_zeros: excluded by filter\n",
+        ));
+}
+
+#[test]
+fn warns_when_filters_remove_everything_in_a_named_file() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str("\n[package.metadata.mutants]\nexclude_functions = [\"loops::*\"]\n");
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--list",
+            "--json",
+            "-f",
+            "loops.rs",
+            "-f",
+            "no_such_file.rs",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout("[]")
+        .stderr(contains(
+            "warning: --file \"no_such_file.rs\" matched no source files\n",
+        ))
+        .stderr(contains(
+            "warning: all 11 mutants in src/loops.rs were filtered out: 11 by exclude_functions\n",
+        ));
+}
+
+#[test]
+fn score_below_minimum_in_config_fails_the_run() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str(
+        "\n[package.metadata.mutants.score]\n\
+        \"src/bin\" = 60\n\
+        \"crate\" = 40\n",
+    );
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(5)
+        .stdout(contains(
+            "Mutation score for src/bin is 50%, below the minimum of 60%\n",
+        ))
+        .stdout(contains("Mutation score for crate").not());
+}
+
+#[test]
+fn timeout_multiplier_in_config_must_be_positive() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str(
+        "\n[package.metadata.mutants.timeout_multipliers]\n\
+        \"src/bin/**\" = -2\n",
+    );
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "timeout multiplier for \"src/bin/**\" should be a positive number, not -2",
+        ));
+}
+
+#[test]
+fn allowed_missed_mutants_dont_fail_the_run() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    fs::write(
+        tmp_src_dir.path().join("mutants-allowlist.toml"),
+        r#"
+# The output of main isn't tested.
+allow = [
+    "src/bin/main.rs: replace main with ()",
+    "src/bin/main.rs: replace 1..=6 with 1..6 in main",
+    "src/bin/main.rs: replace 1..=6 with 1 + 1..=6 in main",
+]
+"#,
+    )
+    .unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "fn-value: 1 caught, 0 missed, 0 unviable, 0 timed out, 1 allowed, score 100%\n",
+        ));
+    let summary: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/summary.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(summary["run_score"]["allowed"], 3);
+    assert_eq!(summary["run_score"]["score"], 100.0);
+    assert_eq!(summary["missed"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn invalid_config_is_an_error() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str("\n[package.metadata.mutants]\nmutation_kinds = [\"sideways\"]\n");
+    fs::write(&manifest_path, manifest).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(contains("unknown mutation kind \"sideways\""));
+}
+
+#[test]
+fn list_guard_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "guard"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_guard_mutants_well_tested");
+}
+
+#[test]
+fn list_swap_args_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "swap-args"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_swap_args_mutants_well_tested");
+}
+
+#[test]
+fn list_delete_call_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "delete-call"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_delete_call_mutants_well_tested");
+}
+
+#[test]
+fn list_swap_if_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "swap-if"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_swap_if_mutants_well_tested");
+}
+
+#[test]
+fn list_const_value_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "const-value"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_const_value_mutants_well_tested");
+}
+
+#[test]
+fn list_try_mutants_well_tested() {
+    run()
+        .arg("mutants")
+        .args(["--list", "--mutation-kinds", "try"])
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_try_mutants_well_tested");
+}
+
+#[test]
+fn unknown_mutation_kind_is_an_error() {
+    run_assert_cmd()
+        .args(["mutants", "--list", "--mutation-kinds", "loop,sideways"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains("unknown mutation kind \"sideways\""));
+}
+
 #[test]
 fn list_mutants_json_well_tested() {
     run()
@@ -283,6 +628,37 @@ fn list_files_json_well_tested() {
         .assert_insta("list_files_json_well_tested");
 }
 
+#[test]
+fn list_skipped_text_well_tested() {
+    run()
+        .arg("mutants")
+        .arg("--list-skipped")
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_skipped_text_well_tested");
+}
+
+#[test]
+fn list_skipped_json_skip_comment() {
+    run()
+        .arg("mutants")
+        .arg("--list-skipped")
+        .arg("--json")
+        .current_dir("testdata/tree/skip_comment")
+        .assert_insta("list_skipped_json_skip_comment");
+}
+
+#[test]
+fn list_skipped_shows_generated_code() {
+    run_assert_cmd()
+        .args(["mutants", "--list-skipped", "-d", "testdata/tree/generated"])
+        .assert()
+        .success()
+        .stdout(contains("src/bindings.rs: generated code\n"))
+        .stdout(contains(
+            "src/lib.rs:13: <impl PartialEq for Meters>: generated code\n",
+        ));
+}
+
 #[test]
 fn copy_testdata_doesnt_include_build_artifacts() {
     // If there is a target or mutants.out in the source directory, we don't want it in the copy,
@@ -295,94 +671,1052 @@ fn copy_testdata_doesnt_include_build_artifacts() {
 }
 
 #[test]
-fn well_tested_tree_quiet() {
-    let tmp_src_dir = copy_of_testdata("well_tested");
+fn dry_run_applies_mutants_without_running_cargo() {
+    let tmp_src_dir = copy_of_testdata("factorial");
     run_assert_cmd()
-        .arg("mutants")
-        .arg("--no-times")
-        .arg("--no-shuffle")
-        .current_dir(&tmp_src_dir.path())
+        .args(["mutants", "--dry-run"])
+        .current_dir(tmp_src_dir.path())
         .assert()
         .success()
-        .stdout(predicate::function(|stdout| {
-            insta::assert_snapshot!(stdout);
-            true
-        }));
-    // The format of outcomes.json is not pinned down yet, but it should exist.
-    assert!(tmp_src_dir
-        .path()
-        .join("mutants.out/outcomes.json")
-        .exists());
+        .stdout(contains(
+            "Dry run: applied and reverted 6 mutants without running cargo",
+        ))
+        .stdout(contains("baseline").not());
+    let output_dir = tmp_src_dir.path().join("mutants.out");
+    assert!(output_dir.join("mutants.json").is_file());
+    assert!(!output_dir.join("outcomes.json").exists());
+    assert_eq!(fs::read_dir(output_dir.join("log")).unwrap().count(), 6);
 }
 
 #[test]
-fn well_tested_tree_finds_no_problems() {
-    let tmp_src_dir = copy_of_testdata("well_tested");
+fn quiet_copy_hides_copy_progress() {
+    let tmp_src_dir = copy_of_testdata("factorial");
     run_assert_cmd()
-        .arg("mutants")
-        .arg("--no-times")
-        .arg("--caught")
-        .arg("--no-shuffle")
-        .current_dir(&tmp_src_dir.path())
+        .args(["mutants", "--dry-run", "--quiet-copy"])
+        .current_dir(tmp_src_dir.path())
         .assert()
         .success()
-        .stdout(predicate::function(|stdout| {
-            insta::assert_snapshot!(stdout);
-            true
-        }));
-    // The format of outcomes.json is not pinned down yet, but it should exist.
-    assert!(tmp_src_dir
-        .path()
-        .join("mutants.out/outcomes.json")
-        .exists());
+        .stdout(contains("Copy source").not())
+        .stdout(contains("Dry run: applied and reverted 6 mutants"));
 }
 
 #[test]
-fn well_tested_tree_check_only() {
-    let tmp_src_dir = copy_of_testdata("well_tested");
+fn run_in_workspace_member_copies_workspace_and_mutates_only_member() {
+    let tmp_src_dir = copy_of_testdata("workspace");
+    let member_dir = tmp_src_dir.path().join("main");
     run_assert_cmd()
-        .args(["mutants", "--check", "--no-shuffle", "--no-times"])
-        .current_dir(&tmp_src_dir.path())
+        .args(["mutants", "--list"])
+        .current_dir(&member_dir)
         .assert()
         .success()
-        .stdout(predicate::function(|stdout| {
-            insta::assert_snapshot!(stdout);
-            true
-        }));
+        .stdout("src/lib.rs:3: replace double -> u32 with Default::default()\n");
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(&member_dir)
+        .assert()
+        .success()
+        .stdout(contains("Found 1 mutant to test"))
+        .stdout(contains("1 caught, 0 missed"));
+    assert!(member_dir.join("mutants.out/outcomes.json").is_file());
+    // Only the member is checked and built, not the rest of the workspace.
+    let baseline_log = fs::read_to_string(member_dir.join("mutants.out/log/baseline.log")).unwrap();
+    assert!(baseline_log.contains(
+        "check --tests --message-format=json --quiet --package cargo-mutants-testdata-workspace-main"
+    ));
 }
 
 #[test]
-fn well_tested_tree_check_only_shuffled() {
-    let tmp_src_dir = copy_of_testdata("well_tested");
+#[cfg(unix)]
+fn corrupt_build_cache_is_cleaned_in_workspace_target_dir() {
+    use std::os::unix::fs::PermissionsExt;
+    let tmp_src_dir = copy_of_testdata("workspace");
+    let member_dir = tmp_src_dir.path().join("main");
+    // Corrupt the incremental state in the scratch workspace's target directory on
+    // the first build, and fail every build until it's removed.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    fs::write(
+        &wrapper,
+        "#!/bin/sh\n\
+        case \"$PWD\" in\n\
+        */cargo-mutants-*.tmp/main)\n\
+            if [ ! -e ../target/corrupted ]; then\n\
+                mkdir -p ../target/debug/incremental/corrupt\n\
+                touch ../target/corrupted\n\
+            fi;;\n\
+        esac\n\
+        if [ -d ../target/debug/incremental/corrupt ]; then\n\
+            echo 'error: incremental compilation: could not create session directory lock file' >&2\n\
+            exit 101\n\
+        fi\n\
+        exec cargo \"$@\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
     run_assert_cmd()
-        .args(["mutants", "--check", "--no-times", "--shuffle"])
-        .current_dir(&tmp_src_dir.path())
+        .args(["mutants", "--no-times", "--no-shuffle", "--cargo-command"])
+        .arg(&wrapper)
+        .current_dir(&member_dir)
         .assert()
-        .success();
-    // Caution: No assertions about output here, we just check that it runs.
+        .success()
+        .stdout(contains("1 caught, 0 missed"));
+    let baseline_log = fs::read_to_string(member_dir.join("mutants.out/log/baseline.log")).unwrap();
+    assert!(baseline_log.contains("removing incremental state and retrying"));
 }
 
 #[test]
-fn error_when_no_mutants_found() {
-    let tmp_src_dir = copy_of_testdata("no_opportunities");
+fn baseline_only_stops_before_testing_mutants() {
+    let tmp_src_dir = copy_of_testdata("factorial");
     run_assert_cmd()
-        .args(["mutants", "--check", "--no-times", "--no-shuffle"])
-        .current_dir(&tmp_src_dir.path())
+        .args(["mutants", "--baseline-only", "--no-times"])
+        .current_dir(tmp_src_dir.path())
         .assert()
-        .stderr(predicate::str::contains("Error: No mutants found"))
-        .stdout(predicate::str::contains("Found 0 mutants to test"))
-        .failure();
+        .success()
+        .stdout(
+            "Freshen source tree ... ok\n\
+            Copy source and build products to scratch directory ... done\n\
+            Unmutated baseline ... ok\n\
+            Baseline succeeded; not testing 6 mutants because of --baseline-only\n",
+        );
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(outcomes["outcomes"].as_array().unwrap().len(), 2);
+    assert!(!tmp_src_dir
+        .path()
+        .join("mutants.out/history.jsonl")
+        .exists());
 }
 
+#[cfg(target_os = "linux")]
 #[test]
-fn uncaught_mutant_in_factorial() {
+fn peak_memory_is_recorded_in_outcomes() {
     let tmp_src_dir = copy_of_testdata("factorial");
-
     run_assert_cmd()
-        .arg("mutants")
+        .args(["mutants", "--baseline-only"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    let baseline = &outcomes["outcomes"][1];
+    assert_eq!(baseline["scenario"], "Baseline");
+    assert!(baseline["peak_rss"].as_u64().unwrap() > 0);
+    assert!(baseline["phase_results"][2]["peak_rss"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn baseline_only_fails_if_tests_fail() {
+    let tmp_src_dir = copy_of_testdata("already_failing_tests");
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn baseline_skip_reuses_saved_timeout() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    let baseline_times: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/baseline.json")).unwrap(),
+    )
+    .unwrap();
+    let auto_timeout = baseline_times["auto_timeout"].as_f64().unwrap();
+    assert!(auto_timeout >= 20.0);
+
+    run_assert_cmd()
+        .args(["mutants", "--baseline", "skip", "--max-mutants", "1"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .stdout(predicate::str::contains("Unmutated baseline").not())
+        .stdout(predicate::str::contains(format!(
+            "Auto-set test timeout to {:.1}s",
+            auto_timeout
+        )));
+    // The times are kept for the next run.
+    assert!(tmp_src_dir
+        .path()
+        .join("mutants.out/baseline.json")
+        .is_file());
+}
+
+#[test]
+fn baseline_only_conflicts_with_baseline_skip() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only", "--baseline", "skip"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "--baseline-only can't be used with --baseline skip",
+        ));
+}
+
+#[test]
+fn isolate_nested_cargo_gives_tests_their_own_target_dir() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let tests_dir = tmp_src_dir.path().join("tests");
+    fs::create_dir(&tests_dir).unwrap();
+    fs::write(
+        tests_dir.join("nested.rs"),
+        r#"
+#[test]
+fn nested_target_dir() {
+    let target_dir = std::env::var("CARGO_TARGET_DIR").unwrap();
+    assert!(target_dir.ends_with("mutants-nested"), "{}", target_dir);
+}
+"#,
+    )
+    .unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only", "--isolate-nested-cargo"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn config_adds_cargo_args_for_each_phase() {
+    let tmp_src_dir = copy_of_testdata("already_failing_tests");
+    let manifest_path = tmp_src_dir.path().join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path).unwrap();
+    manifest.push_str(
+        "\n[package.metadata.mutants.phase.test]\nargs = [\"--\", \"--skip\", \"test_factorial\"]\n",
+    );
+    fs::write(&manifest_path, manifest).unwrap();
+    // The only test is skipped, so the baseline passes but runs no tests.
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(6);
+    // Arguments on the command line replace those from the config.
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only", "--", "--lib"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn deterministic_env_ignores_rustflags_from_the_environment() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let main_path = tmp_src_dir.path().join("src/bin/main.rs");
+    let source = fs::read_to_string(&main_path).unwrap();
+    fs::write(
+        &main_path,
+        format!(
+            "#[cfg(from_rustflags)]\ncompile_error!(\"built with RUSTFLAGS\");\n{}",
+            source
+        ),
+    )
+    .unwrap();
+    // The source tree isn't built with --no-copy-target, so only the scratch build
+    // sees the flags.
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only", "--no-copy-target"])
+        .env("RUSTFLAGS", "--cfg from_rustflags")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4);
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--baseline-only",
+            "--no-copy-target",
+            "--deterministic-env",
+        ])
+        .env("RUSTFLAGS", "--cfg from_rustflags")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+}
+
+#[test]
+fn estimate_run_time_before_testing_mutants() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--check", "--yes"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("Estimated time to test all mutants: "));
+}
+
+#[test]
+fn well_tested_tree_quiet() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    run_assert_cmd()
+        .arg("mutants")
+        .arg("--no-times")
+        .arg("--no-shuffle")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout| {
+            insta::assert_snapshot!(stdout);
+            true
+        }));
+    // The format of outcomes.json is not pinned down yet, but it should exist.
+    assert!(tmp_src_dir
+        .path()
+        .join("mutants.out/outcomes.json")
+        .exists());
+    // outcomes.ndjson has one line for each of the same outcomes.
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    let ndjson =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.ndjson")).unwrap();
+    assert_eq!(
+        ndjson
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect_vec(),
+        *outcomes["outcomes"].as_array().unwrap()
+    );
+    // Each outcome has an explicit status.
+    let statuses = outcomes["outcomes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|outcome| outcome["status"].as_str().unwrap())
+        .collect_vec();
+    assert_eq!(statuses[..2], ["Passed", "Passed"]);
+    assert!(statuses[2..]
+        .iter()
+        .all(|&status| status == "Caught" || status == "Unviable"));
+    // The log of each outcome can be found from its relative path, and the
+    // output of each phase by its offsets.
+    let baseline = &outcomes["outcomes"][1];
+    let baseline_log = fs::read(
+        tmp_src_dir
+            .path()
+            .join("mutants.out")
+            .join(baseline["log_file"].as_str().unwrap()),
+    )
+    .unwrap();
+    let test_phase = &baseline["phase_results"][2];
+    assert_eq!(test_phase["phase"], "Test");
+    let test_output = String::from_utf8_lossy(
+        &baseline_log[test_phase["log_start"].as_u64().unwrap() as usize
+            ..test_phase["log_end"].as_u64().unwrap() as usize],
+    );
+    assert_eq!(test_output.matches("*** run ").count(), 1);
+    assert!(test_output.contains("test result: ok."));
+    // The command of each phase is recorded, so that it can be run again by hand.
+    let command = &test_phase["command"];
+    assert_eq!(command["argv"][1], "test");
+    assert!(Path::new(command["cwd"].as_str().unwrap()).is_absolute());
+    assert!(command["env"].is_object());
+    // Scores are also broken down by the kind of mutation.
+    assert_eq!(outcomes["kinds"]["loop"]["caught"], 5);
+    assert_eq!(outcomes["kinds"]["loop"]["missed"], 0);
+    assert_eq!(outcomes["kinds"]["fn-value"]["score"], 100.0);
+    // And by source file and module, where each module includes its submodules.
+    assert_eq!(outcomes["files"]["src/closures.rs"]["missed"], 0);
+    assert_eq!(outcomes["modules"]["crate::closures"]["missed"], 0);
+    let n_mutants = statuses.len() - 2;
+    let crate_score = &outcomes["modules"]["crate"];
+    assert_eq!(
+        crate_score["caught"].as_u64().unwrap() + crate_score["unviable"].as_u64().unwrap(),
+        n_mutants as u64
+    );
+    let history = fs::read_to_string(tmp_src_dir.path().join("mutants.out/history.jsonl")).unwrap();
+    assert_eq!(history.lines().count(), 1);
+    assert!(history.contains(r#""missed":0"#));
+    let badge_json = fs::read_to_string(tmp_src_dir.path().join("mutants.out/badge.json")).unwrap();
+    assert!(badge_json.contains(r#""message": "100%""#));
+    assert!(tmp_src_dir.path().join("mutants.out/badge.svg").is_file());
+    let summary: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/summary.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(summary["run_score"]["missed"], 0);
+    assert_eq!(summary["missed"].as_array().unwrap().len(), 0);
+    assert_eq!(summary["slowest_mutants"].as_array().unwrap().len(), 10);
+    let summary_txt =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/summary.txt")).unwrap();
+    assert!(!summary_txt.contains("Missed mutants:"));
+    let diagnostics =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/diagnostics.json")).unwrap();
+    assert_eq!(diagnostics, "[]");
+    let test_efficiency: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/test_efficiency.json")).unwrap(),
+    )
+    .unwrap();
+    let pack = test_efficiency["tests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["test"] == "bits::test::pack")
+        .expect("pack test is listed");
+    assert!(pack["killed"].as_u64().unwrap() > 0);
+    let test_efficiency_txt =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/test_efficiency.txt")).unwrap();
+    assert!(test_efficiency_txt.starts_with(
+        "Mutants killed by each test:
+"
+    ));
+    // With --no-times, times are left out of the reports or zeroed, so they're
+    // reproducible.
+    assert!(!summary_txt.contains("Total time"));
+    assert!(!summary_txt.contains("Slowest mutants:"));
+    assert_eq!(summary["total_duration"], 0.0);
+    assert_eq!(summary["slowest_mutants"][0]["duration"], 0.0);
+    assert!(outcomes["outcomes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|outcome| outcome["phase_results"].as_array().unwrap())
+        .all(|phase_result| phase_result["duration"] == 0.0));
+    assert!(history.contains(r#""start_time":"1970-01-01T00:00:00+00:00""#));
+    assert!(history.contains(r#""end_time":"1970-01-01T00:00:00+00:00""#));
+}
+
+#[test]
+fn well_tested_tree_finds_no_problems() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    run_assert_cmd()
+        .arg("mutants")
+        .arg("--no-times")
+        .arg("--caught")
+        .arg("--no-shuffle")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout| {
+            insta::assert_snapshot!(stdout);
+            true
+        }));
+    // The format of outcomes.json is not pinned down yet, but it should exist.
+    assert!(tmp_src_dir
+        .path()
+        .join("mutants.out/outcomes.json")
+        .exists());
+}
+
+#[test]
+fn well_tested_tree_check_only() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-shuffle", "--no-times"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout| {
+            insta::assert_snapshot!(stdout);
+            true
+        }));
+}
+
+/// `--cargo-command` runs every phase through a cargo-compatible wrapper, like `cross`.
+#[cfg(unix)]
+#[test]
+fn cargo_command_wraps_every_phase() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cross");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$1\" >> '{}'\nexec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times", "--cargo-command"])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    let subcommands = fs::read_to_string(&wrapper_log).unwrap();
+    // The source tree, the baseline, and every mutant were checked by the wrapper.
+    assert!(subcommands.lines().count() > 2);
+    assert!(subcommands.lines().all(|line| line == "check"));
+}
+
+/// `--test-tool miri` runs the tests with `cargo miri test`.
+#[cfg(unix)]
+#[test]
+fn test_tool_miri_runs_cargo_miri_test() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    // Record the subcommands, and then run the tests without Miri, which might not be
+    // installed.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$1 $2\" >> '{}'\n[ \"$1\" = miri ] && shift\nexec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--no-times",
+            "--no-copy-target",
+            "--test-tool",
+            "miri",
+            "--cargo-command",
+        ])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2); // missed mutants
+    let subcommands = fs::read_to_string(&wrapper_log).unwrap();
+    assert!(subcommands.lines().any(|line| line == "miri test"));
+    assert!(!subcommands
+        .lines()
+        .any(|line| line.starts_with("test ") || line == "test"));
+}
+
+/// A failure caused by the machine, like a full disk, is retried rather than counted
+/// against the scenario.
+#[cfg(unix)]
+#[test]
+fn infrastructure_failure_is_retried() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    // The first test run fails as if the disk were full.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let marker = bin_dir.path().join("failed-once");
+    fs::write(
+        &wrapper,
+        format!(
+            r#"#!/bin/sh
+if [ "$1" = test ] && [ ! -f '{marker}' ]; then
+    touch '{marker}'
+    echo "error: failed to write to target: No space left on device (os error 28)" >&2
+    exit 101
+fi
+exec cargo "$@"
+"#,
+            marker = marker.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--baseline-only",
+            "--no-times",
+            "--cargo-command",
+        ])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("Unmutated baseline ... ok"));
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    let baseline = &outcomes["outcomes"][1];
+    assert_eq!(baseline["status"], "Passed");
+    assert_eq!(baseline["retried_because"], "the disk is full");
+    let baseline_log =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/log/baseline.log")).unwrap();
+    assert!(baseline_log.contains("failure looks like the disk is full: retrying the scenario"));
+}
+
+/// `--test-tool nextest` builds an archive of the tests and then runs the tests from it.
+#[cfg(unix)]
+#[test]
+fn test_tool_nextest_runs_tests_from_archive() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    // Nextest might not be installed, so the wrapper builds the tests and makes an
+    // empty archive, and then runs the tests with plain cargo if the archive exists.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            r#"#!/bin/sh
+echo "$1 $2" >> '{}'
+if [ "$1" = nextest ]; then
+    sub="$2"
+    shift 2
+    while [ "$1" != --archive-file ]; do shift; done
+    archive="$2"
+    shift 2
+    if [ "$sub" = archive ]; then
+        cargo build --tests "$@" && touch "$archive"
+    else
+        [ -f "$archive" ] && exec cargo test "$@"
+    fi
+else
+    exec cargo "$@"
+fi
+"#,
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--no-times",
+            "--no-copy-target",
+            "--test-tool",
+            "nextest",
+            "--cargo-command",
+        ])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2); // missed mutants
+    let subcommands = fs::read_to_string(&wrapper_log).unwrap();
+    assert!(subcommands.lines().any(|line| line == "nextest archive"));
+    assert!(subcommands.lines().any(|line| line == "nextest run"));
+    assert!(!subcommands
+        .lines()
+        .any(|line| line.starts_with("test ") || line.starts_with("build ")));
+}
+
+/// `--fuzz-time` runs each fuzz target after the tests pass, and a crash catches the
+/// mutant.
+#[cfg(unix)]
+#[test]
+fn fuzz_time_runs_fuzz_targets_after_tests() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let fuzz_targets = tmp_src_dir.path().join("fuzz/fuzz_targets");
+    fs::create_dir_all(&fuzz_targets).unwrap();
+    fs::write(fuzz_targets.join("table.rs"), "").unwrap();
+    // Instead of fuzzing, "crash" if the program doesn't print the right table, which
+    // catches the mutants of `main` that the tests miss.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\n\
+            if [ \"$1\" = fuzz ]; then\n\
+                echo \"$*\" >> '{}'\n\
+                table=$(cargo run -q | tr '\\n' ' ')\n\
+                [ \"$table\" = '1! = 1 2! = 2 3! = 6 4! = 24 5! = 120 6! = 720 ' ]\n\
+                exit $?\n\
+            fi\n\
+            exec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--no-times",
+            "--no-shuffle",
+            "--caught",
+            "--fuzz-time",
+            "2.5",
+            "--cargo-command",
+        ])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("caught by fuzzing"));
+    let fuzz_runs = fs::read_to_string(&wrapper_log).unwrap();
+    assert!(fuzz_runs.lines().count() > 1);
+    assert!(fuzz_runs
+        .lines()
+        .all(|line| line == "fuzz run table -- -max_total_time=3"));
+}
+
+#[test]
+fn fuzz_time_without_fuzz_targets_is_an_error() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--fuzz-time", "10"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(contains("fuzz/fuzz_targets"));
+}
+
+#[test]
+fn max_mutants_selects_mutants_to_test() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--no-shuffle", "--max-mutants", "1", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(
+            "Selected 1 of 6 mutants to fit --max-mutants or --max-duration",
+        ))
+        .stdout(contains("Found 1 mutant to test"));
+}
+
+#[test]
+fn max_duration_is_ignored_without_baseline_times() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--no-shuffle",
+            "--check",
+            "--baseline",
+            "skip",
+            "--max-duration",
+            "1",
+            "-d",
+        ])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "--max-duration is ignored because there are no baseline times to \
+            estimate how long each mutant will take",
+        ))
+        .stdout(contains("Found 6 mutants to test"));
+}
+
+#[test]
+fn max_duration_must_be_positive() {
+    run_assert_cmd()
+        .args(["mutants", "--list", "--max-duration", "0"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "--max-duration should be a positive number of seconds, not 0",
+        ));
+}
+
+#[test]
+fn max_mutants_per_fn_zero_is_an_error() {
+    run_assert_cmd()
+        .args(["mutants", "--list", "--max-mutants-per-fn", "0"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains("max mutants per function should be at least 1"));
+}
+
+/// `--proptest-cases` and `--mutant-env` set variables for the tests of mutants, but
+/// not for the baseline.
+#[cfg(unix)]
+#[test]
+fn mutant_env_is_set_only_when_testing_mutants() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\n\
+            [ \"$1\" = test ] && echo \"$PROPTEST_CASES $MY_SEED\" >> '{}'\n\
+            exec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--no-times",
+            "--no-shuffle",
+            "--proptest-cases",
+            "16",
+            "--mutant-env",
+            "MY_SEED=7",
+            "--cargo-command",
+        ])
+        .arg(&wrapper)
+        .env_remove("PROPTEST_CASES")
+        .env_remove("MY_SEED")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2); // missed mutants
+    let test_envs = fs::read_to_string(&wrapper_log).unwrap();
+    let mut lines = test_envs.lines();
+    assert_eq!(
+        lines.next(),
+        Some(" "),
+        "baseline has the default environment"
+    );
+    assert!(lines.clone().count() > 1);
+    assert!(lines.all(|line| line == "16 7"));
+}
+
+#[cfg(unix)]
+#[test]
+fn broken_symlinks_are_skipped_when_following_symlinks() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    std::os::unix::fs::symlink("/nonexistent/data", tmp_src_dir.path().join("data")).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--baseline-only", "--symlinks", "follow"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("warning: skipped broken symlink data: "));
+}
+
+/// `.git` is only copied to the scratch directory with `--copy-vcs`.
+#[cfg(unix)]
+#[test]
+fn vcs_directories_are_copied_only_when_asked() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    fs::create_dir(tmp_src_dir.path().join(".git")).unwrap();
+    fs::write(
+        tmp_src_dir.path().join(".git/HEAD"),
+        "ref: refs/heads/main\n",
+    )
+    .unwrap();
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\n\
+            if [ \"$1\" = test ]; then\n\
+            [ -f .git/HEAD ] && echo copied >> '{0}' || echo missing >> '{0}'\n\
+            fi\n\
+            exec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    for (copy_vcs, expected) in [(false, "missing\n"), (true, "copied\n")] {
+        let _ = fs::remove_file(&wrapper_log);
+        let mut cmd = run_assert_cmd();
+        cmd.args(["mutants", "--baseline-only", "--cargo-command"])
+            .arg(&wrapper)
+            .current_dir(tmp_src_dir.path());
+        if copy_vcs {
+            cmd.arg("--copy-vcs");
+        }
+        cmd.assert().success();
+        assert_eq!(fs::read_to_string(&wrapper_log).unwrap(), expected);
+    }
+}
+
+#[test]
+fn mutant_env_without_value_is_an_error() {
+    run_assert_cmd()
+        .args(["mutants", "--mutant-env", "PROPTEST_CASES"])
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "--mutant-env should be NAME=VALUE, not \"PROPTEST_CASES\"",
+        ));
+}
+
+#[test]
+fn unknown_exclude_tag_is_an_error() {
+    run_assert_cmd()
+        .args(["mutants", "--exclude-tag", "unstable"])
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "unknown tag \"unstable\": expected deprecated or hidden",
+        ));
+}
+
+#[test]
+fn unknown_test_tool_is_an_error() {
+    run_assert_cmd()
+        .args(["mutants", "--test-tool", "valgrind"])
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "unknown test tool \"valgrind\": expected cargo, miri, or nextest",
+        ));
+}
+
+/// A toolchain file in the tree is passed to rustup, and recorded in the history.
+#[cfg(unix)]
+#[test]
+fn rust_toolchain_file_chooses_toolchain() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Name the toolchain running the tests, so that it's installed.
+    let channel = match env::var("RUSTUP_TOOLCHAIN") {
+        Ok(channel) => channel,
+        Err(_) => return, // not run by rustup
+    };
+    let tmp_src_dir = copy_of_testdata("factorial");
+    fs::write(
+        tmp_src_dir.path().join("rust-toolchain.toml"),
+        format!("[toolchain]\nchannel = \"{}\"\n", channel),
+    )
+    .unwrap();
+    // Record the toolchain each cargo command is given.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cross");
+    let wrapper_log = bin_dir.path().join("wrapper.log");
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$RUSTUP_TOOLCHAIN\" >> '{}'\nexec cargo \"$@\"\n",
+            wrapper_log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times", "--cargo-command"])
+        .arg(&wrapper)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(format!("Using toolchain {} from ", channel)));
+    let toolchains = fs::read_to_string(&wrapper_log).unwrap();
+    assert!(toolchains.lines().count() > 2);
+    assert!(toolchains.lines().all(|line| line == channel));
+    let history = fs::read_to_string(tmp_src_dir.path().join("mutants.out/history.jsonl")).unwrap();
+    assert!(history.contains(&format!(r#""rust_toolchain":"{}""#, channel)));
+}
+
+#[test]
+fn well_tested_tree_check_only_shuffled() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times", "--shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    // Caution: No assertions about output here, we just check that it runs.
+}
+
+#[test]
+fn cap_lints_makes_mutants_that_cause_warnings_viable() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let main_path = tmp_src_dir.path().join("src/bin/main.rs");
+    let source = fs::read_to_string(&main_path).unwrap();
+    fs::write(&main_path, format!("#![deny(warnings)]\n{}", source)).unwrap();
+    // Replacing the body leaves `n` unused.
+    let mutant = "replace factorial -> u32 with Default::default()";
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--check",
+            "--no-times",
+            "--no-shuffle",
+            "--unviable",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "{} ... check failed (unused_variables)",
+            mutant
+        )));
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--check",
+            "--no-times",
+            "--no-shuffle",
+            "--cap-lints",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(format!("{} ... check ok", mutant)));
+}
+
+#[test]
+fn error_when_no_mutants_found() {
+    let tmp_src_dir = copy_of_testdata("no_opportunities");
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .stderr(predicate::str::contains("Error: No mutants found"))
+        .failure();
+    // The error is found before the baseline is tested.
+    assert!(!tmp_src_dir
+        .path()
+        .join("mutants.out/log/baseline.log")
+        .exists());
+}
+
+#[test]
+fn warn_when_tree_has_no_tests() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let main_path = tmp_src_dir.path().join("src/bin/main.rs");
+    let code = fs::read_to_string(&main_path).unwrap();
+    let (code_without_tests, _) = code.split_once("#[test]").unwrap();
+    fs::write(&main_path, code_without_tests).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(6)
+        .stdout(contains(
+            "cargo test ran no tests in the unmutated tree, so no mutants were tested",
+        ));
+    // No mutants are tested.
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(!outcomes["outcomes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|o| o["scenario"].get("Mutant").is_some()));
+}
+
+#[test]
+fn uncaught_mutant_in_factorial() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+
+    run_assert_cmd()
+        .arg("mutants")
         .arg("--no-shuffle")
         .arg("-d")
-        .arg(&tmp_src_dir.path())
+        .arg(tmp_src_dir.path())
         .assert()
         .code(2)
         .stderr("")
@@ -404,10 +1738,154 @@ fn uncaught_mutant_in_factorial() {
 
     insta::assert_debug_snapshot!("factorial__log_names", &names);
 
-    // A mutants.json is in the mutants.out directory.
-    let mutants_json =
-        fs::read_to_string(tmp_src_dir.path().join("mutants.out/mutants.json")).unwrap();
-    insta::assert_snapshot!("mutants.json", mutants_json);
+    // A mutants.json is in the mutants.out directory.
+    let mutants_json =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/mutants.json")).unwrap();
+    insta::assert_snapshot!("mutants.json", mutants_json);
+
+    // Each missed mutant suggests the tests in the same file as the place to start.
+    let summary_txt =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/summary.txt")).unwrap();
+    assert_eq!(
+        summary_txt
+            .matches("\n    tests to extend: src/bin/main.rs\n")
+            .count(),
+        3
+    );
+
+    // The missed mutants are also written as diagnostics for editors.
+    let diagnostics: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/diagnostics.json")).unwrap(),
+    )
+    .unwrap();
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 3);
+    assert!(diagnostics.iter().all(|d| d["file"] == "src/bin/main.rs"
+        && d["severity"] == 2
+        && d["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("missed mutant: ")));
+    assert!(diagnostics
+        .iter()
+        .any(|d| d["code"] == "src/bin/main.rs: replace main with ()"
+            && d["range"]["start"]["line"] == 0));
+}
+
+#[test]
+fn annotate_factorial_after_run() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--no-shuffle", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    run_assert_cmd()
+        .args(["mutants", "annotate", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("mutants.out/annotated/src/bin/main.rs\n"));
+    let annotated = fs::read_to_string(
+        tmp_src_dir
+            .path()
+            .join("mutants.out/annotated/src/bin/main.rs"),
+    )
+    .unwrap();
+    insta::assert_snapshot!("annotated_factorial", annotated);
+}
+
+#[test]
+fn clean_removes_output_and_stale_scratch_dirs() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let cache_dir = tempdir().unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times"])
+        .current_dir(tmp_src_dir.path())
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .assert()
+        .success();
+    let artifacts: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/artifacts.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(artifacts["files"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("outcomes.json")));
+    // The scratch directory of the finished run was removed and unregistered.
+    let registry = cache_dir.path().join("cargo-mutants/scratch");
+    assert_eq!(fs::read_dir(&registry).unwrap().count(), 0);
+
+    // A run that crashed left its scratch directory and registry entry behind.
+    let stale_scratch = tempdir().unwrap().into_path();
+    fs::write(
+        registry.join("crashed.json"),
+        serde_json::json!({
+            "scratch_dir": stale_scratch,
+            "source_dir": tmp_src_dir.path(),
+            "cargo_mutants_version": "0.0.0",
+        })
+        .to_string(),
+    )
+    .unwrap();
+    fs::create_dir(tmp_src_dir.path().join("mutants.out.old")).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "clean"])
+        .current_dir(tmp_src_dir.path())
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("mutants.out\n"))
+        .stdout(contains("mutants.out.old\n"))
+        .stdout(contains(format!(
+            "Removed stale scratch directory {}\n",
+            stale_scratch.display()
+        )));
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+    assert!(!tmp_src_dir.path().join("mutants.out.old").exists());
+    assert!(!stale_scratch.exists());
+    assert_eq!(fs::read_dir(&registry).unwrap().count(), 0);
+}
+
+#[test]
+fn minimal_tests_from_test_efficiency_report() {
+    let tmp = tempdir().unwrap();
+    fs::write(
+        tmp.path().join("test_efficiency.json"),
+        r#"{
+            "tests": [
+                {"test": "a", "killed": 2, "unique": 1, "mutants": ["m1", "m2"]},
+                {"test": "b", "killed": 2, "unique": 1, "mutants": ["m2", "m3"]},
+                {"test": "c", "killed": 1, "unique": 0, "mutants": ["m3"]},
+                {"test": "d", "killed": 0, "unique": 0, "mutants": []}
+            ],
+            "killed_none": ["d"]
+        }"#,
+    )
+    .unwrap();
+    run_assert_cmd()
+        .args(["mutants", "minimal-tests"])
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stdout("2 of 4 tests kill all 3 caught mutants:\n  a (+2)\n  b (+1)\n");
+}
+
+#[test]
+fn slowest_mutants_and_files_are_listed_at_end() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--no-shuffle", "--slowest", "2", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(
+            predicate::str::is_match(
+                r"(?m)^Slowest mutants:\n  \d+\.\ds replace .* in src/bin/main\.rs:\d+\n  \d+\.\ds replace .* in src/bin/main\.rs:\d+\nSlowest files:\n  \d+\.\ds src/bin/main\.rs\n",
+            )
+            .unwrap(),
+        );
 }
 
 #[test]
@@ -421,7 +1899,7 @@ fn factorial_mutants_with_all_logs() {
         .arg("-v")
         .arg("-V")
         .arg("-d")
-        .arg(&tmp_src_dir.path())
+        .arg(tmp_src_dir.path())
         .assert()
         .code(2)
         .stderr("")
@@ -447,7 +1925,7 @@ fn factorial_mutants_with_all_logs_and_nocapture() {
         .arg("mutants")
         .args(["--all-logs", "-v", "-V"])
         .arg("-d")
-        .arg(&tmp_src_dir.path())
+        .arg(tmp_src_dir.path())
         .args(["--", "--", "--nocapture"])
         .assert()
         .code(2)
@@ -462,9 +1940,9 @@ fn factorial_mutants_no_copy_target() {
     let tmp_src_dir = copy_of_testdata("factorial");
     run_assert_cmd()
         .arg("mutants")
-        .args(["--no-copy-target", "--no-times"])
+        .args(["--no-copy-target", "--no-times", "--no-shuffle"])
         .arg("-d")
-        .arg(&tmp_src_dir.path())
+        .arg(tmp_src_dir.path())
         .assert()
         .code(2)
         .stderr("")
@@ -485,10 +1963,10 @@ fn output_option() {
     run_assert_cmd()
         .arg("mutants")
         .arg("--output")
-        .arg(&output_tmpdir.path())
+        .arg(output_tmpdir.path())
         .args(["--check", "--no-times"])
         .arg("-d")
-        .arg(&tmp_src_dir.path())
+        .arg(tmp_src_dir.path())
         .assert()
         .success();
     assert!(
@@ -523,7 +2001,8 @@ fn check_succeeds_in_tree_that_builds_but_fails_tests() {
         .arg("mutants")
         .arg("--check")
         .arg("--no-times")
-        .current_dir(&tmp_src_dir.path())
+        .arg("--no-shuffle")
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .success()
@@ -540,7 +2019,8 @@ fn check_tree_with_mutants_skip() {
         .arg("mutants")
         .arg("--check")
         .arg("--no-times")
-        .current_dir(&tmp_src_dir.path())
+        .arg("--no-shuffle")
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .success()
@@ -555,26 +2035,128 @@ fn already_failing_tests_are_detected_before_running_mutants() {
     let tmp_src_dir = copy_of_testdata("already_failing_tests");
     run_assert_cmd()
         .arg("mutants")
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .code(4)
+        .stdout(predicate::str::contains("stdout] running 1 test\n"))
+        .stdout(predicate::str::contains(
+            "stdout] test test_factorial ... FAILED\n",
+        ))
         .stdout(
-            predicate::str::contains("running 1 test\ntest test_factorial ... FAILED").normalize(),
-        )
-        .stdout(
-            predicate::str::contains(
-                "thread 'test_factorial' panicked at 'assertion failed: `(left == right)`
-  left: `720`,
- right: `72`'",
-            )
-            .normalize(),
+            // The format of panic messages varies between Rust versions.
+            is_match(r"thread 'test_factorial'.* panicked at")
+                .unwrap()
+                .and(is_match(r"left: `?720`?,?\r?\n(\[[^\]]*\] )? right: `?72`?").unwrap()),
         )
         .stdout(predicate::str::contains("lib.rs:11:5"))
         .stdout(predicate::str::contains(
             "cargo test failed in an unmutated tree, so no mutants were tested",
         ))
-        .stdout(predicate::str::contains("test result: FAILED. 0 passed; 1 failed;").normalize());
+        .stdout(predicate::str::contains(
+            "stdout] test result: FAILED. 0 passed; 1 failed;",
+        ))
+        .stdout(contains("Failed tests:\n\n  test_factorial\n"));
+    let report =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/baseline-failure.txt")).unwrap();
+    assert!(report.starts_with("cargo test failed in an unmutated tree"));
+    assert!(report.contains("\n  test_factorial\n    thread 'test_factorial'"));
+    assert!(report.ends_with("The full output is in log/baseline.log.\n"));
+}
+
+#[test]
+fn tests_failing_in_the_baseline_can_be_allowed() {
+    let tmp_src_dir = copy_of_testdata("already_failing_tests");
+    let cache_dir = tempdir().unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--allow-baseline-failures"])
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2) // missed mutants
+        .stdout(contains(
+            "1 test failed in the unmutated tree, and only other tests failing will catch \
+            mutants, because of --allow-baseline-failures: test_factorial",
+        ))
+        .stdout(contains("fn-value: 0 caught, 1 missed"))
+        .stdout(contains("range: 0 caught, 2 missed"));
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(outcomes["outcomes"][1]["scenario"], "Baseline");
+    assert_eq!(outcomes["outcomes"][1]["summary"], "Success");
+    assert_eq!(
+        outcomes["outcomes"][1]["ignored_failed_tests"],
+        serde_json::json!(["test_factorial"])
+    );
+
+    // With another test that passes, the mutants are caught, and the failures in the
+    // baseline are remembered with --baseline skip.
+    let lib_path = tmp_src_dir.path().join("src/lib.rs");
+    let mut lib = fs::read_to_string(&lib_path).unwrap();
+    lib.push_str("\n#[test]\nfn factorial_of_three() {\n    assert_eq!(factorial(3), 6);\n}\n");
+    fs::write(&lib_path, lib).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--allow-baseline-failures"])
+        .args(["--baseline", "skip"])
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("failed in the unmutated tree"))
+        .stdout(contains("NOT CAUGHT").not());
+}
+
+#[test]
+fn second_order_mutants_apply_pairs_of_mutations() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--list", "--order", "2"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "src/bin/main.rs:1: replace main with () + \
+            src/bin/main.rs:7: replace factorial -> u32 with Default::default()\n",
+        ));
+    run_assert_cmd()
+        .args(["mutants", "--no-shuffle", "--order", "2", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains("Found 3 mutants to test"))
+        .stdout(contains("fn-value: 1 caught, 0 missed"))
+        .stdout(contains("range: 2 caught, 0 missed"));
+}
+
+#[test]
+fn order_must_be_1_or_2() {
+    run_assert_cmd()
+        .args(["mutants", "--list", "--order", "3"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains("--order should be 1 or 2, not 3"));
+}
+
+#[test]
+fn allow_baseline_failures_needs_libtest() {
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--allow-baseline-failures",
+            "--test-tool",
+            "nextest",
+        ])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "--allow-baseline-failures can't be used with --test-tool nextest",
+        ));
 }
 
 #[test]
@@ -582,7 +2164,7 @@ fn already_failing_doctests_are_detected() {
     let tmp_src_dir = copy_of_testdata("already_failing_doctests");
     run_assert_cmd()
         .arg("mutants")
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .code(4) // CLEAN_TESTS_FAILED
@@ -601,7 +2183,7 @@ fn already_failing_doctests_can_be_skipped_with_cargo_arg() {
     run_assert_cmd()
         .arg("mutants")
         .args(["--", "--all-targets"])
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .code(0)
@@ -613,7 +2195,7 @@ fn source_tree_parse_fails() {
     let tmp_src_dir = copy_of_testdata("parse_fails");
     run_assert_cmd()
         .arg("mutants")
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .failure() // TODO: This should be a distinct error code
@@ -631,7 +2213,7 @@ fn source_tree_typecheck_fails() {
     let tmp_src_dir = copy_of_testdata("typecheck_fails");
     run_assert_cmd()
         .arg("mutants")
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .failure() // TODO: This should be a distinct error code
@@ -644,7 +2226,14 @@ fn source_tree_typecheck_fails() {
         .stdout(contains("check --tests")) // Caught at the check phase
         .stdout(contains("lib.rs:6"))
         .stdout(contains("*** cargo result: "))
-        .stdout(contains("check failed in source tree, not continuing"));
+        .stdout(contains("check failed in source tree, not continuing"))
+        .stdout(contains(
+            "Compiler errors:\n\nerror[E0369]: cannot add `{integer}` to `&str`",
+        ));
+    let report =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/baseline-failure.txt")).unwrap();
+    assert!(report.contains("error[E0369]"));
+    assert!(!report.contains("aborting due to"));
 }
 
 #[test]
@@ -653,7 +2242,7 @@ fn timeout_when_unmutated_tree_test_hangs() {
     run_assert_cmd()
         .arg("mutants")
         .args(["--timeout", "2.9"])
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .code(4) // exit_code::CLEAN_TESTS_FAILED
@@ -670,15 +2259,160 @@ fn hang_when_mutated() {
     run_assert_cmd()
         .arg("mutants")
         .args(["-t", "1.9"])
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .code(3) // exit_code::TIMEOUT
         .stdout(contains(
             "replace should_stop -> bool with false ... TIMEOUT",
         ))
+        // Replacing `true` with `true` is equivalent, so it's not tested.
+        .stdout(contains("replace should_stop -> bool with true").not());
+}
+
+#[test]
+fn silent_tests_are_stopped_by_idle_timeout() {
+    let tmp_src_dir = copy_of_testdata("hang_when_mutated");
+    run_assert_cmd()
+        .args(["mutants", "-t", "300", "--idle-timeout", "1.5"])
+        .current_dir(tmp_src_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .assert()
+        .code(3) // exit_code::TIMEOUT
+        .stdout(contains("replace should_stop -> bool with false ... HUNG"));
+    let outcomes: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap(),
+    )
+    .unwrap();
+    let statuses = outcomes["outcomes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|outcome| outcome["status"].as_str().unwrap())
+        .collect_vec();
+    assert!(statuses.contains(&"Hung"));
+    assert_eq!(outcomes["kinds"]["fn-value"]["timeout"], 1);
+}
+
+/// The tests are stopped once they use too much CPU time, even if the wall-clock time
+/// is unlimited.
+#[cfg(target_os = "linux")]
+#[test]
+fn busy_tests_are_stopped_by_cpu_timeout() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    // Instead of running the tests, spin.
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("fake-cargo");
+    fs::write(
+        &wrapper,
+        "#!/bin/sh\nif [ \"$1\" = test ]; then while :; do :; done; fi\nexec cargo \"$@\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--timeout-cpu-seconds", "1", "--cargo-command"])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .assert()
+        .code(4) // exit_code::CLEAN_TESTS_FAILED
+        .stdout(is_match(r"Unmutated baseline \.\.\. TIMEOUT in \d+\.\d{3}s").unwrap());
+    let log = fs::read_to_string(tmp_src_dir.path().join("mutants.out/log/baseline.log")).unwrap();
+    assert!(is_match(r"timeout after \d+\.\d{3}s of CPU time")
+        .unwrap()
+        .eval(&log));
+}
+
+#[test]
+fn cpu_timeout_must_be_positive() {
+    run_assert_cmd()
+        .args(["mutants", "--list", "--timeout-cpu-seconds", "-1"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "CPU timeout should be a positive number of seconds, not -1",
+        ));
+}
+
+/// When cargo fails because the tree needs a nightly toolchain, the reason is
+/// shown, not just left in the log.
+#[cfg(unix)]
+#[test]
+fn nightly_only_flags_are_explained() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let bin_dir = tempdir().unwrap();
+    let wrapper = bin_dir.path().join("stable-cargo");
+    fs::write(
+        &wrapper,
+        "#!/bin/sh\necho \"error: the \\`-Z\\` flag is only accepted on the nightly channel \
+        of Cargo, but this is the \\`stable\\` channel\" >&2\nexit 101\n",
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--cargo-command"])
+        .arg(&wrapper)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .failure()
+        .stdout(contains("cargo check failed in source tree"))
+        .stdout(contains(
+            "the tree uses features that need a nightly toolchain",
+        ));
+}
+
+#[test]
+fn tests_that_depend_on_the_source_tree_location_are_explained() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let fixtures_dir = tmp_src_dir.path().join("fixtures");
+    fs::create_dir(&fixtures_dir).unwrap();
+    fs::write(fixtures_dir.join("data.txt"), "720\n").unwrap();
+    let main_path = tmp_src_dir.path().join("src/bin/main.rs");
+    let mut main = fs::read_to_string(&main_path).unwrap();
+    main.push_str(
+        r#"
+#[test]
+fn factorial_matches_fixture() {
+    let expected = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/data.txt"))
+        .unwrap();
+    assert_eq!(factorial(6).to_string(), expected.trim());
+}
+"#,
+    );
+    fs::write(&main_path, main).unwrap();
+    let cache_dir = tempdir().unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-copy-target"])
+        .args(["--copy-exclude", "fixtures"])
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4)
+        .stdout(contains(
+            "the tests pass in the source tree but fail in a copy of it",
+        ))
+        .stdout(contains(
+            "src/bin/main.rs:23: \"/fixtures/data.txt\" relative to CARGO_MANIFEST_DIR \
+            is not in the scratch directory",
+        ));
+}
+
+#[test]
+fn timeouts_can_count_as_caught() {
+    let tmp_src_dir = copy_of_testdata("hang_when_mutated");
+    run_assert_cmd()
+        .args(["mutants", "-t", "1.9", "--timeout-counts-as", "caught"])
+        .current_dir(tmp_src_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .assert()
+        .code(2) // exit_code::FOUND_PROBLEMS, because some mutants are missed
         .stdout(contains(
-            "replace should_stop -> bool with true ... NOT CAUGHT",
+            "loop: 0 caught, 0 missed, 0 unviable, 1 timed out, score 100%\n",
         ));
 }
 
@@ -689,7 +2423,7 @@ fn log_file_names_are_short_and_dont_collide() {
     let cmd_assert = run_assert_cmd()
         .arg("mutants")
         .args(["--check", "-v", "-V"])
-        .current_dir(&tmp_src_dir.path())
+        .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .assert()
         .success();
@@ -709,9 +2443,254 @@ fn log_file_names_are_short_and_dont_collide() {
         "log file names are too long"
     );
     assert!(
+        all_log_names.iter().all(|filename| filename
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')),
+        "log file names are not portable"
+    );
+    // logs.ndjson maps each log to a distinct scenario.
+    let index = fs::read_to_string(tmp_src_dir.path().join("mutants.out/logs.ndjson")).unwrap();
+    let entries = index
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect_vec();
+    assert_eq!(entries.len(), all_log_names.len());
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry["log_file"].as_str().unwrap())
+            .sorted()
+            .collect_vec(),
         all_log_names
             .iter()
-            .any(|filename| filename.ends_with("_001.log")),
-        "log file names are not disambiguated"
+            .map(|name| format!("log/{}", name))
+            .sorted()
+            .collect_vec()
+    );
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry["scenario"].as_str().unwrap())
+            .unique()
+            .count(),
+        entries.len()
+    );
+}
+
+#[test]
+fn compare_reports_regressions() {
+    let tmp = tempdir().unwrap();
+    let outcomes = |summary: &str| {
+        format!(
+            r#"{{"outcomes": [
+                {{"scenario": "Baseline", "log_path": "baseline.log", "summary": "Success", "phase_results": []}},
+                {{"scenario": {{"Mutant": {{"file": "src/lib.rs", "line": 3, "function": "f",
+                    "return_type": "-> bool", "replacement": "true"}}}},
+                  "log_path": "f.log", "summary": "{}", "phase_results": []}}
+            ]}}"#,
+            summary
+        )
+    };
+    let old_path = tmp.path().join("old.json");
+    let new_path = tmp.path().join("new.json");
+    fs::write(&old_path, outcomes("CaughtMutant")).unwrap();
+    fs::write(&new_path, outcomes("MissedMutant")).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "compare"])
+        .arg(&old_path)
+        .arg(&new_path)
+        .assert()
+        .code(2)
+        .stderr("")
+        .stdout(
+            "Mutation score: 100.0% -> 0.0% (-100.0)\n\
+            Caught: 1 -> 0, missed: 0 -> 1\n\
+            \n\
+            Caught before, missed now:\n  \
+            src/lib.rs:3: replace f -> bool with true\n",
+        );
+    run_assert_cmd()
+        .args(["mutants", "compare", "--json"])
+        .arg(&old_path)
+        .arg(&old_path)
+        .assert()
+        .success()
+        .stdout(contains(r#""score_delta": 0.0"#));
+}
+
+#[test]
+fn prioritize_uses_history_from_previous_run() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    // The first run records in the history that `main` is missed.
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    // The second run reads that history to put `main` first, and appends to it.
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--prioritize", "-v"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(is_match(r"(?s)replace main with \(\).*replace factorial -> u32").unwrap());
+    let history = fs::read_to_string(tmp_src_dir.path().join("mutants.out/history.jsonl")).unwrap();
+    assert_eq!(history.lines().count(), 2);
+}
+
+#[test]
+fn list_uncovered_mutants_from_lcov() {
+    let tmp = tempdir().unwrap();
+    let lcov_path = tmp.path().join("lcov.info");
+    fs::write(
+        &lcov_path,
+        "SF:/somewhere/factorial/src/bin/main.rs\nDA:2,0\nDA:3,0\nDA:8,1\nDA:9,6\nend_of_record\n",
+    )
+    .unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--list", "--json", "--coverage"])
+        .arg(&lcov_path)
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success()
+        .stdout(contains(r#""covered": false"#))
+        .stdout(contains(r#""covered": true"#));
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--list",
+            "--coverage-order",
+            "uncovered-only",
+            "--coverage",
+        ])
+        .arg(&lcov_path)
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success()
+        .stdout(
+            "src/bin/main.rs:1: replace main with ()\n\
+            src/bin/main.rs:2: replace 1..=6 with 1..6 in main\n\
+            src/bin/main.rs:2: replace 1..=6 with 1 + 1..=6 in main\n",
+        );
+    run_assert_cmd()
+        .args(["mutants", "--list", "--coverage-order", "covered-first"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .code(1)
+        .stderr(contains("--coverage-order requires --coverage"));
+}
+
+#[test]
+fn output_can_be_in_target_dir() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args([
+            "mutants",
+            "--check",
+            "--no-times",
+            "--output",
+            "target/mutants",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    assert!(tmp_src_dir
+        .path()
+        .join("target/mutants/mutants.out/outcomes.json")
+        .is_file());
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+}
+
+#[test]
+fn output_dir_from_config_is_used_by_run_and_clean() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let manifest = tmp_src_dir.path().join("Cargo.toml");
+    let mut toml = fs::read_to_string(&manifest).unwrap();
+    toml.push_str("\n[package.metadata.mutants]\noutput = \"target/mutants\"\n");
+    fs::write(&manifest, toml).unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--check", "--no-times"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    let output_dir = tmp_src_dir.path().join("target/mutants/mutants.out");
+    assert!(output_dir.join("outcomes.json").is_file());
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+    run_assert_cmd()
+        .args(["mutants", "clean"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    assert!(!output_dir.exists());
+}
+
+/// The history is kept in the user cache, so a run whose output goes somewhere new,
+/// as it might for a read-only checkout, can still iterate on the last run.
+#[test]
+fn iterate_reads_history_from_user_cache() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    let cache_dir = tempdir().unwrap();
+    let first_output = tempdir().unwrap();
+    let second_output = tempdir().unwrap();
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle", "-o"])
+        .arg(first_output.path())
+        .current_dir(tmp_src_dir.path())
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .assert()
+        .code(2);
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--iterate", "-o"])
+        .arg(second_output.path())
+        .current_dir(tmp_src_dir.path())
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(
+            "Skipping 3 mutants already tested in previous runs\n",
+        ));
+    let projects = cache_dir.path().join("cargo-mutants/projects");
+    assert_eq!(fs::read_dir(&projects).unwrap().count(), 1);
+
+    // `clean` forgets the project's history.
+    run_assert_cmd()
+        .args(["mutants", "clean"])
+        .current_dir(tmp_src_dir.path())
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&projects).unwrap().count(), 0);
+}
+
+#[test]
+fn iterate_retests_only_missed_mutants() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    // The `factorial` mutants were caught, so only the missed `main` mutants are tested again.
+    run_assert_cmd()
+        .args(["mutants", "--no-times", "--iterate", "-v"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(
+            "Skipping 3 mutants already tested in previous runs\nFound 3 mutants to test\n",
+        ))
+        .stdout(contains("replace main with ()"))
+        .stdout(contains("replace factorial").not());
+    // The history of the second run includes the carried-forward outcome.
+    let history = fs::read_to_string(tmp_src_dir.path().join("mutants.out/history.jsonl")).unwrap();
+    let last_run = history.lines().last().unwrap();
+    assert!(
+        last_run.contains(r#""caught":3,"missed":3"#),
+        "{}",
+        last_run
     );
 }